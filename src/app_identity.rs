@@ -0,0 +1,181 @@
+use std::path::Path;
+
+use super::*;
+#[cfg(windows)]
+use crate::logging::*;
+
+/// The [Application User Model ID][AUMID] of the Windows App
+/// for toast notifications when it's not installed by a MSIX package.
+///
+/// [AUMID]: https://learn.microsoft.com/windows/win32/shell/appids
+pub const APP_USER_MODEL_ID: &str = "KojiIshi.MonitorInput";
+
+/// The URI scheme to run this program from the toast notification actions.
+/// The scheme is registered by the `--register` option,
+/// or by the MSIX package.
+pub const URI_SCHEME: &str = "monitor-input";
+
+/// The application ID for toast notifications when [`application_id()`] is `None`.
+/// This is the same as the default of the `toast-logger-win` crate.
+pub const DEFAULT_APPLICATION_ID: &str =
+    r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+
+/// The application ID in the `Application` element of the MSIX package manifest.
+#[cfg_attr(not(windows), allow(dead_code))]
+const PACKAGE_APP_ID: &str = "App";
+
+/// The name shown in the notification center for [`APP_USER_MODEL_ID`].
+#[cfg_attr(not(windows), allow(dead_code))]
+const DISPLAY_NAME: &str = "Monitor Input";
+
+/// The registry key to register the [`APP_USER_MODEL_ID`] for the current user.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn app_user_model_id_key() -> String {
+    format!(r"Software\Classes\AppUserModelId\{APP_USER_MODEL_ID}")
+}
+
+/// The registry key to register the [`URI_SCHEME`] for the current user.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn uri_scheme_key() -> String {
+    format!(r"Software\Classes\{URI_SCHEME}")
+}
+
+/// The application ID for toast notifications.
+///
+/// This is the AUMID of the package if this program runs from a MSIX package,
+/// or [`APP_USER_MODEL_ID`] if it's registered by [`register_app()`].
+/// Otherwise `None`, and [`DEFAULT_APPLICATION_ID`] should be used.
+pub fn application_id() -> Option<String> {
+    if let Some(family_name) = package_family_name() {
+        return Some(format!("{family_name}!{PACKAGE_APP_ID}"));
+    }
+    if is_registered() {
+        return Some(APP_USER_MODEL_ID.to_string());
+    }
+    None
+}
+
+/// Whether this program runs from a MSIX package.
+pub fn is_packaged() -> bool {
+    package_family_name().is_some()
+}
+
+/// Whether the `daemon` runs at sign-in by the `autostart enable` command.
+/// See also [`set_start_on_login()`].
+pub fn is_start_on_login() -> bool {
+    autostart::status().is_some()
+}
+
+/// Enable or disable running the `daemon` at sign-in,
+/// the same as the `autostart enable` and `autostart disable` commands.
+/// The `config_path` is kept in the autostart entry if specified.
+pub fn set_start_on_login(enabled: bool, config_path: Option<&Path>) -> anyhow::Result<()> {
+    if enabled {
+        autostart::enable(config_path)
+    } else {
+        autostart::disable()
+    }
+}
+
+/// Register the [`APP_USER_MODEL_ID`] and the [`URI_SCHEME`] for the current user,
+/// so that toast notifications show the name of this program,
+/// and their actions can run this program.
+/// This isn't needed when installed by a MSIX package,
+/// because the package manifest registers them.
+#[cfg(windows)]
+pub(crate) fn register_app() -> anyhow::Result<()> {
+    if is_packaged() {
+        info!("The MSIX package registers the application identity.");
+        return Ok(());
+    }
+    let exe = std::env::current_exe()?;
+    let aumid_key = app_user_model_id_key();
+    registry::set_value(&aumid_key, "DisplayName", DISPLAY_NAME)?;
+    let scheme_key = uri_scheme_key();
+    registry::set_value(&scheme_key, "", &format!("URL:{URI_SCHEME}"))?;
+    registry::set_value(&scheme_key, "URL Protocol", "")?;
+    registry::set_value(
+        &format!(r"{scheme_key}\shell\open\command"),
+        "",
+        &format!("\"{}\" \"%1\"", exe.display()),
+    )?;
+    info!("Registered \"{APP_USER_MODEL_ID}\" and \"{URI_SCHEME}:\" for the current user.");
+    Ok(())
+}
+
+/// Remove the registrations by [`register_app()`].
+#[cfg(windows)]
+pub(crate) fn unregister_app() -> anyhow::Result<()> {
+    registry::delete_tree(&app_user_model_id_key())?;
+    registry::delete_tree(&uri_scheme_key())?;
+    info!("Unregistered \"{APP_USER_MODEL_ID}\" and \"{URI_SCHEME}:\" for the current user.");
+    Ok(())
+}
+
+#[cfg(windows)]
+fn is_registered() -> bool {
+    registry::has_value(&app_user_model_id_key(), "DisplayName")
+}
+
+#[cfg(windows)]
+fn package_family_name() -> Option<String> {
+    use windows_sys::Win32::Foundation::ERROR_INSUFFICIENT_BUFFER;
+    use windows_sys::Win32::Storage::Packaging::Appx::GetCurrentPackageFamilyName;
+
+    let mut len = 0u32;
+    // SAFETY: Querying the length with the null buffer is allowed.
+    let result = unsafe { GetCurrentPackageFamilyName(&mut len, std::ptr::null_mut()) };
+    // `APPMODEL_ERROR_NO_PACKAGE` if not packaged.
+    if result != ERROR_INSUFFICIENT_BUFFER {
+        return None;
+    }
+    let mut buffer = vec![0u16; len as usize];
+    // SAFETY: `buffer` has `len` elements.
+    if unsafe { GetCurrentPackageFamilyName(&mut len, buffer.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+    Some(String::from_utf16_lossy(&buffer[..len]))
+}
+
+#[cfg(not(windows))]
+pub(crate) fn register_app() -> anyhow::Result<()> {
+    anyhow::bail!("The `--register` option is available only on Windows.");
+}
+
+#[cfg(not(windows))]
+pub(crate) fn unregister_app() -> anyhow::Result<()> {
+    anyhow::bail!("The `--unregister` option is available only on Windows.");
+}
+
+#[cfg(not(windows))]
+fn is_registered() -> bool {
+    false
+}
+
+#[cfg(not(windows))]
+fn package_family_name() -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        assert_eq!(
+            app_user_model_id_key(),
+            r"Software\Classes\AppUserModelId\KojiIshi.MonitorInput"
+        );
+        assert_eq!(uri_scheme_key(), r"Software\Classes\monitor-input");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn not_windows() {
+        assert!(!is_packaged());
+        assert_eq!(application_id(), None);
+        assert!(register_app().is_err());
+    }
+}
@@ -0,0 +1,333 @@
+use std::str::FromStr;
+
+use crate::{AUDIO_VOLUME, CONTRAST, LUMINANCE, POWER_MODE, VcpCode};
+
+/// Characters that a backslash escapes.
+/// Other backslashes are literal,
+/// so that names such as `\\.\DISPLAY1` on Windows don't need escaping.
+const ESCAPABLE: &[char] = &['=', ',', ':', '?', '"', '\'', '~'];
+
+/// The VCP features that `name.feature=value` sets, and their codes.
+const FEATURES: &[(&str, VcpCode)] = &[
+    ("brightness", LUMINANCE),
+    ("contrast", CONTRAST),
+    ("volume", AUDIO_VOLUME),
+    ("power", POWER_MODE),
+];
+
+/// A positional argument of the command line.
+///
+/// Names and input sources can be quoted by `"` or `'` at their starts,
+/// or their special characters can be escaped by `\`,
+/// such as `"A=B"=dp1` or `A\=B=dp1` for the display monitor `A=B`.
+/// Quotes in the middle are literal, such as `Bob's=dp1`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(not(feature = "cli"), allow(dead_code))]
+pub(crate) enum Arg {
+    /// `name` to list the display monitors.
+    List(String),
+    /// `name=input`, or `name=input,input` to toggle.
+    /// The `~N` suffix of the last input advances the toggle by `N` entries.
+    Set {
+        name: String,
+        values: Vec<String>,
+        step: isize,
+    },
+    /// `name?=condition:input` to set only if the current input source is the `condition`.
+    SetIf {
+        name: String,
+        condition: String,
+        value: String,
+    },
+    /// `name.pbp=mode` to set the picture-by-picture mode.
+    SetPbp { name: String, mode: String },
+    /// `name.brightness=value` to set a VCP feature of the [`FEATURES`].
+    SetVcp {
+        name: String,
+        feature: &'static str,
+        code: VcpCode,
+        value: String,
+    },
+}
+
+/// A character of an argument, and whether it's quoted or escaped.
+type Char = (char, bool);
+
+impl FromStr for Arg {
+    type Err = anyhow::Error;
+
+    fn from_str(arg: &str) -> anyhow::Result<Self> {
+        let chars = scan(arg)?;
+        let Some(eq) = position(&chars, '=') else {
+            return Ok(Arg::List(text(&chars)));
+        };
+        let (name, value) = (&chars[..eq], &chars[eq + 1..]);
+        if position(value, '=').is_some() {
+            anyhow::bail!(
+                "\"{arg}\" has more than one `=`. \
+                Quote the name if it contains `=`, such as `\"A=B\"=dp1`."
+            );
+        }
+        if let Some(name) = strip_suffix(name, "?") {
+            let parts = split(value, ':');
+            let [condition, value] = parts.as_slice() else {
+                anyhow::bail!("\"{arg}\" is not in the form of `name?=condition:input`.");
+            };
+            return Ok(Arg::SetIf {
+                name: non_empty_name(name, arg)?,
+                condition: non_empty_value(condition, arg)?,
+                value: non_empty_value(value, arg)?,
+            });
+        }
+        if let Some(name) = strip_suffix(name, ".pbp") {
+            return Ok(Arg::SetPbp {
+                name: non_empty_name(name, arg)?,
+                mode: non_empty_value(value, arg)?,
+            });
+        }
+        for (feature, code) in FEATURES {
+            if let Some(name) = strip_suffix(name, &format!(".{feature}")) {
+                return Ok(Arg::SetVcp {
+                    name: non_empty_name(name, arg)?,
+                    feature,
+                    code: *code,
+                    value: non_empty_value(value, arg)?,
+                });
+            }
+        }
+        let mut parts = split(value, ',');
+        let mut step = 1;
+        if parts.len() > 1 {
+            let last = parts.last_mut().unwrap();
+            if let Some(tilde) = last.iter().rposition(|&(c, quoted)| c == '~' && !quoted) {
+                let step_str = text(&last[tilde + 1..]);
+                step = step_str
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid toggle step \"{step_str}\""))?;
+                *last = &last[..tilde];
+            }
+        }
+        Ok(Arg::Set {
+            name: non_empty_name(name, arg)?,
+            values: parts
+                .iter()
+                .map(|part| non_empty_value(part, arg))
+                .collect::<anyhow::Result<_>>()?,
+            step,
+        })
+    }
+}
+
+/// The VCP feature code of the `feature` in `name.feature=value`, such as `brightness`.
+pub(crate) fn feature_code(feature: &str) -> Option<VcpCode> {
+    FEATURES
+        .iter()
+        .find(|(name, _)| *name == feature)
+        .map(|(_, code)| *code)
+}
+
+/// Parse a VCP code, which is hexadecimal with or without `0x`.
+#[cfg_attr(not(feature = "cli"), allow(dead_code))]
+pub(crate) fn parse_vcp_code(code: &str) -> Option<VcpCode> {
+    let code = code.trim();
+    let digits = code
+        .strip_prefix("0x")
+        .or_else(|| code.strip_prefix("0X"))
+        .unwrap_or(code);
+    VcpCode::from_str_radix(digits, 16).ok()
+}
+
+/// Escape the special characters of the `name`,
+/// so that it's parsed as the name of a display monitor.
+pub(crate) fn quote(name: &str) -> String {
+    let mut quoted = String::with_capacity(name.len());
+    for c in name.chars() {
+        if ESCAPABLE.contains(&c) {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted
+}
+
+/// Unquote and unescape the `arg`.
+fn scan(arg: &str) -> anyhow::Result<Vec<Char>> {
+    let mut chars = vec![];
+    let mut quote = None;
+    let mut iter = arg.chars().peekable();
+    while let Some(c) = iter.next() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            } else {
+                chars.push((c, true));
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' if is_at_start(&chars) => quote = Some(c),
+            '\\' if iter.peek().is_some_and(|next| ESCAPABLE.contains(next)) => {
+                chars.push((iter.next().unwrap(), true));
+            }
+            _ => chars.push((c, false)),
+        }
+    }
+    if let Some(q) = quote {
+        anyhow::bail!("\"{arg}\" has an unterminated quote `{q}`.");
+    }
+    Ok(chars)
+}
+
+/// Whether the next character is at the start of a name or an input source,
+/// where quotes start quoting.
+fn is_at_start(chars: &[Char]) -> bool {
+    match chars.last() {
+        None => true,
+        Some(&(c, quoted)) => !quoted && matches!(c, '=' | ',' | ':'),
+    }
+}
+
+/// The position of the first `separator` that isn't quoted nor escaped.
+fn position(chars: &[Char], separator: char) -> Option<usize> {
+    chars
+        .iter()
+        .position(|&(c, quoted)| c == separator && !quoted)
+}
+
+fn split(chars: &[Char], separator: char) -> Vec<&[Char]> {
+    chars
+        .split(|&(c, quoted)| c == separator && !quoted)
+        .collect()
+}
+
+fn strip_suffix<'a>(chars: &'a [Char], suffix: &str) -> Option<&'a [Char]> {
+    let start = chars.len().checked_sub(suffix.chars().count())?;
+    chars[start..]
+        .iter()
+        .zip(suffix.chars())
+        .all(|(&(c, quoted), s)| c == s && !quoted)
+        .then_some(&chars[..start])
+}
+
+fn text(chars: &[Char]) -> String {
+    chars.iter().map(|&(c, _)| c).collect()
+}
+
+fn non_empty_name(chars: &[Char], arg: &str) -> anyhow::Result<String> {
+    if chars.is_empty() {
+        anyhow::bail!("The display monitor name is empty in \"{arg}\".");
+    }
+    Ok(text(chars))
+}
+
+fn non_empty_value(chars: &[Char], arg: &str) -> anyhow::Result<String> {
+    if chars.is_empty() {
+        anyhow::bail!("The input source is empty in \"{arg}\".");
+    }
+    Ok(text(chars))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(name: &str, values: &[&str], step: isize) -> Arg {
+        Arg::Set {
+            name: name.to_string(),
+            values: values.iter().map(|value| value.to_string()).collect(),
+            step,
+        }
+    }
+
+    #[test]
+    fn parse_set() {
+        assert_eq!("a".parse::<Arg>().unwrap(), Arg::List("a".into()));
+        assert_eq!("a=b".parse::<Arg>().unwrap(), set("a", &["b"], 1));
+        assert_eq!("1=23".parse::<Arg>().unwrap(), set("1", &["23"], 1));
+        assert_eq!("12=3,4".parse::<Arg>().unwrap(), set("12", &["3", "4"], 1));
+        assert_eq!(
+            "a=b,c~-1".parse::<Arg>().unwrap(),
+            set("a", &["b", "c"], -1)
+        );
+        assert!("a=".parse::<Arg>().is_err());
+        assert!("=a".parse::<Arg>().is_err());
+        assert!("a=b,".parse::<Arg>().is_err());
+        assert!("a=b,c~x".parse::<Arg>().is_err());
+    }
+
+    #[test]
+    fn parse_set_if() {
+        assert_eq!(
+            "a?=b:c".parse::<Arg>().unwrap(),
+            Arg::SetIf {
+                name: "a".into(),
+                condition: "b".into(),
+                value: "c".into()
+            }
+        );
+        assert!("a?=b".parse::<Arg>().is_err());
+        assert!("a?=b:".parse::<Arg>().is_err());
+        assert!("a?=b:c:d".parse::<Arg>().is_err());
+        assert_eq!(
+            "a.pbp=2x1".parse::<Arg>().unwrap(),
+            Arg::SetPbp {
+                name: "a".into(),
+                mode: "2x1".into()
+            }
+        );
+        assert_eq!(
+            "a.brightness=50%".parse::<Arg>().unwrap(),
+            Arg::SetVcp {
+                name: "a".into(),
+                feature: "brightness",
+                code: LUMINANCE,
+                value: "50%".into()
+            }
+        );
+        assert_eq!(
+            "a.power=off".parse::<Arg>().unwrap(),
+            Arg::SetVcp {
+                name: "a".into(),
+                feature: "power",
+                code: POWER_MODE,
+                value: "off".into()
+            }
+        );
+        assert!("a.volume=".parse::<Arg>().is_err());
+    }
+
+    #[test]
+    fn parse_escape() {
+        assert_eq!(
+            r#""A=B,C"=dp1"#.parse::<Arg>().unwrap(),
+            set("A=B,C", &["dp1"], 1)
+        );
+        assert_eq!(r"A\=B=dp1".parse::<Arg>().unwrap(), set("A=B", &["dp1"], 1));
+        assert_eq!(r"A\?=dp1".parse::<Arg>().unwrap(), set("A?", &["dp1"], 1));
+        assert_eq!("'A:B'".parse::<Arg>().unwrap(), Arg::List("A:B".into()));
+        assert_eq!(
+            r"\\.\DISPLAY1=dp1".parse::<Arg>().unwrap(),
+            set(r"\\.\DISPLAY1", &["dp1"], 1)
+        );
+        assert_eq!(
+            format!("{}=dp1", quote("A=B,C")).parse::<Arg>().unwrap(),
+            set("A=B,C", &["dp1"], 1)
+        );
+        assert!("A=B=dp1".parse::<Arg>().is_err());
+        assert!("\"A=dp1".parse::<Arg>().is_err());
+
+        // Quotes in the middle are literal, as they were before quoting is supported.
+        assert_eq!(
+            "Bob's Monitor=dp1".parse::<Arg>().unwrap(),
+            set("Bob's Monitor", &["dp1"], 1)
+        );
+        assert_eq!(
+            r#"24" LCD"#.parse::<Arg>().unwrap(),
+            Arg::List(r#"24" LCD"#.into())
+        );
+        assert_eq!(
+            "A='dp1','Hdmi1'".parse::<Arg>().unwrap(),
+            set("A", &["dp1", "Hdmi1"], 1)
+        );
+    }
+}
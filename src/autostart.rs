@@ -0,0 +1,212 @@
+use std::path::{Path, PathBuf};
+
+use super::*;
+use crate::logging::*;
+
+/// The name of the autostart entries.
+pub(crate) const AUTOSTART_NAME: &str = "monitor-input";
+
+/// The label of the LaunchAgent on macOS.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub(crate) const LAUNCH_AGENT_LABEL: &str = "io.github.kojiishi.monitor-input";
+
+/// The command line arguments to run the `daemon` at sign-in.
+fn daemon_args(exe: &Path, config_path: Option<&Path>) -> Vec<String> {
+    let mut args = vec![exe.display().to_string()];
+    if let Some(config_path) = config_path {
+        args.push("--config".to_string());
+        args.push(config_path.display().to_string());
+    }
+    args.push("daemon".to_string());
+    args
+}
+
+/// The executable to run at sign-in.
+/// On Windows, this is the `monitor-inputw.exe` if it's installed,
+/// so that no console windows pop up.
+fn autostart_exe() -> anyhow::Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    if cfg!(windows) {
+        let winapp = exe.with_file_name("monitor-inputw.exe");
+        if winapp.exists() {
+            return Ok(winapp);
+        }
+    }
+    Ok(exe)
+}
+
+/// Quote the `args` for the Windows command line.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) fn windows_command_line(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| format!("\"{arg}\""))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The XDG autostart desktop entry to run the `args`.
+/// See the [Desktop Application Autostart Specification].
+///
+/// [Desktop Application Autostart Specification]: https://specifications.freedesktop.org/autostart-spec/latest/
+#[cfg_attr(any(windows, target_os = "macos"), allow(dead_code))]
+pub(crate) fn xdg_desktop_entry(args: &[String]) -> String {
+    let exec = args
+        .iter()
+        .map(|arg| {
+            let mut quoted = String::from("\"");
+            for c in arg.chars() {
+                if matches!(c, '"' | '`' | '$' | '\\') {
+                    quoted.push('\\');
+                }
+                quoted.push(c);
+            }
+            quoted.push('"');
+            quoted
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "[Desktop Entry]
+Type=Application
+Name=monitor-input
+Comment=Change input sources of display monitors
+Exec={exec}
+Terminal=false
+X-GNOME-Autostart-enabled=true
+"
+    )
+}
+
+/// The LaunchAgent property list to run the `args` on macOS.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub(crate) fn launch_agent_plist(args: &[String]) -> String {
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    let args = args
+        .iter()
+        .map(|arg| format!("        <string>{}</string>\n", escape(arg)))
+        .collect::<String>();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LAUNCH_AGENT_LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+{args}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#
+    )
+}
+
+/// Add the autostart entry to run the `daemon` when the current user signs in.
+/// The `config_path` is kept in the entry if specified.
+///
+/// Fails if the `daemon` is already registered by the `service install`
+/// or the `--install-systemd`, so that it isn't started twice at sign-in.
+pub(crate) fn enable(config_path: Option<&Path>) -> anyhow::Result<()> {
+    if service::is_installed() {
+        anyhow::bail!(
+            "The `daemon` is already registered by {}. Uninstall it first.",
+            if cfg!(windows) {
+                "the `service install`"
+            } else {
+                "the `--install-systemd`"
+            }
+        );
+    }
+    let config_path = config_path.map(std::path::absolute).transpose()?;
+    let args = daemon_args(&autostart_exe()?, config_path.as_deref());
+    native().enable_autostart(&args)
+}
+
+/// Remove the autostart entry added by [`enable()`].
+/// It's not an error if it doesn't exist.
+pub(crate) fn disable() -> anyhow::Result<()> {
+    native().disable_autostart()
+}
+
+/// The location of the autostart entry if it's enabled.
+pub(crate) fn status() -> Option<String> {
+    native().autostart_status()
+}
+
+/// Write the autostart entry file of the `contents` to the `path`,
+/// on platforms where the entry is a file.
+#[cfg_attr(windows, allow(dead_code))]
+pub(crate) fn write_file(path: &Path, contents: &str) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, contents)?;
+    info!("Wrote {}", path.display());
+    Ok(())
+}
+
+/// Remove the autostart entry file written by [`write_file()`].
+/// It's not an error if it doesn't exist.
+#[cfg_attr(windows, allow(dead_code))]
+pub(crate) fn remove_file(path: &Path) -> anyhow::Result<()> {
+    match std::fs::remove_file(path) {
+        Ok(()) => info!("Removed {}", path.display()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args() -> Vec<String> {
+        daemon_args(
+            Path::new("/opt/my apps/monitor-input"),
+            Some(Path::new("/home/me/a&b.toml")),
+        )
+    }
+
+    #[test]
+    fn command_line() {
+        assert_eq!(
+            args(),
+            [
+                "/opt/my apps/monitor-input",
+                "--config",
+                "/home/me/a&b.toml",
+                "daemon"
+            ]
+        );
+        assert_eq!(
+            windows_command_line(&args()),
+            r#""/opt/my apps/monitor-input" "--config" "/home/me/a&b.toml" "daemon""#
+        );
+    }
+
+    #[test]
+    fn xdg_desktop_entry() {
+        let entry = super::xdg_desktop_entry(&args());
+        assert!(entry.starts_with("[Desktop Entry]\n"));
+        assert!(entry.contains(
+            "\nExec=\"/opt/my apps/monitor-input\" \"--config\" \"/home/me/a&b.toml\" \"daemon\"\n"
+        ));
+        let entry = super::xdg_desktop_entry(&[r#"a"$b"#.to_string()]);
+        assert!(entry.contains(r#"Exec="a\"\$b""#));
+    }
+
+    #[test]
+    fn launch_agent_plist() {
+        let plist = super::launch_agent_plist(&args());
+        assert!(plist.contains("<string>io.github.kojiishi.monitor-input</string>"));
+        assert!(plist.contains("<string>/home/me/a&amp;b.toml</string>"));
+        assert!(plist.contains("<string>daemon</string>\n    </array>"));
+    }
+}
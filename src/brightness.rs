@@ -0,0 +1,172 @@
+use jiff::Zoned;
+use jiff::civil::Time;
+
+use super::*;
+use crate::logging::*;
+
+/// Adjusts the brightness by the [`BrightnessConfig`] in the `daemon`.
+#[derive(Debug)]
+pub(crate) struct BrightnessController {
+    config: BrightnessConfig,
+    /// The [`BrightnessConfig::schedule`] parsed and sorted by the times.
+    schedule: Vec<(Time, u8)>,
+    stepped: SteppedController,
+}
+
+impl BrightnessController {
+    pub fn new(config: BrightnessConfig) -> anyhow::Result<Self> {
+        let mut schedule = config
+            .schedule
+            .iter()
+            .map(|point| {
+                let time: Time = point
+                    .time
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("Invalid time \"{}\": {e}", point.time))?;
+                if point.percent > 100 {
+                    anyhow::bail!("The brightness {}% is more than 100%", point.percent);
+                }
+                Ok((time, point.percent))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        schedule.sort();
+        if schedule.is_empty() && !config.sensor {
+            anyhow::bail!("`brightness` needs the `schedule` or the `sensor`");
+        }
+        if config.sensor && read_ambient_lux().is_none() {
+            warn!("No ambient light sensors found, the `schedule` is used instead.");
+        }
+        let stepped =
+            SteppedController::new(config.interval_secs, config.step_percent, config.step_ms);
+        Ok(Self {
+            config,
+            schedule,
+            stepped,
+        })
+    }
+
+    /// The [`SteppedController`] to time and step the adjustments.
+    pub fn stepped(&self) -> &SteppedController {
+        &self.stepped
+    }
+
+    /// The brightness in percent to adjust to, if it's time to adjust and it changed.
+    pub fn update(&mut self, now: &Zoned) -> Option<u8> {
+        let (config, schedule) = (&self.config, &self.schedule);
+        self.stepped.update(|| target(config, schedule, now))
+    }
+
+    /// Whether the `monitor` at `index` is adjusted, by [`BrightnessConfig::monitors`].
+    pub fn is_target(&self, index: usize, monitor: &Monitor) -> bool {
+        self.config.monitors.is_empty()
+            || self
+                .config
+                .monitors
+                .iter()
+                .any(|name| Selector::from_name(name).matches_monitor(index, monitor))
+    }
+}
+
+/// The brightness in percent from the ambient light sensor if available,
+/// or from the `schedule`.
+fn target(config: &BrightnessConfig, schedule: &[(Time, u8)], now: &Zoned) -> Option<u8> {
+    if config.sensor
+        && let Some(lux) = read_ambient_lux()
+    {
+        let percent = percent_from_lux(lux, config);
+        debug!("Ambient light {lux:.1} lux = {percent}%");
+        return Some(percent);
+    }
+    scheduled_percent(schedule, now.time())
+}
+
+/// The brightness at the `time`,
+/// interpolated linearly between the points of the `schedule` around it,
+/// across midnight.
+fn scheduled_percent(schedule: &[(Time, u8)], time: Time) -> Option<u8> {
+    let minutes = |time: Time| i32::from(time.hour()) * 60 + i32::from(time.minute());
+    let now = minutes(time);
+    let next_index = schedule
+        .iter()
+        .position(|(time, _)| minutes(*time) > now)
+        .unwrap_or(0);
+    let (next_time, next_percent) = *schedule.get(next_index)?;
+    let (prev_time, prev_percent) = schedule[(next_index + schedule.len() - 1) % schedule.len()];
+    let span = (minutes(next_time) - minutes(prev_time)).rem_euclid(24 * 60);
+    if span == 0 {
+        return Some(prev_percent);
+    }
+    let elapsed = (now - minutes(prev_time)).rem_euclid(24 * 60);
+    let percent = i32::from(prev_percent)
+        + (i32::from(next_percent) - i32::from(prev_percent)) * elapsed / span;
+    Some(percent as u8)
+}
+
+/// The brightness for the ambient light of `lux`,
+/// logarithmic as human eyes perceive.
+fn percent_from_lux(lux: f64, config: &BrightnessConfig) -> u8 {
+    let ratio = ((1.0 + lux.max(0.0)).ln() / (1.0 + config.max_lux.max(1.0)).ln()).min(1.0);
+    let min = f64::from(config.min_percent);
+    let max = f64::from(config.max_percent.max(config.min_percent));
+    (min + (max - min) * ratio).round() as u8
+}
+
+/// Read the ambient light in lux from the first sensor of the Industrial I/O subsystem.
+#[cfg(target_os = "linux")]
+fn read_ambient_lux() -> Option<f64> {
+    let read = |path: std::path::PathBuf| -> Option<f64> {
+        std::fs::read_to_string(path).ok()?.trim().parse().ok()
+    };
+    for entry in std::fs::read_dir("/sys/bus/iio/devices").ok()?.flatten() {
+        let dir = entry.path();
+        if let Some(lux) = read(dir.join("in_illuminance_input")) {
+            return Some(lux);
+        }
+        if let Some(raw) = read(dir.join("in_illuminance_raw")) {
+            let scale = read(dir.join("in_illuminance_scale")).unwrap_or(1.0);
+            let offset = read(dir.join("in_illuminance_offset")).unwrap_or(0.0);
+            return Some((raw + offset) * scale);
+        }
+    }
+    None
+}
+
+/// Ambient light sensors are available only on Linux.
+#[cfg(not(target_os = "linux"))]
+fn read_ambient_lux() -> Option<f64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: i8, minute: i8) -> Time {
+        Time::constant(hour, minute, 0, 0)
+    }
+
+    #[test]
+    fn scheduled_percent() {
+        let schedule = [(time(7, 0), 80), (time(19, 0), 20)];
+        let percent = |hour, minute| super::scheduled_percent(&schedule, time(hour, minute));
+        assert_eq!(percent(7, 0), Some(80));
+        assert_eq!(percent(13, 0), Some(50));
+        assert_eq!(percent(19, 0), Some(20));
+        assert_eq!(percent(1, 0), Some(50));
+        assert_eq!(
+            super::scheduled_percent(&schedule[..1], time(1, 0)),
+            Some(80)
+        );
+        assert_eq!(super::scheduled_percent(&[], time(1, 0)), None);
+    }
+
+    #[test]
+    fn percent_from_lux() {
+        let config = BrightnessConfig::default();
+        assert_eq!(super::percent_from_lux(0.0, &config), config.min_percent);
+        assert_eq!(super::percent_from_lux(100_000.0, &config), 100);
+        let dim = super::percent_from_lux(10.0, &config);
+        let bright = super::percent_from_lux(200.0, &config);
+        assert!(config.min_percent < dim && dim < bright && bright < 100);
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// A token to cancel long operations from other threads,
+/// such as when the user closes the window of a GUI host.
+///
+/// Clones share the same state,
+/// so cancelling one of them cancels all of them.
+/// Operations check it before each request to display monitors,
+/// before retries, and while waiting for display monitors to be ready.
+/// A request already sent to a display monitor can't be cancelled.
+/// # Examples
+/// ```
+/// # use monitor_input::{CancellationToken,Cancelled,MockMonitor,Monitor};
+/// let token = CancellationToken::new();
+/// let mut monitor = Monitor::from_device(MockMonitor::new("A"));
+/// monitor.set_cancellation_token(token.clone());
+/// std::thread::spawn(move || token.cancel()).join().unwrap();
+/// let error = monitor.input_source().unwrap_err();
+/// assert!(error.is::<Cancelled>());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    state: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl CancellationToken {
+    /// Create a new token that isn't cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the operations using this token,
+    /// and wake up threads in [`CancellationToken::sleep()`].
+    pub fn cancel(&self) {
+        let (is_cancelled, condvar) = &*self.state;
+        *is_cancelled.lock().unwrap() = true;
+        condvar.notify_all();
+    }
+
+    /// Whether [`CancellationToken::cancel()`] was called.
+    pub fn is_cancelled(&self) -> bool {
+        *self.state.0.lock().unwrap()
+    }
+
+    /// Returns the [`Cancelled`] error if cancelled.
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+
+    /// Sleep for the `duration`, or until cancelled.
+    /// Returns the [`Cancelled`] error if cancelled.
+    pub fn sleep(&self, duration: Duration) -> anyhow::Result<()> {
+        let deadline = Instant::now() + duration;
+        let (is_cancelled, condvar) = &*self.state;
+        let mut guard = is_cancelled.lock().unwrap();
+        while !*guard {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(());
+            }
+            guard = condvar.wait_timeout(guard, remaining).unwrap().0;
+        }
+        Err(Cancelled.into())
+    }
+}
+
+/// The error when an operation is cancelled by [`CancellationToken::cancel()`].
+/// Use [`anyhow::Error::is()`] to distinguish it from other errors.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sleep() {
+        let token = CancellationToken::new();
+        assert!(token.sleep(Duration::from_millis(1)).is_ok());
+        assert!(token.check().is_ok());
+
+        let canceller = token.clone();
+        let start = Instant::now();
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            canceller.cancel();
+        });
+        let error = token.sleep(Duration::from_secs(10)).unwrap_err();
+        assert!(error.is::<Cancelled>());
+        assert!(start.elapsed() < Duration::from_secs(10));
+        thread.join().unwrap();
+        assert!(token.is_cancelled());
+        assert!(token.check().is_err());
+    }
+}
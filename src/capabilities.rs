@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+
+use super::*;
+use crate::logging::*;
+
+/// The MCCS version to assume when [`salvage_vcp_section()`] can't find one.
+const SALVAGED_MCCS_VERSION: &str = "2.1";
+
+/// The parsed MCCS capabilities string of a display monitor.
+/// See [`Monitor::capabilities()`].
+/// # Examples
+/// ```
+/// # use monitor_input::Capabilities;
+/// let capabilities =
+///     Capabilities::parse("(prot(monitor)type(lcd)model(XYZ)mccs_ver(2.2)vcp(10 60(0F 11)))")
+///         .unwrap();
+/// assert_eq!(capabilities.model.as_deref(), Some("XYZ"));
+/// assert_eq!(capabilities.vcp_features[&0x60].values, [0x0F, 0x11]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Capabilities {
+    /// The protocol class, such as `monitor`.
+    pub protocol: Option<String>,
+    /// The type of the display, such as `lcd`.
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub display_type: Option<String>,
+    /// The model name.
+    pub model: Option<String>,
+    /// The MCCS version, such as `2.2`.
+    pub mccs_version: Option<String>,
+    /// The supported VCP feature codes.
+    pub vcp_features: BTreeMap<VcpCode, VcpFeature>,
+}
+
+/// A supported VCP feature code in the [`Capabilities`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct VcpFeature {
+    /// The name of the feature, if the display monitor provides one.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub name: Option<String>,
+    /// The allowed values for non-continuous features, such as input sources.
+    /// Empty for continuous features such as the brightness.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub values: Vec<u8>,
+}
+
+impl Capabilities {
+    /// Parse the MCCS capabilities string.
+    /// If it's broken, as some display monitors return,
+    /// the `vcp(...)` section is salvaged and the other sections are ignored.
+    /// See also [`DdcDevice::capabilities_string()`].
+    /// # Examples
+    /// ```
+    /// # use monitor_input::Capabilities;
+    /// let capabilities = Capabilities::parse("(prot(monitor)model(X\0vcp(60(0F 11)").unwrap();
+    /// assert_eq!(capabilities.model, None);
+    /// assert_eq!(capabilities.vcp_features[&0x60].values, [0x0F, 0x11]);
+    /// ```
+    pub fn parse(capabilities: impl AsRef<[u8]>) -> anyhow::Result<Self> {
+        let capabilities = parse_mccs_capabilities(capabilities.as_ref())?;
+        Ok(Self::from(&capabilities))
+    }
+}
+
+/// Parse the MCCS capabilities string by [`mccs_caps`],
+/// or only its `vcp(...)` section if the string is broken.
+pub(crate) fn parse_mccs_capabilities(capabilities: &[u8]) -> anyhow::Result<mccs::Capabilities> {
+    let error = match mccs_caps::parse_capabilities(capabilities) {
+        Ok(capabilities) => return Ok(capabilities),
+        Err(error) => error,
+    };
+    let Some(salvaged) = salvage_vcp_section(&String::from_utf8_lossy(capabilities)) else {
+        return Err(error.into());
+    };
+    match mccs_caps::parse_capabilities(&salvaged) {
+        Ok(capabilities) => {
+            warn!("Salvaged VCP features from the broken capabilities string: {error}");
+            Ok(capabilities)
+        }
+        Err(_) => Err(error.into()),
+    }
+}
+
+/// Create a capabilities string from the `vcp(...)` and the `mccs_ver(...)` sections
+/// of the broken `capabilities`.
+/// Anything in the `vcp(...)` section other than two-digit hexadecimal numbers is removed,
+/// and unbalanced parentheses are fixed.
+fn salvage_vcp_section(capabilities: &str) -> Option<String> {
+    let vcp = find_section(capabilities, "vcp")?;
+    let mut salvaged = String::new();
+    let mut depth = 0;
+    for token in vcp
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+    {
+        match token {
+            "(" => depth += 1,
+            ")" if depth == 0 => continue,
+            ")" => {
+                depth -= 1;
+                // Remove empty parentheses, such as those of removed sections.
+                if salvaged.ends_with('(') {
+                    salvaged.pop();
+                    continue;
+                }
+            }
+            word if word.len() == 2 && word.chars().all(|c| c.is_ascii_hexdigit()) => {
+                if salvaged.ends_with(|c: char| c != '(') {
+                    salvaged.push(' ');
+                }
+            }
+            _ => continue,
+        }
+        salvaged.push_str(token);
+    }
+    if !salvaged.contains(|c: char| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    while salvaged.ends_with('(') {
+        salvaged.pop();
+        depth -= 1;
+    }
+    salvaged.extend(std::iter::repeat_n(')', depth));
+    let version = find_section(capabilities, "mccs_ver")
+        .map(str::trim)
+        .filter(|version| {
+            version.split_once('.').is_some_and(|(major, minor)| {
+                [major, minor]
+                    .iter()
+                    .all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+            })
+        })
+        .unwrap_or(SALVAGED_MCCS_VERSION);
+    Some(format!("(vcp({salvaged})mccs_ver({version}))"))
+}
+
+/// The content of the `name(...)` section in the `capabilities`,
+/// up to the end of the string if the parenthesis isn't closed.
+fn find_section<'a>(capabilities: &'a str, name: &str) -> Option<&'a str> {
+    let start = capabilities
+        .to_ascii_lowercase()
+        .match_indices(&format!("{name}("))
+        .map(|(index, _)| index)
+        .find(|index| {
+            capabilities[..*index]
+                .chars()
+                .next_back()
+                .is_none_or(|c| !c.is_ascii_alphanumeric() && c != '_')
+        })?
+        + name.len()
+        + 1;
+    let mut depth = 1;
+    for (index, c) in capabilities[start..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&capabilities[start..start + index]);
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(&capabilities[start..])
+}
+
+impl From<&mccs::Capabilities> for Capabilities {
+    fn from(capabilities: &mccs::Capabilities) -> Self {
+        Self {
+            protocol: capabilities.protocol.as_ref().map(ToString::to_string),
+            display_type: capabilities.ty.as_ref().map(ToString::to_string),
+            model: capabilities.model.clone(),
+            mccs_version: capabilities.mccs_version.as_ref().map(ToString::to_string),
+            vcp_features: capabilities
+                .vcp_features
+                .iter()
+                .map(|(code, descriptor)| {
+                    (
+                        *code,
+                        VcpFeature {
+                            name: descriptor.name.clone(),
+                            values: descriptor.values().cloned().collect(),
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let capabilities = Capabilities::parse(
+            "(prot(monitor)type(LCD)model(U2723QE)cmds(01 02 03)vcp(02 10 12 60(0F 11 1B) DC(00 02))mccs_ver(2.1))",
+        )
+        .unwrap();
+        assert_eq!(capabilities.protocol.as_deref(), Some("monitor"));
+        assert_eq!(capabilities.display_type.as_deref(), Some("lcd"));
+        assert_eq!(capabilities.model.as_deref(), Some("U2723QE"));
+        assert_eq!(capabilities.mccs_version.as_deref(), Some("2.1"));
+        assert_eq!(
+            capabilities.vcp_features.keys().collect::<Vec<_>>(),
+            [&0x02, &0x10, &0x12, &0x60, &0xDC]
+        );
+        assert!(capabilities.vcp_features[&0x10].values.is_empty());
+        assert_eq!(capabilities.vcp_features[&0x60].values, [0x0F, 0x11, 0x1B]);
+        assert_eq!(capabilities.vcp_features[&0xDC].values, [0x00, 0x02]);
+    }
+
+    #[test]
+    fn salvage_vcp_section() {
+        use super::salvage_vcp_section;
+        assert_eq!(
+            salvage_vcp_section("(prot(monitor)model(X\0)vcp(10 60(0F 11))mccs_ver(2.2))")
+                .as_deref(),
+            Some("(vcp(10 60(0F 11))mccs_ver(2.2))")
+        );
+        assert_eq!(
+            salvage_vcp_section("(prot(monitor)vcp(60(0F 1\u{FFFD}x)mccs_ver(").as_deref(),
+            Some("(vcp(60(0F))mccs_ver(2.1))")
+        );
+        assert_eq!(
+            salvage_vcp_section("vcp(10 60(0F 11").as_deref(),
+            Some("(vcp(10 60(0F 11))mccs_ver(2.1))")
+        );
+        assert_eq!(salvage_vcp_section("(vcpname(10(x))"), None);
+        assert_eq!(salvage_vcp_section("(prot(monitor))"), None);
+    }
+
+    #[test]
+    fn parse_broken() {
+        let capabilities =
+            Capabilities::parse("(prot(monitor)vcp(10 60(0F 11 1B)) garbage((").unwrap();
+        assert_eq!(capabilities.mccs_version.as_deref(), Some("2.1"));
+        assert_eq!(capabilities.vcp_features[&0x60].values, [0x0F, 0x11, 0x1B]);
+        assert!(Capabilities::parse("(prot(monitor").is_err());
+    }
+}
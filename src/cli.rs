@@ -1,12 +1,17 @@
-use std::time::Instant;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use super::*;
-use clap::{ArgAction, Parser};
-use log::*;
-use regex::Regex;
+use crate::logging::*;
+use anyhow::Context;
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use strum::VariantArray;
 
 #[derive(Debug, Default, Parser)]
-#[command(version, about)]
+#[command(version, about, disable_version_flag = true)]
 /// A command line tool to change display monitors' input sources via DDC/CI.
 ///
 /// # Examples
@@ -21,29 +26,167 @@ use regex::Regex;
 /// To setup [`Cli`] from the command line arguments:
 /// ```no_run
 /// use clap::Parser;
-/// use monitor_input::{Cli,Monitor};
+/// use monitor_input::{Cli,MonitorSet};
 ///
 /// fn main() -> anyhow::Result<()> {
 ///     let mut cli = Cli::parse();
 ///     cli.init_logger();
-///     cli.monitors = Monitor::enumerate();
+///     cli.monitors = MonitorSet::enumerate();
 ///     cli.run()
 /// }
 /// ```
 /// See <https://github.com/kojiishi/monitor-input-rs> for more details.
 pub struct Cli {
     #[arg(skip)]
-    /// The list of [`Monitor`]s to run the command line tool on.
-    /// This field is usually initialized to [`Monitor::enumerate()`].
-    pub monitors: Vec<Monitor>,
+    /// The [`Monitor`]s to run the command line tool on.
+    /// This field is usually initialized to [`MonitorSet::enumerate()`].
+    pub monitors: MonitorSet,
+
+    #[arg(long = "config")]
+    /// The path of the configuration file.
+    pub config_path: Option<PathBuf>,
+
+    #[arg(skip)]
+    /// The configurations.
+    /// When this is `None`, [`Cli::run()`] loads it from [`Cli::config_path`].
+    pub config: Option<Config>,
+
+    #[arg(long = "state", value_name = "PATH")]
+    /// The path of the file to keep the states across runs, such as for `undo` and `--fast`.
+    /// The history is saved next to it.
+    /// The default is `monitor-input/state.json` in the state directory of the platform.
+    pub state_path: Option<PathBuf>,
 
     #[arg(short, long)]
     /// Filter by the backend name.
+    /// `ddcutil` uses the `ddcutil` command on Linux.
     pub backend: Option<String>,
 
-    #[arg(id = "capabilities", short, long)]
-    /// Get capabilities from the display monitors.
-    pub needs_capabilities: bool,
+    #[arg(long, value_name = "BACKENDS", value_delimiter = ',')]
+    /// For display monitors found by multiple backends,
+    /// use only the one of the first backend in this list, such as `winapi,nvapi`.
+    pub prefer_backend: Vec<String>,
+
+    #[arg(long, value_enum)]
+    /// The order of display monitors, which determines their indices.
+    /// The default is `none`, the order the backends enumerated them.
+    pub sort: Option<MonitorOrder>,
+
+    #[arg(long)]
+    /// Include internal panels such as laptop screens,
+    /// which are excluded because they don't support changing input sources.
+    pub include_internal: bool,
+
+    #[arg(long)]
+    /// Include display monitors that failed to communicate in the last runs,
+    /// which are skipped to avoid waiting for their timeouts.
+    pub include_unresponsive: bool,
+
+    #[arg(skip)]
+    /// The [`State::failures`] of the last run.
+    failures: BTreeMap<String, u32>,
+
+    #[arg(
+        id = "capabilities",
+        short,
+        long,
+        value_enum,
+        value_name = "STRATEGY",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "always"
+    )]
+    /// When to get capabilities from the display monitors.
+    /// `-c` is `--capabilities=always`.
+    /// It's `capabilities` in the configuration file, or `never`, if omitted.
+    pub capabilities: Option<CapabilitiesStrategy>,
+
+    #[arg(long, conflicts_with = "capabilities")]
+    /// Don't get capabilities, even if the configuration file specifies.
+    /// This is `--capabilities=never`.
+    pub no_capabilities: bool,
+
+    #[arg(skip)]
+    /// The capabilities strings saved for [`CapabilitiesStrategy::Cached`],
+    /// loaded from the [`State::capabilities`] on the first use.
+    capabilities_cache: Option<BTreeMap<String, CapabilitiesCacheEntry>>,
+
+    #[arg(skip)]
+    /// Whether the `capabilities_cache` has changes to save.
+    is_capabilities_cache_changed: bool,
+
+    #[arg(long, conflicts_with = "capabilities")]
+    /// Open only the display monitors found by the previous run,
+    /// instead of enumerating all, for lower latency.
+    /// It falls back to enumerating all if any of them can't be opened.
+    pub fast: bool,
+
+    #[arg(short, long, value_enum, default_value_t)]
+    /// The format of the display monitor listings.
+    pub format: OutputFormat,
+
+    #[arg(long, value_name = "MILLISECONDS")]
+    /// Additional delay after changes, for display monitors that need more time.
+    pub settle_ms: Option<u64>,
+
+    #[arg(long, value_name = "FORMAT")]
+    /// Display monitors by the format, such as `"{model} ({serial})"`,
+    /// in logs, listings, and notifications.
+    /// The placeholders are `{id}`, `{model}`, `{serial}`, `{manufacturer}`,
+    /// `{backend}`, and `{output}`.
+    /// The formatted names can also be used to match display monitors.
+    pub id_format: Option<String>,
+
+    #[arg(long, hide = true, value_name = "PATTERN")]
+    /// Simulate failures of display monitors, for testing retries and error handling.
+    /// The pattern is `[NAME=]KIND[*COUNT]`, such as `nack` or `B=timeout*2`,
+    /// where `KIND` is `timeout`, `nack`, `garbage-capabilities`, or `stale-handle`.
+    pub simulate_failure: Vec<SimulatedFailure>,
+
+    #[arg(long, conflicts_with = "exactly_one")]
+    /// Use only the first display monitor when a name matches multiple display monitors.
+    pub first: bool,
+
+    #[arg(long, visible_alias = "one")]
+    /// Fail when a name matches multiple display monitors.
+    pub exactly_one: bool,
+
+    #[arg(long, conflicts_with_all = ["first", "exactly_one"])]
+    /// Change all display monitors when a part of names matches multiple display monitors.
+    pub all: bool,
+
+    #[arg(long)]
+    /// Set input sources even if they're not in the capabilities,
+    /// or if they're already the current input sources.
+    pub force: bool,
+
+    #[arg(long, value_enum)]
+    /// Print the results of changes in the format.
+    pub report: Option<ReportFormat>,
+
+    #[arg(long, value_name = "NAME")]
+    /// Set the display monitors to the profile in the configuration file,
+    /// in addition to the `args`,
+    /// with the configurations of the profile in `profile_settings`.
+    pub profile: Option<String>,
+
+    #[arg(long, value_name = "NAME=INPUT")]
+    /// Change the display monitors only if the display monitor `NAME`
+    /// is currently on the `INPUT`, such as `DELL=dp1`.
+    /// The `NAME` must match one display monitor.
+    /// When specified more than once, all of them must be met.
+    /// This applies only to the `args` and the `--profile`, not to commands.
+    pub input_of: Vec<ProfileEntry>,
+
+    #[arg(long, value_name = "BYTES")]
+    /// Send the raw DDC/CI command to the display monitor of `args`, such as `"C2 01"`,
+    /// for vendor-specific commands.
+    /// The `args` must match exactly one display monitor.
+    pub raw_ddc: Option<String>,
+
+    #[arg(long, value_name = "LENGTH", requires = "raw_ddc", default_value_t)]
+    /// Read and print a reply of up to `LENGTH` bytes after `--raw-ddc`.
+    pub raw_ddc_read: usize,
 
     #[arg(short = 'n', long)]
     /// Dry-run (prevent actual changes).
@@ -53,178 +196,2772 @@ pub struct Cli {
     /// Show verbose information.
     pub verbose: u8,
 
+    #[arg(short, long, conflicts_with = "verbose")]
+    /// Suppress informational messages.
+    pub quiet: bool,
+
+    #[arg(long, value_enum, default_value_t)]
+    /// The format of log messages.
+    pub log_format: LogFormat,
+
+    #[arg(long, value_name = "PATH")]
+    /// Write log messages to the file, rotating by its size.
+    pub log_file: Option<PathBuf>,
+
     #[arg(skip)]
     set_index: Option<usize>,
 
-    /// `name` to search,
-    /// `name=input` to change the input source,
-    /// or `name=input1,input2` to toggle.
-    pub args: Vec<String>,
-}
+    #[arg(long, value_parser = parse_duration, conflicts_with = "at")]
+    /// Wait for the duration before changes, such as `5s` or `1m30s`.
+    pub delay: Option<Duration>,
+
+    #[arg(long, value_name = "NAME")]
+    /// Wait until a display monitor matching the name is found,
+    /// such as after booting or docking.
+    pub wait_for: Option<String>,
+
+    #[arg(long, value_parser = parse_duration, value_name = "DURATION", requires = "wait_for")]
+    /// The maximum duration of `--wait-for`. The default is 30 seconds.
+    pub wait_timeout: Option<Duration>,
+
+    #[arg(long, value_parser = parse_duration, value_name = "DURATION")]
+    /// Skip display monitors not found within the duration, such as `3s`,
+    /// for GPU drivers that take long to enumerate.
+    pub enumerate_timeout: Option<Duration>,
+
+    #[arg(long, value_name = "HH:MM")]
+    /// Wait until the time of the day before changes.
+    pub at: Option<jiff::civil::Time>,
+
+    #[arg(long)]
+    /// Cycle toggles backwards.
+    pub reverse: bool,
+
+    #[arg(long)]
+    /// Restore the input sources changed by the arguments when any of the changes fail.
+    pub rollback: bool,
+
+    #[arg(long)]
+    /// Confirm or refuse changes that leave no display monitors showing this computer.
+    pub safe: bool,
+
+    #[arg(long, value_name = "HOST[:PORT]")]
+    /// Run on the `daemon --listen` on the host.
+    pub host: Option<String>,
+
+    #[arg(long)]
+    /// Install and start a systemd user unit to run the `daemon` on Linux.
+    pub install_systemd: bool,
+
+    #[arg(long, conflicts_with = "unregister")]
+    /// Register the application identity for toast notifications
+    /// and the URI scheme of their actions for the current user on Windows.
+    /// Not needed when installed by a MSIX package.
+    pub register: bool,
+
+    #[arg(long)]
+    /// Remove the registrations by `--register`.
+    pub unregister: bool,
+
+    #[arg(short = 'V', long)]
+    /// Print version.
+    pub version: bool,
+
+    #[arg(long, requires = "version")]
+    /// Print the version, the enabled features, and the available backends in JSON,
+    /// with `--version`.
+    pub json: bool,
+
+    #[arg(skip)]
+    /// The standard output to send to `--host`, instead of printing.
+    output: Option<Vec<u8>>,
+
+    #[arg(skip)]
+    safe_guard: Option<SafeGuard>,
+
+    #[arg(skip)]
+    session: Session,
+
+    #[arg(skip)]
+    /// The token to cancel the requests to the display monitors.
+    /// See [`Cli::cancellation_token()`].
+    cancellation: CancellationToken,
+
+    #[arg(skip)]
+    set_results: Vec<SetResult>,
+
+    #[arg(skip)]
+    /// The args run by [`Cli::run_args()`],
+    /// with their ranges in the `set_results` and their errors.
+    arg_results: Vec<(String, std::ops::Range<usize>, Option<String>)>,
+
+    #[arg(skip)]
+    run_report: RunReport,
+
+    #[command(subcommand)]
+    /// The subcommand to run instead of `args`.
+    pub command: Option<Command>,
+
+    /// `name` to search,
+    /// where `name` is a part of the names, an index, or a group name,
+    /// `name=input` to change the input source,
+    /// or `name=input1,input2` to toggle,
+    /// where `name=input1,input2,input3~N` advances `N` entries,
+    /// or `name?=input1:input2` to change to `input2` only if it's `input1`,
+    /// or `name.pbp=mode` to change the picture-by-picture mode, such as `2x1`.
+    pub args: Vec<String>,
+}
+
+/// Parse hexadecimal bytes such as `C2 01`, `0xC2,0x01`, or `C201`.
+fn parse_hex_bytes(value: &str) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    for token in value.split([' ', ',']).filter(|token| !token.is_empty()) {
+        let token = token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("0X"))
+            .unwrap_or(token);
+        if token.len() % 2 != 0 && token.len() != 1 {
+            anyhow::bail!("Invalid hexadecimal bytes: \"{value}\"");
+        }
+        for start in (0..token.len()).step_by(2) {
+            let digits = token.get(start..token.len().min(start + 2));
+            bytes.push(
+                digits
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                    .with_context(|| format!("Invalid hexadecimal bytes: \"{value}\""))?,
+            );
+        }
+    }
+    Ok(bytes)
+}
+
+/// The default of [`Cli::wait_timeout`].
+const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The interval to enumerate display monitors for [`Cli::wait_for`].
+const WAIT_FOR_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Parse a duration such as `5s`, `1m30s`, or `500ms`.
+fn parse_duration(value: &str) -> anyhow::Result<Duration> {
+    let duration: jiff::SignedDuration = value.parse()?;
+    Ok(Duration::try_from(duration)?)
+}
+
+/// The keys of the `monitors` in the [`Cli::failures`].
+/// The [`Monitor::id()`] isn't unique, such as "Generic PnP Monitor" of the `winapi` backend,
+/// so the name of the [`Monitor::display_output()`] is appended if it's known,
+/// and the index among the display monitors with the same key is appended if it's not unique.
+fn failure_keys(monitors: &[Monitor]) -> Vec<String> {
+    let keys: Vec<String> = monitors
+        .iter()
+        .map(|monitor| match monitor.display_output() {
+            Some(output) => format!("{} {}", monitor.id(), output.name),
+            None => monitor.id().to_string(),
+        })
+        .collect();
+    keys.iter()
+        .enumerate()
+        .map(
+            |(i, key)| match keys[..i].iter().filter(|other| *other == key).count() {
+                0 => key.clone(),
+                index => format!("{key} #{index}"),
+            },
+        )
+        .collect()
+}
+
+#[derive(Debug, Default)]
+/// The states of display monitors read in a run of the [`Cli::args`],
+/// so that following args reuse them instead of reading them again,
+/// such as `A=DP1,HDMI1 A?=DP1:USBC1 A`.
+/// The keys are the indices of the [`Cli::monitors`].
+/// The input sources are reused by [`Monitor::cached_input_source()`].
+/// See also [`Cli::start_session()`].
+struct Session {
+    /// The display monitors whose capabilities are prepared.
+    capabilities: BTreeSet<usize>,
+}
+
+#[derive(Debug, Default)]
+/// The states of changing input sources.
+/// They're moved out of [`Cli`] while [`Cli::for_each()`] borrows it.
+struct SetContext {
+    force: bool,
+    safe_guard: Option<SafeGuard>,
+    set_results: Vec<SetResult>,
+    session: Session,
+}
+
+impl SetContext {
+    /// Check if `input_source` is in the capabilities of the `monitor`.
+    /// Unsupported values are errors unless `force`.
+    fn validate_input_source(
+        &self,
+        monitor: &mut Monitor,
+        input_source: InputSourceRaw,
+    ) -> anyhow::Result<()> {
+        if monitor.supports_feature(INPUT_SELECT) == Some(false) {
+            let message = format!(
+                "{monitor}: Changing input sources isn't supported \
+                (the VCP code {INPUT_SELECT:02X} is not in the capabilities)"
+            );
+            if !self.force {
+                anyhow::bail!("{message}. Use `--force` to set it anyway.");
+            }
+            warn!("{message}");
+            return Ok(());
+        }
+        if let Some(input_sources) = monitor.input_sources()
+            && !input_sources.contains(&input_source)
+        {
+            let message = format!(
+                "{monitor}: {value} is not in the supported input sources ({supported})",
+                value = InputSource::str_from_raw(input_source),
+                supported = input_sources
+                    .iter()
+                    .map(|value| InputSource::str_from_raw(*value))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            if !self.force {
+                anyhow::bail!("{message}. Use `--force` to set it anyway.");
+            }
+            warn!("{message}");
+        }
+        Ok(())
+    }
+
+    fn set_input_source(
+        &mut self,
+        index: usize,
+        monitor: &mut Monitor,
+        input_source: InputSourceRaw,
+    ) -> anyhow::Result<()> {
+        let previous = monitor.cached_input_source().ok();
+        if previous == Some(input_source) && !self.force {
+            // Avoid the on-screen display and the delay after changes.
+            info!(
+                "{}",
+                tr_args(
+                    "InputSource({monitor}) = {value}{mode}",
+                    &[
+                        ("monitor", monitor),
+                        ("value", &InputSource::str_from_raw(input_source)),
+                        ("mode", &format!(" {}", tr("(no change)")))
+                    ]
+                )
+            );
+            let mut result = SetResult::new(index, monitor, input_source, previous, &Ok(()));
+            result.skipped = true;
+            self.set_results.push(result);
+            return Ok(());
+        }
+        let result = self
+            .validate_input_source(monitor, input_source)
+            .and_then(|_| match &self.safe_guard {
+                Some(safe_guard) => safe_guard.check(index, monitor, input_source),
+                None => Ok(()),
+            })
+            .and_then(|_| monitor.set_input_source(input_source));
+        if result.is_ok()
+            && let Some(safe_guard) = &mut self.safe_guard
+        {
+            safe_guard.update(index, input_source);
+        }
+        self.set_results.push(SetResult::new(
+            index,
+            monitor,
+            input_source,
+            previous,
+            &result,
+        ));
+        result
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, ValueEnum)]
+/// The format of log messages. See [`Cli::log_format`].
+pub enum LogFormat {
+    #[default]
+    /// Human-readable text.
+    Text,
+    /// A JSON object per line.
+    Json,
+}
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+/// When to get capabilities from display monitors. See [`Cli::capabilities`].
+/// Getting capabilities is slow on some display monitors,
+/// but it's needed to validate input sources,
+/// and to list their input sources and model names.
+pub enum CapabilitiesStrategy {
+    #[default]
+    /// Never get capabilities.
+    Never,
+    /// Get capabilities of the display monitors that matched the names.
+    Lazy,
+    /// Get capabilities of all display monitors before matching names,
+    /// so that the model names in capabilities can match.
+    Always,
+    /// Same as `always`, but reuse the capabilities saved by previous runs.
+    Cached,
+}
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+/// The order of display monitors, which determines their indices. See [`Cli::sort`].
+pub enum MonitorOrder {
+    #[default]
+    /// The order the backends enumerated them,
+    /// which may vary across runs and platforms.
+    None,
+    /// By the backends, then by the EDIDs and the serial numbers.
+    /// See [`MonitorSet::sort_stable()`].
+    Stable,
+    /// By the [`Monitor::id()`].
+    Id,
+    /// By the names of the [`Monitor::display_output()`],
+    /// such as `\\.\DISPLAY1` on Windows.
+    /// Display monitors without the display outputs are at the end.
+    Output,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+/// Subcommands of [`Cli`].
+pub enum Command {
+    /// Undo the changes of the last run.
+    Undo,
+
+    /// Keep running, and run the `schedules` in the configuration file.
+    Daemon {
+        #[arg(long)]
+        /// Print the next runs of the schedules and exit.
+        next: bool,
+
+        #[arg(long, value_name = "ADDRESS[:PORT]")]
+        /// Listen to requests from `--host` at the address, such as `0.0.0.0`.
+        /// Addresses other than the loopback require the `token` in the configuration file.
+        listen: Option<String>,
+
+        #[arg(long, value_name = "ADDRESS[:PORT]")]
+        /// Listen to WebSocket connections at the address, such as from Stream Deck plugins.
+        /// Requires the `websocket` feature.
+        websocket: Option<String>,
+    },
+
+    /// Check if the display monitors are in the input sources of the profile.
+    /// It's an error if any of them aren't.
+    Status {
+        /// The name of the profile in the configuration file.
+        profile: String,
+    },
+
+    /// Check if the display monitors respond to DDC/CI,
+    /// by reading their capabilities and some VCP features without changing them.
+    /// It's an error if any of the checks fail.
+    Selftest {
+        /// The display monitors to check, as in `name` of `args`.
+        name: String,
+    },
+
+    /// Read the VCP features of the display monitors in the range of codes,
+    /// and print their values with their names if known,
+    /// to discover controls not in the capabilities.
+    /// This only reads, and doesn't change anything.
+    Probe {
+        /// The display monitors to probe, as in `name` of `args`.
+        name: String,
+
+        #[arg(long, value_name = "START-END", default_value = "00-FF")]
+        /// The range of the VCP codes in hexadecimal, such as `E0-FF`.
+        range: String,
+    },
+
+    /// Print a line whenever the input source of any of the display monitors changes,
+    /// with the time, the display monitor, and the previous and the new input sources,
+    /// to debug KVM switches and on-screen displays.
+    /// This only reads, and doesn't change anything.
+    WatchInputs {
+        #[arg(default_value = "")]
+        /// The display monitors to watch, as in `name` of `args`. The default is all.
+        name: String,
+
+        #[arg(long, value_parser = parse_duration, value_name = "DURATION")]
+        /// The interval to read the input sources, such as `500ms`.
+        /// The default is `interval_secs` in the `[watch]` configuration.
+        interval: Option<Duration>,
+    },
+
+    /// Print the history of changes of input sources, the oldest first.
+    History {
+        #[arg(long)]
+        /// Print only the display monitors whose names contain this.
+        monitor: Option<String>,
+
+        #[arg(long, value_name = "N")]
+        /// Print only the last `N` changes.
+        last: Option<usize>,
+    },
+
+    /// Allow the current user to use DDC/CI on Linux,
+    /// by loading the `i2c-dev` kernel module and adding the user to the `i2c` group.
+    /// Prints the commands to run, unless `--print-udev` or `--install`.
+    SetupPermissions {
+        #[arg(long)]
+        /// Print the udev rules file that allows the `i2c` group to use `/dev/i2c-*`.
+        print_udev: bool,
+
+        #[arg(long, conflicts_with = "print_udev")]
+        /// Run the commands by `sudo`.
+        install: bool,
+    },
+
+    /// Run the `daemon` when signing in to Windows.
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+
+    /// Run the `daemon` when signing in, by the autostart entry of the platform:
+    /// the `Run` registry key on Windows, a LaunchAgent on macOS,
+    /// or an XDG autostart desktop file on Linux.
+    Autostart {
+        #[command(subcommand)]
+        action: AutostartAction,
+    },
+
+    /// Check the `rules` in the configuration file,
+    /// or preview what they run for an event.
+    Rules {
+        #[command(subcommand)]
+        action: RulesAction,
+    },
+
+    /// Print, create, validate, import, or export the configuration file.
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Subcommand)]
+/// Subcommands of [`Command::Service`].
+pub enum ServiceAction {
+    /// Register the `daemon` to run when the current user signs in.
+    Install,
+    /// Unregister the `daemon` registered by `install`.
+    Uninstall,
+    /// Run the `daemon`. This is what the registered task runs.
+    Run,
+}
+
+#[derive(Clone, Debug, PartialEq, Subcommand)]
+/// Subcommands of [`Command::Autostart`].
+pub enum AutostartAction {
+    /// Add the autostart entry for the current user.
+    Enable,
+    /// Remove the autostart entry added by `enable`.
+    Disable,
+    /// Print whether the autostart entry exists, and where it is.
+    Status,
+}
+
+#[derive(Clone, Debug, PartialEq, Subcommand)]
+/// Subcommands of [`Command::Config`].
+pub enum ConfigAction {
+    /// Print the configurations in TOML.
+    Show {
+        #[arg(long, value_name = "NAME")]
+        /// Print the configurations for the display monitor `NAME` instead,
+        /// merged from `defaults`, `monitors`, `profile_settings` of `--profile`,
+        /// and the command line options.
+        effective: Option<String>,
+    },
+
+    /// Create a configuration file with the display monitors connected now.
+    Init {
+        #[arg(long)]
+        /// Overwrite the configuration file if it exists.
+        force: bool,
+    },
+
+    /// Check the configuration file against the display monitors connected now.
+    /// It's an error if there are input sources that can't be parsed,
+    /// or rules that conflict.
+    Validate,
+
+    /// Convert the configuration file of another tool to profiles,
+    /// or to the `usb` table for display-switch, and print them in TOML.
+    Import {
+        /// The file to convert, such as `display-switch.ini`.
+        file: PathBuf,
+
+        #[arg(long, value_enum)]
+        /// The format of the `file`.
+        /// It's guessed from the extension if omitted:
+        /// `.cfg` for `controlmymonitor`, `.ini` for `display-switch`, or `ddcutil`.
+        from: Option<ProfileFormat>,
+
+        #[arg(long, default_value = "imported")]
+        /// The name of the profile.
+        /// Not used for `display-switch`.
+        name: String,
+
+        #[arg(long)]
+        /// Add the profiles to the configuration file instead of printing them.
+        /// Comments in the configuration file are not kept.
+        save: bool,
+    },
+
+    /// Print the profile in the format of another tool.
+    Export {
+        /// The name of the profile in the configuration file.
+        profile: String,
+
+        #[arg(long, value_enum)]
+        /// The format to print.
+        to: ProfileFormat,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Subcommand)]
+/// Subcommands of [`Command::Rules`].
+pub enum RulesAction {
+    /// Print the rules if they're valid,
+    /// or preview what they run for the `EVENT`, such as `hotplug` or `idle(10)`,
+    /// without changing display monitors.
+    Test {
+        /// The event to preview.
+        event: Option<String>,
+    },
+}
+
+impl Cli {
+    /// Construct an instance with display monitors from [`Monitor::enumerate()`].
+    pub fn new() -> Self {
+        Cli {
+            monitors: MonitorSet::enumerate(),
+            ..Default::default()
+        }
+    }
+
+    /// Initialize the logging.
+    /// The configurations depend on [`Cli::verbose`].
+    #[deprecated(
+        since = "1.2.1",
+        note = "To remove loggers from the lib. Please setup your favorite logger."
+    )]
+    pub fn init_logger(&self) {
+        simplelog::CombinedLogger::init(vec![simplelog::TermLogger::new(
+            match self.verbose {
+                0 => LevelFilter::Info,
+                1 => LevelFilter::Debug,
+                _ => LevelFilter::Trace,
+            },
+            simplelog::ConfigBuilder::new()
+                .set_time_level(LevelFilter::Debug)
+                .build(),
+            simplelog::TerminalMode::Mixed,
+            simplelog::ColorChoice::Auto,
+        )])
+        .unwrap();
+    }
+
+    /// Open the log file of [`Cli::log_file`],
+    /// or of the [`LogConfig::file`] if [`Cli::config`] is loaded.
+    pub fn open_log_file(&self) -> anyhow::Result<Option<RotatingFile>> {
+        let default_config = LogConfig::default();
+        let config = self
+            .config
+            .as_ref()
+            .map_or(&default_config, |config| &config.log);
+        let Some(path) = self.log_file.as_ref().or(config.file.as_ref()) else {
+            return Ok(None);
+        };
+        let file = RotatingFile::new(path, config.max_bytes, config.max_files)
+            .with_context(|| format!("Failed to open the log file {path:?}"))?;
+        Ok(Some(file))
+    }
+
+    /// Enumerate display monitors for the [`Cli::backend`].
+    /// This is [`MonitorSet::enumerate()`],
+    /// except the `ddcutil` backend on Linux uses the [`DdcutilDevice`],
+    /// and it's empty for [`Cli::host`], [`Cli::version`], and [`Command::SetupPermissions`].
+    pub fn enumerate_monitors(&self) -> anyhow::Result<MonitorSet> {
+        if self.host.is_some()
+            || self.version
+            || matches!(self.command, Some(Command::SetupPermissions { .. }))
+        {
+            // The `--host` doesn't use display monitors of this computer.
+            // The `setup-permissions` is for when they can't be used.
+            return Ok(MonitorSet::default());
+        }
+        #[cfg(target_os = "linux")]
+        if self.backend.as_deref() == Some("ddcutil") {
+            let mut monitors: Vec<Monitor> = DdcutilDevice::enumerate()?
+                .into_iter()
+                .map(Monitor::from_device)
+                .collect();
+            assign_display_outputs(&mut monitors);
+            return Ok(monitors.into());
+        }
+        if self.fast {
+            match self.open_cached_monitors() {
+                Ok(monitors) => return Ok(monitors.into()),
+                Err(e) => info!("Enumerating all display monitors, because `--fast` failed: {e}"),
+            }
+        }
+        self.cancellation.check()?;
+        let mut monitors = match self.enumerate_timeout {
+            Some(timeout) => MonitorSet::enumerate_with_timeout(timeout),
+            None => MonitorSet::enumerate(),
+        };
+        monitors.set_cancellation_token(&self.cancellation)?;
+        diagnose_monitors(&monitors);
+        self.save_monitor_cache(&monitors);
+        Ok(monitors)
+    }
+
+    /// The [`Cli::state_path`], or the default path if it's not set.
+    fn state_path_or_default(&self) -> Option<PathBuf> {
+        self.state_path.clone().or_else(State::default_path)
+    }
+
+    /// Load the [`State`] from the [`Cli::state_path_or_default()`].
+    fn load_state(&self) -> anyhow::Result<State> {
+        State::load(self.state_path_or_default().as_deref())
+    }
+
+    /// Save the [`State`] to the [`Cli::state_path_or_default()`].
+    fn save_state(&self, state: &State) -> anyhow::Result<()> {
+        state.save(self.state_path_or_default().as_deref())
+    }
+
+    /// Open the display monitors saved by [`Cli::save_monitor_cache()`], for `--fast`.
+    fn open_cached_monitors(&self) -> anyhow::Result<Vec<Monitor>> {
+        let state = self.load_state()?;
+        if state.monitors.is_empty() {
+            anyhow::bail!("No display monitors are saved by previous runs");
+        }
+        Monitor::open_cached(&state.monitors)
+    }
+
+    /// Save the `monitors` that [`Monitor::open_cached()`] can open, for `--fast`.
+    /// Other display monitors are skipped,
+    /// such as those of backends that are slow to open.
+    fn save_monitor_cache(&self, monitors: &[Monitor]) {
+        let entries: Vec<MonitorCacheEntry> = monitors
+            .iter()
+            .filter(|monitor| MonitorCacheEntry::is_supported_backend(&monitor.backend()))
+            .map(Monitor::cache_entry)
+            .collect();
+        let mut state = self.load_state().unwrap_or_default();
+        if state.monitors == entries {
+            return;
+        }
+        state.monitors = entries;
+        if let Err(e) = self.save_state(&state) {
+            debug!("Failed to save the display monitors: {e}");
+        }
+    }
+
+    /// Enumerate display monitors until one of them matches the `name`,
+    /// up to [`Cli::wait_timeout`].
+    /// Display monitors excluded by [`Cli::apply_filters()`] don't match.
+    fn wait_for_monitor(&mut self, name: &str) -> anyhow::Result<()> {
+        let selector = Selector::parse(name, self.config.get_or_insert_default())?;
+        let timeout = self.wait_timeout.unwrap_or(DEFAULT_WAIT_TIMEOUT);
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self
+                .monitors
+                .iter()
+                .enumerate()
+                .any(|(index, monitor)| selector.matches_monitor(index, monitor))
+            {
+                return Ok(());
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                anyhow::bail!(
+                    "{} (waited for {timeout:?})",
+                    tr_args(
+                        "No display monitors found for \"{name}\".",
+                        &[("name", &name)]
+                    )
+                );
+            }
+            debug!("Waiting for \"{name}\"");
+            self.cancellation.sleep(WAIT_FOR_INTERVAL.min(remaining))?;
+            self.monitors = self.enumerate_monitors()?;
+            self.apply_filters()?;
+        }
+    }
+
+    fn apply_filters(&mut self) -> anyhow::Result<()> {
+        let mut monitors = std::mem::take(&mut self.monitors);
+        self.remove_excluded(&mut monitors);
+        if self.prefer_backend.is_empty() {
+            diagnose_duplicates(&monitors);
+        } else {
+            monitors.prefer_backends(&self.prefer_backend);
+        }
+        self.sort_monitors(&mut monitors);
+        self.monitors = monitors;
+        Ok(())
+    }
+
+    /// Sort the `monitors` by [`Cli::sort`] or [`Config::sort`].
+    fn sort_monitors(&mut self, monitors: &mut MonitorSet) {
+        let order = self
+            .sort
+            .or_else(|| self.config.get_or_insert_default().sort)
+            .unwrap_or_default();
+        match order {
+            MonitorOrder::None => {}
+            MonitorOrder::Stable => monitors.sort_stable(),
+            MonitorOrder::Id => monitors.sort_by(|a, b| a.id().cmp(b.id())),
+            MonitorOrder::Output => monitors.sort_by_key(|monitor| {
+                monitor
+                    .display_output()
+                    .map_or((true, String::new()), |output| (false, output.name.clone()))
+            }),
+        }
+    }
+
+    /// Remove the display monitors excluded by [`Cli::is_excluded()`].
+    fn remove_excluded(&self, monitors: &mut MonitorSet) {
+        let mut keys = failure_keys(monitors).into_iter();
+        monitors.retain(|monitor| !self.is_excluded(monitor, &keys.next().unwrap()));
+    }
+
+    /// Whether the `monitor` is excluded by `--backend`, `--include-internal`,
+    /// or `--include-unresponsive`.
+    /// The `failure_key` is the key of the `monitor` in the [`Cli::failures`].
+    fn is_excluded(&self, monitor: &Monitor, failure_key: &str) -> bool {
+        if let Some(backend_str) = &self.backend
+            && !monitor.contains_backend(backend_str)
+        {
+            return true;
+        }
+        if !self.include_internal && monitor.is_internal() {
+            debug!("Excluded the internal panel {monitor}. Use `--include-internal`.");
+            return true;
+        }
+        if !self.include_unresponsive && self.is_unresponsive(failure_key) {
+            info!("Excluded the unresponsive {monitor}. Use `--include-unresponsive`.");
+            return true;
+        }
+        false
+    }
+
+    /// Whether the display monitor of the `failure_key` failed to communicate
+    /// in the [`Config::unresponsive_after`] runs in a row.
+    fn is_unresponsive(&self, failure_key: &str) -> bool {
+        let threshold = self.unresponsive_after();
+        threshold > 0
+            && self
+                .failures
+                .get(failure_key)
+                .is_some_and(|count| *count >= threshold)
+    }
+
+    fn unresponsive_after(&self) -> u32 {
+        self.config
+            .as_ref()
+            .and_then(|config| config.unresponsive_after)
+            .unwrap_or(Config::DEFAULT_UNRESPONSIVE_AFTER)
+    }
+
+    /// Update the [`Cli::failures`] by [`Monitor::is_responsive()`],
+    /// and save them if they're changed.
+    fn save_failures(&mut self) {
+        let mut failures = self.failures.clone();
+        let threshold = self.unresponsive_after();
+        for (monitor, key) in self.monitors.iter().zip(failure_keys(&self.monitors)) {
+            match monitor.is_responsive() {
+                Some(true) => {
+                    failures.remove(&key);
+                }
+                Some(false) => {
+                    let count = failures.entry(key).or_default();
+                    *count += 1;
+                    if *count == threshold {
+                        warn!(
+                            "{monitor} failed {count} times in a row, and will be skipped. \
+                            Use `--include-unresponsive` to retry."
+                        );
+                    }
+                }
+                None => {}
+            }
+        }
+        if failures == self.failures {
+            return;
+        }
+        self.failures = failures;
+        let Ok(mut state) = self.load_state() else {
+            return;
+        };
+        state.failures = self.failures.clone();
+        if let Err(e) = self.save_state(&state) {
+            debug!("Failed to save the failures: {e}");
+        }
+    }
+
+    /// The [`CapabilitiesStrategy`] by the options, or by the configurations.
+    fn capabilities_strategy(&self) -> CapabilitiesStrategy {
+        if self.no_capabilities {
+            return CapabilitiesStrategy::Never;
+        }
+        self.capabilities
+            .or_else(|| self.config.as_ref().and_then(|config| config.capabilities))
+            .unwrap_or_default()
+    }
+
+    /// Update the capabilities of the display monitor at `index`,
+    /// from the `capabilities_cache` if it's [`CapabilitiesStrategy::Cached`].
+    fn update_capabilities(&mut self, index: usize) {
+        if !self.session.capabilities.insert(index) {
+            return;
+        }
+        let is_cached = self.capabilities_strategy() == CapabilitiesStrategy::Cached;
+        if !is_cached {
+            // This may fail in some cases. Print warning but keep looking.
+            let _ = self.monitors[index].update_capabilities();
+            return;
+        }
+        if self.capabilities_cache.is_none() {
+            self.capabilities_cache = Some(
+                self.load_state()
+                    .map(|state| state.capabilities)
+                    .unwrap_or_default(),
+            );
+        }
+        let monitor = &mut self.monitors[index];
+        let cache = self.capabilities_cache.as_mut().unwrap();
+        if let Some(entry) = cache.get(monitor.id())
+            && entry.edid.as_deref() == monitor.edid()
+            && monitor.set_capabilities_string(&entry.capabilities).is_ok()
+        {
+            return;
+        }
+        if monitor.update_capabilities().is_err() {
+            return;
+        }
+        if let Some(capabilities) = monitor.capabilities_string() {
+            cache.insert(
+                monitor.id().to_string(),
+                CapabilitiesCacheEntry {
+                    edid: monitor.edid().map(<[u8]>::to_vec),
+                    capabilities: capabilities.to_string(),
+                },
+            );
+            self.is_capabilities_cache_changed = true;
+        }
+    }
+
+    /// Save the `capabilities_cache` if it's changed.
+    fn save_capabilities_cache(&mut self) {
+        if !self.is_capabilities_cache_changed {
+            return;
+        }
+        self.is_capabilities_cache_changed = false;
+        let Some(cache) = &self.capabilities_cache else {
+            return;
+        };
+        let mut state = self.load_state().unwrap_or_default();
+        state.capabilities = cache.clone();
+        if let Err(e) = self.save_state(&state) {
+            debug!("Failed to save the capabilities: {e}");
+        }
+    }
+
+    /// Apply the configurations to the display monitors from the index `start`.
+    fn apply_monitor_configs(&mut self, start: usize) {
+        let config = self.config.get_or_insert_default();
+        let id_format = self.id_format.as_deref().or(config.id_format.as_deref());
+        for (index, monitor) in self.monitors.iter_mut().enumerate().skip(start) {
+            monitor.set_cancellation_token(self.cancellation.clone());
+            if id_format.is_some() {
+                monitor.set_id_format(id_format);
+            }
+            for failure in &self.simulate_failure {
+                failure.apply(index, monitor, config);
+            }
+        }
+        let profile = self.profile.clone();
+        self.apply_monitor_settings(start, profile.as_deref());
+    }
+
+    /// Apply the [`Cli::effective_monitor_config()`] in the `profile`
+    /// to the display monitors from the index `start`.
+    fn apply_monitor_settings(&mut self, start: usize, profile: Option<&str>) {
+        let monitor_configs: Vec<MonitorConfig> = self
+            .monitors
+            .iter()
+            .skip(start)
+            .map(|monitor| self.effective_monitor_config(monitor.id(), profile))
+            .collect();
+        for (monitor, monitor_config) in self.monitors.iter_mut().skip(start).zip(monitor_configs) {
+            if let Some(settle_ms) = monitor_config.settle_ms {
+                monitor.set_settle_delay(Duration::from_millis(settle_ms));
+            }
+            if let Some(retries) = monitor_config.retries {
+                monitor.set_retries(retries);
+            }
+            if let Some(verify) = monitor_config.verify {
+                monitor.set_verify(verify);
+            }
+            for (name, value) in &monitor_config.inputs {
+                match InputSource::raw_from_str(name) {
+                    Ok(standard) => monitor.set_input_remap(standard, *value),
+                    Err(e) => warn!("{monitor}: {e} in `inputs`"),
+                }
+            }
+        }
+    }
+
+    /// Run `f` with the [`Config::profile_settings`] of the `profile` applied,
+    /// and restore the settings of the [`Cli::profile`] after it.
+    /// This is for running profiles other than the [`Cli::profile`],
+    /// such as by `status`, `schedules`, `rules`, or `--host`.
+    fn with_profile_settings<T>(
+        &mut self,
+        profile: Option<&str>,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        if profile.is_none() || profile == self.profile.as_deref() {
+            return f(self);
+        }
+        let settings: Vec<MonitorSettings> = self.monitors.iter().map(Monitor::settings).collect();
+        self.apply_monitor_settings(0, profile);
+        let result = f(self);
+        for (monitor, settings) in self.monitors.iter_mut().zip(settings) {
+            monitor.restore_settings(settings);
+        }
+        result
+    }
+
+    /// The [`Config::effective_monitor()`] for the display monitor `name`
+    /// in the `profile`, overridden by the command line options.
+    fn effective_monitor_config(&self, name: &str, profile: Option<&str>) -> MonitorConfig {
+        let mut monitor_config = self
+            .config
+            .as_ref()
+            .map(|config| config.effective_monitor(name, profile))
+            .unwrap_or_default();
+        if self.settle_ms.is_some() {
+            monitor_config.settle_ms = self.settle_ms;
+        }
+        monitor_config
+    }
+
+    /// Print the configurations in TOML,
+    /// or the [`Cli::effective_monitor_config()`] of the display monitor `effective`
+    /// in the [`Cli::profile`].
+    fn show_config(&mut self, effective: Option<&str>) -> anyhow::Result<()> {
+        let text = match effective {
+            Some(name) => {
+                toml::to_string(&self.effective_monitor_config(name, self.profile.as_deref()))?
+            }
+            None => toml::to_string(self.config.get_or_insert_default())?,
+        };
+        print!("{text}");
+        Ok(())
+    }
+
+    /// Write the [`config_template()`] to the [`Cli::config_path`]
+    /// or the [`Config::default_path()`].
+    fn init_config(&mut self, force: bool) -> anyhow::Result<()> {
+        let Some(path) = self.config_path.clone().or_else(Config::default_path) else {
+            anyhow::bail!("No default path for the configuration file. Use `--config`.");
+        };
+        if path.exists() && !force {
+            anyhow::bail!("{path:?} already exists. Use `--force` to overwrite.");
+        }
+        for monitor in self.monitors.iter_mut() {
+            // Errors are logged, and the input sources are omitted.
+            let _ = monitor.update_capabilities();
+        }
+        let text = config_template(&mut self.monitors);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, text)
+            .with_context(|| format!("Failed to write the config file {path:?}"))?;
+        println!("Created {path:?}");
+        Ok(())
+    }
+
+    /// Print the issues of [`validate_config()`],
+    /// and fail if any of them are errors.
+    fn validate_config(&mut self) -> anyhow::Result<()> {
+        let config = self.config.take().unwrap_or_default();
+        let issues = validate_config(&config, &mut self.monitors);
+        self.config = Some(config);
+        for issue in &issues {
+            println!("{issue}");
+        }
+        let errors = issues
+            .iter()
+            .filter(|issue| issue.severity == config_check::Severity::Error)
+            .count();
+        if errors > 0 {
+            anyhow::bail!("The configuration file has {errors} errors.");
+        }
+        if issues.is_empty() {
+            println!("No issues found.");
+        }
+        Ok(())
+    }
+
+    /// Convert the `file` of another tool to profiles,
+    /// and print them or add them to the configuration file.
+    fn import_config(
+        &mut self,
+        file: &std::path::Path,
+        format: Option<ProfileFormat>,
+        name: &str,
+        save: bool,
+    ) -> anyhow::Result<()> {
+        let text =
+            std::fs::read_to_string(file).with_context(|| format!("Failed to read {file:?}"))?;
+        let format = format.unwrap_or_else(|| ProfileFormat::from_path(file));
+        let imported = Imported::parse(format, &text, name)?;
+        for warning in &imported.warnings {
+            warn!("{warning}");
+        }
+        if !save {
+            print!("{}", imported.to_toml()?);
+            return Ok(());
+        }
+        let Some(path) = self.config_path.clone().or_else(Config::default_path) else {
+            anyhow::bail!("No default path for the configuration file. Use `--config`.");
+        };
+        imported.save(&path)?;
+        info!(
+            "Added {names} to {path:?}",
+            names = imported
+                .profiles
+                .keys()
+                .map(String::as_str)
+                .chain(imported.usb.as_ref().map(|_| "usb"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Ok(())
+    }
+
+    /// The `name=input` args of the `profile` in the configuration file.
+    fn profile_args(&mut self, profile: &str) -> anyhow::Result<Vec<String>> {
+        match self.config.get_or_insert_default().profiles.get(profile) {
+            Some(args) => Ok(args.clone()),
+            None => anyhow::bail!(tr_args(
+                "The profile \"{profile}\" is not in the configuration file.",
+                &[("profile", &profile)]
+            )),
+        }
+    }
+
+    /// The token to cancel this run from other threads,
+    /// such as when the user closes the window of a GUI host.
+    /// Requests to the display monitors fail with the [`Cancelled`] error after it's cancelled.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{Cli,Cancelled,MockMonitor,Monitor};
+    /// let mut cli = Cli::default();
+    /// cli.monitors = vec![Monitor::from_device(MockMonitor::new("A"))].into();
+    /// cli.args = vec!["A=hdmi1".to_string()];
+    /// # cli.config = Some(Default::default());
+    /// # cli.state_path = Some(std::env::temp_dir().join("monitor-input-doctest/state.json"));
+    /// # cli.dry_run = true;
+    /// cli.cancellation_token().cancel();
+    /// assert!(cli.run().unwrap_err().is::<Cancelled>());
+    /// ```
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    /// Enumerate display monitors again for display monitors connected or disconnected.
+    /// The instances of unchanged display monitors are kept,
+    /// and connected display monitors are appended to the end.
+    /// Returns the disconnected display monitors,
+    /// and the indices of connected display monitors.
+    fn refresh_monitors(&mut self) -> anyhow::Result<(Vec<Monitor>, std::ops::Range<usize>)> {
+        let mut current = self.enumerate_monitors()?;
+        self.remove_excluded(&mut current);
+        current.prefer_backends(&self.prefer_backend);
+        self.sort_monitors(&mut current);
+        let diff = MonitorDiff::new(&self.monitors, current.into_vec());
+        if diff.is_empty() {
+            return Ok((vec![], self.monitors.len()..self.monitors.len()));
+        }
+        // The indices of display monitors change.
+        self.start_session();
+        let mut removed = vec![];
+        for index in diff.removed.into_iter().rev() {
+            let monitor = self.monitors.remove(index);
+            info!("Disconnected: {monitor}");
+            removed.push(monitor);
+        }
+        removed.reverse();
+        let start = self.monitors.len();
+        for monitor in diff.added {
+            info!("Connected: {monitor}");
+            self.monitors.push(monitor);
+        }
+        self.apply_monitor_configs(start);
+        if self.safe_guard.is_some() {
+            self.safe_guard = Some(SafeGuard::new(
+                &mut self.monitors,
+                self.config.get_or_insert_default(),
+            ));
+        }
+        Ok((removed, start..self.monitors.len()))
+    }
+
+    /// The indices of the display monitors that match the `name`,
+    /// limited by [`Cli::first`] and [`Cli::exactly_one`].
+    fn matched_indices(&mut self, name: &str) -> anyhow::Result<(Selector, Vec<usize>)> {
+        self.select_indices(name, false)
+    }
+
+    /// The [`MatchOptions`] of [`Cli::all`], [`Cli::first`], and [`Cli::exactly_one`].
+    fn match_options(&mut self) -> MatchOptions {
+        MatchOptions {
+            all: self.all || self.config.get_or_insert_default().allow_multiple_matches,
+            first: self.first,
+            exactly_one: self.exactly_one,
+        }
+    }
+
+    /// Same as [`Cli::matched_indices()`],
+    /// and also checks [`MatchOptions::all`] if `to_set`.
+    fn select_indices(
+        &mut self,
+        name: &str,
+        to_set: bool,
+    ) -> anyhow::Result<(Selector, Vec<usize>)> {
+        let selector = Selector::parse(name, self.config.get_or_insert_default())?;
+        let strategy = self.capabilities_strategy();
+        let mut indices: Vec<usize> = vec![];
+        if let Selector::Index(index) = selector {
+            if index < self.monitors.len() {
+                if strategy != CapabilitiesStrategy::Never {
+                    self.update_capabilities(index);
+                }
+                indices.push(index);
+            }
+        } else {
+            for index in 0..self.monitors.len() {
+                if matches!(
+                    strategy,
+                    CapabilitiesStrategy::Always | CapabilitiesStrategy::Cached
+                ) {
+                    self.update_capabilities(index);
+                }
+                if selector.matches_monitor(index, &self.monitors[index]) {
+                    indices.push(index);
+                }
+            }
+        }
+        let indices =
+            self.match_options()
+                .limit(name, &selector, indices, to_set, &self.monitors)?;
+        if strategy == CapabilitiesStrategy::Lazy && !matches!(selector, Selector::Index(_)) {
+            for index in &indices {
+                self.update_capabilities(*index);
+            }
+        }
+        Ok((selector, indices))
+    }
+
+    fn ids(&self, indices: &[usize]) -> String {
+        indices
+            .iter()
+            .map(|index| self.monitors[*index].to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn for_each<C>(&mut self, name: &str, mut callback: C) -> anyhow::Result<()>
+    where
+        C: FnMut(usize, &mut Monitor) -> anyhow::Result<()>,
+    {
+        let (_, indices) = self.matched_indices(name)?;
+        for index in indices {
+            self.cancellation.check()?;
+            callback(index, &mut self.monitors[index])?;
+        }
+        Ok(())
+    }
+
+    /// Same as [`Cli::for_each()`], but fails when a part of names matches
+    /// multiple display monitors, unless [`Cli::all`] or [`Config::allow_multiple_matches`].
+    fn for_each_to_set<C>(&mut self, name: &str, mut callback: C) -> anyhow::Result<()>
+    where
+        C: FnMut(usize, &mut Monitor) -> anyhow::Result<()>,
+    {
+        for index in self.select_indices(name, true)?.1 {
+            self.cancellation.check()?;
+            callback(index, &mut self.monitors[index])?;
+        }
+        Ok(())
+    }
+
+    /// The index of the `input_sources` to toggle to,
+    /// advancing `step` entries from the `current_input_source` with wrap-around.
+    /// If it's not in the list, the first entry is `step` entries from the beginning,
+    /// or from the end if `step` is negative.
+    fn compute_toggle_set_index(
+        current_input_source: InputSourceRaw,
+        input_sources: &[InputSourceRaw],
+        step: isize,
+    ) -> usize {
+        let len = input_sources.len() as isize;
+        let position = input_sources
+            .iter()
+            .position(|v| *v == current_input_source)
+            .map_or(if step < 0 { len } else { -1 }, |i| i as isize);
+        (position + step).rem_euclid(len) as usize
+    }
+
+    fn toggle(&mut self, name: &str, values: &[&str], step: isize) -> anyhow::Result<()> {
+        let mut input_sources: Vec<InputSourceRaw> = vec![];
+        for value in values {
+            input_sources.push(InputSource::raw_from_str(value)?);
+        }
+        let step = if self.reverse { -step } else { step };
+        let mut set_index = self.set_index;
+        let mut context = self.take_set_context();
+        let result = self.for_each_to_set(name, |index, monitor: &mut Monitor| {
+            if set_index.is_none() {
+                let current_input_source = monitor.cached_input_source()?;
+                set_index = Some(Self::compute_toggle_set_index(
+                    current_input_source,
+                    &input_sources,
+                    step,
+                ));
+                debug!(
+                    "Set = {index} (because InputSource({monitor}) is {input_source})",
+                    index = set_index.unwrap(),
+                    input_source = InputSource::str_from_raw(current_input_source)
+                );
+            }
+            let used_index = set_index.unwrap().min(input_sources.len() - 1);
+            let input_source = input_sources[used_index];
+            context.set_input_source(index, monitor, input_source)
+        });
+        self.set_index = set_index;
+        self.restore_set_context(context);
+        result
+    }
+
+    fn set(&mut self, name: &str, values: &[String], step: isize) -> anyhow::Result<()> {
+        if values.len() > 1 {
+            let values: Vec<&str> = values.iter().map(String::as_str).collect();
+            return self.toggle(name, &values, step);
+        }
+        let value = &values[0];
+        let input_source = InputSource::raw_from_str(value)?;
+        let mut context = self.take_set_context();
+        let result = self.for_each_to_set(name, |index, monitor: &mut Monitor| {
+            context.set_input_source(index, monitor, input_source)
+        });
+        self.restore_set_context(context);
+        result
+    }
+
+    /// Print whether the display monitors are in the input sources of the `profile`,
+    /// with the [`Config::profile_settings`] of the `profile`.
+    /// It's an error if any of them aren't, so that scripts can check the exit code.
+    fn status(&mut self, profile: &str) -> anyhow::Result<()> {
+        let args = self.profile_args(profile)?;
+        let entries = Profile::parse(&args)
+            .map_err(|e| anyhow::anyhow!("{profile}: {e}"))?
+            .entries;
+        let mut status = output::Status {
+            profile: profile.to_string(),
+            ..Default::default()
+        };
+        self.start_session();
+        self.with_profile_settings(Some(profile), |cli| {
+            for entry in &entries {
+                let expected = entry.input_source()?;
+                cli.for_each(&entry.monitor, |_, monitor| {
+                    let current = monitor.cached_input_source().ok();
+                    status.monitors.push(output::StatusItem {
+                        monitor: monitor.to_string(),
+                        current: current.map(InputSource::str_from_raw),
+                        expected: InputSource::str_from_raw(expected),
+                        matches: current == Some(expected),
+                    });
+                    Ok(())
+                })?;
+            }
+            anyhow::Ok(())
+        })?;
+        status.matches = status.monitors.iter().all(|item| item.matches);
+        output::write_status(&mut std::io::stdout().lock(), self.format, &status)?;
+        if !status.matches {
+            anyhow::bail!(tr_args(
+                "Not in the profile \"{profile}\".",
+                &[("profile", &profile)]
+            ));
+        }
+        Ok(())
+    }
+
+    /// Run the [`SelfTestReport`] on the display monitors that match the `name`.
+    fn self_test(&mut self, name: &str) -> anyhow::Result<()> {
+        let mut reports = vec![];
+        self.for_each(name, |_, monitor| {
+            reports.push(SelfTestReport::run(monitor));
+            Ok(())
+        })?;
+        output::write_self_test(&mut std::io::stdout().lock(), self.format, &reports)?;
+        let failed: Vec<&str> = reports
+            .iter()
+            .filter(|report| !report.passed())
+            .map(|report| report.monitor.as_str())
+            .collect();
+        if !failed.is_empty() {
+            anyhow::bail!("Self-test failed: {}", failed.join(", "));
+        }
+        Ok(())
+    }
+
+    /// Read the VCP features of the `range` from the display monitors of the `name`,
+    /// and print the reports.
+    fn probe(&mut self, name: &str, range: &str) -> anyhow::Result<()> {
+        let codes = parse_vcp_range(range)?;
+        let mut reports = vec![];
+        self.for_each(name, |_, monitor| {
+            reports.push(ProbeReport::run(monitor, codes.clone()));
+            Ok(())
+        })?;
+        output::write_probe(&mut std::io::stdout().lock(), self.format, &reports)
+    }
+
+    /// Print the changes of the input sources of the display monitors of the `name`,
+    /// read every `interval`, until cancelled.
+    fn watch_inputs(&mut self, name: &str, interval: Option<Duration>) -> anyhow::Result<()> {
+        let interval = interval.unwrap_or_else(|| {
+            let config = self.config.get_or_insert_default();
+            let interval_secs = config.watch.as_ref().map_or_else(
+                || WatchConfig::default().interval_secs,
+                |watch| watch.interval_secs,
+            );
+            Duration::from_secs(interval_secs.max(1))
+        });
+        let (_, indices) = self.matched_indices(name)?;
+        loop {
+            for change in self.poll_input_changes(&indices) {
+                let now = jiff::Zoned::now().strftime("%Y-%m-%d %H:%M:%S%.3f");
+                match change {
+                    Ok(change) => println!("{now} {change}"),
+                    Err(e) => println!("{now} {e:#}"),
+                }
+            }
+            self.cancellation.sleep(interval)?;
+        }
+    }
+
+    /// Read the input sources of the display monitors at the `indices`
+    /// by [`Monitor::poll_input_source()`],
+    /// and return the changes since the last read, or the errors of the reads.
+    /// The first read of each display monitor is returned without the `previous`.
+    pub(crate) fn poll_input_changes(
+        &mut self,
+        indices: &[usize],
+    ) -> Vec<anyhow::Result<InputSourceChange>> {
+        let mut changes = vec![];
+        for &index in indices {
+            let monitor = &mut self.monitors[index];
+            let previous = monitor.last_input_source();
+            match monitor.poll_input_source() {
+                Ok(Some(value)) => {
+                    changes.push(Ok(InputSourceChange::new(monitor, previous, value)))
+                }
+                Ok(None) => {
+                    if previous.is_none()
+                        && let Some(value) = monitor.last_input_source()
+                    {
+                        changes.push(Ok(InputSourceChange::new(monitor, None, value)));
+                    }
+                }
+                Err(e) => changes.push(Err(e)),
+            }
+        }
+        changes
+    }
+
+    /// Set the picture-by-picture mode of the display monitors, such as `2x1`.
+    fn set_pbp(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
+        let mode: PbpMode = value.parse().with_context(|| {
+            format!(
+                "Unknown picture-by-picture mode \"{value}\". Use one of: {}",
+                PbpMode::VARIANTS
+                    .iter()
+                    .map(|mode| mode.as_ref())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+        self.for_each_to_set(name, |_, monitor| monitor.set_pbp_mode(mode))
+    }
+
+    /// Set the VCP feature `code` of the display monitors to the `value`,
+    /// or to the percentage of the maximum if it ends with `%`, such as `50%`.
+    /// It fails if the `value` is more than the maximum of the display monitor.
+    fn set_vcp(
+        &mut self,
+        name: &str,
+        feature: &str,
+        code: VcpCode,
+        value: &str,
+    ) -> anyhow::Result<()> {
+        let value = FeatureValue::parse(feature, value)?;
+        self.for_each_to_set(name, |_, monitor| {
+            let target = value.resolve(feature, code, monitor)?;
+            // `apply()` for the delay after turning on.
+            for (_, result) in monitor.apply(&[VcpWrite::new(code, target)]) {
+                result?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Send the [`Cli::raw_ddc`] to the display monitor of the [`Cli::args`],
+    /// and print the reply if [`Cli::raw_ddc_read`] isn't zero.
+    /// It fails unless the [`Cli::args`] match exactly one display monitor,
+    /// because vendor-specific commands may have different meanings for other models.
+    fn raw_ddc(&mut self, bytes: &str) -> anyhow::Result<()> {
+        let data = parse_hex_bytes(bytes)?;
+        let [name] = self.args.as_slice() else {
+            anyhow::bail!("`--raw-ddc` requires exactly one display monitor name");
+        };
+        let name = name.clone();
+        let (_, indices) = self.matched_indices(&name)?;
+        let [index] = indices.as_slice() else {
+            anyhow::bail!(
+                "\"{name}\" matches {count} display monitors: {ids}. \
+                `--raw-ddc` sends to only one display monitor.",
+                count = indices.len(),
+                ids = self.ids(&indices)
+            );
+        };
+        let monitor = &mut self.monitors[*index];
+        let reply = monitor.ddc_raw(&data, self.raw_ddc_read)?;
+        if self.raw_ddc_read > 0 {
+            let reply: Vec<String> = reply.iter().map(|byte| format!("{byte:02X}")).collect();
+            println!("{monitor}: {}", reply.join(" "));
+        }
+        Ok(())
+    }
+
+    /// Set the input source to `value`
+    /// only for display monitors whose current input source is `condition`.
+    fn set_if(&mut self, name: &str, condition: &str, value: &str) -> anyhow::Result<()> {
+        let condition = InputSource::raw_from_str(condition)?;
+        let input_source = InputSource::raw_from_str(value)?;
+        let mut context = self.take_set_context();
+        let result = self.for_each_to_set(name, |index, monitor: &mut Monitor| {
+            let current = monitor.cached_input_source()?;
+            if current != condition {
+                info!(
+                    "InputSource({monitor}) = {current} (not {condition})",
+                    current = InputSource::str_from_raw(current),
+                    condition = InputSource::str_from_raw(condition)
+                );
+                return Ok(());
+            }
+            context.set_input_source(index, monitor, input_source)
+        });
+        self.restore_set_context(context);
+        result
+    }
+
+    fn take_set_context(&mut self) -> SetContext {
+        SetContext {
+            force: self.force,
+            safe_guard: self.safe_guard.take(),
+            set_results: std::mem::take(&mut self.set_results),
+            session: std::mem::take(&mut self.session),
+        }
+    }
+
+    fn restore_set_context(&mut self, context: SetContext) {
+        self.safe_guard = context.safe_guard;
+        self.set_results = context.set_results;
+        self.session = context.session;
+    }
+
+    /// The duration to wait for [`Cli::delay`] or [`Cli::at`].
+    pub fn wait_duration(&self) -> anyhow::Result<Option<Duration>> {
+        if let Some(delay) = self.delay {
+            return Ok(Some(delay));
+        }
+        let Some(at) = self.at else {
+            return Ok(None);
+        };
+        let now = jiff::Zoned::now();
+        Ok(Some(Self::duration_until(&now, at)?))
+    }
+
+    /// The duration from `now` until the next `time` of the day.
+    fn duration_until(now: &jiff::Zoned, time: jiff::civil::Time) -> anyhow::Result<Duration> {
+        let mut target = now.with().time(time).build()?;
+        if target <= *now {
+            target = now.tomorrow()?.with().time(time).build()?;
+        }
+        Ok(Duration::try_from(now.duration_until(&target))?)
+    }
+
+    /// Wait for [`Cli::delay`] or [`Cli::at`], and clear them.
+    /// [`Cli::run()`] calls this before making changes.
+    pub fn wait(&mut self) -> anyhow::Result<()> {
+        let Some(duration) = self.wait_duration()? else {
+            return Ok(());
+        };
+        self.delay = None;
+        self.at = None;
+        let seconds = duration.as_secs_f64().ceil() as u64;
+        info!("Changing in {seconds} seconds...");
+        // Show the message before waiting, for loggers that buffer messages.
+        log::logger().flush();
+        let end_time = Instant::now() + duration;
+        loop {
+            let remaining = end_time.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            debug!("{} seconds...", remaining.as_secs_f64().ceil());
+            std::thread::sleep(remaining.min(Duration::from_secs(1)));
+        }
+        Ok(())
+    }
+
+    /// The results of changes made by [`Cli::run()`].
+    pub fn set_results(&self) -> &[SetResult] {
+        &self.set_results
+    }
+
+    /// The summary statistics of the last [`Cli::run()`],
+    /// such as the numbers of changes and the elapsed time.
+    pub fn run_report(&self) -> &RunReport {
+        &self.run_report
+    }
+
+    /// Run as [`Cli::run()`], and return the [`RunReport`],
+    /// including the outcomes of each argument in [`RunReport::args`].
+    /// When this returns an error,
+    /// the report is still available by [`Cli::run_report()`].
+    /// # Examples
+    /// ```
+    /// # use clap::Parser;
+    /// # use monitor_input::{Cli,InputSource,MockMonitor,Monitor};
+    /// let mut cli = Cli::parse_from(["monitor-input", "A=hdmi1"]);
+    /// cli.monitors = vec![Monitor::from_device(
+    ///     MockMonitor::new("A").with_input_source(InputSource::DisplayPort1.as_raw()),
+    /// )]
+    /// .into();
+    /// # cli.config = Some(Default::default());
+    /// # cli.state_path = Some(std::env::temp_dir().join("monitor-input-doctest/state.json"));
+    /// # cli.dry_run = true;
+    /// let report = cli.run_with_report().unwrap();
+    /// assert_eq!(report.args[0].arg, "A=hdmi1");
+    /// assert_eq!(report.args[0].results[0].requested, "Hdmi1");
+    /// ```
+    pub fn run_with_report(&mut self) -> anyhow::Result<RunReport> {
+        self.run()?;
+        Ok(self.run_report.clone())
+    }
+
+    /// The summary of the changes made by [`Cli::run()`] for notifications,
+    /// or `None` if they don't need a summary. See [`ChangeSummary::new()`].
+    pub fn change_summary(&self) -> Option<ChangeSummary> {
+        ChangeSummary::new(self.profile.as_deref(), &self.set_results)
+    }
+
+    fn print_list(&mut self, name: &str) -> anyhow::Result<()> {
+        let mut items = vec![];
+        self.for_each(name, |index, monitor| {
+            let input_source = monitor.cached_input_source();
+            items.push(output::ListItem::new(index, monitor, input_source));
+            trace!("{monitor:?}");
+            Ok(())
+        })?;
+        match &mut self.output {
+            Some(output) => output::write_list(output, self.format, &items),
+            None => output::write_list(&mut std::io::stdout().lock(), self.format, &items),
+        }
+    }
+
+    /// Sleep until all changes are applied, before hooks and exiting.
+    /// Requests in between don't need this,
+    /// because they wait for the [`Monitor::ready_at()`] of each display monitor.
+    /// This waits only for the longest delay of the display monitors,
+    /// because [`Monitor::sleep_if_needed()`] counts from each change.
+    fn sleep_all_if_needed(&mut self) {
+        let start_time = Instant::now();
+        for monitor in &mut self.monitors {
+            monitor.sleep_if_needed();
+        }
+        debug!("sleep_all() elapsed: {:?}", start_time.elapsed());
+    }
+
+    /// Run the command line tool.
+    pub fn run(&mut self) -> anyhow::Result<()> {
+        let start_time = Instant::now();
+        if self.version {
+            return self.print_version();
+        }
+        Monitor::set_dry_run(self.dry_run);
+        if self.config.is_none() {
+            self.config = Some(Config::load(self.config_path.as_deref())?);
+        }
+        if let Some(language) = &self.config.get_or_insert_default().language {
+            match Language::from_tag(language) {
+                Some(language) => Language::set_current(language),
+                None => warn!("The language \"{language}\" is not supported."),
+            }
+        }
+        if let Some(host) = self.host.clone() {
+            return self.wait().and_then(|_| self.run_remote(&host));
+        }
+        if self.install_systemd {
+            return service::install_systemd(self.config_path.as_deref());
+        }
+        if self.register {
+            return register_app();
+        }
+        if self.unregister {
+            return unregister_app();
+        }
+        if !self.input_of.is_empty() && (self.command.is_some() || self.raw_ddc.is_some()) {
+            anyhow::bail!(
+                "`--input-of` applies only to the args and the `--profile`, not to commands."
+            );
+        }
+        self.failures = self
+            .load_state()
+            .map(|state| state.failures)
+            .unwrap_or_default();
+        self.apply_filters()?;
+        if let Some(name) = self.wait_for.clone() {
+            self.wait_for_monitor(&name)?;
+        }
+        self.apply_monitor_configs(0);
+        if self.safe {
+            self.safe_guard = Some(SafeGuard::new(
+                &mut self.monitors,
+                self.config.get_or_insert_default(),
+            ));
+        }
+        let mut stages = vec![];
+        let mut stage_start = start_time;
+        let mut end_stage = |name: &'static str| {
+            let now = Instant::now();
+            stages.push((name, now - stage_start));
+            stage_start = now;
+        };
+        end_stage("setup");
+
+        let result = self.wait();
+        end_stage("wait");
+        let result = result.and_then(|_| match self.command.clone() {
+            Some(Command::Undo) => self.undo(),
+            Some(Command::Daemon {
+                next,
+                listen,
+                websocket,
+            }) => self.daemon(next, listen.as_deref(), websocket.as_deref()),
+            Some(Command::Service { action }) => match action {
+                ServiceAction::Install => service::install(self.config_path.as_deref()),
+                ServiceAction::Uninstall => service::uninstall(),
+                ServiceAction::Run => self.daemon(false, None, None),
+            },
+            Some(Command::Autostart { action }) => match action {
+                AutostartAction::Enable => autostart::enable(self.config_path.as_deref()),
+                AutostartAction::Disable => autostart::disable(),
+                AutostartAction::Status => {
+                    match autostart::status() {
+                        Some(location) => println!("{}: {location}", tr("Enabled")),
+                        None => println!("{}", tr("Disabled")),
+                    }
+                    Ok(())
+                }
+            },
+            Some(Command::Rules { action }) => match action {
+                RulesAction::Test { event } => self.test_rules(event.as_deref()),
+            },
+            Some(Command::Config { action }) => match action {
+                ConfigAction::Show { effective } => self.show_config(effective.as_deref()),
+                ConfigAction::Init { force } => self.init_config(force),
+                ConfigAction::Validate => self.validate_config(),
+                ConfigAction::Import {
+                    file,
+                    from,
+                    name,
+                    save,
+                } => self.import_config(&file, from, &name, save),
+                ConfigAction::Export { profile, to } => {
+                    let profile = Profile::parse(self.profile_args(&profile)?)?;
+                    print!("{}", export_profile(to, &profile)?);
+                    Ok(())
+                }
+            },
+            Some(Command::Status { profile }) => self.status(&profile),
+            Some(Command::History { monitor, last }) => self.history(monitor.as_deref(), last),
+            Some(Command::Selftest { name }) => self.self_test(&name),
+            Some(Command::Probe { name, range }) => self.probe(&name, &range),
+            Some(Command::WatchInputs { name, interval }) => self.watch_inputs(&name, interval),
+            Some(Command::SetupPermissions {
+                print_udev,
+                install,
+            }) => setup_permissions(print_udev, install),
+            None => match self.raw_ddc.clone() {
+                Some(bytes) => self.raw_ddc(&bytes),
+                None => {
+                    if self.is_input_of()? {
+                        let (args, profile) = (self.args.clone(), self.profile.clone());
+                        self.run_profile_args(&args, profile.as_deref())
+                    } else {
+                        Ok(())
+                    }
+                }
+            },
+        });
+        end_stage("command");
+        self.sleep_all_if_needed();
+        if !self.dry_run {
+            self.save_undo_state();
+            self.run_on_switch_hooks();
+            let trigger = match self.command {
+                Some(Command::Undo) => "undo",
+                _ => "command",
+            };
+            self.save_history(0, trigger);
+            self.save_failures();
+        }
+        self.save_capabilities_cache();
+        let write_result = self.write_results();
+        end_stage("save");
+        self.run_report = RunReport::new(&self.set_results, start_time.elapsed(), stages);
+        self.run_report.args = self.arg_reports();
+        self.run_report.log();
+        write_result?;
+        if let Err(e) = &result {
+            diagnose_error(e);
+        }
+        result
+    }
+
+    /// Print the version, or the [`output::VersionInfo`] in JSON if `--json`.
+    fn print_version(&self) -> anyhow::Result<()> {
+        let info = output::VersionInfo::new();
+        if self.json {
+            serde_json::to_writer_pretty(std::io::stdout().lock(), &info)?;
+            println!();
+        } else {
+            println!("{} {}", info.name, info.version);
+        }
+        Ok(())
+    }
+
+    /// Start a new [`Session`], and invalidate the input sources cached by the display monitors,
+    /// because they may be changed since the last session, such as by the on-screen display.
+    fn start_session(&mut self) {
+        self.session = Session::default();
+        for monitor in &mut self.monitors {
+            monitor.invalidate_input_source_cache();
+        }
+    }
+
+    /// Whether the display monitors of [`Cli::input_of`] are on their input sources.
+    /// Each name must match one display monitor,
+    /// so that the condition isn't met by any of multiple display monitors unexpectedly.
+    fn is_input_of(&mut self) -> anyhow::Result<bool> {
+        for entry in self.input_of.clone() {
+            let expected = entry.input_source()?;
+            let (_, indices) = self.select_indices(&entry.monitor, false)?;
+            if indices.len() > 1 {
+                anyhow::bail!(
+                    "`--input-of` \"{name}\" matches {count} display monitors: {ids}. \
+                    Specify one of them.",
+                    name = entry.monitor,
+                    count = indices.len(),
+                    ids = self.ids(&indices)
+                );
+            }
+            let monitor = &mut self.monitors[indices[0]];
+            let current = monitor.input_source()?;
+            if current != expected {
+                info!(
+                    "Skipped, because InputSource({monitor}) is {current} (not {expected})",
+                    current = InputSource::str_from_raw(current),
+                    expected = InputSource::str_from_raw(expected)
+                );
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Run the `args`, and the `profile` in the configuration file if specified,
+    /// with the [`Config::profile_settings`] of the `profile`.
+    fn run_profile_args(&mut self, args: &[String], profile: Option<&str>) -> anyhow::Result<()> {
+        let mut args = args.to_vec();
+        if let Some(profile) = profile {
+            args.extend(self.profile_args(profile)?);
+        }
+        self.with_profile_settings(profile, |cli| cli.run_args(&args))
+    }
+
+    fn run_args(&mut self, args: &[String]) -> anyhow::Result<()> {
+        // Toggles and the states of display monitors are consistent within the `args`,
+        // not across runs of the `daemon`.
+        self.set_index = None;
+        self.start_session();
+        let start = self.set_results.len();
+        let result = self.run_args_without_rollback(args);
+        if result.is_err() && self.rollback {
+            self.rollback(start);
+        }
+        result
+    }
+
+    fn run_args_without_rollback(&mut self, args: &[String]) -> anyhow::Result<()> {
+        let mut has_valid_args = false;
+        for arg in args {
+            let start = self.set_results.len();
+            let result = self.run_arg(arg);
+            self.arg_results.push((
+                arg.clone(),
+                start..self.set_results.len(),
+                result.as_ref().err().map(|e| e.to_string()),
+            ));
+            result?;
+            has_valid_args = true;
+        }
+        if !has_valid_args {
+            self.print_list("")?;
+        }
+        Ok(())
+    }
+
+    fn run_arg(&mut self, arg: &str) -> anyhow::Result<()> {
+        match arg.parse::<Arg>()? {
+            Arg::List(name) => self.print_list(&name),
+            Arg::Set { name, values, step } => self.set(&name, &values, step),
+            Arg::SetIf {
+                name,
+                condition,
+                value,
+            } => self.set_if(&name, &condition, &value),
+            Arg::SetPbp { name, mode } => self.set_pbp(&name, &mode),
+            Arg::SetVcp {
+                name,
+                feature,
+                code,
+                value,
+            } => self.set_vcp(&name, feature, code, &value),
+        }
+    }
+
+    /// The [`ArgReport`]s of the args run by [`Cli::run_args()`].
+    fn arg_reports(&self) -> Vec<ArgReport> {
+        self.arg_results
+            .iter()
+            .map(|(arg, range, error)| ArgReport {
+                arg: arg.clone(),
+                results: self.set_results[range.clone()].to_vec(),
+                error: error.clone(),
+            })
+            .collect()
+    }
+
+    /// Restore the input sources changed by `set_results[start..]`, in the reverse order.
+    /// Failures are logged but ignored, because this is the best effort.
+    fn rollback(&mut self, start: usize) {
+        for result in self.set_results[start..].iter_mut().rev() {
+            let Some(entry) = result.undo_entry() else {
+                continue;
+            };
+            let Some(monitor) = self.monitors.find_exact_mut(&entry.id) else {
+                continue;
+            };
+            warn!(
+                "Rolling back InputSource({monitor}) to {}",
+                entry.input_source
+            );
+            match InputSource::raw_from_str(&entry.input_source)
+                .and_then(|value| monitor.set_input_source(value))
+            {
+                Ok(_) => result.rolled_back = true,
+                Err(e) => error!("Failed to roll back {monitor}: {e}"),
+            }
+        }
+    }
+
+    fn undo(&mut self) -> anyhow::Result<()> {
+        let state = self.load_state()?;
+        if state.undo.is_empty() {
+            anyhow::bail!("There are no changes to undo.");
+        }
+        for entry in &state.undo {
+            self.set_undo_entry(entry)?;
+        }
+        Ok(())
+    }
+
+    /// Restore the input source of the `entry`.
+    /// Unlike [`Cli::set()`], the display monitor is found by the exact [`UndoEntry::id`],
+    /// so that the name doesn't match other display monitors.
+    fn set_undo_entry(&mut self, entry: &UndoEntry) -> anyhow::Result<()> {
+        if entry.id.is_empty() {
+            // The states saved by old versions don't have the ID.
+            return self.set(
+                &crate::arg::quote(&entry.monitor),
+                std::slice::from_ref(&entry.input_source),
+                1,
+            );
+        }
+        let input_source = InputSource::raw_from_str(&entry.input_source)?;
+        let Some(index) = (0..self.monitors.len()).find(|&i| self.monitors[i].id() == entry.id)
+        else {
+            anyhow::bail!(tr_args(
+                "No display monitors found for \"{name}\".",
+                &[("name", &entry.monitor)]
+            ));
+        };
+        if self.capabilities_strategy() != CapabilitiesStrategy::Never {
+            self.update_capabilities(index);
+        }
+        let mut context = self.take_set_context();
+        let result = context.set_input_source(index, &mut self.monitors[index], input_source);
+        self.restore_set_context(context);
+        result
+    }
+
+    /// Run the [`Config::schedules`], the [`Config::idle`], the [`Config::watch`],
+    /// the [`Config::dock`], the [`Config::session`], and the [`Config::rules`]
+    /// until the process is requested to stop.
+    /// If `print_next` is set, print the next runs of the schedules and return instead.
+    fn daemon(
+        &mut self,
+        print_next: bool,
+        listen: Option<&str>,
+        websocket: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let config = self.config.get_or_insert_default();
+        let schedules = config.schedules.clone();
+        let idle_config = config.idle.clone();
+        let watch_config = config.watch.clone();
+        let dock_config = config.dock.clone();
+        let session_config = config.session.clone();
+        let brightness_config = config.brightness.clone();
+        let night_config = config.night.clone();
+        let usb_config = config.usb.clone();
+        let rules = compile_config_rules(config)?;
+        let rule_events: Vec<RuleEvent> = rules.iter().flat_map(|rule| rule.events()).collect();
+        let token = config.remote.token.clone();
+        let origins = config.remote.origins.clone();
+        for schedule in &schedules {
+            schedule.validate()?;
+        }
+        if print_next {
+            let now = jiff::Zoned::now();
+            for schedule in &schedules {
+                let time = schedule.next_run(&now)?;
+                let mut args = schedule.args.clone();
+                if let Some(profile) = &schedule.profile {
+                    args.extend(["--profile".to_string(), profile.clone()]);
+                }
+                println!(
+                    "{time}\t{args}",
+                    time = time.strftime("%a %Y-%m-%d %H:%M"),
+                    args = args.join(" ")
+                );
+            }
+            return Ok(());
+        }
+        if schedules.is_empty()
+            && idle_config.is_none()
+            && watch_config.is_none()
+            && dock_config.is_none()
+            && session_config.is_none()
+            && rules.is_empty()
+            && brightness_config.is_none()
+            && night_config.is_none()
+            && usb_config.is_none()
+            && listen.is_none()
+            && websocket.is_none()
+        {
+            anyhow::bail!(
+                "There are no `schedules`, `idle`, `watch`, `dock`, `session`, `rules`, \
+                `brightness`, `night`, nor `usb` \
+                in the configuration file, \
+                and no `--listen` nor `--websocket`."
+            );
+        }
+        // Requests from `--host` are run in this thread,
+        // because display monitors can't be shared across threads.
+        let (sender, receiver) = std::sync::mpsc::channel::<DaemonEvent>();
+        if let Some(address) = listen {
+            remote::listen(address, token.clone(), sender.clone())?;
+        }
+        if let Some(address) = websocket {
+            websocket::listen(address, token, origins, sender.clone())?;
+        }
+        // Display changes are needed to enumerate display monitors again.
+        if let Err(e) = native().listen_hotplug(sender.clone()) {
+            if dock_config.is_some() || rule_events.iter().any(RuleEvent::is_dock) {
+                warn!("{e}");
+            } else {
+                info!("{e}. Restart the daemon after connecting display monitors.");
+            }
+        }
+        // Keep the listener until the `daemon` stops, to stop its process on Linux.
+        let _session_listener =
+            if session_config.is_some() || rule_events.iter().any(RuleEvent::is_session) {
+                Some(native().listen_session(sender.clone())?)
+            } else {
+                None
+            };
+        set_shutdown_handler(sender)?;
+        notify_ready()?;
+        let mut idle_watcher = idle_config
+            .as_ref()
+            .map(|idle| IdleWatcher::new(Duration::from_secs(idle.minutes * 60)));
+        let mut idle_undo: Vec<UndoEntry> = vec![];
+        let mut power_watcher = PowerWatcher::default();
+        let mut display_change_at: Option<Instant> = None;
+        let mut session_watcher = SessionWatcher::default();
+        let mut lock_undo: Vec<UndoEntry> = vec![];
+        let mut lid_undo: Vec<UndoEntry> = vec![];
+        // The `idle(N)` of the `rules`, the shortest first.
+        let mut rule_idle_minutes: Vec<u64> = rule_events
+            .iter()
+            .filter_map(|event| match event {
+                RuleEvent::Idle(minutes) => Some(*minutes),
+                _ => None,
+            })
+            .collect();
+        rule_idle_minutes.sort();
+        rule_idle_minutes.dedup();
+        let mut rule_idle_watchers: Vec<(u64, IdleWatcher)> = rule_idle_minutes
+            .into_iter()
+            .map(|minutes| (minutes, IdleWatcher::new(Duration::from_secs(minutes * 60))))
+            .collect();
+        let uses_minute = rule_events.contains(&RuleEvent::Minute);
+        let current_minute = || {
+            let now = jiff::Zoned::now();
+            (now.date(), now.hour(), now.minute())
+        };
+        let mut last_minute = current_minute();
+        let mut brightness = brightness_config
+            .map(BrightnessController::new)
+            .transpose()?;
+        let mut night = night_config.map(NightController::new).transpose()?;
+        let mut usb_watcher = usb_config.as_ref().map(UsbWatcher::new).transpose()?;
+
+        self.run_rules(&rules, RuleEvent::Start, None);
+        self.finish_daemon_run();
+
+        let mut next = Self::next_schedules(&schedules)?;
+        loop {
+            // Check the wall clock periodically,
+            // because the monotonic clock may stop while the computer is asleep.
+            let mut timeout = Duration::from_secs(
+                if idle_watcher.is_some() || !rule_idle_watchers.is_empty() {
+                    5
+                } else {
+                    60
+                },
+            );
+            if let Some(watch_config) = &watch_config {
+                timeout = timeout.min(Duration::from_secs(watch_config.interval_secs.max(1)));
+            }
+            if let Some((time, _)) = &next {
+                let remaining = jiff::Zoned::now().duration_until(time);
+                timeout = timeout.min(Duration::try_from(remaining).unwrap_or_default());
+            }
+            if let Some(time) = display_change_at {
+                timeout = timeout.min(time.saturating_duration_since(Instant::now()));
+            }
+            if let Some(brightness) = &brightness {
+                timeout = timeout.min(brightness.stepped().timeout());
+            }
+            if let Some(night) = &night {
+                timeout = timeout.min(night.stepped().timeout());
+            }
+            if let Some(usb_watcher) = &usb_watcher {
+                timeout = timeout.min(usb_watcher.timeout());
+            }
+            if uses_minute {
+                let second = jiff::Zoned::now().second() as u64;
+                timeout = timeout.min(Duration::from_secs(60u64.saturating_sub(second)));
+            }
+            match receiver.recv_timeout(timeout) {
+                Ok(DaemonEvent::Remote((request, response_sender))) => {
+                    let response = self.run_remote_request(request);
+                    if response_sender.send(response).is_err() {
+                        warn!("Failed to respond to the remote request");
+                    }
+                }
+                Ok(DaemonEvent::Shutdown) => {
+                    info!("Shutting down");
+                    return Ok(());
+                }
+                Ok(DaemonEvent::Dock(DockEvent::DisplayChange)) => {
+                    display_change_at = Some(Instant::now() + DISPLAY_CHANGE_DELAY);
+                }
+                Ok(DaemonEvent::Dock(DockEvent::Power(on_ac))) => {
+                    if let Some(on_ac) = power_watcher.update(on_ac) {
+                        if on_ac {
+                            info!("AC power connected");
+                        } else {
+                            info!("AC power disconnected");
+                        }
+                        if let Some(dock_config) = &dock_config {
+                            let args = if on_ac {
+                                &dock_config.ac_args
+                            } else {
+                                &dock_config.battery_args
+                            };
+                            if !args.is_empty() {
+                                self.run_daemon_args(args, "dock");
+                            }
+                        }
+                        let event = if on_ac {
+                            RuleEvent::Ac
+                        } else {
+                            RuleEvent::Battery
+                        };
+                        self.run_rules(&rules, event, None);
+                    }
+                }
+                Ok(DaemonEvent::Session(event)) => {
+                    if session_watcher.update(event) {
+                        info!("{event:?}");
+                        if let Some(session_config) = &session_config {
+                            match event {
+                                SessionEvent::Lock => {
+                                    lock_undo =
+                                        self.run_revertible_args(&session_config.lock_args, "lock");
+                                }
+                                SessionEvent::Unlock => self.revert_args(
+                                    &session_config.unlock_args,
+                                    std::mem::take(&mut lock_undo),
+                                    "lock",
+                                ),
+                                SessionEvent::LidClose => {
+                                    lid_undo = self
+                                        .run_revertible_args(&session_config.lid_close_args, "lid");
+                                }
+                                SessionEvent::LidOpen => self.revert_args(
+                                    &session_config.lid_open_args,
+                                    std::mem::take(&mut lid_undo),
+                                    "lid",
+                                ),
+                            }
+                        }
+                        self.run_rules(&rules, event.into(), None);
+                    }
+                }
+                Err(_) => {}
+            }
+
+            if let Some(time) = display_change_at
+                && Instant::now() >= time
+            {
+                display_change_at = None;
+                self.on_display_change(dock_config.as_ref(), &rules);
+            }
+
+            if let Some((time, due)) = &next
+                && jiff::Zoned::now() >= *time
+            {
+                for schedule in due {
+                    self.run_daemon_profile_args(
+                        &schedule.args,
+                        schedule.profile.as_deref(),
+                        "schedule",
+                    );
+                }
+                next = Self::next_schedules(&schedules)?;
+            }
+
+            if let (Some(idle_watcher), Some(idle_config)) = (&mut idle_watcher, &idle_config)
+                && let Some(idle_time) = idle_time()
+            {
+                match idle_watcher.update(idle_time) {
+                    Some(IdleEvent::Idle) => {
+                        info!("Idle for {} minutes", idle_config.minutes);
+                        idle_undo = self.run_revertible_args(&idle_config.args, "idle");
+                    }
+                    Some(IdleEvent::Active) => {
+                        info!("Active");
+                        self.revert_args(
+                            &idle_config.active_args,
+                            std::mem::take(&mut idle_undo),
+                            "idle",
+                        );
+                    }
+                    None => {}
+                }
+            }
+
+            if !rule_idle_watchers.is_empty()
+                && let Some(idle_time) = idle_time()
+            {
+                let mut events = vec![];
+                for (i, (minutes, watcher)) in rule_idle_watchers.iter_mut().enumerate() {
+                    match watcher.update(idle_time) {
+                        Some(IdleEvent::Idle) => events.push(RuleEvent::Idle(*minutes)),
+                        // Only once, when the user becomes active after the shortest `idle`.
+                        Some(IdleEvent::Active) if i == 0 => events.push(RuleEvent::Active),
+                        _ => {}
+                    }
+                }
+                for event in events {
+                    self.run_rules(&rules, event, None);
+                }
+            }
+
+            if uses_minute {
+                let minute = current_minute();
+                if minute != last_minute {
+                    last_minute = minute;
+                    self.run_rules(&rules, RuleEvent::Minute, None);
+                }
+            }
+
+            if let Some(watch_config) = &watch_config {
+                self.poll_input_sources(watch_config);
+            }
+
+            if let Some(brightness) = &mut brightness
+                && let Some(percent) = brightness.update(&jiff::Zoned::now())
+            {
+                self.adjust_brightness(brightness, percent);
+            }
+
+            if let Some(night) = &mut night
+                && let Some(percent) = night.update(&jiff::Zoned::now())
+            {
+                self.adjust_night(night, percent);
+            }
+
+            if let Some(usb_watcher) = &mut usb_watcher
+                && let Some(connected) = usb_watcher.update()
+            {
+                let event = if connected {
+                    info!("USB device connected");
+                    RuleEvent::UsbConnect
+                } else {
+                    info!("USB device disconnected");
+                    RuleEvent::UsbDisconnect
+                };
+                self.run_rules(&rules, event, None);
+            }
+
+            self.finish_daemon_run();
+        }
+    }
+
+    /// Wait for display monitors, and save and notify the changes
+    /// made in an iteration of the `daemon`.
+    fn finish_daemon_run(&mut self) {
+        self.arg_results.clear();
+        if self.set_results.is_empty() {
+            return;
+        }
+        self.sleep_all_if_needed();
+        if !self.dry_run {
+            self.save_undo_state();
+            self.run_on_switch_hooks();
+            self.broadcast_set_results();
+        }
+        self.set_results.clear();
+    }
+
+    /// Run the `rules` that match the `event`, in the `daemon`.
+    /// The `monitors` are the display monitors of the `event`,
+    /// or all display monitors if `None`.
+    fn run_rules(
+        &mut self,
+        rules: &[CompiledRule],
+        event: RuleEvent,
+        monitors: Option<Vec<RuleMonitor>>,
+    ) {
+        if !rules.iter().any(|rule| rule.events().contains(&event)) {
+            return;
+        }
+        let monitors = monitors.unwrap_or_else(|| self.rule_monitors(false));
+        let connected = self.rule_inputs(rules, event);
+        let now = jiff::Zoned::now();
+        for rule in rules {
+            if let Some(args) = rule.args(event, &monitors, &connected, &now) {
+                info!("{} matched {event}", rule.location);
+                self.run_daemon_profile_args(&args, rule.profile(), "rule");
+            }
+        }
+    }
+
+    /// All display monitors as [`RuleMonitor`]s,
+    /// as if they're disconnected if `is_removed`.
+    fn rule_monitors(&self, is_removed: bool) -> Vec<RuleMonitor> {
+        self.monitors
+            .iter()
+            .enumerate()
+            .map(|(index, monitor)| RuleMonitor::new((!is_removed).then_some(index), monitor))
+            .collect()
+    }
+
+    /// The connected display monitors with their current input sources,
+    /// if any of the `rules` for the `event` use `input('NAME')`.
+    /// Otherwise empty, to avoid reading input sources of all display monitors.
+    fn rule_inputs(&mut self, rules: &[CompiledRule], event: RuleEvent) -> Vec<RuleMonitor> {
+        if !rules
+            .iter()
+            .any(|rule| rule.uses_input() && rule.events().contains(&event))
+        {
+            return vec![];
+        }
+        let mut connected = self.rule_monitors(false);
+        for (rule_monitor, monitor) in connected.iter_mut().zip(self.monitors.iter_mut()) {
+            rule_monitor.input = monitor.input_source().ok();
+        }
+        connected
+    }
+
+    /// Check the [`Config::rules`], and print them if no `event` is specified.
+    /// Otherwise preview what the rules run for the `event`,
+    /// with all display monitors as the display monitors of the `event`,
+    /// without changing display monitors.
+    fn test_rules(&mut self, event: Option<&str>) -> anyhow::Result<()> {
+        let config = self.config.get_or_insert_default();
+        let config_rules = config.rules.clone();
+        let rules = compile_config_rules(config)?;
+        let Some(event) = event else {
+            for rule in &config_rules {
+                println!("{}\t{}", rule.when, rule.then);
+            }
+            return Ok(());
+        };
+        let event: RuleEvent = event.parse()?;
+        self.dry_run = true;
+        Monitor::set_dry_run(true);
+        let monitors = self.rule_monitors(event == RuleEvent::Unplug);
+        let connected = self.rule_inputs(&rules, event);
+        let now = jiff::Zoned::now();
+        for rule in &rules {
+            if let Some(args) = rule.args(event, &monitors, &connected, &now) {
+                match rule.profile() {
+                    Some(profile) => println!("{}\tprofile {profile}", rule.location),
+                    None => println!("{}\t{}", rule.location, args.join(" ")),
+                }
+                self.run_profile_args(&args, rule.profile())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the `args` in the `daemon` as [`Cli::run_daemon_args()`],
+    /// and return the entries to restore the input sources before the `args`,
+    /// for [`Cli::revert_args()`].
+    fn run_revertible_args(&mut self, args: &[String], trigger: &str) -> Vec<UndoEntry> {
+        if args.is_empty() {
+            return vec![];
+        }
+        let start = self.set_results.len();
+        self.run_daemon_args(args, trigger);
+        self.set_results[start..]
+            .iter()
+            .filter_map(|result| result.undo_entry())
+            .collect()
+    }
+
+    /// Run the `args` in the `daemon` as [`Cli::run_daemon_args()`],
+    /// or restore the input sources in the `undo` if the `args` is empty.
+    fn revert_args(&mut self, args: &[String], undo: Vec<UndoEntry>, trigger: &str) {
+        if !args.is_empty() {
+            self.run_daemon_args(args, trigger);
+            return;
+        }
+        let start = self.set_results.len();
+        for entry in undo {
+            if let Err(e) = self.set_undo_entry(&entry) {
+                error!("{e}");
+            }
+        }
+        if !self.dry_run {
+            self.save_history(start, trigger);
+        }
+    }
 
-impl Cli {
-    /// Construct an instance with display monitors from [`Monitor::enumerate()`].
-    pub fn new() -> Self {
-        Cli {
-            monitors: Monitor::enumerate(),
-            ..Default::default()
+    /// Enumerate display monitors again after [`DockEvent::DisplayChange`],
+    /// and run the [`DockConfig`] and the `rules`
+    /// for display monitors connected or disconnected.
+    fn on_display_change(&mut self, dock_config: Option<&DockConfig>, rules: &[CompiledRule]) {
+        let (removed, added) = match self.refresh_monitors() {
+            Ok(changes) => changes,
+            Err(e) => {
+                warn!("Failed to enumerate display monitors: {e}");
+                return;
+            }
+        };
+        if let Some(dock_config) = dock_config {
+            if !added.is_empty() {
+                if let Some(input) = &dock_config.connected_input {
+                    // Select the connected display monitors by their indices.
+                    let args: Vec<String> = added
+                        .clone()
+                        .map(|index| format!("{index}={input}"))
+                        .collect();
+                    self.run_daemon_args(&args, "dock");
+                }
+                if !dock_config.connected_args.is_empty() {
+                    self.run_daemon_args(&dock_config.connected_args, "dock");
+                }
+            }
+            if !removed.is_empty() && !dock_config.disconnected_args.is_empty() {
+                self.run_daemon_args(&dock_config.disconnected_args, "dock");
+            }
+        }
+        if !added.is_empty() {
+            let monitors = added
+                .map(|index| RuleMonitor::new(Some(index), &self.monitors[index]))
+                .collect();
+            self.run_rules(rules, RuleEvent::Hotplug, Some(monitors));
+        }
+        if !removed.is_empty() {
+            let monitors = removed
+                .iter()
+                .map(|monitor| RuleMonitor::new(None, monitor))
+                .collect();
+            self.run_rules(rules, RuleEvent::Unplug, Some(monitors));
         }
     }
 
-    /// Initialize the logging.
-    /// The configurations depend on [`Cli::verbose`].
-    #[deprecated(
-        since = "1.2.1",
-        note = "To remove loggers from the lib. Please setup your favorite logger."
-    )]
-    pub fn init_logger(&self) {
-        simplelog::CombinedLogger::init(vec![simplelog::TermLogger::new(
-            match self.verbose {
-                0 => LevelFilter::Info,
-                1 => LevelFilter::Debug,
-                _ => LevelFilter::Trace,
-            },
-            simplelog::ConfigBuilder::new()
-                .set_time_level(LevelFilter::Debug)
-                .build(),
-            simplelog::TerminalMode::Mixed,
-            simplelog::ColorChoice::Auto,
-        )])
-        .unwrap();
+    /// Run the `args` and the [`Cli::profile`] on the `daemon --listen` on the `host`,
+    /// and print the output.
+    fn run_remote(&mut self, host: &str) -> anyhow::Result<()> {
+        let request = RemoteRequest {
+            args: self.args.clone(),
+            profile: self.profile.clone(),
+            dry_run: self.dry_run,
+            token: self.config.get_or_insert_default().remote.token.clone(),
+        };
+        let response = remote::send(host, &request)?;
+        print!("{}", response.output);
+        self.set_results = response.results;
+        self.write_results()?;
+        if let Some(error) = response.error {
+            anyhow::bail!("{host}: {error}");
+        }
+        Ok(())
     }
 
-    fn apply_filters(&mut self) -> anyhow::Result<()> {
-        if let Some(backend_str) = &self.backend {
-            self.monitors
-                .retain(|monitor| monitor.contains_backend(backend_str));
+    /// Print the [`Cli::set_results()`] as the `--report`,
+    /// or as the changes they would make if `--dry-run`.
+    /// This should be called even on failures, so that callers can know
+    /// which changes were made before the failure.
+    fn write_results(&self) -> anyhow::Result<()> {
+        if let Some(report) = self.report {
+            return output::write_report(&mut std::io::stdout().lock(), report, &self.set_results);
+        }
+        if self.dry_run {
+            return output::write_dry_run(&mut std::io::stdout().lock(), &self.set_results);
         }
         Ok(())
     }
 
-    fn for_each<C>(&mut self, name: &str, mut callback: C) -> anyhow::Result<()>
-    where
-        C: FnMut(usize, &mut Monitor) -> anyhow::Result<()>,
-    {
-        if let Ok(index) = name.parse::<usize>() {
-            let monitor = &mut self.monitors[index];
-            if self.needs_capabilities {
-                // This may fail in some cases. Print warning but keep looking.
-                let _ = monitor.update_capabilities();
+    /// Run a [`RemoteRequest`] from `--host` in the `daemon`.
+    fn run_remote_request(&mut self, request: RemoteRequest) -> RemoteResponse {
+        Monitor::set_dry_run(self.dry_run || request.dry_run);
+        self.output = Some(vec![]);
+        let start = self.set_results.len();
+        let result = self.run_profile_args(&request.args, request.profile.as_deref());
+        let output = self.output.take().unwrap_or_default();
+        if !self.dry_run && !request.dry_run {
+            self.save_history(start, "remote");
+        }
+        Monitor::set_dry_run(self.dry_run);
+        if let Err(e) = &result {
+            error!("{e}");
+        }
+        RemoteResponse {
+            output: String::from_utf8_lossy(&output).into_owned(),
+            results: self.set_results.clone(),
+            error: result.err().map(|e| e.to_string()),
+        }
+    }
+
+    /// Send the changes in the [`Cli::set_results()`]
+    /// to the clients of the `daemon --websocket`.
+    fn broadcast_set_results(&self) {
+        for result in &self.set_results {
+            if result.success && !result.rolled_back {
+                websocket::broadcast_input_source(&InputSourceChange {
+                    monitor: result.monitor.clone(),
+                    previous: result.previous.clone(),
+                    input_source: result.requested.clone(),
+                });
             }
-            return callback(index, monitor);
         }
+    }
 
-        let mut has_match = false;
-        for (index, monitor) in self.monitors.iter_mut().enumerate() {
-            if self.needs_capabilities {
-                // This may fail in some cases. Print warning but keep looking.
-                let _ = monitor.update_capabilities();
+    /// Read the current input sources,
+    /// and notify changes made outside of this program.
+    fn poll_input_sources(&mut self, watch_config: &WatchConfig) {
+        let state_path = self.state_path_or_default();
+        let indices: Vec<usize> = (0..self.monitors.len()).collect();
+        for change in self.poll_input_changes(&indices) {
+            match change {
+                // The first reads are not changes made outside of this program.
+                Ok(change) if change.previous.is_none() => {}
+                Ok(change) => {
+                    websocket::broadcast_input_source(&change);
+                    change.notify(watch_config, self.report);
+                    let entry = HistoryEntry::new(
+                        &change.monitor,
+                        change.previous.as_deref(),
+                        &change.input_source,
+                        "external",
+                    );
+                    if let Err(e) = append_history(state_path.as_deref(), &[entry]) {
+                        warn!("{e}");
+                    }
+                }
+                Err(e) => debug!("{e:#}"),
             }
-            if !name.is_empty() && !monitor.contains(name) {
+        }
+    }
+
+    /// Change the brightness of the display monitors to the `percent`
+    /// in steps of the `brightness`.
+    fn adjust_brightness(&mut self, brightness: &BrightnessController, percent: u8) {
+        info!("Brightness = {percent}%");
+        let mut steps: Vec<(usize, Vec<Vec<VcpWrite>>)> = vec![];
+        for (index, monitor) in self.monitors.iter_mut().enumerate() {
+            if !brightness.is_target(index, monitor) {
                 continue;
             }
-            has_match = true;
-            callback(index, monitor)?;
-        }
-        if has_match {
-            return Ok(());
+            match monitor.brightness() {
+                Ok(value) => {
+                    let target = (u32::from(value.maximum) * u32::from(percent) / 100) as u16;
+                    let values = brightness
+                        .stepped()
+                        .steps(value.value, target, value.maximum);
+                    let writes = values
+                        .into_iter()
+                        .map(|value| vec![VcpWrite::Brightness(value)])
+                        .collect();
+                    steps.push((index, writes));
+                }
+                Err(e) => debug!("{monitor}: {e}"),
+            }
         }
-
-        anyhow::bail!("No display monitors found for \"{name}\".");
+        self.apply_steps(&steps, brightness.stepped().step_delay());
     }
 
-    fn compute_toggle_set_index(
-        current_input_source: InputSourceRaw,
-        input_sources: &[InputSourceRaw],
-    ) -> usize {
-        input_sources
-            .iter()
-            .position(|v| *v == current_input_source)
-            // Toggle to the next index, or 0 if it's not in the list.
-            .map_or(0, |i| i + 1)
+    /// Shift the red, green, and blue gains of the display monitors
+    /// to the night by the `percent`, in steps of the `night`.
+    fn adjust_night(&mut self, night: &NightController, percent: u8) {
+        let gains = night.gains(percent);
+        info!("Night = {percent}%, gains = {gains:?}");
+        let mut steps: Vec<(usize, Vec<Vec<VcpWrite>>)> = vec![];
+        for (index, monitor) in self.monitors.iter_mut().enumerate() {
+            if !night.is_target(index, monitor) {
+                continue;
+            }
+            if let Some(preset) = night.preset() {
+                match monitor.vcp_feature(COLOR_PRESET) {
+                    Ok(value) if value.value == preset => {}
+                    Ok(_) => {
+                        if let Err(e) = monitor.set_vcp_feature(COLOR_PRESET, preset) {
+                            warn!("{monitor}: {e}");
+                            continue;
+                        }
+                    }
+                    Err(e) => {
+                        debug!("{monitor}: {e}");
+                        continue;
+                    }
+                }
+            }
+            let mut channels: Vec<Vec<VcpWrite>> = vec![];
+            for (code, gain) in RGB_GAINS.into_iter().zip(gains) {
+                match monitor.vcp_feature(code) {
+                    Ok(value) => {
+                        let target = (u32::from(value.maximum) * u32::from(gain) / 100) as u16;
+                        let values = night.stepped().steps(value.value, target, value.maximum);
+                        channels.push(
+                            values
+                                .into_iter()
+                                .map(|value| VcpWrite::Feature(code, value))
+                                .collect(),
+                        );
+                    }
+                    Err(e) => debug!("{monitor}: {e}"),
+                }
+            }
+            // Change the red, green, and blue gains together in each step,
+            // so that the color doesn't tint on the way.
+            let count = channels.iter().map(Vec::len).max().unwrap_or(0);
+            let writes = (0..count)
+                .map(|i| {
+                    channels
+                        .iter()
+                        .filter_map(|writes| writes.get(i))
+                        .copied()
+                        .collect()
+                })
+                .collect();
+            steps.push((index, writes));
+        }
+        self.apply_steps(&steps, night.stepped().step_delay());
     }
 
-    fn toggle(&mut self, name: &str, values: &[&str]) -> anyhow::Result<()> {
-        let mut input_sources: Vec<InputSourceRaw> = vec![];
-        for value in values {
-            input_sources.push(InputSource::raw_from_str(value)?);
-        }
-        let mut set_index = self.set_index;
-        let result = self.for_each(name, |_, monitor: &mut Monitor| {
-            if set_index.is_none() {
-                let current_input_source = monitor.input_source()?;
-                set_index = Some(Self::compute_toggle_set_index(
-                    current_input_source,
-                    &input_sources,
-                ));
-                debug!(
-                    "Set = {index} (because InputSource({monitor}) is {input_source})",
-                    index = set_index.unwrap(),
-                    input_source = InputSource::str_from_raw(current_input_source)
-                );
+    /// Apply the `steps` of each display monitor index with the `delay` between them.
+    /// The steps of all display monitors are interleaved,
+    /// so that they change together.
+    fn apply_steps(&mut self, steps: &[(usize, Vec<Vec<VcpWrite>>)], delay: Duration) {
+        let count = steps
+            .iter()
+            .map(|(_, writes)| writes.len())
+            .max()
+            .unwrap_or(0);
+        for i in 0..count {
+            if i > 0 && self.cancellation.sleep(delay).is_err() {
+                return;
             }
-            let used_index = set_index.unwrap().min(input_sources.len() - 1);
-            let input_source = input_sources[used_index];
-            monitor.set_input_source(input_source)
-        });
-        self.set_index = set_index;
-        result
+            for (index, writes) in steps {
+                if let Some(write) = writes.get(i) {
+                    let monitor = &mut self.monitors[*index];
+                    for (_, result) in monitor.apply(write) {
+                        if let Err(e) = result {
+                            warn!("{monitor}: {e}");
+                        }
+                    }
+                }
+            }
+        }
     }
 
-    fn set(&mut self, name: &str, value: &str) -> anyhow::Result<()> {
-        let toggle_values: Vec<&str> = value.split(',').collect();
-        if toggle_values.len() > 1 {
-            return self.toggle(name, &toggle_values);
+    /// The earliest next run of the `schedules`, with logging.
+    fn next_schedules(
+        schedules: &[Schedule],
+    ) -> anyhow::Result<Option<(jiff::Zoned, Vec<&Schedule>)>> {
+        let next = next_schedules(schedules, &jiff::Zoned::now())?;
+        if let Some((time, due)) = &next {
+            info!(
+                "Next run at {time}: {args}",
+                time = time.strftime("%a %Y-%m-%d %H:%M"),
+                args = due
+                    .iter()
+                    .map(|schedule| schedule.args.join(" "))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            log::logger().flush();
         }
-        let input_source = InputSource::raw_from_str(value)?;
-        self.for_each(name, |_, monitor: &mut Monitor| {
-            monitor.set_input_source(input_source)
-        })
+        Ok(next)
     }
 
-    fn print_list(&mut self, name: &str) -> anyhow::Result<()> {
-        self.for_each(name, |index, monitor| {
-            println!("{index}: {}", monitor.to_long_string());
-            trace!("{monitor:?}");
-            Ok(())
-        })
+    /// Run the `args` in the `daemon`.
+    /// Errors are logged, to keep the `daemon` running.
+    /// The `trigger` is recorded in the history, such as `schedule`.
+    /// Display monitors are enumerated again only on [`DockEvent::DisplayChange`],
+    /// not for each run, because it takes seconds.
+    fn run_daemon_args(&mut self, args: &[String], trigger: &str) {
+        self.run_daemon_profile_args(args, None, trigger);
     }
 
-    fn sleep_all_if_needed(&mut self) {
-        let start_time = Instant::now();
-        for monitor in &mut self.monitors {
-            monitor.sleep_if_needed();
+    /// Run the `args` and the `profile` in the `daemon` as [`Cli::run_profile_args()`],
+    /// with errors logged as [`Cli::run_daemon_args()`].
+    fn run_daemon_profile_args(&mut self, args: &[String], profile: Option<&str>, trigger: &str) {
+        match profile {
+            Some(profile) => info!("Running: {} --profile {profile}", args.join(" ")),
+            None => info!("Running: {}", args.join(" ")),
+        }
+        let start = self.set_results.len();
+        if let Err(e) = self.run_profile_args(args, profile) {
+            error!("{e}");
+            diagnose_error(&e);
+        }
+        if !self.dry_run {
+            self.save_history(start, trigger);
         }
-        debug!("sleep_all() elapsed: {:?}", start_time.elapsed());
     }
 
-    const RE_SET_PATTERN: &str = r"^([^=]+)=(.+)$";
+    /// Append the changes in `set_results[start..]` to the history file.
+    /// Failures are not fatal, because the changes are already made.
+    fn save_history(&self, start: usize, trigger: &str) {
+        let entries: Vec<HistoryEntry> = self.set_results[start..]
+            .iter()
+            .filter_map(|result| HistoryEntry::from_set_result(result, trigger))
+            .collect();
+        if let Err(e) = append_history(self.state_path_or_default().as_deref(), &entries) {
+            warn!("{e}");
+        }
+    }
 
-    /// Run the command line tool.
-    pub fn run(&mut self) -> anyhow::Result<()> {
-        let start_time = Instant::now();
-        Monitor::set_dry_run(self.dry_run);
-        self.apply_filters()?;
+    /// Print the history of changes,
+    /// only for the display monitors whose names contain `monitor` if specified,
+    /// and only the `last` entries if specified.
+    fn history(&mut self, monitor: Option<&str>, last: Option<usize>) -> anyhow::Result<()> {
+        let mut entries = read_history(self.state_path_or_default().as_deref())?;
+        if let Some(monitor) = monitor {
+            entries.retain(|entry| entry.monitor.contains(monitor));
+        }
+        if let Some(last) = last {
+            entries.drain(..entries.len().saturating_sub(last));
+        }
+        output::write_history(&mut std::io::stdout().lock(), self.format, &entries)
+    }
 
-        let re_set = Regex::new(Self::RE_SET_PATTERN).unwrap();
-        let mut has_valid_args = false;
-        let args = self.args.clone();
-        for arg in args {
-            if let Some(captures) = re_set.captures(&arg) {
-                self.set(&captures[1], &captures[2])?;
-                has_valid_args = true;
-                continue;
+    /// Run the [`Config::on_switch`] for each display monitor switched.
+    fn run_on_switch_hooks(&self) {
+        let Some(on_switch) = self
+            .config
+            .as_ref()
+            .and_then(|config| config.on_switch.as_deref())
+        else {
+            return;
+        };
+        for result in &self.set_results {
+            if result.success
+                && !result.rolled_back
+                && result.previous.as_ref() != Some(&result.requested)
+            {
+                run_on_switch(on_switch, result);
             }
+        }
+    }
 
-            self.print_list(&arg)?;
-            has_valid_args = true;
+    /// Save the previous input sources, so that the `undo` can restore them.
+    /// Failures are not fatal, because the changes are already made.
+    fn save_undo_state(&self) {
+        let undo: Vec<UndoEntry> = self
+            .set_results
+            .iter()
+            .filter_map(|result| result.undo_entry())
+            .collect();
+        if undo.is_empty() {
+            return;
         }
-        if !has_valid_args {
-            self.print_list("")?;
+        let mut state = self.load_state().unwrap_or_default();
+        state.undo = undo;
+        if let Err(e) = self.save_state(&state) {
+            warn!("Failed to save the undo state: {e}");
         }
-        self.sleep_all_if_needed();
-        debug!("Elapsed: {:?}", start_time.elapsed());
-        Ok(())
     }
 }
 
@@ -253,6 +2990,90 @@ mod tests {
         assert_eq!(cli.args, ["abc", "def"]);
     }
 
+    #[test]
+    fn cli_parse_log_format() {
+        let cli = Cli::parse_from([""]);
+        assert_eq!(cli.log_format, LogFormat::Text);
+        let cli = Cli::parse_from(["", "--log-format", "json"]);
+        assert_eq!(cli.log_format, LogFormat::Json);
+    }
+
+    #[test]
+    fn cli_parse_quiet() {
+        let cli = Cli::parse_from(["", "-q"]);
+        assert!(cli.quiet);
+        assert!(Cli::try_parse_from(["", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn cli_parse_subcommand() {
+        let cli = Cli::parse_from(["", "undo"]);
+        assert!(matches!(cli.command, Some(Command::Undo)));
+        assert_eq!(cli.args.len(), 0);
+
+        let cli = Cli::parse_from(["", "-n", "undo"]);
+        assert!(cli.dry_run);
+        assert!(matches!(cli.command, Some(Command::Undo)));
+
+        let cli = Cli::parse_from(["", "daemon", "--next"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Daemon {
+                next: true,
+                listen: None,
+                websocket: None
+            })
+        ));
+
+        let cli = Cli::parse_from(["", "history", "--last", "3"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::History {
+                monitor: None,
+                last: Some(3)
+            })
+        ));
+
+        let cli = Cli::parse_from(["", "watch-inputs", "--interval", "500ms"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::WatchInputs { name, interval: Some(interval) })
+                if name.is_empty() && interval == Duration::from_millis(500)
+        ));
+
+        let cli = Cli::parse_from(["", "--version", "--json"]);
+        assert!(cli.version && cli.json);
+        assert!(Cli::try_parse_from(["", "--json"]).is_err());
+
+        let cli = Cli::parse_from(["", "service", "install"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Service {
+                action: ServiceAction::Install
+            })
+        ));
+
+        let cli = Cli::parse_from(["", "rules", "test", "idle(10)"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Rules {
+                action: RulesAction::Test { event: Some(event) }
+            }) if event == "idle(10)"
+        ));
+
+        let cli = Cli::parse_from(["", "autostart", "status"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Autostart {
+                action: AutostartAction::Status
+            })
+        ));
+
+        let cli = Cli::parse_from(["", "abc", "undo"]);
+        assert!(cli.command.is_none());
+        assert_eq!(cli.args, ["abc", "undo"]);
+    }
+
     #[test]
     fn cli_parse_option_after_positional() {
         let cli = Cli::parse_from(["", "abc", "def", "-v"]);
@@ -266,35 +3087,496 @@ mod tests {
         assert_eq!(cli.args, ["-abc", "-def"]);
     }
 
-    fn matches<'a>(re: &'a Regex, input: &'a str) -> Vec<&'a str> {
-        re.captures(input)
-            .unwrap()
-            .iter()
-            .skip(1)
-            .map(|m| m.unwrap().as_str())
+    #[test]
+    fn cli_parse_delay() {
+        let cli = Cli::parse_from(["", "--delay", "1m30s"]);
+        assert_eq!(cli.delay, Some(Duration::from_secs(90)));
+        let cli = Cli::parse_from(["", "--delay", "500ms"]);
+        assert_eq!(cli.delay, Some(Duration::from_millis(500)));
+        assert!(Cli::try_parse_from(["", "--delay", "-5s"]).is_err());
+        assert!(Cli::try_parse_from(["", "--delay", "5s", "--at", "10:00"]).is_err());
+
+        let cli = Cli::parse_from(["", "--at", "18:00"]);
+        assert_eq!(cli.at, Some(jiff::civil::time(18, 0, 0, 0)));
+    }
+
+    #[test]
+    fn duration_until() {
+        let now: jiff::Zoned = "2025-01-01T09:00[UTC]".parse().unwrap();
+        let at = jiff::civil::time(9, 30, 0, 0);
+        assert_eq!(
+            Cli::duration_until(&now, at).unwrap(),
+            Duration::from_secs(30 * 60)
+        );
+        // If the time has passed, it's the next day.
+        let at = jiff::civil::time(8, 0, 0, 0);
+        assert_eq!(
+            Cli::duration_until(&now, at).unwrap(),
+            Duration::from_secs(23 * 60 * 60)
+        );
+    }
+
+    #[test]
+    fn effective_monitor_config() {
+        let mut cli = cli_with_mocks(&[]);
+        cli.config = Some(
+            Config::from_toml(
+                r#"
+                defaults.retries = 2
+                monitors.B.settle_ms = 500
+                profile_settings.p.retries = 0
+                "#,
+            )
+            .unwrap(),
+        );
+        let monitor_config = cli.effective_monitor_config("B", None);
+        assert_eq!(monitor_config.retries, Some(2));
+        assert_eq!(monitor_config.settle_ms, Some(500));
+        cli.settle_ms = Some(100);
+        let monitor_config = cli.effective_monitor_config("B", Some("p"));
+        assert_eq!(monitor_config.retries, Some(0));
+        assert_eq!(monitor_config.settle_ms, Some(100));
+        assert_eq!(
+            toml::to_string(&monitor_config).unwrap(),
+            "settle_ms = 100\nretries = 0\n"
+        );
+    }
+
+    fn cli_with_mocks(args: &[&str]) -> Cli {
+        let mock = |id: &str, value: InputSource| {
+            Monitor::from_device(MockMonitor::new(id).with_input_source(value.as_raw()))
+        };
+        Cli {
+            monitors: vec![
+                mock("A1", InputSource::DisplayPort1),
+                mock("A2", InputSource::Hdmi1),
+                mock("B", InputSource::DisplayPort1),
+            ]
+            .into(),
+            config: Some(Config::from_toml(r#"groups.g = ["A*"]"#).unwrap()),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            state_path: Some(crate::testing::temp_state_path()),
+            ..Default::default()
+        }
+    }
+
+    fn input_sources(cli: &mut Cli) -> Vec<String> {
+        cli.monitors
+            .iter_mut()
+            .map(|monitor| InputSource::str_from_raw(monitor.input_source().unwrap()))
             .collect()
     }
 
     #[test]
-    fn re_set() {
-        let re_set = Regex::new(Cli::RE_SET_PATTERN).unwrap();
-        assert!(!re_set.is_match("a"));
-        assert!(!re_set.is_match("a="));
-        assert!(!re_set.is_match("=a"));
-        assert_eq!(matches(&re_set, "a=b"), vec!["a", "b"]);
-        assert_eq!(matches(&re_set, "1=23"), vec!["1", "23"]);
-        assert_eq!(matches(&re_set, "12=34"), vec!["12", "34"]);
-        assert_eq!(matches(&re_set, "12=3,4"), vec!["12", "3,4"]);
+    fn run_set() {
+        let mut cli = cli_with_mocks(&["B=hdmi2"]);
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["DP1", "Hdmi1", "Hdmi2"]);
+        assert_eq!(cli.set_results().len(), 1);
+
+        let mut cli = cli_with_mocks(&["1=usbc1"]);
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["DP1", "UsbC1", "DP1"]);
+
+        let mut cli = cli_with_mocks(&["g=usbc1"]);
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["UsbC1", "UsbC1", "DP1"]);
+
+        assert!(cli_with_mocks(&["C=dp1"]).run().is_err());
+        assert!(cli_with_mocks(&["3=dp1"]).run().is_err());
+    }
+
+    #[test]
+    fn run_set_if() {
+        let mut cli = cli_with_mocks(&["A1?=dp1:usbc1", "A2?=dp1:usbc1"]);
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["UsbC1", "Hdmi1", "DP1"]);
+        assert_eq!(cli.set_results().len(), 1);
+
+        let mut cli = cli_with_mocks(&["A?=hdmi1:usbc1"]);
+        cli.all = true;
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["DP1", "UsbC1", "DP1"]);
+    }
+
+    #[test]
+    fn run_status() {
+        let status = |profile: &str| {
+            let mut cli = cli_with_mocks(&[]);
+            cli.config = Some(
+                Config::from_toml(
+                    r#"
+                    profiles.home = ["A1=dp1", "B=dp1"]
+                    profiles.work = ["g=dp1"]
+                    profiles.invalid = ["A1"]
+                    "#,
+                )
+                .unwrap(),
+            );
+            cli.command = Some(Command::Status {
+                profile: profile.to_string(),
+            });
+            cli.run()
+        };
+        assert!(status("home").is_ok());
+        assert!(status("work").is_err());
+        assert!(status("invalid").is_err());
+        assert!(status("none").is_err());
+    }
+
+    #[test]
+    fn run_selftest() {
+        let self_test = |name: &str| {
+            let mut cli = cli_with_mocks(&[]);
+            cli.monitors.push(Monitor::from_device(
+                MockMonitor::new("C")
+                    .with_capabilities("(vcp(60(0F 11)))")
+                    .with_input_source(InputSource::DisplayPort1.as_raw()),
+            ));
+            cli.command = Some(Command::Selftest {
+                name: name.to_string(),
+            });
+            cli.run()
+        };
+        assert!(self_test("C").is_ok());
+        assert!(self_test("B").is_err());
+        assert!(self_test("D").is_err());
+    }
+
+    #[test]
+    fn run_set_input_remap() {
+        let mut cli = cli_with_mocks(&["B=usbc1"]);
+        cli.config = Some(Config::from_toml("monitors.B.inputs.UsbC1 = 0x1B").unwrap());
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["DP1", "Hdmi1", "UsbC1"]);
+        assert_eq!(cli.monitors[2].vcp_feature(0x60).unwrap().value, 0x1B);
+    }
+
+    #[test]
+    fn run_set_pbp() {
+        let mut cli = cli_with_mocks(&["B.pbp=2x1"]);
+        assert!(cli.run().is_err());
+        let mut cli = cli_with_mocks(&["B.pbp=2x1"]);
+        cli.monitors[2] =
+            Monitor::from_device(MockMonitor::new("B").with_model_name("DELL U3818DW"));
+        cli.run().unwrap();
+        assert_eq!(cli.monitors[2].pbp_mode().unwrap(), PbpMode::Pbp2x1);
+        cli.args = vec!["B.pbp=3x1".into()];
+        assert!(cli.run().is_err());
+    }
+
+    #[test]
+    fn parse_hex_bytes() {
+        assert_eq!(super::parse_hex_bytes("C2 01").unwrap(), [0xC2, 0x01]);
+        assert_eq!(super::parse_hex_bytes("0xC2,0x1").unwrap(), [0xC2, 0x01]);
+        assert_eq!(super::parse_hex_bytes("c201").unwrap(), [0xC2, 0x01]);
+        assert!(super::parse_hex_bytes("C20").is_err());
+        assert!(super::parse_hex_bytes("XY").is_err());
+    }
+
+    #[test]
+    fn run_raw_ddc() {
+        let raw_ddc = |args: &[&str], read_len: usize| {
+            let mut cli = cli_with_mocks(args);
+            cli.monitors[2] = Monitor::from_device(
+                MockMonitor::new("B").with_raw_reply(&[0xC2, 0x01], &[0x01, 0x02]),
+            );
+            cli.raw_ddc = Some("C2 01".into());
+            cli.raw_ddc_read = read_len;
+            cli.run()
+        };
+        assert!(raw_ddc(&["B"], 2).is_ok());
+        assert!(raw_ddc(&["B"], 0).is_ok());
+        assert!(raw_ddc(&["A1"], 2).is_err());
+        assert!(raw_ddc(&["A"], 0).is_err());
+        assert!(raw_ddc(&["B", "A1"], 0).is_err());
+        assert!(raw_ddc(&[], 0).is_err());
+    }
+
+    #[test]
+    fn run_rollback() {
+        let args = ["A1=usbc1", "A2=usbc1", "C=usbc1"];
+        let mut cli = cli_with_mocks(&args);
+        assert!(cli.run().is_err());
+        assert_eq!(input_sources(&mut cli), ["UsbC1", "UsbC1", "DP1"]);
+
+        let mut cli = cli_with_mocks(&args);
+        cli.rollback = true;
+        assert!(cli.run().is_err());
+        assert_eq!(input_sources(&mut cli), ["DP1", "Hdmi1", "DP1"]);
+        let results = cli.set_results();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.rolled_back));
+        assert!(results.iter().all(|result| result.undo_arg().is_none()));
+    }
+
+    #[test]
+    fn run_no_change() {
+        let mut cli = cli_with_mocks(&["A1=dp1", "A2=dp1"]);
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["DP1", "DP1", "DP1"]);
+        let results = cli.set_results();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.success));
+        assert_eq!(results[0].undo_arg(), None);
+        assert_eq!(results[1].undo_arg().unwrap(), "A2=Hdmi1");
+    }
+
+    #[test]
+    fn run_all() {
+        let mut cli = cli_with_mocks(&["A=usbc1"]);
+        assert!(cli.run().is_err());
+        assert_eq!(input_sources(&mut cli), ["DP1", "Hdmi1", "DP1"]);
+
+        let mut cli = cli_with_mocks(&["A=usbc1"]);
+        cli.all = true;
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["UsbC1", "UsbC1", "DP1"]);
+
+        let mut cli = cli_with_mocks(&["A=usbc1"]);
+        cli.config.as_mut().unwrap().allow_multiple_matches = true;
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["UsbC1", "UsbC1", "DP1"]);
+
+        // Listing doesn't need `--all`.
+        let mut cli = cli_with_mocks(&["A"]);
+        cli.output = Some(vec![]);
+        cli.run().unwrap();
+    }
+
+    #[test]
+    fn run_first() {
+        let mut cli = cli_with_mocks(&["A=usbc1"]);
+        cli.first = true;
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["UsbC1", "Hdmi1", "DP1"]);
+
+        let mut cli = cli_with_mocks(&["A=usbc1"]);
+        cli.exactly_one = true;
+        assert!(cli.run().is_err());
+        assert_eq!(input_sources(&mut cli), ["DP1", "Hdmi1", "DP1"]);
+
+        let mut cli = cli_with_mocks(&["B=usbc1"]);
+        cli.exactly_one = true;
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["DP1", "Hdmi1", "UsbC1"]);
+    }
+
+    #[test]
+    fn run_include_internal() {
+        let internal = || {
+            Monitor::from_device(
+                MockMonitor::new("A-Internal")
+                    .with_internal()
+                    .with_input_source(InputSource::DisplayPort1.as_raw()),
+            )
+        };
+        let mut cli = cli_with_mocks(&["A=usbc1"]);
+        cli.monitors.push(internal());
+        cli.all = true;
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["UsbC1", "UsbC1", "DP1"]);
+
+        let mut cli = cli_with_mocks(&["A=usbc1"]);
+        cli.monitors.push(internal());
+        cli.all = true;
+        cli.include_internal = true;
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["UsbC1", "UsbC1", "DP1", "UsbC1"]);
+    }
+
+    #[test]
+    fn sort_monitors() {
+        let mut cli = cli_with_mocks(&[]);
+        cli.monitors.reverse();
+        cli.apply_filters().unwrap();
+        assert_eq!(cli.ids(&[0, 1, 2]), "B, A2, A1");
+
+        let mut cli = cli_with_mocks(&[]);
+        cli.monitors.reverse();
+        cli.sort = Some(MonitorOrder::Id);
+        cli.apply_filters().unwrap();
+        assert_eq!(cli.ids(&[0, 1, 2]), "A1, A2, B");
+
+        let mut cli = cli_with_mocks(&[]);
+        cli.monitors[0].set_display_output(Some(DisplayOutput {
+            name: "DISPLAY2".to_string(),
+            ..Default::default()
+        }));
+        cli.monitors[2].set_display_output(Some(DisplayOutput {
+            name: "DISPLAY1".to_string(),
+            ..Default::default()
+        }));
+        cli.config = Some(Config::from_toml(r#"sort = "output""#).unwrap());
+        cli.apply_filters().unwrap();
+        assert_eq!(cli.ids(&[0, 1, 2]), "B, A1, A2");
+    }
+
+    #[test]
+    fn exclude_unresponsive() {
+        let failures = BTreeMap::from([("A2".to_string(), 3), ("B".to_string(), 2)]);
+        let mut cli = cli_with_mocks(&[]);
+        cli.failures = failures.clone();
+        cli.apply_filters().unwrap();
+        assert_eq!(cli.ids(&[0, 1]), "A1, B");
+
+        let mut cli = cli_with_mocks(&[]);
+        cli.failures = failures.clone();
+        cli.include_unresponsive = true;
+        cli.apply_filters().unwrap();
+        assert_eq!(cli.monitors.len(), 3);
+
+        let mut cli = cli_with_mocks(&[]);
+        cli.failures = failures;
+        cli.config = Some(Config::from_toml("unresponsive_after = 0").unwrap());
+        cli.apply_filters().unwrap();
+        assert_eq!(cli.monitors.len(), 3);
+    }
+
+    #[test]
+    fn capabilities_cached() {
+        let mut cli = cli_with_mocks(&["B=hdmi1"]);
+        cli.capabilities = Some(CapabilitiesStrategy::Cached);
+        cli.capabilities_cache = Some(BTreeMap::from([(
+            "B".to_string(),
+            CapabilitiesCacheEntry {
+                edid: None,
+                capabilities: "(prot(monitor)mccs_ver(2.2)vcp(60(0F 11)))".to_string(),
+            },
+        )]));
+        cli.run().unwrap();
+        // The mocks don't have capabilities, but they're in the cache.
+        assert_eq!(cli.monitors[2].input_sources(), Some(vec![0x0F, 0x11]));
+        assert_eq!(cli.monitors[0].capabilities(), None);
+        assert!(!cli.is_capabilities_cache_changed);
+    }
+
+    #[test]
+    fn save_failures() {
+        use crate::testing::FakeDevice;
+        let mut cli = cli_with_mocks(&[]);
+        let device = FakeDevice::new("C").with_input_source(InputSource::DisplayPort1);
+        cli.monitors.push(Monitor::from_device(device.clone()));
+        // A display monitor that can't read the input source.
+        cli.monitors
+            .push(Monitor::from_device(MockMonitor::new("D")));
+        cli.failures = BTreeMap::from([("A1".to_string(), 1), ("B".to_string(), 2)]);
+        cli.monitors[0].input_source().unwrap();
+        cli.monitors[3].input_source().unwrap();
+        device.fail_code(INPUT_SELECT);
+        assert!(cli.monitors[3].input_source().is_err());
+        assert!(cli.monitors[4].input_source().is_err());
+        cli.save_failures();
+        assert_eq!(
+            cli.failures,
+            BTreeMap::from([("B".to_string(), 2), ("C".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn failure_keys() {
+        let generic = |output: Option<&str>| {
+            let mut monitor = Monitor::from_device(MockMonitor::new("Generic PnP Monitor"));
+            monitor.set_display_output(output.map(|name| DisplayOutput {
+                name: name.into(),
+                ..Default::default()
+            }));
+            monitor
+        };
+        let monitors = [
+            generic(Some(r"\\.\DISPLAY1")),
+            generic(Some(r"\\.\DISPLAY2")),
+            generic(None),
+            generic(None),
+        ];
+        assert_eq!(
+            super::failure_keys(&monitors),
+            [
+                r"Generic PnP Monitor \\.\DISPLAY1",
+                r"Generic PnP Monitor \\.\DISPLAY2",
+                "Generic PnP Monitor",
+                "Generic PnP Monitor #1",
+            ]
+        );
+    }
+
+    #[test]
+    fn run_unsupported() {
+        let with_capabilities = |capabilities: &str| {
+            let mut monitor = Monitor::from_device(
+                MockMonitor::new("C")
+                    .with_input_source(InputSource::DisplayPort1.as_raw())
+                    .with_capabilities(capabilities),
+            );
+            monitor.update_capabilities().unwrap();
+            monitor
+        };
+        let mut cli = cli_with_mocks(&["C=hdmi1"]);
+        cli.monitors
+            .push(with_capabilities("(prot(monitor)vcp(10))"));
+        assert!(cli.run().is_err());
+
+        let mut cli = cli_with_mocks(&["C=hdmi1"]);
+        cli.monitors
+            .push(with_capabilities("(prot(monitor)vcp(10))"));
+        cli.force = true;
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["DP1", "Hdmi1", "DP1", "Hdmi1"]);
+
+        let mut cli = cli_with_mocks(&["C=hdmi1"]);
+        cli.monitors
+            .push(with_capabilities("(prot(monitor)vcp(60(0F 11)))"));
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["DP1", "Hdmi1", "DP1", "Hdmi1"]);
+    }
+
+    #[test]
+    fn run_toggle() {
+        // The first display monitor determines the input source to set.
+        let mut cli = cli_with_mocks(&["g=dp1,hdmi1"]);
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["Hdmi1", "Hdmi1", "DP1"]);
+
+        cli.args = vec!["A=hdmi1,dp1".into()];
+        cli.all = true;
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["DP1", "DP1", "DP1"]);
+
+        // Wrap around to the first entry.
+        cli.args = vec!["B=hdmi1,dp1".into()];
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["DP1", "DP1", "Hdmi1"]);
+
+        let mut cli = cli_with_mocks(&["B=dp1,hdmi1,usbc1~2"]);
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["DP1", "Hdmi1", "UsbC1"]);
+
+        let mut cli = cli_with_mocks(&["B=dp1,hdmi1,usbc1"]);
+        cli.reverse = true;
+        cli.run().unwrap();
+        assert_eq!(input_sources(&mut cli), ["DP1", "Hdmi1", "UsbC1"]);
+
+        assert!(cli_with_mocks(&["B=dp1,hdmi1~x"]).run().is_err());
     }
 
     #[test]
     fn compute_toggle_set_index() {
-        assert_eq!(Cli::compute_toggle_set_index(1, &[1, 4, 9]), 1);
-        assert_eq!(Cli::compute_toggle_set_index(4, &[1, 4, 9]), 2);
-        assert_eq!(Cli::compute_toggle_set_index(9, &[1, 4, 9]), 3);
+        assert_eq!(Cli::compute_toggle_set_index(1, &[1, 4, 9], 1), 1);
+        assert_eq!(Cli::compute_toggle_set_index(4, &[1, 4, 9], 1), 2);
+        // It wraps around to the first entry.
+        assert_eq!(Cli::compute_toggle_set_index(9, &[1, 4, 9], 1), 0);
         // The result should be 0 if the `value` isn't in the list.
-        assert_eq!(Cli::compute_toggle_set_index(0, &[1, 4, 9]), 0);
-        assert_eq!(Cli::compute_toggle_set_index(2, &[1, 4, 9]), 0);
-        assert_eq!(Cli::compute_toggle_set_index(10, &[1, 4, 9]), 0);
+        assert_eq!(Cli::compute_toggle_set_index(0, &[1, 4, 9], 1), 0);
+        assert_eq!(Cli::compute_toggle_set_index(2, &[1, 4, 9], 1), 0);
+        assert_eq!(Cli::compute_toggle_set_index(10, &[1, 4, 9], 1), 0);
+
+        assert_eq!(Cli::compute_toggle_set_index(1, &[1, 4, 9], 2), 2);
+        assert_eq!(Cli::compute_toggle_set_index(4, &[1, 4, 9], 2), 0);
+        assert_eq!(Cli::compute_toggle_set_index(0, &[1, 4, 9], 2), 1);
+
+        assert_eq!(Cli::compute_toggle_set_index(1, &[1, 4, 9], -1), 2);
+        assert_eq!(Cli::compute_toggle_set_index(9, &[1, 4, 9], -1), 1);
+        // The result should be the last entry if the `value` isn't in the list.
+        assert_eq!(Cli::compute_toggle_set_index(0, &[1, 4, 9], -1), 2);
     }
 }
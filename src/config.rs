@@ -0,0 +1,761 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::logging::*;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{CapabilitiesStrategy, MonitorOrder, Rule, Schedule};
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+/// The configurations of the command line tool.
+///
+/// The configurations are read from a [TOML] file.
+/// See [`Config::default_path()`] for the location.
+/// # Examples
+/// ```toml
+/// [groups]
+/// desk = ["DELL*", "LG*"]
+///
+/// [monitors."DELL U2723QE"]
+/// settle_ms = 500
+///
+/// [[schedules]]
+/// time = "09:00"
+/// days = ["weekdays"]
+/// args = ["desk=DP1"]
+/// ```
+///
+/// [TOML]: https://toml.io/
+pub struct Config {
+    /// Named groups of display monitors.
+    /// Each group is a list of patterns of display monitor names,
+    /// where `*` matches any characters and `?` matches one character.
+    pub groups: BTreeMap<String, Vec<String>>,
+
+    /// The configurations for all display monitors,
+    /// overridden by [`Config::monitors`] and [`Config::profile_settings`].
+    pub defaults: MonitorConfig,
+
+    /// Configurations for each display monitor.
+    /// The key is the name of the display monitor, or a pattern as in [`Config::groups`].
+    pub monitors: BTreeMap<String, MonitorConfig>,
+
+    /// Named sets of `name=input` arguments,
+    /// such as `work = ["U2723=dp1", "P3223=hdmi1"]`.
+    /// The `status` command checks if the display monitors are in a profile.
+    /// [`ProfileStore`] parses them into [`Profile`]s for libraries.
+    pub profiles: BTreeMap<String, Vec<String>>,
+
+    /// The configurations for all display monitors while running a profile by `--profile`,
+    /// overriding [`Config::monitors`]. The key is the name of the profile.
+    pub profile_settings: BTreeMap<String, MonitorConfig>,
+
+    /// The rules to run at times of the day, in the `daemon` command.
+    pub schedules: Vec<Schedule>,
+
+    /// The rule to run when the user is idle, in the `daemon` command.
+    pub idle: Option<IdleConfig>,
+
+    /// Watch changes of input sources, in the `daemon` command.
+    pub watch: Option<WatchConfig>,
+
+    /// The rules to run when display monitors are connected or disconnected,
+    /// or when the power source changes, in the `daemon` command.
+    pub dock: Option<DockConfig>,
+
+    /// The rules to run when the session is locked or unlocked,
+    /// or when the lid is closed or opened, in the `daemon` command.
+    pub session: Option<SessionConfig>,
+
+    /// The rules to run when their conditions are met, in the `daemon` command.
+    pub rules: Vec<Rule>,
+
+    /// Adjust the brightness by the time of the day or the ambient light,
+    /// in the `daemon` command.
+    pub brightness: Option<BrightnessConfig>,
+
+    /// Shift the color temperature warmer in the evening, in the `daemon` command.
+    pub night: Option<NightConfig>,
+
+    /// Switch input sources when a USB device is connected or disconnected,
+    /// compatible with display-switch, in the `daemon` command.
+    pub usb: Option<UsbConfig>,
+
+    /// The configurations of logging.
+    pub log: LogConfig,
+
+    /// The configurations of `--host` and `daemon --listen`.
+    pub remote: RemoteConfig,
+
+    /// Change all display monitors when a part of names matches multiple display monitors,
+    /// as if `--all` is specified.
+    pub allow_multiple_matches: bool,
+
+    /// When to get capabilities from the display monitors,
+    /// if `--capabilities` isn't specified.
+    pub capabilities: Option<CapabilitiesStrategy>,
+
+    /// The order of display monitors, which determines their indices,
+    /// if `--sort` isn't specified.
+    pub sort: Option<MonitorOrder>,
+
+    /// Skip display monitors that failed to communicate in this number of runs in a row,
+    /// unless `--include-unresponsive` is specified.
+    /// It's [`Config::DEFAULT_UNRESPONSIVE_AFTER`] if omitted, and `0` never skips.
+    pub unresponsive_after: Option<u32>,
+
+    /// The command to run after each display monitor is switched,
+    /// such as `"switch-audio.sh {monitor} {input}"`.
+    /// `{monitor}`, `{input}`, and `{previous}` are replaced
+    /// with the display monitor name, the new and the previous input sources.
+    pub on_switch: Option<String>,
+
+    /// The format to display display monitors, such as `"{model} ({serial})"`,
+    /// if `--id-format` isn't specified.
+    /// See [`Monitor::format_id()`][crate::Monitor::format_id] for the placeholders.
+    pub id_format: Option<String>,
+
+    /// The language of messages, such as `en` or `ja`.
+    /// It's detected from the system settings if omitted.
+    pub language: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+/// The configurations for a display monitor.
+/// See [`Config::monitors`].
+pub struct MonitorConfig {
+    /// Additional milliseconds to wait after changes,
+    /// for display monitors that need more time than DDC/CI requires.
+    pub settle_ms: Option<u64>,
+
+    /// The number of times to retry failed requests.
+    /// Display monitors behind DisplayPort MST hubs retry by default.
+    pub retries: Option<u32>,
+
+    /// Read the input source after changing it,
+    /// and fail if the display monitor didn't switch.
+    pub verify: Option<bool>,
+
+    /// The input source this computer is connected to.
+    /// This is used by the `--safe` option.
+    pub local_input: Option<String>,
+
+    /// The values the display monitor uses for input sources, instead of the standard ones,
+    /// such as `UsbC1 = 0x1B`.
+    /// The keys are input source names as in `name=input`.
+    /// The values are 16 bits, for display monitors that use the high byte.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub inputs: BTreeMap<String, u16>,
+}
+
+impl MonitorConfig {
+    /// Override the configurations by the ones specified in the `other`.
+    pub fn merge(&mut self, other: &MonitorConfig) {
+        if other.settle_ms.is_some() {
+            self.settle_ms = other.settle_ms;
+        }
+        if other.retries.is_some() {
+            self.retries = other.retries;
+        }
+        if other.verify.is_some() {
+            self.verify = other.verify;
+        }
+        if other.local_input.is_some() {
+            self.local_input.clone_from(&other.local_input);
+        }
+        self.inputs.extend(
+            other
+                .inputs
+                .iter()
+                .map(|(name, value)| (name.clone(), *value)),
+        );
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+/// The rule to run when the user is idle.
+/// See [`Config::idle`].
+/// # Examples
+/// ```toml
+/// [idle]
+/// minutes = 10
+/// args = ["desk=Hdmi1"]
+/// ```
+pub struct IdleConfig {
+    /// The minutes of no user inputs to consider idle.
+    pub minutes: u64,
+
+    /// The command line arguments to run when the user becomes idle.
+    pub args: Vec<String>,
+
+    #[serde(default)]
+    /// The command line arguments to run when the user becomes active again.
+    /// If empty, the input sources before [`IdleConfig::args`] are restored.
+    pub active_args: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+/// The configurations to watch changes of input sources
+/// made outside of this program, such as by the on-screen display.
+/// See [`Config::watch`].
+/// # Examples
+/// ```toml
+/// [watch]
+/// interval_secs = 5
+/// webhook = "https://example.com/hook"
+/// notification = true
+/// ```
+pub struct WatchConfig {
+    /// The interval in seconds to read the current input sources.
+    pub interval_secs: u64,
+
+    /// The URL to post the changes to, as JSON.
+    /// Requires the `webhook` feature.
+    pub webhook: Option<String>,
+
+    /// Show desktop notifications of the changes.
+    pub notification: bool,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 5,
+            webhook: None,
+            notification: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+/// The configurations to adjust the brightness.
+/// See [`Config::brightness`].
+/// # Examples
+/// ```toml
+/// [brightness]
+/// schedule = [{ time = "07:00", percent = 80 }, { time = "19:00", percent = 30 }]
+/// sensor = true
+/// ```
+pub struct BrightnessConfig {
+    /// The brightness at times of the day.
+    /// The brightness between them is interpolated linearly.
+    pub schedule: Vec<BrightnessPoint>,
+
+    /// Use the ambient light sensor if available, instead of the `schedule`.
+    /// This is available only on Linux.
+    pub sensor: bool,
+
+    /// The brightness in percent in the dark, for the `sensor`.
+    pub min_percent: u8,
+
+    /// The brightness in percent at the `max_lux`, for the `sensor`.
+    pub max_percent: u8,
+
+    /// The ambient light in lux to use the `max_percent`, for the `sensor`.
+    pub max_lux: f64,
+
+    /// The percent to change at a time, to avoid visible jumps.
+    pub step_percent: u8,
+
+    /// The milliseconds between the steps.
+    pub step_ms: u64,
+
+    /// The interval in seconds to adjust the brightness.
+    pub interval_secs: u64,
+
+    /// The names of the display monitors to adjust,
+    /// matched by the same rules as names in the command line. All if empty.
+    pub monitors: Vec<String>,
+}
+
+impl Default for BrightnessConfig {
+    fn default() -> Self {
+        Self {
+            schedule: vec![],
+            sensor: false,
+            min_percent: 10,
+            max_percent: 100,
+            max_lux: 400.0,
+            step_percent: 2,
+            step_ms: 100,
+            interval_secs: 60,
+            monitors: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+/// The brightness at a time of the day. See [`BrightnessConfig::schedule`].
+pub struct BrightnessPoint {
+    /// The time of the day, such as `07:00`.
+    pub time: String,
+
+    /// The brightness in percent.
+    pub percent: u8,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+/// The configurations to shift the color temperature at night
+/// by the red, green, and blue gains of display monitors.
+/// See [`Config::night`].
+/// # Examples
+/// ```toml
+/// [night]
+/// start = "21:00"
+/// end = "07:00"
+/// night = [100, 80, 60]
+/// exclude = ["PA278"]
+/// ```
+pub struct NightConfig {
+    /// The time of the day to start shifting to the `night` gains, such as `21:00`.
+    pub start: String,
+
+    /// The time of the day to start shifting back to the `day` gains, such as `07:00`.
+    pub end: String,
+
+    /// The minutes to shift gradually after the `start` and the `end`.
+    pub transition_mins: u64,
+
+    /// The red, green, and blue gains in percent of their maximums in the day.
+    pub day: [u8; 3],
+
+    /// The red, green, and blue gains in percent of their maximums at night.
+    pub night: [u8; 3],
+
+    /// The color preset to select before changing the gains, such as `0x0B` for "User 1",
+    /// for display monitors that use the gains only in the user presets.
+    pub preset: Option<u16>,
+
+    /// The percent to change at a time, to avoid visible jumps.
+    pub step_percent: u8,
+
+    /// The milliseconds between the steps.
+    pub step_ms: u64,
+
+    /// The interval in seconds to adjust the gains.
+    pub interval_secs: u64,
+
+    /// The names of the display monitors not to adjust,
+    /// such as ones calibrated for color-sensitive work,
+    /// matched by the same rules as names in the command line.
+    pub exclude: Vec<String>,
+}
+
+impl Default for NightConfig {
+    fn default() -> Self {
+        Self {
+            start: "21:00".into(),
+            end: "07:00".into(),
+            transition_mins: 60,
+            day: [100, 100, 100],
+            night: [100, 80, 60],
+            preset: None,
+            step_percent: 2,
+            step_ms: 100,
+            interval_secs: 60,
+            exclude: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+/// The input sources to switch to when a USB device is connected or disconnected,
+/// in the same keys as `display-switch.ini` of display-switch.
+/// The `[monitorN]` sections of display-switch are `[usb.monitors.monitorN]`.
+/// They run as the [`Config::rules`] on `usb_connect` and `usb_disconnect`,
+/// which other rules can also use.
+/// See [`Config::usb`].
+/// # Examples
+/// ```toml
+/// [usb]
+/// usb_device = "1050:0407"
+/// on_usb_connect = "Hdmi1"
+/// on_usb_disconnect = "DisplayPort2"
+///
+/// [usb.monitors.monitor1]
+/// monitor_id = "DELL"
+/// on_usb_connect = "DisplayPort1"
+/// ```
+pub struct UsbConfig {
+    /// The hexadecimal vendor ID and product ID of the USB device to watch,
+    /// such as `"1050:0407"`.
+    pub usb_device: String,
+
+    /// The input source to set all display monitors to when the USB device is connected.
+    pub on_usb_connect: Option<String>,
+
+    /// The input source to set all display monitors to when the USB device is disconnected.
+    pub on_usb_disconnect: Option<String>,
+
+    /// The interval in milliseconds to check the USB device.
+    pub interval_ms: u64,
+
+    /// The input sources for display monitors,
+    /// which take precedence over the `on_usb_connect` and the `on_usb_disconnect`.
+    /// The keys are the section names, such as `monitor1`.
+    pub monitors: BTreeMap<String, UsbMonitorConfig>,
+}
+
+impl Default for UsbConfig {
+    fn default() -> Self {
+        Self {
+            usb_device: String::new(),
+            on_usb_connect: None,
+            on_usb_disconnect: None,
+            interval_ms: 1000,
+            monitors: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+/// The input sources of display monitors in a [`UsbConfig`].
+pub struct UsbMonitorConfig {
+    /// The name of the display monitor,
+    /// matched by the same rules as names in the command line.
+    pub monitor_id: String,
+
+    /// The input source to set to when the USB device is connected.
+    pub on_usb_connect: Option<String>,
+
+    /// The input source to set to when the USB device is disconnected.
+    pub on_usb_disconnect: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+/// The rules to run on docking and undocking.
+/// See [`Config::dock`].
+/// # Examples
+/// ```toml
+/// [dock]
+/// connected_input = "DP1"
+/// disconnected_args = ["desk=Hdmi1"]
+/// battery_args = ["desk=UsbC1"]
+/// ```
+pub struct DockConfig {
+    /// The input source to set display monitors to when they're connected,
+    /// such as `"DP1"`.
+    pub connected_input: Option<String>,
+
+    /// The command line arguments to run when display monitors are connected.
+    pub connected_args: Vec<String>,
+
+    /// The command line arguments to run when display monitors are disconnected.
+    pub disconnected_args: Vec<String>,
+
+    /// The command line arguments to run when the AC power is connected.
+    pub ac_args: Vec<String>,
+
+    /// The command line arguments to run when the AC power is disconnected.
+    pub battery_args: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+/// The rules to run on locking the session and closing the lid.
+/// See [`Config::session`].
+/// # Examples
+/// ```toml
+/// [session]
+/// lock_args = ["desk=Hdmi1"]
+/// ```
+pub struct SessionConfig {
+    /// The command line arguments to run when the session is locked.
+    pub lock_args: Vec<String>,
+
+    /// The command line arguments to run when the session is unlocked.
+    /// If empty, the input sources before [`SessionConfig::lock_args`] are restored.
+    pub unlock_args: Vec<String>,
+
+    /// The command line arguments to run when the lid is closed.
+    pub lid_close_args: Vec<String>,
+
+    /// The command line arguments to run when the lid is opened.
+    /// If empty, the input sources before [`SessionConfig::lid_close_args`] are restored.
+    pub lid_open_args: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+/// The configurations of logging.
+/// See [`Config::log`].
+/// # Examples
+/// ```toml
+/// [log]
+/// file = "/var/log/monitor-input.log"
+/// max_bytes = 1048576
+/// ```
+pub struct LogConfig {
+    /// The path of the log file. The `--log-file` option has priority.
+    pub file: Option<PathBuf>,
+
+    /// The size in bytes to rotate the log file at.
+    pub max_bytes: u64,
+
+    /// The number of rotated log files to keep.
+    pub max_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            max_bytes: 1024 * 1024,
+            max_files: 3,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+/// The configurations of `--host` and `daemon --listen`.
+/// See [`Config::remote`].
+pub struct RemoteConfig {
+    /// The token to authenticate requests.
+    /// The `daemon --listen` rejects requests without the same token.
+    pub token: Option<String>,
+    /// The origins of web pages allowed to connect to the `daemon --websocket`
+    /// without the [`RemoteConfig::token`], such as `"https://example.com"`.
+    pub origins: Vec<String>,
+}
+
+impl Config {
+    /// The default of [`Config::unresponsive_after`].
+    pub const DEFAULT_UNRESPONSIVE_AFTER: u32 = 3;
+
+    /// The default path of the configuration file,
+    /// `monitor-input/config.toml` in the [`dirs::config_dir()`].
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("monitor-input").join("config.toml"))
+    }
+
+    /// Load the configurations from the `path`,
+    /// or from the [`Config::default_path()`] if `None`.
+    /// The default path is optional; it's not an error if it doesn't exist.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        if let Some(path) = path {
+            return Self::load_from(path);
+        }
+        match Self::default_path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    fn load_from(path: &Path) -> anyhow::Result<Self> {
+        debug!("Config: {path:?}");
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the config file {path:?}"))?;
+        Self::from_toml(&text).with_context(|| format!("Failed to parse the config file {path:?}"))
+    }
+
+    /// Get the [`MonitorConfig`] for the display monitor of the `name`.
+    /// The exact match has priority over patterns.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::Config;
+    /// let config = Config::from_toml(r#"monitors."DELL*".settle_ms = 500"#).unwrap();
+    /// assert_eq!(config.monitor("DELL U2723QE").unwrap().settle_ms, Some(500));
+    /// assert!(config.monitor("LG").is_none());
+    /// ```
+    pub fn monitor(&self, name: &str) -> Option<&MonitorConfig> {
+        if let Some(monitor_config) = self.monitors.get(name) {
+            return Some(monitor_config);
+        }
+        self.monitors.iter().find_map(|(pattern, monitor_config)| {
+            crate::selector::regex_from_wildcard(pattern)
+                .is_ok_and(|re| re.is_match(name))
+                .then_some(monitor_config)
+        })
+    }
+
+    /// Get the [`MonitorConfig`] for the display monitor of the `name`,
+    /// merging the [`Config::defaults`], the [`Config::monitor()`],
+    /// and the [`Config::profile_settings`] of the `profile`, in this order.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::Config;
+    /// let config = Config::from_toml(r#"
+    ///     defaults = { retries = 2, settle_ms = 100 }
+    ///     monitors."DELL*" = { settle_ms = 500 }
+    ///     profile_settings.game = { retries = 0 }
+    /// "#).unwrap();
+    /// let effective = config.effective_monitor("DELL U2723QE", Some("game"));
+    /// assert_eq!(effective.settle_ms, Some(500));
+    /// assert_eq!(effective.retries, Some(0));
+    /// ```
+    pub fn effective_monitor(&self, name: &str, profile: Option<&str>) -> MonitorConfig {
+        let mut effective = self.defaults.clone();
+        if let Some(monitor_config) = self.monitor(name) {
+            effective.merge(monitor_config);
+        }
+        if let Some(profile_config) = profile.and_then(|profile| self.profile_settings.get(profile))
+        {
+            effective.merge(profile_config);
+        }
+        effective
+    }
+
+    /// Parse the configurations from a TOML string.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::Config;
+    /// let config = Config::from_toml(r#"groups.desk = ["DELL*", "LG*"]"#).unwrap();
+    /// assert_eq!(config.groups["desk"], ["DELL*", "LG*"]);
+    /// ```
+    pub fn from_toml(text: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_toml() {
+        let config = Config::from_toml("").unwrap();
+        assert!(config.groups.is_empty());
+
+        let config = Config::from_toml(
+            r#"
+            [groups]
+            desk = ["DELL*", "LG*"]
+            tv = ["Sony"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.groups.len(), 2);
+        assert_eq!(config.groups["tv"], ["Sony"]);
+
+        assert!(Config::from_toml("groups = 1").is_err());
+
+        let config = Config::from_toml(r#"profiles.work = ["desk=DP1"]"#).unwrap();
+        assert_eq!(config.profiles["work"], ["desk=DP1"]);
+
+        let config = Config::from_toml(
+            r#"
+            [[schedules]]
+            time = "09:00"
+            days = ["weekdays"]
+            args = ["desk=DP1"]
+            [[schedules]]
+            time = "18:00"
+            args = ["desk=Hdmi1"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.schedules.len(), 2);
+        assert_eq!(config.schedules[1].args, ["desk=Hdmi1"]);
+        assert!(config.schedules[1].days.is_empty());
+        assert!(config.idle.is_none());
+
+        let config = Config::from_toml(
+            r#"
+            [idle]
+            minutes = 10
+            args = ["desk=Hdmi1"]
+            "#,
+        )
+        .unwrap();
+        let idle = config.idle.unwrap();
+        assert_eq!(idle.minutes, 10);
+        assert!(idle.active_args.is_empty());
+
+        let config = Config::from_toml("[watch]").unwrap();
+        let watch = config.watch.unwrap();
+        assert_eq!(watch.interval_secs, 5);
+        assert!(watch.webhook.is_none());
+        assert!(config.dock.is_none());
+
+        let config = Config::from_toml(
+            r#"
+            [dock]
+            connected_input = "DP1"
+            battery_args = ["desk=UsbC1"]
+            "#,
+        )
+        .unwrap();
+        let dock = config.dock.unwrap();
+        assert_eq!(dock.connected_input.as_deref(), Some("DP1"));
+        assert!(dock.connected_args.is_empty());
+        assert_eq!(dock.battery_args, ["desk=UsbC1"]);
+
+        let config = Config::from_toml(r#"session.lock_args = ["desk=Hdmi1"]"#).unwrap();
+        let session = config.session.unwrap();
+        assert_eq!(session.lock_args, ["desk=Hdmi1"]);
+        assert!(session.unlock_args.is_empty());
+        assert!(config.rules.is_empty());
+
+        let config = Config::from_toml(
+            r#"
+            [[rules]]
+            when = "hotplug && monitor ~ 'DELL'"
+            then = "set DP1"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.rules.len(), 1);
+        assert_eq!(config.rules[0].then, "set DP1");
+
+        let config = Config::from_toml(r#"log.file = "a.log""#).unwrap();
+        assert_eq!(config.log.file, Some(PathBuf::from("a.log")));
+        assert_eq!(config.log.max_files, 3);
+
+        let config = Config::from_toml(r#"on_switch = "a.sh {monitor}""#).unwrap();
+        assert_eq!(config.on_switch.as_deref(), Some("a.sh {monitor}"));
+        assert!(!config.allow_multiple_matches);
+    }
+
+    #[test]
+    fn monitor() {
+        let config = Config::from_toml(
+            r#"
+            [monitors."DELL*"]
+            settle_ms = 100
+            [monitors."DELL U2723QE"]
+            settle_ms = 200
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.monitor("DELL U2723QE").unwrap().settle_ms, Some(200));
+        assert_eq!(config.monitor("DELL P3223QE").unwrap().settle_ms, Some(100));
+        assert!(config.monitor("LG").is_none());
+    }
+
+    #[test]
+    fn effective_monitor() {
+        let config = Config::from_toml(
+            r#"
+            [defaults]
+            settle_ms = 100
+            verify = true
+            [monitors."DELL*"]
+            settle_ms = 500
+            inputs.UsbC1 = 0x1B
+            [profile_settings.game]
+            verify = false
+            "#,
+        )
+        .unwrap();
+        let effective = config.effective_monitor("DELL U2723QE", None);
+        assert_eq!(effective.settle_ms, Some(500));
+        assert_eq!(effective.verify, Some(true));
+        assert_eq!(effective.inputs["UsbC1"], 0x1B);
+        let effective = config.effective_monitor("DELL U2723QE", Some("game"));
+        assert_eq!(effective.settle_ms, Some(500));
+        assert_eq!(effective.verify, Some(false));
+        let effective = config.effective_monitor("LG", Some("unknown"));
+        assert_eq!(effective.settle_ms, Some(100));
+        assert!(effective.inputs.is_empty());
+    }
+}
@@ -0,0 +1,525 @@
+use super::*;
+use crate::logging::*;
+
+/// The severity of a [`ConfigIssue`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Severity {
+    /// Something that may not work as expected,
+    /// such as a display monitor that isn't connected now.
+    Warning,
+    /// Something that fails when it runs.
+    Error,
+}
+
+/// A problem in the configuration file found by [`validate_config()`].
+#[derive(Debug, PartialEq)]
+pub(crate) struct ConfigIssue {
+    pub severity: Severity,
+    /// Where in the configuration file, such as `profiles.work`.
+    pub location: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{severity}: {}: {}", self.location, self.message)
+    }
+}
+
+/// Check the `config` against the `monitors` connected now.
+/// Input source names that can't be parsed and conflicting rules are errors,
+/// and display monitors not found or input sources not in the capabilities are warnings.
+pub(crate) fn validate_config(config: &Config, monitors: &mut [Monitor]) -> Vec<ConfigIssue> {
+    let mut checker = Checker {
+        config,
+        monitors,
+        issues: vec![],
+    };
+    checker.check_monitors();
+    checker.check_profiles();
+    checker.check_schedules();
+    checker.check_events();
+    checker.check_rules();
+    checker.issues
+}
+
+struct Checker<'a> {
+    config: &'a Config,
+    monitors: &'a mut [Monitor],
+    issues: Vec<ConfigIssue>,
+}
+
+impl Checker<'_> {
+    fn push(&mut self, severity: Severity, location: &str, message: String) {
+        self.issues.push(ConfigIssue {
+            severity,
+            location: location.to_string(),
+            message,
+        });
+    }
+
+    fn error(&mut self, location: &str, message: String) {
+        self.push(Severity::Error, location, message);
+    }
+
+    fn warning(&mut self, location: &str, message: String) {
+        self.push(Severity::Warning, location, message);
+    }
+
+    /// Whether any display monitors match the wildcard `pattern`,
+    /// as the keys of [`Config::monitors`] and the patterns of [`Config::groups`].
+    fn matches_pattern(&self, pattern: &str) -> bool {
+        let re = crate::selector::regex_from_wildcard(pattern).ok();
+        self.monitors.iter().any(|monitor| {
+            monitor.id() == pattern || re.as_ref().is_some_and(|re| re.is_match(monitor.id()))
+        })
+    }
+
+    /// The indices of the display monitors the `name` of args selects.
+    fn matched_indices(&mut self, location: &str, name: &str) -> Vec<usize> {
+        let selector = match Selector::parse(name, self.config) {
+            Ok(selector) => selector,
+            Err(e) => {
+                self.error(location, format!("\"{name}\": {e}"));
+                return vec![];
+            }
+        };
+        let indices: Vec<usize> = self
+            .monitors
+            .iter()
+            .enumerate()
+            .filter(|(index, monitor)| selector.matches_monitor(*index, monitor))
+            .map(|(index, _)| index)
+            .collect();
+        if indices.is_empty() {
+            self.warning(
+                location,
+                format!("\"{name}\" doesn't match any display monitors connected now"),
+            );
+        }
+        indices
+    }
+
+    /// Parse the input source `value`, and check if the display monitors support it.
+    fn check_input(&mut self, location: &str, value: &str, indices: &[usize]) {
+        let input_source = match InputSource::raw_from_str(value) {
+            Ok(input_source) => input_source,
+            Err(e) => {
+                self.error(location, e.to_string());
+                return;
+            }
+        };
+        for index in indices {
+            let monitor = &mut self.monitors[*index];
+            if let Some(input_sources) = monitor.input_sources()
+                && !input_sources.contains(&input_source)
+            {
+                let message = format!(
+                    "{monitor} doesn't support {value}",
+                    value = InputSource::str_from_raw(input_source)
+                );
+                self.warning(location, message);
+            }
+        }
+    }
+
+    /// Check the command line arguments, such as `name=input`.
+    fn check_args(&mut self, location: &str, args: &[String]) {
+        for arg in args.iter().filter(|arg| !arg.starts_with('-')) {
+            match arg.parse::<Arg>() {
+                Ok(Arg::List(_)) => {}
+                Ok(Arg::Set { name, values, .. }) => {
+                    let indices = self.matched_indices(location, &name);
+                    for value in &values {
+                        self.check_input(location, value, &indices);
+                    }
+                }
+                Ok(Arg::SetIf {
+                    name,
+                    condition,
+                    value,
+                }) => {
+                    let indices = self.matched_indices(location, &name);
+                    self.check_input(location, &condition, &indices);
+                    self.check_input(location, &value, &indices);
+                }
+                Ok(Arg::SetPbp { name, .. } | Arg::SetVcp { name, .. }) => {
+                    self.matched_indices(location, &name);
+                }
+                Err(e) => self.error(location, e.to_string()),
+            }
+        }
+    }
+
+    fn check_monitors(&mut self) {
+        for (pattern, monitor_config) in &self.config.monitors {
+            let location = format!("monitors.\"{pattern}\"");
+            if !self.matches_pattern(pattern) {
+                self.warning(
+                    &location,
+                    "No display monitors connected now match".to_string(),
+                );
+            }
+            if let Some(local_input) = &monitor_config.local_input
+                && let Err(e) = InputSource::raw_from_str(local_input)
+            {
+                self.error(&location, e.to_string());
+            }
+            for name in monitor_config.inputs.keys() {
+                if let Err(e) = InputSource::raw_from_str(name) {
+                    self.error(&format!("{location}.inputs"), e.to_string());
+                }
+            }
+        }
+        for (group, patterns) in &self.config.groups {
+            for pattern in patterns {
+                if !self.matches_pattern(pattern) {
+                    self.warning(
+                        &format!("groups.{group}"),
+                        format!("\"{pattern}\" doesn't match any display monitors connected now"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_profiles(&mut self) {
+        for (profile, args) in &self.config.profiles {
+            self.check_args(&format!("profiles.{profile}"), args);
+        }
+        for profile in self.config.profile_settings.keys() {
+            if !self.config.profiles.contains_key(profile) {
+                self.warning(
+                    &format!("profile_settings.{profile}"),
+                    format!("The profile \"{profile}\" is not in `profiles`"),
+                );
+            }
+        }
+    }
+
+    /// Check if the `profile` run at the `location` is in the `profiles`.
+    fn check_profile(&mut self, location: &str, profile: &str) {
+        if !self.config.profiles.contains_key(profile) {
+            self.error(
+                location,
+                format!("The profile \"{profile}\" is not in `profiles`"),
+            );
+        }
+    }
+
+    fn check_schedules(&mut self) {
+        let schedules = &self.config.schedules;
+        for (index, schedule) in schedules.iter().enumerate() {
+            let location = format!("schedules[{index}]");
+            if let Err(e) = schedule.validate() {
+                self.error(&location, e.to_string());
+                continue;
+            }
+            self.check_args(&location, &schedule.args);
+            if let Some(profile) = &schedule.profile {
+                self.check_profile(&location, profile);
+            }
+            for (other_index, other) in schedules.iter().enumerate().take(index) {
+                if !schedule.overlaps(other) {
+                    continue;
+                }
+                for (name, input) in set_args(&schedule.args) {
+                    if set_args(&other.args)
+                        .iter()
+                        .any(|(other_name, other_input)| {
+                            *other_name == name && *other_input != input
+                        })
+                    {
+                        self.error(
+                            &location,
+                            format!(
+                                "Sets \"{name}\" to {input} at the same time as schedules[{other_index}] \
+                                sets it to another input source"
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_events(&mut self) {
+        if let Some(idle) = &self.config.idle {
+            self.check_args("idle.args", &idle.args);
+            self.check_args("idle.active_args", &idle.active_args);
+        }
+        if let Some(dock) = &self.config.dock {
+            if let Some(connected_input) = &dock.connected_input
+                && let Err(e) = InputSource::raw_from_str(connected_input)
+            {
+                self.error("dock.connected_input", e.to_string());
+            }
+            self.check_args("dock.connected_args", &dock.connected_args);
+            self.check_args("dock.disconnected_args", &dock.disconnected_args);
+            self.check_args("dock.ac_args", &dock.ac_args);
+            self.check_args("dock.battery_args", &dock.battery_args);
+        }
+        if let Some(session) = &self.config.session {
+            self.check_args("session.lock_args", &session.lock_args);
+            self.check_args("session.unlock_args", &session.unlock_args);
+            self.check_args("session.lid_close_args", &session.lid_close_args);
+            self.check_args("session.lid_open_args", &session.lid_open_args);
+        }
+        if let Some(usb) = &self.config.usb {
+            if let Err(e) = crate::usb::parse_usb_device(&usb.usb_device) {
+                self.error("usb.usb_device", e.to_string());
+            }
+            let all: Vec<usize> = (0..self.monitors.len()).collect();
+            for (key, value) in [
+                ("on_usb_connect", &usb.on_usb_connect),
+                ("on_usb_disconnect", &usb.on_usb_disconnect),
+            ] {
+                if let Some(value) = value {
+                    self.check_input(&format!("usb.{key}"), value, &all);
+                }
+            }
+            for (section, monitor) in &usb.monitors {
+                let location = format!("usb.monitors.{section}");
+                let indices = self.matched_indices(&location, &monitor.monitor_id);
+                for value in [&monitor.on_usb_connect, &monitor.on_usb_disconnect]
+                    .into_iter()
+                    .flatten()
+                {
+                    self.check_input(&location, value, &indices);
+                }
+            }
+        }
+    }
+
+    fn check_rules(&mut self) {
+        let rules = &self.config.rules;
+        for (index, rule) in rules.iter().enumerate() {
+            let location = format!("rules[{index}]");
+            let compiled = match compile_rules(std::slice::from_ref(rule)) {
+                Ok(compiled) => compiled,
+                Err(e) => {
+                    self.error(&location, e.to_string());
+                    continue;
+                }
+            };
+            if self.config.usb.is_none() && compiled[0].events().iter().any(RuleEvent::is_usb) {
+                self.error(
+                    &location,
+                    "`usb_connect` and `usb_disconnect` need the `usb` table".to_string(),
+                );
+            }
+            let mut words = rule.then.split_whitespace();
+            match words.next() {
+                Some("set") => {
+                    for value in words {
+                        if let Err(e) = InputSource::raw_from_str(value) {
+                            self.error(&location, e.to_string());
+                        }
+                    }
+                }
+                Some("run") => {
+                    let args: Vec<String> = words.map(str::to_string).collect();
+                    self.check_args(&location, &args);
+                }
+                Some("profile") => {
+                    if let Some(profile) = words.next() {
+                        self.check_profile(&location, profile);
+                    }
+                }
+                _ => {}
+            }
+            let when = normalize_space(&rule.when);
+            for (other_index, other) in rules.iter().enumerate().take(index) {
+                if normalize_space(&other.when) == when
+                    && normalize_space(&other.then) != normalize_space(&rule.then)
+                {
+                    self.error(
+                        &location,
+                        format!(
+                            "The same condition as rules[{other_index}] with a different action: \
+                            when = \"{}\"",
+                            rule.when
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The pairs of the name and the input source of `name=input` in the `args`.
+fn set_args(args: &[String]) -> Vec<(String, String)> {
+    args.iter()
+        .filter_map(|arg| match arg.parse::<Arg>() {
+            Ok(Arg::Set { name, values, .. }) if values.len() == 1 => {
+                Some((name, values.into_iter().next().unwrap()))
+            }
+            _ => None,
+        })
+        .filter_map(|(name, value)| {
+            let value = InputSource::str_from_raw(InputSource::raw_from_str(&value).ok()?);
+            Some((name, value))
+        })
+        .collect()
+}
+
+fn normalize_space(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// A starter configuration file for the `monitors`,
+/// with their IDs, supported input sources, and current input sources.
+pub(crate) fn config_template(monitors: &mut [Monitor]) -> String {
+    let mut text = String::from(
+        "# The configuration file of monitor-input.\n\
+        # See https://github.com/kojiishi/monitor-input-rs#configuration\n\
+        \n\
+        # [groups]\n\
+        # desk = [\"DELL*\", \"LG*\"]\n\
+        \n\
+        # [defaults]\n\
+        # retries = 2\n",
+    );
+    let mut current = vec![];
+    for monitor in monitors.iter_mut() {
+        let key = toml::Value::String(monitor.id().to_string()).to_string();
+        text.push_str(&format!("\n[monitors.{key}]\n"));
+        let mut about = vec![];
+        if let Some(model) = monitor.model_name() {
+            about.push(format!("Model: {model}"));
+        }
+        about.push(format!("Backend: {}", monitor.backend()));
+        text.push_str(&format!("# {}\n", about.join(", ")));
+        if let Some(names) = monitor.input_source_names() {
+            let names: Vec<String> = names.into_iter().map(|(_, name)| name).collect();
+            text.push_str(&format!("# Inputs: {}\n", names.join(", ")));
+        }
+        match monitor.input_source() {
+            Ok(input_source) => {
+                let input_source = InputSource::str_from_raw(input_source);
+                text.push_str(&format!("# Current: {input_source}\n"));
+                text.push_str(&format!("# local_input = \"{input_source}\"\n"));
+                current.push(format!(
+                    "{name}={input_source}",
+                    name = crate::arg::quote(monitor.id())
+                ));
+            }
+            Err(e) => debug!("{monitor}: {e}"),
+        }
+        text.push_str("# settle_ms = 500\n");
+    }
+    if !current.is_empty() {
+        let args = toml::Value::Array(current.into_iter().map(toml::Value::String).collect());
+        text.push_str(&format!("\n# [profiles]\n# current = {args}\n"));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitors() -> Vec<Monitor> {
+        vec![
+            Monitor::from_device(
+                MockMonitor::new("DELL U2723QE")
+                    .with_input_sources(&[0x0F, 0x11])
+                    .with_input_source(0x0F),
+            ),
+            Monitor::from_device(MockMonitor::new("LG").with_input_source(0x11)),
+        ]
+    }
+
+    fn issues(config: &str) -> Vec<String> {
+        let config = Config::from_toml(config).unwrap();
+        let mut monitors = monitors();
+        for monitor in &mut monitors {
+            let _ = monitor.update_capabilities();
+        }
+        super::validate_config(&config, &mut monitors)
+            .iter()
+            .map(ConfigIssue::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn validate_config() {
+        assert_eq!(
+            issues(
+                r#"
+                profiles.work = ["DELL=dp1", "LG=hdmi1"]
+                schedules = [{ time = "09:00", args = ["DELL=hdmi1"] }]
+                "#
+            ),
+            [] as [String; 0]
+        );
+        assert_eq!(
+            issues(
+                r#"
+                monitors.HP.settle_ms = 100
+                profiles.work = ["DELL=xyz", "Acer=dp1", "DELL=hdmi2"]
+                "#
+            ),
+            [
+                "warning: monitors.\"HP\": No display monitors connected now match",
+                "error: profiles.work: \"xyz\" is not a valid input source",
+                "warning: profiles.work: \"Acer\" doesn't match any display monitors connected now",
+                "warning: profiles.work: DELL U2723QE doesn't support Hdmi2",
+            ]
+        );
+        assert_eq!(
+            issues(
+                r#"
+                usb.usb_device = "1050"
+                usb.on_usb_connect = "dp1"
+                usb.monitors.monitor1 = { monitor_id = "DELL", on_usb_disconnect = "hdmi2" }
+                "#
+            ),
+            [
+                "error: usb.usb_device: Invalid `usb_device` \"1050\", \
+                it should be hexadecimal IDs of the vendor and the product, such as \"1050:0407\"",
+                "warning: usb.monitors.monitor1: DELL U2723QE doesn't support Hdmi2",
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_config_conflicts() {
+        let issues = issues(
+            r#"
+            [[schedules]]
+            time = "09:00"
+            args = ["DELL=dp1"]
+            [[schedules]]
+            time = "09:00"
+            days = ["mon"]
+            args = ["DELL=hdmi1"]
+            [[rules]]
+            when = "hotplug"
+            then = "set dp1"
+            [[rules]]
+            when = " hotplug "
+            then = "set hdmi1"
+            "#,
+        );
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].starts_with("error: schedules[1]: Sets \"DELL\" to Hdmi1"));
+        assert!(issues[1].starts_with("error: rules[1]: The same condition as rules[0]"));
+    }
+
+    #[test]
+    fn config_template() {
+        let mut monitors = monitors();
+        monitors[0].update_capabilities().unwrap();
+        let text = super::config_template(&mut monitors);
+        assert!(text.contains("[monitors.\"DELL U2723QE\"]\n"));
+        assert!(text.contains("# Inputs: DP1, Hdmi1\n"));
+        assert!(text.contains("# current = [\"DELL U2723QE=DP1\", \"LG=Hdmi1\"]\n"));
+        let config = Config::from_toml(&text).unwrap();
+        assert_eq!(config.monitors.len(), 2);
+    }
+}
@@ -0,0 +1,425 @@
+use ddc_hi::{Ddc, DdcHost};
+
+/// VCP feature codes, such as `0x60` for the input select.
+pub type VcpCode = u8;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// The value of a VCP feature.
+pub struct VcpValue {
+    /// The current value.
+    pub value: u16,
+    /// The maximum value.
+    pub maximum: u16,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// The value of a VCP feature and its [`VcpKind`], from [`Monitor::vcp_value()`].
+///
+/// [`Monitor::vcp_value()`]: crate::Monitor::vcp_value
+pub struct TypedVcpValue {
+    /// The current value.
+    pub value: u16,
+    /// The maximum value.
+    pub maximum: u16,
+    /// Whether the values are continuous, if known.
+    pub kind: VcpKind,
+}
+
+impl TypedVcpValue {
+    /// Whether the `value` can be set.
+    /// It's not more than the [`TypedVcpValue::maximum`] unless the feature is non-continuous,
+    /// whose maximum may be the number of the values.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{TypedVcpValue,VcpKind};
+    /// let brightness = TypedVcpValue { value: 50, maximum: 100, kind: VcpKind::Continuous };
+    /// assert!(brightness.accepts(100));
+    /// assert!(!brightness.accepts(150));
+    /// ```
+    pub fn accepts(&self, value: u16) -> bool {
+        self.kind == VcpKind::NonContinuous || self.maximum == 0 || value <= self.maximum
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(rename_all = "lowercase")
+)]
+/// The type of the values of a VCP feature.
+pub enum VcpKind {
+    /// Not known, because the device doesn't report it.
+    #[default]
+    Unknown,
+    /// A value in a range from zero to the maximum, such as the brightness.
+    Continuous,
+    /// One of specific values, such as the input source.
+    NonContinuous,
+}
+
+/// The communication with a display monitor, used by [`Monitor`].
+///
+/// This is implemented for [`ddc_hi::Display`],
+/// and for [`MockMonitor`] to run without display monitors.
+/// Other transports can be plugged in by implementing this trait,
+/// and creating [`Monitor`]s by [`Monitor::from_device()`].
+///
+/// [`Monitor`]: crate::Monitor
+/// [`Monitor::from_device()`]: crate::Monitor::from_device
+/// [`MockMonitor`]: crate::MockMonitor
+pub trait DdcDevice {
+    /// The identifier of the display monitor.
+    fn id(&self) -> &str;
+
+    /// The model name of the display monitor, if available.
+    fn model_name(&self) -> Option<&str>;
+
+    /// The serial number of the display monitor, if available.
+    fn serial(&self) -> Option<String>;
+
+    /// The name of the backend, such as `i2c-dev` or `winapi`.
+    fn backend_name(&self) -> String;
+
+    /// The EDID of the display monitor, if available.
+    fn edid(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Whether this is an internal panel such as laptop screens,
+    /// which don't support changing input sources.
+    fn is_internal(&self) -> bool {
+        false
+    }
+
+    /// Whether this is behind a DisplayPort Multi-Stream Transport (MST) hub,
+    /// such as daisy-chained display monitors,
+    /// whose DDC/CI fails more often than others.
+    fn is_mst(&self) -> bool {
+        false
+    }
+
+    /// Read the MCCS capabilities string from the display monitor,
+    /// such as `(prot(monitor)mccs_ver(2.2)vcp(60(0F 11)))`.
+    fn capabilities_string(&mut self) -> anyhow::Result<String>;
+
+    /// Get the value of the VCP feature.
+    fn get_vcp_feature(&mut self, code: VcpCode) -> anyhow::Result<VcpValue>;
+
+    /// The [`VcpKind`] of the VCP feature, if the device reported it
+    /// by [`DdcDevice::get_vcp_feature()`].
+    /// The default implementation is [`VcpKind::Unknown`].
+    fn vcp_kind(&self, code: VcpCode) -> VcpKind {
+        let _ = code;
+        VcpKind::Unknown
+    }
+
+    /// Set the value of the VCP feature.
+    fn set_vcp_feature(&mut self, code: VcpCode, value: u16) -> anyhow::Result<()>;
+
+    /// Sleep until previous commands are executed.
+    fn sleep(&mut self);
+
+    /// Send the raw DDC/CI command `data`, such as vendor-specific commands,
+    /// and read a reply of up to `read_len` bytes if it's not zero.
+    /// The `data` and the reply are the payloads,
+    /// without the addresses, the lengths, and the checksums.
+    /// The default implementation is an error.
+    fn ddc_raw(&mut self, data: &[u8], read_len: usize) -> anyhow::Result<Vec<u8>> {
+        let _ = (data, read_len);
+        anyhow::bail!(
+            "{}: Raw DDC/CI commands aren't supported by the `{}` backend",
+            self.id(),
+            self.backend_name()
+        )
+    }
+
+    /// Open the display monitor again, when its handle is no longer valid,
+    /// such as after the computer resumes from sleep,
+    /// or after the display monitor is reconnected.
+    /// The default implementation is an error.
+    fn reopen(&mut self) -> anyhow::Result<()> {
+        anyhow::bail!("{}: Reopening isn't supported", self.id())
+    }
+}
+
+/// Whether `a` and `b` are the same display monitor,
+/// possibly from different enumerations.
+/// The EDIDs are compared if both are available, otherwise the IDs.
+/// The IDs are compared too if the EDIDs don't have serial numbers,
+/// such as display monitors of the same model daisy-chained by DisplayPort MST.
+pub(crate) fn is_same_device(a: &(impl DdcDevice + ?Sized), b: &(impl DdcDevice + ?Sized)) -> bool {
+    if a.backend_name() != b.backend_name() {
+        return false;
+    }
+    match (a.edid(), b.edid()) {
+        (Some(a_edid), Some(b_edid)) => {
+            crate::is_same_edid(a_edid, b_edid)
+                && (crate::edid_has_serial(a_edid) || a.id() == b.id())
+        }
+        _ => a.id() == b.id(),
+    }
+}
+
+/// Find the same display monitor as the `device` in the `candidates`,
+/// for [`DdcDevice::reopen()`].
+/// The `device` must have the EDID, because the IDs of some backends aren't unique,
+/// such as `Generic PnP Monitor` of `winapi`,
+/// and a different display monitor of the same ID shouldn't be used instead.
+pub(crate) fn find_same_device<T: DdcDevice>(
+    candidates: impl IntoIterator<Item = T>,
+    device: &(impl DdcDevice + ?Sized),
+) -> anyhow::Result<T> {
+    if device.edid().is_none() {
+        anyhow::bail!(
+            "{}: Can't reopen without the EDID, because other display monitors may have the same ID",
+            device.id()
+        );
+    }
+    candidates
+        .into_iter()
+        .find(|candidate| is_same_device(candidate, device))
+        .ok_or_else(|| anyhow::anyhow!("{}: Not found when reopening", device.id()))
+}
+
+/// Whether the `error` indicates that the handle of the display monitor is no longer valid.
+/// See [`DdcDevice::reopen()`].
+pub(crate) fn is_stale_handle_error(error: &anyhow::Error) -> bool {
+    // Windows `ERROR_INVALID_HANDLE` and `ERROR_GRAPHICS_INVALID_PHYSICAL_MONITOR_HANDLE`.
+    #[cfg(windows)]
+    const OS_ERRORS: &[i32] = &[6, 0xC026258Cu32 as i32];
+    // `ENXIO` and `ENODEV`.
+    #[cfg(not(windows))]
+    const OS_ERRORS: &[i32] = &[6, 19];
+    // Messages are checked too, because some backends don't keep the OS errors.
+    const PATTERNS: &[&str] = &[
+        "handle is invalid",
+        "invalid handle",
+        "no such device",
+        // `ddcutil`.
+        "no monitor detected",
+        "display not found",
+    ];
+    error.chain().any(|cause| {
+        if let Some(error) = cause.downcast_ref::<std::io::Error>()
+            && let Some(code) = error.raw_os_error()
+        {
+            return OS_ERRORS.contains(&code);
+        }
+        let message = cause.to_string().to_lowercase();
+        PATTERNS.iter().any(|pattern| message.contains(pattern))
+    })
+}
+
+impl DdcDevice for ddc_hi::Display {
+    fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.info.model_name.as_deref()
+    }
+
+    fn serial(&self) -> Option<String> {
+        self.info
+            .serial_number
+            .clone()
+            .or_else(|| self.info.serial.map(|serial| serial.to_string()))
+    }
+
+    fn backend_name(&self) -> String {
+        self.info.backend.to_string()
+    }
+
+    fn edid(&self) -> Option<&[u8]> {
+        self.info.edid_data.as_deref()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn is_internal(&self) -> bool {
+        if self.info.backend != ddc_hi::Backend::I2cDevice {
+            return false;
+        }
+        i2c_bus(&self.info.id).is_some_and(|bus| internal_i2c_buses().contains(&bus))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn is_mst(&self) -> bool {
+        self.info.backend == ddc_hi::Backend::I2cDevice
+            && i2c_bus(&self.info.id).is_some_and(is_mst_i2c_bus)
+    }
+
+    fn capabilities_string(&mut self) -> anyhow::Result<String> {
+        let bytes = self.handle.capabilities_string()?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn get_vcp_feature(&mut self, code: VcpCode) -> anyhow::Result<VcpValue> {
+        let value = self.handle.get_vcp_feature(code)?;
+        Ok(VcpValue {
+            value: value.value(),
+            maximum: value.maximum(),
+        })
+    }
+
+    fn set_vcp_feature(&mut self, code: VcpCode, value: u16) -> anyhow::Result<()> {
+        self.handle.set_vcp_feature(code, value)
+    }
+
+    fn sleep(&mut self) {
+        self.handle.sleep();
+    }
+
+    fn ddc_raw(&mut self, data: &[u8], read_len: usize) -> anyhow::Result<Vec<u8>> {
+        #[cfg(any(target_os = "linux", windows))]
+        use ddc::DdcCommandRaw;
+        // The DDC/CI spec requires 40 ms for replies, but some display monitors need more.
+        #[cfg(any(target_os = "linux", windows))]
+        const RESPONSE_DELAY: std::time::Duration = std::time::Duration::from_millis(50);
+        // The reply has the source address, the length, and the checksum.
+        let mut out = vec![0; if read_len == 0 { 0 } else { read_len + 3 }];
+        #[allow(unreachable_patterns)]
+        match &mut self.handle {
+            #[cfg(target_os = "linux")]
+            ddc_hi::Handle::I2cDevice(i2c) => {
+                Ok(i2c.execute_raw(data, &mut out, RESPONSE_DELAY)?.to_vec())
+            }
+            #[cfg(windows)]
+            ddc_hi::Handle::Nvapi(i2c) => {
+                Ok(i2c.execute_raw(data, &mut out, RESPONSE_DELAY)?.to_vec())
+            }
+            _ => anyhow::bail!(
+                "{}: Raw DDC/CI commands aren't supported by the `{}` backend",
+                self.info.id,
+                self.info.backend
+            ),
+        }
+    }
+
+    fn reopen(&mut self) -> anyhow::Result<()> {
+        *self = find_same_device(ddc_hi::Display::enumerate(), self)?;
+        Ok(())
+    }
+}
+
+/// The I2C bus number of the `id` of the `i2c-dev` backend,
+/// which is the device number of `/dev/i2c-*`.
+#[cfg(target_os = "linux")]
+pub(crate) fn i2c_bus(id: &str) -> Option<u32> {
+    let rdev = id.parse::<u64>().ok()?;
+    Some(((rdev & 0xFF) | ((rdev >> 12) & !0xFF)) as u32)
+}
+
+/// Whether the I2C `bus` is created by the DisplayPort MST helper of the kernel.
+#[cfg(target_os = "linux")]
+fn is_mst_i2c_bus(bus: u32) -> bool {
+    std::fs::read_to_string(format!("/sys/bus/i2c/devices/i2c-{bus}/name"))
+        .is_ok_and(|name| is_mst_i2c_bus_name(&name))
+}
+
+/// Whether the `name` of an I2C bus in `/sys/bus/i2c/devices/i2c-*/name`
+/// is of the DisplayPort MST helper of the kernel, which names them `DPMST`.
+#[cfg(any(target_os = "linux", test))]
+pub(crate) fn is_mst_i2c_bus_name(name: &str) -> bool {
+    name.starts_with("DPMST")
+}
+
+/// Whether the DRM connector name, such as `card1-eDP-1`, is an internal panel.
+#[cfg(target_os = "linux")]
+pub(crate) fn is_internal_connector(connector: &str) -> bool {
+    let connector_type = connector
+        .split_once('-')
+        .map_or(connector, |(_, connector_type)| connector_type);
+    ["eDP-", "LVDS-", "DSI-"]
+        .iter()
+        .any(|prefix| connector_type.starts_with(prefix))
+}
+
+/// The I2C bus numbers of internal panels, from `/sys/class/drm`.
+#[cfg(target_os = "linux")]
+fn internal_i2c_buses() -> Vec<u32> {
+    let Ok(entries) = std::fs::read_dir("/sys/class/drm") else {
+        return vec![];
+    };
+    let mut buses = vec![];
+    for entry in entries.flatten() {
+        if !is_internal_connector(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let path = entry.path();
+        let ddc = std::fs::read_link(path.join("ddc")).ok();
+        let children = std::fs::read_dir(&path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|child| child.path());
+        for i2c in ddc.into_iter().chain(children) {
+            if let Some(bus) = i2c
+                .file_name()
+                .and_then(|name| name.to_str()?.strip_prefix("i2c-")?.parse().ok())
+            {
+                buses.push(bus);
+            }
+        }
+    }
+    buses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DdcDevice;
+    use crate::MockMonitor;
+
+    #[test]
+    fn find_same_device() {
+        use super::find_same_device;
+        let edid = |serial: u8| {
+            let mut edid = vec![0u8; 128];
+            edid[12] = serial;
+            edid
+        };
+        let candidates = || {
+            [
+                MockMonitor::new("Generic PnP Monitor").with_edid(edid(1)),
+                MockMonitor::new("Generic PnP Monitor").with_edid(edid(2)),
+            ]
+        };
+        let device = MockMonitor::new("Generic PnP Monitor").with_edid(edid(2));
+        let found = find_same_device(candidates(), &device).unwrap();
+        assert_eq!(found.edid(), Some(edid(2).as_slice()));
+
+        // Without the EDID, the ID may be of a different display monitor.
+        let device = MockMonitor::new("Generic PnP Monitor");
+        assert!(find_same_device(candidates(), &device).is_err());
+        let device = MockMonitor::new("Generic PnP Monitor").with_edid(edid(3));
+        assert!(find_same_device(candidates(), &device).is_err());
+    }
+
+    #[test]
+    fn is_stale_handle_error() {
+        use super::is_stale_handle_error;
+        let error = std::io::Error::from_raw_os_error(6);
+        assert!(is_stale_handle_error(&anyhow::Error::from(error)));
+        let error = std::io::Error::from_raw_os_error(2);
+        assert!(!is_stale_handle_error(&anyhow::Error::from(error)));
+        assert!(is_stale_handle_error(
+            &anyhow::anyhow!("I/O").context("No monitor detected on bus 4")
+        ));
+        assert!(!is_stale_handle_error(&anyhow::anyhow!(
+            "VCP feature 0x60 is not set"
+        )));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn is_internal_connector() {
+        use super::is_internal_connector;
+        assert!(is_internal_connector("card1-eDP-1"));
+        assert!(is_internal_connector("card0-LVDS-1"));
+        assert!(is_internal_connector("card0-DSI-1"));
+        assert!(!is_internal_connector("card1-DP-1"));
+        assert!(!is_internal_connector("card1-HDMI-A-1"));
+    }
+}
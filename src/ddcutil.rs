@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use std::process::Command;
+
+use super::*;
+use crate::logging::*;
+
+/// A [`DdcDevice`] using the [`ddcutil`] command.
+///
+/// This is for systems where the `i2c-dev` backend of [`ddc_hi`] doesn't work,
+/// such as when the access to `/dev/i2c-*` is restricted,
+/// but `ddcutil` is installed with the permissions.
+///
+/// [`ddcutil`]: https://www.ddcutil.com/
+#[derive(Debug, Default, PartialEq)]
+pub struct DdcutilDevice {
+    bus: u32,
+    id: String,
+    model_name: Option<String>,
+    serial: Option<String>,
+    connector: Option<String>,
+    edid: Option<Vec<u8>>,
+    /// The [`VcpKind`]s that `ddcutil getvcp` reported.
+    kinds: BTreeMap<VcpCode, VcpKind>,
+}
+
+impl DdcutilDevice {
+    /// Enumerate display monitors by `ddcutil detect`.
+    pub fn enumerate() -> anyhow::Result<Vec<Self>> {
+        let output = Self::run(&["detect", "--terse"])?;
+        let mut devices = Self::parse_detect(&output);
+        for device in &mut devices {
+            if let Some(connector) = &device.connector {
+                device.edid = std::fs::read(format!("/sys/class/drm/{connector}/edid")).ok();
+            }
+        }
+        Ok(devices)
+    }
+
+    fn run(args: &[&str]) -> anyhow::Result<String> {
+        debug!("ddcutil {}", args.join(" "));
+        let output = Command::new("ddcutil")
+            .args(args)
+            .output()
+            .map_err(|e| anyhow::anyhow!("Failed to run `ddcutil`: {e}"))?;
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        if !output.status.success() {
+            anyhow::bail!(
+                "`ddcutil {}` failed: {}{}",
+                args.join(" "),
+                stdout.trim(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        trace!("ddcutil: {stdout}");
+        Ok(stdout)
+    }
+
+    fn run_on_bus(&self, args: &[&str]) -> anyhow::Result<String> {
+        let bus = self.bus.to_string();
+        let mut all_args = vec!["--bus", bus.as_str()];
+        all_args.extend(args);
+        Self::run(&all_args)
+    }
+
+    /// Parse the output of `ddcutil detect --terse`.
+    fn parse_detect(output: &str) -> Vec<Self> {
+        let mut devices: Vec<Self> = vec![];
+        let mut current: Option<Self> = None;
+        for line in output.lines() {
+            if line.starts_with("Display ") {
+                devices.extend(current.take());
+                current = Some(Self::default());
+                continue;
+            }
+            if !line.starts_with(char::is_whitespace) {
+                // Such as "Invalid display".
+                devices.extend(current.take());
+                continue;
+            }
+            let Some(device) = &mut current else {
+                continue;
+            };
+            let Some((key, value)) = line.trim().split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key {
+                "I2C bus" => {
+                    if let Some(bus) = value
+                        .strip_prefix("/dev/i2c-")
+                        .and_then(|bus| bus.parse().ok())
+                    {
+                        device.bus = bus;
+                    }
+                }
+                "DRM connector" => device.connector = Some(value.to_string()),
+                "Monitor" => {
+                    // "Manufacturer:Model:Serial"
+                    let mut fields = value.split(':').skip(1).map(str::trim);
+                    device.model_name = fields
+                        .next()
+                        .filter(|model| !model.is_empty())
+                        .map(str::to_string);
+                    device.serial = fields
+                        .next()
+                        .filter(|serial| !serial.is_empty())
+                        .map(str::to_string);
+                }
+                _ => {}
+            }
+        }
+        devices.extend(current);
+        for device in &mut devices {
+            device.id = device
+                .model_name
+                .clone()
+                .unwrap_or_else(|| format!("i2c-{}", device.bus));
+        }
+        devices
+    }
+
+    /// Parse the output of `ddcutil getvcp --brief`,
+    /// such as `VCP 60 SNC x0f` or `VCP 10 C 50 100`.
+    fn parse_getvcp(output: &str) -> anyhow::Result<(VcpValue, VcpKind)> {
+        let fields: Vec<&str> = output.split_whitespace().collect();
+        let hex = |value: &str| -> anyhow::Result<u16> {
+            Ok(u16::from_str_radix(value.trim_start_matches('x'), 16)?)
+        };
+        match fields.as_slice() {
+            ["VCP", _, "SNC", value, ..] => Ok((
+                VcpValue {
+                    value: hex(value)?,
+                    maximum: 0,
+                },
+                VcpKind::NonContinuous,
+            )),
+            ["VCP", _, "C", value, maximum, ..] => Ok((
+                VcpValue {
+                    value: value.parse()?,
+                    maximum: maximum.parse()?,
+                },
+                VcpKind::Continuous,
+            )),
+            ["VCP", _, "CNC", mh, ml, sh, sl, ..] => Ok((
+                VcpValue {
+                    value: (hex(sh)? << 8) | hex(sl)?,
+                    maximum: (hex(mh)? << 8) | hex(ml)?,
+                },
+                VcpKind::NonContinuous,
+            )),
+            _ => anyhow::bail!("Unexpected output from `ddcutil getvcp`: {output}"),
+        }
+    }
+}
+
+impl DdcDevice for DdcutilDevice {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.model_name.as_deref()
+    }
+
+    fn serial(&self) -> Option<String> {
+        self.serial.clone()
+    }
+
+    fn backend_name(&self) -> String {
+        "ddcutil".to_string()
+    }
+
+    fn edid(&self) -> Option<&[u8]> {
+        self.edid.as_deref()
+    }
+
+    fn is_internal(&self) -> bool {
+        self.connector.as_deref().is_some_and(is_internal_connector)
+    }
+
+    fn capabilities_string(&mut self) -> anyhow::Result<String> {
+        let output = self.run_on_bus(&["capabilities", "--verbose"])?;
+        output
+            .lines()
+            .find_map(|line| line.split_once("capabilities string:"))
+            .map(|(_, capabilities)| capabilities.trim().to_string())
+            .ok_or_else(|| anyhow::anyhow!("{self:?}: No capabilities string"))
+    }
+
+    fn get_vcp_feature(&mut self, code: VcpCode) -> anyhow::Result<VcpValue> {
+        let output = self.run_on_bus(&["getvcp", &format!("{code:02x}"), "--brief"])?;
+        let (value, kind) = Self::parse_getvcp(&output)?;
+        self.kinds.insert(code, kind);
+        Ok(value)
+    }
+
+    fn vcp_kind(&self, code: VcpCode) -> VcpKind {
+        self.kinds.get(&code).copied().unwrap_or_default()
+    }
+
+    fn set_vcp_feature(&mut self, code: VcpCode, value: u16) -> anyhow::Result<()> {
+        self.run_on_bus(&["setvcp", &format!("{code:02x}"), &value.to_string()])?;
+        Ok(())
+    }
+
+    fn sleep(&mut self) {
+        // `ddcutil` waits for the commands to complete.
+    }
+
+    fn reopen(&mut self) -> anyhow::Result<()> {
+        // The bus number may change when the display monitor is reconnected.
+        *self = find_same_device(Self::enumerate()?, self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_detect() {
+        let output = "Display 1
+   I2C bus:  /dev/i2c-4
+   DRM connector:  card1-DP-1
+   Monitor:  DEL:DELL U2723QE:ABC123
+
+Invalid display
+   I2C bus:  /dev/i2c-5
+   Monitor:  GSM:LG:
+
+Display 2
+   I2C bus:  /dev/i2c-7
+   Monitor:  ::
+";
+        let devices = DdcutilDevice::parse_detect(output);
+        assert_eq!(
+            devices,
+            [
+                DdcutilDevice {
+                    bus: 4,
+                    id: "DELL U2723QE".into(),
+                    model_name: Some("DELL U2723QE".into()),
+                    serial: Some("ABC123".into()),
+                    connector: Some("card1-DP-1".into()),
+                    edid: None,
+                    kinds: BTreeMap::new(),
+                },
+                DdcutilDevice {
+                    bus: 7,
+                    id: "i2c-7".into(),
+                    model_name: None,
+                    serial: None,
+                    connector: None,
+                    edid: None,
+                    kinds: BTreeMap::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_getvcp() {
+        assert_eq!(
+            DdcutilDevice::parse_getvcp("VCP 60 SNC x0f\n").unwrap(),
+            (
+                VcpValue {
+                    value: 0x0F,
+                    maximum: 0
+                },
+                VcpKind::NonContinuous
+            )
+        );
+        assert_eq!(
+            DdcutilDevice::parse_getvcp("VCP 10 C 50 100").unwrap(),
+            (
+                VcpValue {
+                    value: 50,
+                    maximum: 100
+                },
+                VcpKind::Continuous
+            )
+        );
+        assert_eq!(
+            DdcutilDevice::parse_getvcp("VCP 14 CNC x00 x0b x00 x05").unwrap(),
+            (
+                VcpValue {
+                    value: 5,
+                    maximum: 11
+                },
+                VcpKind::NonContinuous
+            )
+        );
+        assert!(DdcutilDevice::parse_getvcp("VCP 60 ERR").is_err());
+    }
+}
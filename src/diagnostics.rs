@@ -0,0 +1,465 @@
+use super::*;
+use crate::logging::*;
+
+/// Log why display monitors may be missing from the enumerated `monitors`,
+/// so that users aren't left with empty listings and no explanation.
+pub(crate) fn diagnose_monitors(monitors: &[Monitor]) {
+    #[cfg(target_os = "linux")]
+    if !monitors
+        .iter()
+        .any(|monitor| monitor.backend() == "i2c-dev")
+    {
+        diagnose_linux();
+    }
+    #[cfg(target_os = "macos")]
+    diagnose_macos(monitors);
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    let _ = monitors;
+}
+
+/// Log display monitors found by multiple backends,
+/// such as both `nvapi` and `winapi` on Windows,
+/// because one of them may be slower or less reliable than the other.
+pub(crate) fn diagnose_duplicates(monitors: &[Monitor]) {
+    for (index, monitor) in monitors.iter().enumerate() {
+        for other in &monitors[index + 1..] {
+            if is_same_display_across_backends(monitor, other) {
+                info!(
+                    "{monitor} is found by both `{}` and `{}` backends. \
+                    Use `--prefer-backend` to use only one of them.",
+                    monitor.backend(),
+                    other.backend()
+                );
+            }
+        }
+    }
+}
+
+/// Log how to fix the `error` if it's caused by the permissions of the system.
+pub(crate) fn diagnose_error(error: &anyhow::Error) {
+    #[cfg(target_os = "linux")]
+    if is_permission_error(error) {
+        diagnose_linux();
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = error;
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn is_permission_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        if let Some(error) = cause.downcast_ref::<std::io::Error>() {
+            return error.kind() == std::io::ErrorKind::PermissionDenied;
+        }
+        // Some backends don't keep the `std::io::Error`.
+        cause.to_string().contains("Permission denied")
+    })
+}
+
+/// The reasons the `/dev/i2c-*` devices can't be used on Linux.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+enum I2cIssue {
+    /// The `i2c-dev` kernel module isn't loaded.
+    ModuleNotLoaded,
+    /// The module is loaded, but there are no devices.
+    NoDevices,
+    /// None of the devices can be opened by the current user.
+    PermissionDenied(Vec<String>),
+}
+
+impl I2cIssue {
+    /// Find the issue from the `devices` and whether they can be opened.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn find(module_loaded: bool, devices: &[(String, bool)]) -> Option<Self> {
+        if devices.is_empty() {
+            return Some(if module_loaded {
+                Self::NoDevices
+            } else {
+                Self::ModuleNotLoaded
+            });
+        }
+        if devices.iter().any(|(_, accessible)| *accessible) {
+            return None;
+        }
+        Some(Self::PermissionDenied(
+            devices.iter().map(|(path, _)| path.clone()).collect(),
+        ))
+    }
+
+    /// The actionable guidance for the issue.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn guidance(&self) -> String {
+        match self {
+            Self::ModuleNotLoaded => "The `i2c-dev` kernel module isn't loaded. \
+                Load it by `sudo modprobe i2c-dev`, \
+                and add `i2c-dev` to `/etc/modules-load.d/i2c-dev.conf` to load it at boot."
+                .to_string(),
+            Self::NoDevices => "No `/dev/i2c-*` devices found. \
+                The graphics driver may not support DDC/CI. \
+                Also check if DDC/CI is enabled in the on-screen menu of the display monitor."
+                .to_string(),
+            Self::PermissionDenied(devices) => format!(
+                "No permissions to {}. \
+                Add the user to the `i2c` group by `sudo usermod -aG i2c $USER` and sign in again. \
+                If the devices aren't in the `i2c` group, \
+                add the udev rule `{UDEV_RULE}` to `{UDEV_RULES_PATH}`. \
+                `monitor-input setup-permissions` prints the steps.",
+                devices.join(", ")
+            ),
+        }
+    }
+}
+
+/// The udev rule to allow the `i2c` group to use the `/dev/i2c-*` devices.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const UDEV_RULE: &str = r#"KERNEL=="i2c-[0-9]*", GROUP="i2c", MODE="0660""#;
+
+/// The path of the udev rules file for the [`UDEV_RULE`].
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const UDEV_RULES_PATH: &str = "/etc/udev/rules.d/45-i2c.rules";
+
+/// The contents of the udev rules file, for `setup-permissions --print-udev`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn udev_rules() -> String {
+    format!(
+        "# Allow the `i2c` group to use DDC/CI, for `monitor-input`.\n\
+        # Install to {UDEV_RULES_PATH}.\n\
+        {UDEV_RULE}\n"
+    )
+}
+
+/// A command of `setup-permissions`, run by `sudo`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+struct SetupStep {
+    args: Vec<String>,
+    /// The standard input of the command, for `tee`.
+    input: Option<String>,
+}
+
+impl SetupStep {
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn new(args: &[&str]) -> Self {
+        Self {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            input: None,
+        }
+    }
+
+    /// Write the `contents` to the `path` by `tee`.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn write(path: &str, contents: &str) -> Self {
+        Self {
+            input: Some(contents.to_string()),
+            ..Self::new(&["tee", path])
+        }
+    }
+
+    /// The shell command line of this step, for printing.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn command_line(&self) -> String {
+        let command_line = format!("sudo {}", self.args.join(" "));
+        match &self.input {
+            Some(input) => format!("echo '{}' | {command_line}", input.trim_end()),
+            None => command_line,
+        }
+    }
+}
+
+/// The steps to allow the `user` to use the `/dev/i2c-*` devices.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn setup_steps(user: &str) -> Vec<SetupStep> {
+    vec![
+        SetupStep::write("/etc/modules-load.d/i2c-dev.conf", "i2c-dev\n"),
+        SetupStep::new(&["modprobe", "i2c-dev"]),
+        SetupStep::new(&["groupadd", "--system", "--force", "i2c"]),
+        SetupStep::write(UDEV_RULES_PATH, &format!("{UDEV_RULE}\n")),
+        SetupStep::new(&["udevadm", "control", "--reload-rules"]),
+        SetupStep::new(&["udevadm", "trigger", "--subsystem-match=i2c-dev"]),
+        SetupStep::new(&["usermod", "--append", "--groups", "i2c", user]),
+    ]
+}
+
+/// Print the udev rules if `print_udev`,
+/// run the steps to allow the current user to use DDC/CI by `sudo` if `install`,
+/// or print the steps otherwise.
+#[cfg(target_os = "linux")]
+pub(crate) fn setup_permissions(print_udev: bool, install: bool) -> anyhow::Result<()> {
+    if print_udev {
+        print!("{}", udev_rules());
+        return Ok(());
+    }
+    let Some(user) = ["USER", "LOGNAME"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+    else {
+        anyhow::bail!("Failed to get the user name from `$USER`");
+    };
+    let steps = setup_steps(&user);
+    if !install {
+        println!("Run the following commands, and sign in again:");
+        for step in &steps {
+            println!("{}", step.command_line());
+        }
+        return Ok(());
+    }
+    for step in &steps {
+        run_sudo(step)?;
+    }
+    info!("Sign out and sign in again, for the `i2c` group to take effect.");
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn run_sudo(step: &SetupStep) -> anyhow::Result<()> {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    info!("{}", step.command_line());
+    let mut child = std::process::Command::new("sudo")
+        .args(&step.args)
+        .stdin(if step.input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        })
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to run `sudo`: {e}"))?;
+    if let Some(input) = &step.input
+        && let Some(mut stdin) = child.stdin.take()
+    {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        anyhow::bail!("`sudo {}` failed: {status}", step.args.join(" "));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn setup_permissions(_print_udev: bool, _install: bool) -> anyhow::Result<()> {
+    anyhow::bail!("The `setup-permissions` command is available only on Linux.");
+}
+
+#[cfg(target_os = "linux")]
+fn diagnose_linux() {
+    // Log only once, for the `daemon` that may fail repeatedly.
+    static ONCE: std::sync::Once = std::sync::Once::new();
+    ONCE.call_once(|| {
+        let module_loaded = std::path::Path::new("/sys/module/i2c_dev").exists();
+        let mut devices: Vec<(String, bool)> = std::fs::read_dir("/dev")
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("i2c-"))
+            })
+            .map(|path| {
+                let accessible = std::fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(&path)
+                    .is_ok();
+                (path.to_string_lossy().into_owned(), accessible)
+            })
+            .collect();
+        devices.sort();
+        debug!("i2c-dev: module={module_loaded}, devices={devices:?}");
+        if let Some(issue) = I2cIssue::find(module_loaded, &devices) {
+            warn!("{}", issue.guidance());
+        }
+    });
+}
+
+#[cfg(target_os = "macos")]
+fn diagnose_macos(monitors: &[Monitor]) {
+    let output = match std::process::Command::new("system_profiler")
+        .args(["SPDisplaysDataType", "-json"])
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => {
+            debug!("Failed to run `system_profiler`: {e}");
+            return;
+        }
+    };
+    let names = match parse_system_profiler(&String::from_utf8_lossy(&output.stdout)) {
+        Ok(names) => names,
+        Err(e) => {
+            debug!("Failed to parse `system_profiler`: {e}");
+            return;
+        }
+    };
+    let missing = missing_display_names(&names, monitors);
+    if missing.is_empty() {
+        return;
+    }
+    for name in &missing {
+        warn!("{name}: Not found by DDC/CI");
+    }
+    if std::env::consts::ARCH == "aarch64" {
+        warn!(
+            "The built-in HDMI port of some Apple Silicon Macs doesn't support DDC/CI. \
+            Try the USB-C or the Thunderbolt ports."
+        );
+    }
+    warn!(
+        "DisplayLink adapters and some docks don't support DDC/CI. \
+        Also check if DDC/CI is enabled in the on-screen menu of the display monitor."
+    );
+}
+
+/// Parse the names of external displays from the output of
+/// `system_profiler SPDisplaysDataType -json`.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_system_profiler(output: &str) -> anyhow::Result<Vec<String>> {
+    let json: serde_json::Value = serde_json::from_str(output)?;
+    let Some(gpus) = json["SPDisplaysDataType"].as_array() else {
+        anyhow::bail!("No `SPDisplaysDataType`");
+    };
+    Ok(gpus
+        .iter()
+        .filter_map(|gpu| gpu["spdisplays_ndrvs"].as_array())
+        .flatten()
+        .filter(|display| display["spdisplays_connection_type"] != "spdisplays_internal")
+        .filter_map(|display| display["_name"].as_str())
+        .map(str::to_string)
+        .collect())
+}
+
+/// The `names` that don't match any of the `monitors`.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn missing_display_names<'a>(names: &'a [String], monitors: &[Monitor]) -> Vec<&'a str> {
+    let mut monitor_names: Vec<String> = monitors
+        .iter()
+        .map(|monitor| monitor.model_name().unwrap_or(monitor.id()).to_lowercase())
+        .collect();
+    names
+        .iter()
+        .filter(|name| {
+            let name = name.to_lowercase();
+            match monitor_names.iter().position(|monitor_name| {
+                monitor_name.contains(&name) || name.contains(monitor_name.as_str())
+            }) {
+                Some(index) => {
+                    // Each monitor matches only one name, for multiple monitors of the same model.
+                    monitor_names.remove(index);
+                    false
+                }
+                None => true,
+            }
+        })
+        .map(String::as_str)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_permission_error() {
+        let error = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert!(super::is_permission_error(
+            &anyhow::Error::from(error).context("Failed to open")
+        ));
+        assert!(super::is_permission_error(&anyhow::anyhow!(
+            "/dev/i2c-4: Permission denied"
+        )));
+        assert!(!super::is_permission_error(&anyhow::anyhow!(
+            "VCP feature 0x60 is not set"
+        )));
+    }
+
+    #[test]
+    fn i2c_issue() {
+        assert_eq!(I2cIssue::find(false, &[]), Some(I2cIssue::ModuleNotLoaded));
+        assert_eq!(I2cIssue::find(true, &[]), Some(I2cIssue::NoDevices));
+        let devices = [
+            ("/dev/i2c-1".to_string(), false),
+            ("/dev/i2c-2".to_string(), false),
+        ];
+        let issue = I2cIssue::find(true, &devices).unwrap();
+        assert_eq!(
+            issue,
+            I2cIssue::PermissionDenied(vec!["/dev/i2c-1".into(), "/dev/i2c-2".into()])
+        );
+        assert!(issue.guidance().contains("/dev/i2c-1, /dev/i2c-2"));
+        let devices = [
+            ("/dev/i2c-1".to_string(), false),
+            ("/dev/i2c-2".to_string(), true),
+        ];
+        assert_eq!(I2cIssue::find(true, &devices), None);
+    }
+
+    #[test]
+    fn setup_steps() {
+        let command_lines: Vec<String> = super::setup_steps("alice")
+            .iter()
+            .map(SetupStep::command_line)
+            .collect();
+        assert_eq!(
+            command_lines,
+            [
+                "echo 'i2c-dev' | sudo tee /etc/modules-load.d/i2c-dev.conf",
+                "sudo modprobe i2c-dev",
+                "sudo groupadd --system --force i2c",
+                r#"echo 'KERNEL=="i2c-[0-9]*", GROUP="i2c", MODE="0660"' | sudo tee /etc/udev/rules.d/45-i2c.rules"#,
+                "sudo udevadm control --reload-rules",
+                "sudo udevadm trigger --subsystem-match=i2c-dev",
+                "sudo usermod --append --groups i2c alice",
+            ]
+        );
+        assert!(udev_rules().ends_with(&format!("\n{UDEV_RULE}\n")));
+    }
+
+    #[test]
+    fn parse_system_profiler() {
+        let output = r#"{
+  "SPDisplaysDataType" : [
+    {
+      "_name" : "Apple M1",
+      "spdisplays_ndrvs" : [
+        {
+          "_name" : "Color LCD",
+          "spdisplays_connection_type" : "spdisplays_internal"
+        },
+        {
+          "_name" : "DELL U2723QE"
+        },
+        {
+          "_name" : "LG HDR 4K"
+        }
+      ]
+    }
+  ]
+}"#;
+        assert_eq!(
+            super::parse_system_profiler(output).unwrap(),
+            ["DELL U2723QE", "LG HDR 4K"]
+        );
+        assert!(super::parse_system_profiler("{}").is_err());
+    }
+
+    #[test]
+    fn missing_display_names() {
+        let names = [
+            "DELL U2723QE".to_string(),
+            "DELL U2723QE".into(),
+            "LG".into(),
+        ];
+        let monitors = [Monitor::from_device(
+            MockMonitor::new("1").with_model_name("DELL U2723QE"),
+        )];
+        assert_eq!(
+            super::missing_display_names(&names, &monitors),
+            ["DELL U2723QE", "LG"]
+        );
+    }
+}
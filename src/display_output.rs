@@ -0,0 +1,483 @@
+use super::*;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// The display output of the OS that shows a display monitor,
+/// such as `\\.\DISPLAY1` on Windows, or `HDMI-A-1` on Linux.
+/// See [`Monitor::display_output()`].
+pub struct DisplayOutput {
+    /// The name of the display output, such as `\\.\DISPLAY1` or `HDMI-A-1`.
+    pub name: String,
+    /// Whether this is the primary display.
+    pub primary: bool,
+    /// The left position in the virtual screen, in pixels.
+    pub x: i32,
+    /// The top position in the virtual screen, in pixels.
+    pub y: i32,
+    /// The width in pixels, or 0 if it's not known.
+    pub width: u32,
+    /// The height in pixels, or 0 if it's not known.
+    pub height: u32,
+}
+
+impl std::fmt::Display for DisplayOutput {
+    /// Format as `name WIDTHxHEIGHT+X+Y`, with `(primary)` if it's the primary display.
+    /// The position and the size are omitted if they're not known.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::DisplayOutput;
+    /// let output = DisplayOutput {
+    ///     name: "DP-1".into(),
+    ///     primary: true,
+    ///     x: 0,
+    ///     y: 0,
+    ///     width: 2560,
+    ///     height: 1440,
+    /// };
+    /// assert_eq!(output.to_string(), "DP-1 2560x1440+0+0 (primary)");
+    /// ```
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if self.width > 0 && self.height > 0 {
+            write!(f, " {}x{}{:+}{:+}", self.width, self.height, self.x, self.y)?;
+        }
+        if self.primary {
+            write!(f, " (primary)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Set [`Monitor::display_output()`] of the `monitors` from the OS display topology.
+pub(crate) fn assign_display_outputs(monitors: &mut [Monitor]) {
+    #[cfg(windows)]
+    windows::assign_display_outputs(monitors);
+    #[cfg(target_os = "linux")]
+    linux::assign_display_outputs(monitors);
+    #[cfg(not(any(windows, target_os = "linux")))]
+    let _ = monitors;
+}
+
+/// Whether two EDIDs are of the same display monitor.
+/// Only the base blocks are compared,
+/// because some backends read only the base block, or read extra bytes.
+pub(crate) fn is_same_edid(a: &[u8], b: &[u8]) -> bool {
+    const BASE_BLOCK_LEN: usize = 128;
+    a.len() >= BASE_BLOCK_LEN
+        && b.len() >= BASE_BLOCK_LEN
+        && a[..BASE_BLOCK_LEN] == b[..BASE_BLOCK_LEN]
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::process::Command;
+
+    use super::*;
+    use crate::logging::*;
+
+    /// Assign the outputs of `xrandr` if available,
+    /// or the DRM connectors otherwise, by matching their EDIDs.
+    /// The names of DRM connectors are the names in Wayland compositors.
+    pub(super) fn assign_display_outputs(monitors: &mut [Monitor]) {
+        let mut outputs = xrandr_outputs();
+        outputs.extend(drm_outputs());
+        for monitor in monitors {
+            let Some(edid) = monitor.edid() else {
+                continue;
+            };
+            if let Some((output, _)) = outputs
+                .iter()
+                .find(|(_, output_edid)| is_same_edid(edid, output_edid))
+            {
+                monitor.set_display_output(Some(output.clone()));
+            }
+        }
+    }
+
+    /// The outputs of `xrandr --props`, with their EDIDs.
+    fn xrandr_outputs() -> Vec<(DisplayOutput, Vec<u8>)> {
+        if std::env::var_os("DISPLAY").is_none() {
+            return vec![];
+        }
+        match Command::new("xrandr").arg("--props").output() {
+            Ok(output) if output.status.success() => {
+                parse_xrandr(&String::from_utf8_lossy(&output.stdout))
+            }
+            Ok(output) => {
+                debug!("xrandr failed: {}", output.status);
+                vec![]
+            }
+            Err(e) => {
+                debug!("Failed to run `xrandr`: {e}");
+                vec![]
+            }
+        }
+    }
+
+    /// Parse the output of `xrandr --props`.
+    pub(super) fn parse_xrandr(output: &str) -> Vec<(DisplayOutput, Vec<u8>)> {
+        let mut outputs: Vec<(DisplayOutput, Vec<u8>)> = vec![];
+        let mut is_edid = false;
+        for line in output.lines() {
+            if !line.starts_with(char::is_whitespace) {
+                is_edid = false;
+                let mut fields = line.split_whitespace();
+                let (Some(name), Some("connected")) = (fields.next(), fields.next()) else {
+                    continue;
+                };
+                let mut output = DisplayOutput {
+                    name: name.to_string(),
+                    ..Default::default()
+                };
+                for field in fields {
+                    if field == "primary" {
+                        output.primary = true;
+                    } else if let Some(geometry) = parse_geometry(field) {
+                        (output.width, output.height, output.x, output.y) = geometry;
+                        break;
+                    }
+                }
+                outputs.push((output, vec![]));
+                continue;
+            }
+            let line = line.trim();
+            if line == "EDID:" {
+                is_edid = true;
+                continue;
+            }
+            if is_edid && let Some((_, edid)) = outputs.last_mut() {
+                match parse_hex(line) {
+                    Some(bytes) => edid.extend(bytes),
+                    None => is_edid = false,
+                }
+            }
+        }
+        outputs
+    }
+
+    /// Parse a geometry such as `2560x1440+0+0`.
+    fn parse_geometry(value: &str) -> Option<(u32, u32, i32, i32)> {
+        let (width, rest) = value.split_once('x')?;
+        let position = rest.find(['+', '-'])?;
+        let (height, position) = rest.split_at(position);
+        let y_index = position[1..].find(['+', '-'])? + 1;
+        let (x, y) = position.split_at(y_index);
+        Some((
+            width.parse().ok()?,
+            height.parse().ok()?,
+            x.parse().ok()?,
+            y.parse().ok()?,
+        ))
+    }
+
+    fn parse_hex(line: &str) -> Option<Vec<u8>> {
+        if line.is_empty() || !line.len().is_multiple_of(2) {
+            return None;
+        }
+        (0..line.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(line.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+
+    /// The connected DRM connectors, such as `HDMI-A-1`, with their EDIDs.
+    fn drm_outputs() -> Vec<(DisplayOutput, Vec<u8>)> {
+        let Ok(entries) = std::fs::read_dir("/sys/class/drm") else {
+            return vec![];
+        };
+        let mut outputs = vec![];
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            // Connectors are `card1-HDMI-A-1`, while cards are `card1`.
+            let Some((_, name)) = file_name.to_str().and_then(|name| name.split_once('-')) else {
+                continue;
+            };
+            let Ok(edid) = std::fs::read(entry.path().join("edid")) else {
+                continue;
+            };
+            if edid.is_empty() {
+                continue;
+            }
+            let output = DisplayOutput {
+                name: name.to_string(),
+                ..Default::default()
+            };
+            outputs.push((output, edid));
+        }
+        outputs
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::ptr::{null, null_mut};
+
+    use windows_sys::Win32::Devices::Display::GetNumberOfPhysicalMonitorsFromHMONITOR;
+    use windows_sys::Win32::Foundation::{ERROR_SUCCESS, LPARAM, RECT};
+    use windows_sys::Win32::Graphics::Gdi::{
+        DISPLAY_DEVICE_ACTIVE, DISPLAY_DEVICEW, EnumDisplayDevicesW, EnumDisplayMonitors,
+        GetMonitorInfoW, HDC, HMONITOR, MONITORINFO, MONITORINFOEXW,
+    };
+    use windows_sys::Win32::System::Registry::{
+        HKEY_LOCAL_MACHINE, RRF_RT_REG_BINARY, RegGetValueW,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::EDD_GET_DEVICE_INTERFACE_NAME;
+    use windows_sys::core::BOOL;
+
+    use super::*;
+    use crate::logging::*;
+
+    /// `MONITORINFOF_PRIMARY` in `Win32_UI_WindowsAndMessaging`.
+    const MONITORINFOF_PRIMARY: u32 = 1;
+
+    /// The `winapi` backend enumerates physical monitors of each `HMONITOR`
+    /// in the order of `EnumDisplayMonitors`.
+    /// Enumerate them in the same way, and assign them in the same order.
+    /// Other backends such as `nvapi` are assigned by matching their EDIDs.
+    pub(super) fn assign_display_outputs(monitors: &mut [Monitor]) {
+        let Some(outputs) = physical_monitor_outputs() else {
+            return;
+        };
+        for monitor in monitors
+            .iter_mut()
+            .filter(|monitor| monitor.backend() != "winapi")
+        {
+            let Some(edid) = monitor.edid() else {
+                continue;
+            };
+            if let Some((output, _)) = outputs.iter().find(|(_, output_edid)| {
+                output_edid
+                    .as_deref()
+                    .is_some_and(|output_edid| is_same_edid(edid, output_edid))
+            }) {
+                monitor.set_display_output(Some(output.clone()));
+            }
+        }
+        let mut winapi_monitors: Vec<&mut Monitor> = monitors
+            .iter_mut()
+            .filter(|monitor| monitor.backend() == "winapi")
+            .collect();
+        if winapi_monitors.len() != outputs.len() {
+            debug!(
+                "The number of display outputs ({}) doesn't match the display monitors ({})",
+                outputs.len(),
+                winapi_monitors.len()
+            );
+            return;
+        }
+        for (monitor, (output, _)) in winapi_monitors.iter_mut().zip(outputs) {
+            monitor.set_display_output(Some(output));
+        }
+    }
+
+    /// The display outputs of the physical monitors, with their EDIDs if available.
+    fn physical_monitor_outputs() -> Option<Vec<(DisplayOutput, Option<Vec<u8>>)>> {
+        unsafe extern "system" fn callback(
+            hmonitor: HMONITOR,
+            _hdc: HDC,
+            _rect: *mut RECT,
+            data: LPARAM,
+        ) -> BOOL {
+            // SAFETY: `data` is the `hmonitors` below.
+            let hmonitors = unsafe { &mut *(data as *mut Vec<HMONITOR>) };
+            hmonitors.push(hmonitor);
+            1
+        }
+
+        let mut hmonitors: Vec<HMONITOR> = vec![];
+        // SAFETY: `callback` only pushes to `hmonitors`, which outlives the call.
+        let result = unsafe {
+            EnumDisplayMonitors(
+                null_mut(),
+                null(),
+                Some(callback),
+                &mut hmonitors as *mut Vec<HMONITOR> as LPARAM,
+            )
+        };
+        if result == 0 {
+            debug!("EnumDisplayMonitors failed");
+            return None;
+        }
+
+        let mut outputs = vec![];
+        for hmonitor in hmonitors {
+            let mut info = MONITORINFOEXW::default();
+            info.monitorInfo.cbSize = size_of::<MONITORINFOEXW>() as u32;
+            // SAFETY: `info` is a valid `MONITORINFOEXW` with `cbSize` set.
+            if unsafe { GetMonitorInfoW(hmonitor, &mut info as *mut _ as *mut MONITORINFO) } == 0 {
+                debug!("GetMonitorInfoW failed");
+                return None;
+            }
+            let mut count = 0;
+            // SAFETY: `hmonitor` is from `EnumDisplayMonitors`.
+            if unsafe { GetNumberOfPhysicalMonitorsFromHMONITOR(hmonitor, &mut count) } == 0 {
+                debug!("GetNumberOfPhysicalMonitorsFromHMONITOR failed");
+                return None;
+            }
+            let name_len = info
+                .szDevice
+                .iter()
+                .position(|ch| *ch == 0)
+                .unwrap_or(info.szDevice.len());
+            let rect = info.monitorInfo.rcMonitor;
+            let output = DisplayOutput {
+                name: String::from_utf16_lossy(&info.szDevice[..name_len]),
+                primary: info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY != 0,
+                x: rect.left,
+                y: rect.top,
+                width: (rect.right - rect.left) as u32,
+                height: (rect.bottom - rect.top) as u32,
+            };
+            trace!("{output}: {count} physical monitors");
+            let mut edids = attached_edids(&info.szDevice);
+            if edids.len() != count as usize {
+                debug!(
+                    "{output}: {} EDIDs for {count} physical monitors",
+                    edids.len()
+                );
+                edids = vec![None; count as usize];
+            }
+            for edid in edids {
+                outputs.push((output.clone(), edid));
+            }
+        }
+        Some(outputs)
+    }
+
+    /// The EDIDs of the active display monitors attached to the display output
+    /// of the null-terminated `device` name, such as `\\.\DISPLAY1`.
+    fn attached_edids(device: &[u16]) -> Vec<Option<Vec<u8>>> {
+        let mut edids = vec![];
+        for index in 0.. {
+            let mut display = DISPLAY_DEVICEW {
+                cb: size_of::<DISPLAY_DEVICEW>() as u32,
+                ..Default::default()
+            };
+            // SAFETY: `device` is null-terminated, and `display` has `cb` set.
+            let result = unsafe {
+                EnumDisplayDevicesW(
+                    device.as_ptr(),
+                    index,
+                    &mut display,
+                    EDD_GET_DEVICE_INTERFACE_NAME,
+                )
+            };
+            if result == 0 {
+                break;
+            }
+            if display.StateFlags & DISPLAY_DEVICE_ACTIVE == 0 {
+                continue;
+            }
+            let len = display
+                .DeviceID
+                .iter()
+                .position(|ch| *ch == 0)
+                .unwrap_or(display.DeviceID.len());
+            edids.push(edid_of_interface(&String::from_utf16_lossy(
+                &display.DeviceID[..len],
+            )));
+        }
+        edids
+    }
+
+    /// The EDID of the display monitor of the device interface `path`,
+    /// such as `\\?\DISPLAY#DEL4276#5&1a2b3c&0&UID4353#{e6f07b5f-...}`,
+    /// from its `Device Parameters` in the registry.
+    fn edid_of_interface(path: &str) -> Option<Vec<u8>> {
+        let mut parts = path.strip_prefix(r"\\?\")?.split('#');
+        let key = format!(
+            r"SYSTEM\CurrentControlSet\Enum\{}\{}\{}\Device Parameters",
+            parts.next()?,
+            parts.next()?,
+            parts.next()?
+        );
+        let wide = |s: &str| -> Vec<u16> { s.encode_utf16().chain(std::iter::once(0)).collect() };
+        let (key_w, name_w) = (wide(&key), wide("EDID"));
+        let mut size = 0u32;
+        // SAFETY: All strings are null-terminated. Querying the size with the null buffer is allowed.
+        let result = unsafe {
+            RegGetValueW(
+                HKEY_LOCAL_MACHINE,
+                key_w.as_ptr(),
+                name_w.as_ptr(),
+                RRF_RT_REG_BINARY,
+                null_mut(),
+                null_mut(),
+                &mut size,
+            )
+        };
+        if result != ERROR_SUCCESS {
+            debug!("{key}: No EDID");
+            return None;
+        }
+        let mut edid = vec![0u8; size as usize];
+        // SAFETY: `edid` has `size` bytes.
+        let result = unsafe {
+            RegGetValueW(
+                HKEY_LOCAL_MACHINE,
+                key_w.as_ptr(),
+                name_w.as_ptr(),
+                RRF_RT_REG_BINARY,
+                null_mut(),
+                edid.as_mut_ptr().cast(),
+                &mut size,
+            )
+        };
+        if result != ERROR_SUCCESS {
+            return None;
+        }
+        edid.truncate(size as usize);
+        Some(edid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn is_same_edid() {
+        let a = [1u8; 256];
+        let mut b = a[..128].to_vec();
+        assert!(super::is_same_edid(&a, &b));
+        b[127] = 0;
+        assert!(!super::is_same_edid(&a, &b));
+        assert!(!super::is_same_edid(&a[..64], &a[..64]));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_xrandr() {
+        let edid = "00ffffffffffff00".repeat(16);
+        let edid_lines = edid
+            .as_bytes()
+            .chunks(32)
+            .map(|chunk| format!("\t\t{}\n", std::str::from_utf8(chunk).unwrap()))
+            .collect::<String>();
+        let output = format!(
+            "Screen 0: minimum 8 x 8, current 4480 x 1440, maximum 32767 x 32767
+DP-1 connected primary 2560x1440+0+0 (normal left inverted right x axis y axis) 597mm x 336mm
+\tEDID: 
+{edid_lines}\tnon-desktop: 0 
+   2560x1440     59.95*+
+HDMI-1 connected 1920x1080+2560-100 (normal left inverted right x axis y axis) 527mm x 296mm
+HDMI-2 disconnected (normal left inverted right x axis y axis)
+"
+        );
+        let outputs = super::linux::parse_xrandr(&output);
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(
+            outputs[0].0,
+            super::DisplayOutput {
+                name: "DP-1".into(),
+                primary: true,
+                x: 0,
+                y: 0,
+                width: 2560,
+                height: 1440,
+            }
+        );
+        assert_eq!(outputs[0].1.len(), 128);
+        assert_eq!(outputs[0].1[..8], [0, 255, 255, 255, 255, 255, 255, 0]);
+        assert_eq!(outputs[1].0.to_string(), "HDMI-1 1920x1080+2560-100");
+        assert!(outputs[1].1.is_empty());
+    }
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+/// The delay to handle [`DockEvent::DisplayChange`],
+/// because the events come in bursts while docking,
+/// and display monitors need time before they respond to DDC/CI.
+pub(crate) const DISPLAY_CHANGE_DELAY: Duration = Duration::from_secs(3);
+
+/// Changes of docking detected by [`Platform::listen_hotplug()`][crate::platform::Platform::listen_hotplug].
+#[derive(Debug, PartialEq)]
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+pub(crate) enum DockEvent {
+    /// Display monitors or other devices may be connected or disconnected.
+    DisplayChange,
+    /// The power source may be changed. `true` if on AC power.
+    Power(bool),
+}
+
+/// Detects changes of the power source from [`DockEvent::Power`],
+/// which is also sent when only the battery level changes.
+#[derive(Debug, Default)]
+pub(crate) struct PowerWatcher {
+    on_ac: Option<bool>,
+}
+
+impl PowerWatcher {
+    /// Update with whether it's `on_ac` power,
+    /// and return it if it changed since the last update.
+    /// The first update only records the state.
+    pub fn update(&mut self, on_ac: bool) -> Option<bool> {
+        let previous = self.on_ac.replace(on_ac);
+        match previous {
+            Some(previous) if previous != on_ac => Some(on_ac),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_watcher() {
+        let mut watcher = PowerWatcher::default();
+        assert_eq!(watcher.update(true), None);
+        assert_eq!(watcher.update(true), None);
+        assert_eq!(watcher.update(false), Some(false));
+        assert_eq!(watcher.update(false), None);
+        assert_eq!(watcher.update(true), Some(true));
+    }
+}
@@ -0,0 +1,280 @@
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use super::*;
+use crate::logging::*;
+
+/// A function to run on its own thread, with its name for logging.
+type Task<T> = (String, Box<dyn FnOnce() -> T + Send>);
+
+/// The display monitor found on an I2C bus.
+/// Its [`ddc_hi::Display`] is created by [`into_displays()`].
+#[cfg(target_os = "linux")]
+type Found = Option<(ddc_i2c::I2cDeviceDdc, ddc_hi::DisplayInfo)>;
+
+/// The enumerations of display monitors to run in parallel.
+/// On Linux, each I2C bus is read in parallel,
+/// because reading EDIDs from some buses is slow.
+#[cfg(target_os = "linux")]
+fn enumerators() -> Vec<Task<Found>> {
+    use ddc::Edid;
+    use std::os::unix::fs::MetadataExt;
+
+    let Ok(devices) = ddc_i2c::I2cDeviceEnumerator::new() else {
+        return vec![];
+    };
+    devices
+        .map(|mut ddc| -> Task<Found> {
+            let id = ddc
+                .inner_ref()
+                .inner_ref()
+                .metadata()
+                .map(|meta| meta.rdev())
+                .unwrap_or_default()
+                .to_string();
+            let name = match i2c_bus(&id) {
+                Some(bus) => format!("i2c-dev (/dev/i2c-{bus})"),
+                None => format!("i2c-dev ({id})"),
+            };
+            let enumerate = move || {
+                let mut edid = vec![0u8; 0x100];
+                let info = match ddc.read_edid(0, &mut edid) {
+                    Ok(_) => ddc_hi::DisplayInfo::from_edid(ddc_hi::Backend::I2cDevice, id, edid),
+                    Err(e) => Err(e),
+                };
+                match info {
+                    Ok(info) => Some((ddc, info)),
+                    Err(e) => {
+                        debug!("Failed to read EDID: {e}");
+                        None
+                    }
+                }
+            };
+            (name, Box::new(enumerate))
+        })
+        .collect()
+}
+
+/// The display monitors to enumerate on the calling thread, before the [`enumerators()`].
+#[cfg(target_os = "linux")]
+fn enumerate_on_this_thread() -> Vec<ddc_hi::Display> {
+    vec![]
+}
+
+/// Create the [`ddc_hi::Display`]s from the results of the [`enumerators()`].
+#[cfg(target_os = "linux")]
+fn into_displays(found: Vec<Found>) -> Vec<ddc_hi::Display> {
+    found
+        .into_iter()
+        .flatten()
+        .map(|(ddc, info)| ddc_hi::Display::new(ddc_hi::Handle::I2cDevice(ddc), info))
+        .collect()
+}
+
+/// The GPUs and the IDs and [`ddc_hi::DisplayInfo`] of their display monitors,
+/// found by the `nvapi` backend.
+/// The handles of display monitors share an `Rc` of their GPU,
+/// so they're created by [`into_displays()`] on the calling thread.
+#[cfg(windows)]
+type Found = Vec<(nvapi::PhysicalGpu, Vec<(u32, ddc_hi::DisplayInfo)>)>;
+
+/// The enumerations of display monitors to run in parallel.
+/// On Windows, this is only the `nvapi` backend. See [`enumerate_on_this_thread()`].
+#[cfg(windows)]
+fn enumerators() -> Vec<Task<Found>> {
+    vec![("nvapi".to_string(), Box::new(enumerate_nvapi))]
+}
+
+/// Enumerate the `nvapi` backend in the same way as [`ddc_hi::Display::enumerate()`].
+#[cfg(windows)]
+fn enumerate_nvapi() -> Found {
+    use ddc::Edid;
+
+    if nvapi::initialize().is_err() {
+        return vec![];
+    }
+    let Ok(gpus) = nvapi::PhysicalGpu::enumerate() else {
+        return vec![];
+    };
+    gpus.into_iter()
+        .map(|gpu| {
+            let prefix = gpu.short_name().unwrap_or_else(|_| "NVAPI".to_string());
+            let ids = gpu
+                .display_ids_connected(nvapi::ConnectedIdsFlags::empty())
+                .unwrap_or_default();
+            let infos = ids
+                .into_iter()
+                .filter_map(|id| {
+                    let name = format!("{prefix}/{}:{:?}", id.display_id, id.connector);
+                    let mut i2c = nvapi_i2c(&gpu, id.display_id);
+                    // Write the EDID offset first as `ddc_hi` does,
+                    // because some drivers ignore the register argument.
+                    i2c.set_address(0x50);
+                    let _ = i2c.nvapi_write(&[], &[0]);
+                    let mut ddc = ddc_i2c::I2cDdc::new(i2c);
+                    let mut edid = vec![0u8; 0x80];
+                    let info = match ddc.read_edid(0, &mut edid) {
+                        Ok(_) => ddc_hi::DisplayInfo::from_edid(
+                            ddc_hi::Backend::Nvapi,
+                            name.clone(),
+                            edid,
+                        )
+                        .map_err(anyhow::Error::from),
+                        Err(e) => Err(anyhow::anyhow!("Failed to read EDID: {e:?}")),
+                    };
+                    match info {
+                        Ok(info) => Some((id.display_id, info)),
+                        Err(e) => {
+                            warn!("Failed to enumerate the nvapi display {name}: {e}");
+                            None
+                        }
+                    }
+                })
+                .collect();
+            (gpu, infos)
+        })
+        .collect()
+}
+
+/// The I2C of the `display_id` of the `gpu`, opened as `ddc_hi` does.
+#[cfg(windows)]
+fn nvapi_i2c<G>(gpu: G, display_id: u32) -> nvapi::I2c<G> {
+    let mut i2c = nvapi::I2c::new(gpu, display_id);
+    i2c.set_port(None, true);
+    i2c
+}
+
+/// The display monitors to enumerate on the calling thread, before the [`enumerators()`].
+/// On Windows, this is the `winapi` backend,
+/// because its handles can't be moved across threads.
+#[cfg(windows)]
+fn enumerate_on_this_thread() -> Vec<ddc_hi::Display> {
+    let monitors = ddc_winapi::Monitor::enumerate().unwrap_or_default();
+    monitors
+        .into_iter()
+        .map(|ddc| {
+            let info = ddc_hi::DisplayInfo::new(ddc_hi::Backend::WinApi, ddc.description());
+            ddc_hi::Display::new(ddc_hi::Handle::WinApi(ddc), info)
+        })
+        .collect()
+}
+
+/// Create the [`ddc_hi::Display`]s from the results of the [`enumerators()`].
+#[cfg(windows)]
+fn into_displays(found: Vec<Found>) -> Vec<ddc_hi::Display> {
+    let mut displays = vec![];
+    for (gpu, infos) in found.into_iter().flatten() {
+        let gpu = std::rc::Rc::new(gpu);
+        for (display_id, info) in infos {
+            let ddc = ddc_i2c::I2cDdc::new(nvapi_i2c(gpu.clone(), display_id));
+            displays.push(ddc_hi::Display::new(ddc_hi::Handle::Nvapi(ddc), info));
+        }
+    }
+    displays
+}
+
+/// There are no enumerations to run in parallel on other platforms,
+/// because their handles can't be moved across threads.
+#[cfg(not(any(target_os = "linux", windows)))]
+type Found = std::convert::Infallible;
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn enumerators() -> Vec<Task<Found>> {
+    vec![]
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn enumerate_on_this_thread() -> Vec<ddc_hi::Display> {
+    ddc_hi::Display::enumerate()
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn into_displays(_found: Vec<Found>) -> Vec<ddc_hi::Display> {
+    vec![]
+}
+
+/// Run the `tasks` in parallel, and return the results in the order of the `tasks`.
+/// The results of the tasks that don't finish within the `timeout` are skipped,
+/// and their names are logged.
+/// Their threads keep running in the background, and their results are dropped.
+fn run_with_timeout<T: Send + 'static>(tasks: Vec<Task<T>>, timeout: Duration) -> Vec<T> {
+    let deadline = Instant::now() + timeout;
+    let (sender, receiver) = mpsc::channel();
+    let mut names = vec![];
+    for (index, (name, task)) in tasks.into_iter().enumerate() {
+        names.push(name);
+        let sender = sender.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send((index, task()));
+        });
+    }
+    drop(sender);
+    let mut results: Vec<Option<T>> = names.iter().map(|_| None).collect();
+    let mut pending = names.len();
+    while pending > 0 {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let Ok((index, result)) = receiver.recv_timeout(remaining) else {
+            break;
+        };
+        results[index] = Some(result);
+        pending -= 1;
+    }
+    for (name, result) in names.iter().zip(&results) {
+        if result.is_none() {
+            warn!(
+                "Enumerating {name} didn't finish in {timeout:?}, its display monitors are skipped."
+            );
+        }
+    }
+    results.into_iter().flatten().collect()
+}
+
+impl Monitor {
+    /// Enumerate display monitors as [`Monitor::enumerate()`],
+    /// but return only the display monitors found within the `timeout`.
+    ///
+    /// The enumerations of I2C buses on Linux, or the `nvapi` backend on Windows,
+    /// run in parallel, and those still pending after the `timeout` are logged and skipped.
+    /// This is for GPU drivers that take long to enumerate.
+    /// The `winapi` backend on Windows, and all backends on other platforms,
+    /// are enumerated without the `timeout`,
+    /// because their handles can't be moved across threads.
+    pub fn enumerate_with_timeout(timeout: Duration) -> Vec<Self> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("enumerate_with_timeout").entered();
+        let mut displays = enumerate_on_this_thread();
+        displays.extend(into_displays(run_with_timeout(enumerators(), timeout)));
+        let mut monitors: Vec<Self> = displays.into_iter().map(Monitor::new).collect();
+        remove_duplicates(&mut monitors);
+        assign_display_outputs(&mut monitors);
+        monitors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_with_timeout() {
+        let task = |name: &str, value: u32, delay_ms: u64| -> Task<u32> {
+            (
+                name.to_string(),
+                Box::new(move || {
+                    std::thread::sleep(Duration::from_millis(delay_ms));
+                    value
+                }),
+            )
+        };
+        let start = Instant::now();
+        let results = super::run_with_timeout(
+            vec![task("slow", 1, 5000), task("b", 2, 10), task("a", 3, 0)],
+            Duration::from_millis(500),
+        );
+        assert_eq!(results, [2, 3]);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let results = super::run_with_timeout::<u32>(vec![], Duration::ZERO);
+        assert!(results.is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use strum::VariantArray;
+use strum_macros::{AsRefStr, EnumString, VariantArray};
+
+use super::*;
+use crate::logging::*;
+
+/// How long a simulated [`FaultKind::Timeout`] takes.
+const SIMULATED_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Copy, Clone, Debug, PartialEq, AsRefStr, EnumString, VariantArray)]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+/// The kinds of failures of display monitors to simulate.
+pub(crate) enum FaultKind {
+    /// Requests time out after [`SIMULATED_TIMEOUT`].
+    Timeout,
+    /// The display monitor doesn't acknowledge requests.
+    Nack,
+    /// The capabilities string is broken.
+    GarbageCapabilities,
+    /// The handle is no longer valid, as after the computer resumes from sleep.
+    StaleHandle,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A failure to simulate by the `--simulate-failure` option,
+/// in the form of `[NAME=]KIND[*COUNT]`, such as `nack` or `B=timeout*2`.
+pub struct SimulatedFailure {
+    /// The name of display monitors, or empty for all display monitors.
+    name: String,
+    kind: FaultKind,
+    /// The number of requests to fail, or `None` to fail all requests.
+    count: Option<usize>,
+}
+
+impl FromStr for SimulatedFailure {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, s) = s.split_once('=').unwrap_or(("", s));
+        let (kind, count) = match s.split_once('*') {
+            Some((kind, count)) => (kind, Some(count.parse()?)),
+            None => (s, None),
+        };
+        let kind = kind.parse().map_err(|_| {
+            anyhow::anyhow!(
+                "Unknown failure \"{kind}\". Use one of: {}",
+                FaultKind::VARIANTS
+                    .iter()
+                    .map(|kind| kind.as_ref())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+        Ok(Self {
+            name: name.to_string(),
+            kind,
+            count,
+        })
+    }
+}
+
+impl SimulatedFailure {
+    /// Make the `monitor` at `index` fail if it matches the [`SimulatedFailure::name`].
+    pub(crate) fn apply(&self, index: usize, monitor: &mut Monitor, config: &Config) {
+        match Selector::parse(&self.name, config) {
+            Ok(selector) if selector.matches_monitor(index, monitor) => {
+                warn!("{monitor}: Simulating failures: {}", self.kind.as_ref());
+                let failure = self.clone();
+                monitor.wrap_device(|device| Box::new(FaultyDevice { device, failure }));
+            }
+            Ok(_) => {}
+            Err(e) => warn!("{e} in `--simulate-failure`"),
+        }
+    }
+}
+
+/// A [`DdcDevice`] that fails as the [`SimulatedFailure`].
+struct FaultyDevice {
+    device: Box<dyn DdcDevice>,
+    failure: SimulatedFailure,
+}
+
+impl FaultyDevice {
+    /// Whether the next request should fail, counting down the remaining failures.
+    fn should_fail(&mut self) -> bool {
+        match &mut self.failure.count {
+            None => true,
+            Some(0) => false,
+            Some(count) => {
+                *count -= 1;
+                true
+            }
+        }
+    }
+
+    /// Run `f` on the device, or fail as the [`SimulatedFailure`].
+    fn request<T>(
+        &mut self,
+        f: impl FnOnce(&mut dyn DdcDevice) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        if self.failure.kind == FaultKind::GarbageCapabilities || !self.should_fail() {
+            return f(self.device.as_mut());
+        }
+        let id = self.device.id();
+        match self.failure.kind {
+            FaultKind::Timeout => {
+                std::thread::sleep(SIMULATED_TIMEOUT);
+                Err(
+                    anyhow::Error::from(std::io::Error::from(std::io::ErrorKind::TimedOut))
+                        .context(format!("{id}: Simulated timeout")),
+                )
+            }
+            FaultKind::Nack => anyhow::bail!("{id}: Simulated NACK"),
+            FaultKind::StaleHandle => {
+                anyhow::bail!("{id}: Simulated failure: The handle is invalid.")
+            }
+            FaultKind::GarbageCapabilities => unreachable!(),
+        }
+    }
+}
+
+impl DdcDevice for FaultyDevice {
+    fn id(&self) -> &str {
+        self.device.id()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.device.model_name()
+    }
+
+    fn serial(&self) -> Option<String> {
+        self.device.serial()
+    }
+
+    fn backend_name(&self) -> String {
+        self.device.backend_name()
+    }
+
+    fn edid(&self) -> Option<&[u8]> {
+        self.device.edid()
+    }
+
+    fn is_internal(&self) -> bool {
+        self.device.is_internal()
+    }
+
+    fn is_mst(&self) -> bool {
+        self.device.is_mst()
+    }
+
+    fn capabilities_string(&mut self) -> anyhow::Result<String> {
+        if self.failure.kind == FaultKind::GarbageCapabilities && self.should_fail() {
+            return Ok("(prot(monitor)vcp(60(0F 1\u{FFFD}\u{0}x)mccs_ver(".to_string());
+        }
+        self.request(|device| device.capabilities_string())
+    }
+
+    fn get_vcp_feature(&mut self, code: VcpCode) -> anyhow::Result<VcpValue> {
+        self.request(|device| device.get_vcp_feature(code))
+    }
+
+    fn vcp_kind(&self, code: VcpCode) -> VcpKind {
+        self.device.vcp_kind(code)
+    }
+
+    fn set_vcp_feature(&mut self, code: VcpCode, value: u16) -> anyhow::Result<()> {
+        self.request(|device| device.set_vcp_feature(code, value))
+    }
+
+    fn sleep(&mut self) {
+        self.device.sleep()
+    }
+
+    fn ddc_raw(&mut self, data: &[u8], read_len: usize) -> anyhow::Result<Vec<u8>> {
+        self.request(|device| device.ddc_raw(data, read_len))
+    }
+
+    fn reopen(&mut self) -> anyhow::Result<()> {
+        self.device.reopen()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let parse = |s: &str| s.parse::<SimulatedFailure>().unwrap();
+        assert_eq!(
+            parse("nack"),
+            SimulatedFailure {
+                name: String::new(),
+                kind: FaultKind::Nack,
+                count: None
+            }
+        );
+        assert_eq!(
+            parse("B=garbage-capabilities*2"),
+            SimulatedFailure {
+                name: "B".to_string(),
+                kind: FaultKind::GarbageCapabilities,
+                count: Some(2)
+            }
+        );
+        assert!("unknown".parse::<SimulatedFailure>().is_err());
+        assert!("nack*x".parse::<SimulatedFailure>().is_err());
+    }
+
+    fn monitor_with_failure(failure: &str) -> Monitor {
+        let mut monitor = Monitor::from_device(
+            MockMonitor::new("A")
+                .with_input_source(InputSource::DisplayPort1.as_raw())
+                .with_input_sources(&[InputSource::DisplayPort1.as_raw()]),
+        );
+        let failure: SimulatedFailure = failure.parse().unwrap();
+        failure.apply(0, &mut monitor, &Config::default());
+        monitor
+    }
+
+    #[test]
+    fn nack() {
+        let mut monitor = monitor_with_failure("A=nack*2");
+        assert!(monitor.input_source().is_err());
+        assert!(monitor.input_source().is_err());
+        assert!(monitor.input_source().is_ok());
+
+        let mut monitor = monitor_with_failure("B=nack");
+        assert!(monitor.input_source().is_ok());
+    }
+
+    #[test]
+    fn stale_handle() {
+        // The `Monitor` reopens the device and retries.
+        let mut monitor = monitor_with_failure("stale-handle*1");
+        assert!(monitor.input_source().is_ok());
+    }
+
+    #[test]
+    fn garbage_capabilities() {
+        let mut monitor = monitor_with_failure("garbage-capabilities*1");
+        assert!(monitor.input_source().is_ok());
+        // The `vcp(...)` section is salvaged from the broken capabilities string.
+        monitor.update_capabilities().unwrap();
+        assert_eq!(monitor.input_sources(), Some(vec![0x0F]));
+    }
+}
@@ -0,0 +1,205 @@
+//! A lightweight GUI, which shows display monitors as cards
+//! with buttons to change their input sources and sliders for their brightness.
+#![cfg_attr(target_os = "windows", windows_subsystem = "windows")]
+
+use std::sync::{Arc, Mutex};
+
+use eframe::egui;
+use strum::VariantArray;
+
+use monitor_input::{
+    InputSource, InputSourceRaw, InputValue, Monitor, SharedMonitor, VcpValue, tr,
+};
+
+fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let monitors = SharedMonitor::spawn_all(|| {
+        let mut monitors = Monitor::enumerate();
+        monitors.retain(|monitor| !monitor.is_internal());
+        monitors
+    })?;
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([480.0, 360.0]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        "monitor-input",
+        options,
+        Box::new(|cc| Ok(Box::new(App::new(&cc.egui_ctx, monitors)))),
+    )
+    .map_err(|e| anyhow::anyhow!("{e}"))
+}
+
+/// The states of a display monitor shown in a card.
+/// They're updated by requests on other threads,
+/// so that slow DDC/CI requests don't block the UI.
+#[derive(Default)]
+struct CardState {
+    name: String,
+    input_source: Option<InputSourceRaw>,
+    input_sources: Vec<InputSourceRaw>,
+    brightness: Option<VcpValue>,
+    is_busy: bool,
+    error: Option<String>,
+}
+
+struct Card {
+    monitor: SharedMonitor,
+    state: Arc<Mutex<CardState>>,
+    /// The value of the slider while it's dragged.
+    brightness: Option<u16>,
+}
+
+impl Card {
+    fn new(ctx: &egui::Context, monitor: SharedMonitor) -> Self {
+        let card = Self {
+            state: Arc::new(Mutex::new(CardState {
+                name: monitor.id().to_string(),
+                ..Default::default()
+            })),
+            monitor,
+            brightness: None,
+        };
+        card.request(ctx, |monitor, state| {
+            let (name, info, brightness) = monitor.with(|monitor| {
+                // The input sources are available only with the capabilities.
+                let _ = monitor.update_capabilities();
+                (
+                    monitor.to_string(),
+                    monitor.info(),
+                    monitor.brightness().ok(),
+                )
+            })?;
+            let mut state = state.lock().unwrap();
+            state.name = name;
+            state.input_source = info.current_input;
+            state.input_sources = info.inputs.unwrap_or_else(|| {
+                InputSource::VARIANTS
+                    .iter()
+                    .map(|value| value.as_raw())
+                    .collect()
+            });
+            state.brightness = brightness;
+            Ok(())
+        });
+        card
+    }
+
+    /// Run the `request` on a new thread, and show its error if it fails.
+    fn request(
+        &self,
+        ctx: &egui::Context,
+        request: impl FnOnce(&SharedMonitor, &Mutex<CardState>) -> anyhow::Result<()> + Send + 'static,
+    ) {
+        self.state.lock().unwrap().is_busy = true;
+        let monitor = self.monitor.clone();
+        let state = self.state.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let result = request(&monitor, &state);
+            let mut state = state.lock().unwrap();
+            state.is_busy = false;
+            state.error = result.err().map(|e| e.to_string());
+            ctx.request_repaint();
+        });
+    }
+
+    fn set_input_source(&self, ctx: &egui::Context, value: InputSourceRaw) {
+        self.request(ctx, move |monitor, state| {
+            monitor.set_input_source(value)?;
+            state.lock().unwrap().input_source = Some(value);
+            Ok(())
+        });
+    }
+
+    fn set_brightness(&self, ctx: &egui::Context, value: u16) {
+        self.request(ctx, move |monitor, state| {
+            monitor.set_brightness(value)?;
+            if let Some(brightness) = &mut state.lock().unwrap().brightness {
+                brightness.value = value;
+            }
+            Ok(())
+        });
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        let ctx = ui.ctx().clone();
+        let mut state = self.state.lock().unwrap();
+        ui.horizontal(|ui| {
+            ui.heading(&state.name);
+            if state.is_busy {
+                ui.spinner();
+            }
+        });
+        let mut input_source = None;
+        ui.horizontal_wrapped(|ui| {
+            for &value in &state.input_sources {
+                let label = InputValue::from(value).to_string();
+                if ui
+                    .selectable_label(state.input_source == Some(value), label)
+                    .clicked()
+                {
+                    input_source = Some(value);
+                }
+            }
+        });
+        let mut brightness = None;
+        if let Some(value) = &mut state.brightness {
+            let current = self.brightness.get_or_insert(value.value);
+            let response =
+                ui.add(egui::Slider::new(current, 0..=value.maximum).text(tr("Brightness")));
+            // Change the brightness when the slider is released, not on every move.
+            if !response.dragged() {
+                if *current != value.value {
+                    brightness = Some(*current);
+                    value.value = *current;
+                }
+                self.brightness = None;
+            }
+        }
+        if let Some(error) = &state.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        drop(state);
+        if let Some(value) = input_source {
+            self.set_input_source(&ctx, value);
+        }
+        if let Some(value) = brightness {
+            self.set_brightness(&ctx, value);
+        }
+    }
+}
+
+struct App {
+    cards: Vec<Card>,
+}
+
+impl App {
+    fn new(ctx: &egui::Context, monitors: Vec<SharedMonitor>) -> Self {
+        Self {
+            cards: monitors
+                .into_iter()
+                .map(|monitor| Card::new(ctx, monitor))
+                .collect(),
+        }
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if self.cards.is_empty() {
+                ui.label(tr("No display monitors found."));
+                return;
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for card in &mut self.cards {
+                    ui.group(|ui| {
+                        ui.set_width(ui.available_width());
+                        card.ui(ui);
+                    });
+                }
+            });
+        });
+    }
+}
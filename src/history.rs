@@ -0,0 +1,164 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::*;
+use crate::logging::*;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+/// A change of the input source in the history file.
+/// See [`history_path()`] for the location.
+pub(crate) struct HistoryEntry {
+    /// The time of the change, in RFC 3339.
+    pub time: String,
+    pub monitor: String,
+    pub previous: Option<String>,
+    pub input_source: String,
+    /// What made the change, such as `command`, `schedule`, or `external`.
+    pub trigger: String,
+}
+
+impl HistoryEntry {
+    pub fn new(monitor: &str, previous: Option<&str>, input_source: &str, trigger: &str) -> Self {
+        Self {
+            time: jiff::Timestamp::now().to_string(),
+            monitor: monitor.to_string(),
+            previous: previous.map(str::to_string),
+            input_source: input_source.to_string(),
+            trigger: trigger.to_string(),
+        }
+    }
+
+    /// The entry for the `result`, if it changed the input source.
+    pub fn from_set_result(result: &SetResult, trigger: &str) -> Option<Self> {
+        if !result.success
+            || result.rolled_back
+            || result.previous.as_ref() == Some(&result.requested)
+        {
+            return None;
+        }
+        Some(Self::new(
+            &result.monitor,
+            result.previous.as_deref(),
+            &result.requested,
+            trigger,
+        ))
+    }
+
+    /// The [`HistoryEntry::time`] in the local time zone, for printing.
+    pub fn local_time(&self) -> String {
+        match self.time.parse::<jiff::Timestamp>() {
+            Ok(time) => time
+                .to_zoned(jiff::tz::TimeZone::system())
+                .strftime("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            Err(_) => self.time.clone(),
+        }
+    }
+}
+
+/// The path of the history file, `history.jsonl` next to the `state_path`.
+pub(crate) fn history_path(state_path: Option<&Path>) -> Option<PathBuf> {
+    state_path.map(|path| path.with_file_name("history.jsonl"))
+}
+
+/// Append the `entries` to the [`history_path()`].
+pub(crate) fn append_history(
+    state_path: Option<&Path>,
+    entries: &[HistoryEntry],
+) -> anyhow::Result<()> {
+    match history_path(state_path) {
+        Some(path) if !entries.is_empty() => append_history_to(&path, entries),
+        _ => Ok(()),
+    }
+}
+
+fn append_history_to(path: &Path, entries: &[HistoryEntry]) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut text = String::new();
+    for entry in entries {
+        text.push_str(&serde_json::to_string(entry)?);
+        text.push('\n');
+    }
+    // Write all lines at once, so that lines from other processes don't interleave.
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .with_context(|| format!("Failed to write the history file {path:?}"))
+}
+
+/// Read the entries from the [`history_path()`], the oldest first.
+/// Returns empty if the file doesn't exist.
+pub(crate) fn read_history(state_path: Option<&Path>) -> anyhow::Result<Vec<HistoryEntry>> {
+    match history_path(state_path) {
+        Some(path) if path.exists() => read_history_from(&path),
+        _ => Ok(vec![]),
+    }
+}
+
+fn read_history_from(path: &Path) -> anyhow::Result<Vec<HistoryEntry>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the history file {path:?}"))?;
+    Ok(text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                // Skip broken lines, such as when writing was interrupted.
+                debug!("History: {e}: {line}");
+                None
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_and_read() {
+        let dir =
+            std::env::temp_dir().join(format!("monitor-input-history-{}", std::process::id()));
+        let path = dir.join("history.jsonl");
+        let a = HistoryEntry::new("A", Some("DP1"), "Hdmi1", "command");
+        let b = HistoryEntry::new("B", None, "DP1", "schedule");
+        append_history_to(&path, std::slice::from_ref(&a)).unwrap();
+        append_history_to(&path, std::slice::from_ref(&b)).unwrap();
+        std::fs::write(&path, std::fs::read_to_string(&path).unwrap() + "broken\n").unwrap();
+        assert_eq!(read_history_from(&path).unwrap(), [a, b]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn from_set_result() {
+        let result = SetResult {
+            monitor: "A".into(),
+            requested: "Hdmi1".into(),
+            previous: Some("DP1".into()),
+            success: true,
+            ..Default::default()
+        };
+        let entry = HistoryEntry::from_set_result(&result, "idle").unwrap();
+        assert_eq!(entry.previous.as_deref(), Some("DP1"));
+        assert_eq!(entry.input_source, "Hdmi1");
+        assert_eq!(entry.trigger, "idle");
+
+        let unchanged = SetResult {
+            previous: Some("Hdmi1".into()),
+            ..result.clone()
+        };
+        assert!(HistoryEntry::from_set_result(&unchanged, "idle").is_none());
+        let failed = SetResult {
+            success: false,
+            ..result
+        };
+        assert!(HistoryEntry::from_set_result(&failed, "idle").is_none());
+    }
+}
@@ -0,0 +1,84 @@
+use std::process::Command;
+
+use super::*;
+use crate::logging::*;
+
+/// Split the `command_line` into arguments by whitespace.
+/// Whitespace in single or double quotes doesn't split arguments.
+fn split_command_line(command_line: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut arg: Option<String> = None;
+    let mut quote: Option<char> = None;
+    for ch in command_line.chars() {
+        match quote {
+            Some(q) if ch == q => quote = None,
+            Some(_) => arg.get_or_insert_default().push(ch),
+            None if ch == '"' || ch == '\'' => {
+                quote = Some(ch);
+                arg.get_or_insert_default();
+            }
+            None if ch.is_whitespace() => args.extend(arg.take()),
+            None => arg.get_or_insert_default().push(ch),
+        }
+    }
+    args.extend(arg);
+    args
+}
+
+/// Expand the `template` for the `result` into arguments.
+/// `{monitor}`, `{input}`, and `{previous}` are replaced in each argument,
+/// so that values with spaces don't split arguments.
+fn expand(template: &str, result: &SetResult) -> Vec<String> {
+    split_command_line(template)
+        .into_iter()
+        .map(|arg| {
+            arg.replace("{monitor}", &result.monitor)
+                .replace("{input}", &result.requested)
+                .replace("{previous}", result.previous.as_deref().unwrap_or_default())
+        })
+        .collect()
+}
+
+/// Run the [`Config::on_switch`] command for the `result`, and wait for it.
+/// Failures are logged but ignored, because the switch has already succeeded.
+pub(crate) fn run_on_switch(template: &str, result: &SetResult) {
+    let args = expand(template, result);
+    let Some((program, args)) = args.split_first() else {
+        return;
+    };
+    debug!("on_switch: {program} {}", args.join(" "));
+    match Command::new(program).args(args).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => warn!("on_switch: `{program}` failed: {status}"),
+        Err(e) => warn!("on_switch: Failed to run `{program}`: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_command_line() {
+        assert_eq!(
+            super::split_command_line(r#"a  "b c" 'd "e"' f"g h"i "" "#),
+            ["a", "b c", r#"d "e""#, "fg hi", ""]
+        );
+        assert!(super::split_command_line("  ").is_empty());
+    }
+
+    #[test]
+    fn expand() {
+        let result = SetResult {
+            monitor: "DELL U2723QE".to_string(),
+            requested: "Hdmi1".to_string(),
+            previous: Some("DP1".to_string()),
+            success: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            super::expand("switch.sh --monitor={monitor} {input} {previous}", &result),
+            ["switch.sh", "--monitor=DELL U2723QE", "Hdmi1", "DP1"]
+        );
+    }
+}
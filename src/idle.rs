@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use super::*;
+use crate::logging::*;
+
+/// The duration since the last user input,
+/// or `None` if it's not available on the platform.
+///
+/// * Windows uses `GetLastInputInfo`.
+/// * Mac uses `HIDIdleTime` of `ioreg`.
+/// * Linux uses the `xprintidle` command if it's installed.
+pub(crate) fn idle_time() -> Option<Duration> {
+    let result = native().idle_time();
+    trace!("idle_time: {result:?}");
+    result
+}
+
+/// Parse the `HIDIdleTime` in nanoseconds from the output of `ioreg`.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub(crate) fn parse_ioreg(output: &str) -> Option<Duration> {
+    let line = output
+        .lines()
+        .find(|line| line.contains("\"HIDIdleTime\""))?;
+    let nanos = line.rsplit('=').next()?.trim().parse().ok()?;
+    Some(Duration::from_nanos(nanos))
+}
+
+#[derive(Debug, PartialEq)]
+/// A change of the user activity detected by [`IdleWatcher`].
+pub(crate) enum IdleEvent {
+    /// The user became idle.
+    Idle,
+    /// The user became active after [`IdleEvent::Idle`].
+    Active,
+}
+
+#[derive(Debug)]
+/// Detects changes between idle and active.
+pub(crate) struct IdleWatcher {
+    timeout: Duration,
+    is_idle: bool,
+}
+
+impl IdleWatcher {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            is_idle: false,
+        }
+    }
+
+    /// Update with the current `idle_time`,
+    /// and return the event if the state changed.
+    pub fn update(&mut self, idle_time: Duration) -> Option<IdleEvent> {
+        let is_idle = idle_time >= self.timeout;
+        if is_idle == self.is_idle {
+            return None;
+        }
+        self.is_idle = is_idle;
+        Some(if is_idle {
+            IdleEvent::Idle
+        } else {
+            IdleEvent::Active
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_watcher() {
+        let mut watcher = IdleWatcher::new(Duration::from_secs(60));
+        assert_eq!(watcher.update(Duration::from_secs(10)), None);
+        assert_eq!(
+            watcher.update(Duration::from_secs(60)),
+            Some(IdleEvent::Idle)
+        );
+        assert_eq!(watcher.update(Duration::from_secs(70)), None);
+        assert_eq!(
+            watcher.update(Duration::from_secs(1)),
+            Some(IdleEvent::Active)
+        );
+        assert_eq!(watcher.update(Duration::from_secs(2)), None);
+    }
+
+    #[test]
+    fn parse_ioreg() {
+        let output = r#"
+    | |   "HIDIdleTime" = 1234000000
+    | |   "HIDParameters" = {}
+"#;
+        assert_eq!(
+            super::parse_ioreg(output),
+            Some(Duration::from_secs_f64(1.234))
+        );
+        assert_eq!(super::parse_ioreg(""), None);
+    }
+}
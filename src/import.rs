@@ -0,0 +1,500 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Context;
+use clap::ValueEnum;
+
+use super::*;
+
+/// The formats of similar tools for `config import` and `config export`.
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
+pub enum ProfileFormat {
+    /// Shell scripts of `ddcutil setvcp 60` commands.
+    Ddcutil,
+    /// Configuration files saved by NirSoft ControlMyMonitor,
+    /// or its `/SetValue` command lines for exports.
+    #[value(name = "controlmymonitor")]
+    ControlMyMonitor,
+    /// The `display-switch.ini` of display-switch.
+    DisplaySwitch,
+}
+
+impl ProfileFormat {
+    /// Guess the format from the extension of the `path`.
+    pub(crate) fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "cfg" => ProfileFormat::ControlMyMonitor,
+            "ini" | "toml" => ProfileFormat::DisplaySwitch,
+            _ => ProfileFormat::Ddcutil,
+        }
+    }
+}
+
+/// Profiles converted from the configurations of other tools.
+#[derive(Debug, Default)]
+pub(crate) struct Imported {
+    pub profiles: BTreeMap<String, Profile>,
+    /// The [`UsbConfig`] converted from display-switch.
+    pub usb: Option<UsbConfig>,
+    /// What couldn't be converted.
+    pub warnings: Vec<String>,
+}
+
+impl Imported {
+    /// Convert the `text` in the `format`.
+    /// Profiles are named `name`, except display-switch,
+    /// which is converted to the [`UsbConfig`].
+    pub fn parse(format: ProfileFormat, text: &str, name: &str) -> anyhow::Result<Self> {
+        let mut imported = Self::default();
+        match format {
+            ProfileFormat::Ddcutil => imported.parse_ddcutil(text, name)?,
+            ProfileFormat::ControlMyMonitor => imported.parse_control_my_monitor(text, name)?,
+            ProfileFormat::DisplaySwitch => imported.parse_display_switch(text)?,
+        }
+        if imported.profiles.is_empty() && imported.usb.is_none() {
+            anyhow::bail!("No input sources found to import");
+        }
+        Ok(imported)
+    }
+
+    fn add(&mut self, profile: &str, monitor: String, value: &str) -> anyhow::Result<()> {
+        let input_source = InputSource::raw_from_str(value)?;
+        self.profiles
+            .entry(profile.to_string())
+            .or_default()
+            .entries
+            .push(ProfileEntry {
+                monitor,
+                input: InputSource::str_from_raw(input_source),
+            });
+        Ok(())
+    }
+
+    /// Parse `ddcutil [--display N | --sn SERIAL | --model MODEL] setvcp 60 VALUE` lines.
+    fn parse_ddcutil(&mut self, text: &str, name: &str) -> anyhow::Result<()> {
+        for (line_number, line) in text.lines().enumerate() {
+            let words = split_words(line);
+            let Some(start) = words.iter().position(|word| word.ends_with("ddcutil")) else {
+                continue;
+            };
+            let words = &words[start + 1..];
+            let Some(setvcp) = words.iter().position(|word| word == "setvcp") else {
+                continue;
+            };
+            let line_number = line_number + 1;
+            let [code, value, ..] = &words[setvcp + 1..] else {
+                anyhow::bail!("Line {line_number}: `setvcp` needs the code and the value");
+            };
+            if crate::arg::parse_vcp_code(code) != Some(INPUT_SELECT) {
+                self.warnings.push(format!(
+                    "Line {line_number}: The VCP code {code} is skipped, only 60 is imported"
+                ));
+                continue;
+            }
+            let mut monitor = None;
+            let mut options = words[..setvcp].iter();
+            while let Some(option) = options.next() {
+                let (option, inline) = match option.split_once('=') {
+                    Some((option, inline)) => (option, Some(inline.to_string())),
+                    None => (option.as_str(), None),
+                };
+                let mut argument = || inline.clone().or_else(|| options.next().cloned());
+                match option {
+                    "-d" | "--display" | "--dis" => {
+                        let display: usize = argument().unwrap_or_default().parse().unwrap_or(0);
+                        if display == 0 {
+                            anyhow::bail!("Line {line_number}: Invalid `{option}`");
+                        }
+                        self.warnings.push(format!(
+                            "Line {line_number}: The display number {display} is converted \
+                            to the index {index}, which may be a different display monitor",
+                            index = display - 1
+                        ));
+                        monitor = Some((display - 1).to_string());
+                    }
+                    "-n" | "--sn" | "-l" | "--model" => monitor = argument(),
+                    "-b" | "--bus" => {
+                        monitor = None;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            let Some(monitor) = monitor else {
+                self.warnings.push(format!(
+                    "Line {line_number}: Skipped, because display monitors aren't specified \
+                    by `--display`, `--sn`, or `--model`"
+                ));
+                continue;
+            };
+            let value = parse_vcp_value(value)
+                .with_context(|| format!("Line {line_number}: Invalid value \"{value}\""))?;
+            self.add(name, monitor, &value.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Parse a `.cfg` file of ControlMyMonitor,
+    /// which has the monitor names in `[Monitor]` and the VCP values in `[VCP]`.
+    fn parse_control_my_monitor(&mut self, text: &str, name: &str) -> anyhow::Result<()> {
+        let mut monitor = None;
+        let mut input = None;
+        for (section, key, value) in parse_ini(text) {
+            match (section.as_str(), key.as_str()) {
+                ("Monitor", "MonitorName") if !value.is_empty() => monitor = Some(value),
+                ("Monitor", "MonitorDeviceName") if monitor.is_none() => monitor = Some(value),
+                ("VCP", code) if crate::arg::parse_vcp_code(code) == Some(INPUT_SELECT) => {
+                    // The current value, and the maximum value if any.
+                    let current = value.split(',').next().unwrap_or_default().trim();
+                    input = Some(
+                        parse_vcp_value(current)
+                            .with_context(|| format!("Invalid value \"{value}\" of 60"))?,
+                    );
+                }
+                _ => {}
+            }
+        }
+        let Some(monitor) = monitor else {
+            anyhow::bail!("No `MonitorName` in the `[Monitor]` section");
+        };
+        let Some(input) = input else {
+            anyhow::bail!("No VCP code 60 in the `[VCP]` section");
+        };
+        self.add(name, monitor, &input.to_string())
+    }
+
+    /// Parse a `display-switch.ini`, which has the same keys as the [`UsbConfig`].
+    /// Its `[monitorN]` sections are the [`UsbConfig::monitors`].
+    fn parse_display_switch(&mut self, text: &str) -> anyhow::Result<()> {
+        let mut usb = UsbConfig::default();
+        let mut has_input = false;
+        // Check and normalize the input source names.
+        let mut input = |key: &str, value: String| -> anyhow::Result<Option<String>> {
+            let input_source = InputSource::raw_from_str(&value)
+                .with_context(|| format!("Invalid `{key}` \"{value}\""))?;
+            has_input = true;
+            Ok(Some(InputSource::str_from_raw(input_source)))
+        };
+        for (section, key, value) in parse_ini(text) {
+            if key.ends_with("_execute") {
+                self.warnings
+                    .push(format!("`{key}` is skipped, use `on_switch` instead"));
+                continue;
+            }
+            if section.is_empty() {
+                match key.as_str() {
+                    "usb_device" => usb.usb_device = value,
+                    "on_usb_connect" => usb.on_usb_connect = input(&key, value)?,
+                    "on_usb_disconnect" => usb.on_usb_disconnect = input(&key, value)?,
+                    _ => self.warnings.push(format!("`{key}` is skipped")),
+                }
+                continue;
+            }
+            let monitor = usb.monitors.entry(section.clone()).or_default();
+            match key.as_str() {
+                "monitor_id" => monitor.monitor_id = value,
+                "on_usb_connect" => monitor.on_usb_connect = input(&key, value)?,
+                "on_usb_disconnect" => monitor.on_usb_disconnect = input(&key, value)?,
+                _ => self
+                    .warnings
+                    .push(format!("[{section}] `{key}` is skipped")),
+            }
+        }
+        if usb.usb_device.is_empty() {
+            anyhow::bail!("No `usb_device`");
+        }
+        usb.monitors.retain(|section, monitor| {
+            if monitor.monitor_id.is_empty() {
+                self.warnings
+                    .push(format!("[{section}] is skipped, it has no `monitor_id`"));
+                return false;
+            }
+            true
+        });
+        if has_input {
+            self.usb = Some(usb);
+        }
+        Ok(())
+    }
+
+    /// The `profiles` and the `usb` in TOML.
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        Ok(toml::to_string(&self.table())?)
+    }
+
+    fn table(&self) -> toml::Table {
+        let mut table = toml::Table::new();
+        if !self.profiles.is_empty() {
+            table.insert(
+                "profiles".to_string(),
+                toml::Value::try_from(&self.profiles).unwrap(),
+            );
+        }
+        if let Some(usb) = &self.usb {
+            table.insert("usb".to_string(), toml::Value::try_from(usb).unwrap());
+        }
+        table
+    }
+
+    /// Add the `profiles` and the `usb` to the configuration file at the `path`,
+    /// replacing the ones of the same names.
+    /// Other configurations in the file are kept, but comments are not.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut table: toml::Table = match std::fs::read_to_string(path) {
+            Ok(text) => text.parse()?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+            Err(e) => return Err(e.into()),
+        };
+        for (key, value) in self.table() {
+            let toml::Value::Table(imported) = value else {
+                continue;
+            };
+            match table.get_mut(&key) {
+                Some(toml::Value::Table(existing)) => existing.extend(imported),
+                _ => {
+                    table.insert(key, toml::Value::Table(imported));
+                }
+            }
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string(&table)?)?;
+        Ok(())
+    }
+}
+
+/// Convert the `profile` to the `format`.
+pub(crate) fn export_profile(format: ProfileFormat, profile: &Profile) -> anyhow::Result<String> {
+    let mut text = String::new();
+    match format {
+        ProfileFormat::Ddcutil => {
+            text.push_str("#!/bin/sh\n");
+            for entry in &profile.entries {
+                let monitor = match entry.monitor.parse::<usize>() {
+                    Ok(index) => format!("--display {}", index + 1),
+                    Err(_) => format!("--model {}", shell_quote(&entry.monitor)),
+                };
+                text.push_str(&format!(
+                    "ddcutil {monitor} setvcp 60 {value:#04x}\n",
+                    value = entry.input_source()?
+                ));
+            }
+        }
+        ProfileFormat::ControlMyMonitor => {
+            for entry in &profile.entries {
+                text.push_str(&format!(
+                    "ControlMyMonitor.exe /SetValue \"{monitor}\" 60 {value}\n",
+                    monitor = entry.monitor,
+                    value = entry.input_source()?
+                ));
+            }
+        }
+        ProfileFormat::DisplaySwitch => {
+            for (index, entry) in profile.entries.iter().enumerate() {
+                if index > 0 {
+                    text.push('\n');
+                }
+                text.push_str(&format!(
+                    "[monitor{number}]\nmonitor_id = {monitor}\non_usb_connect = \"{value:#04x}\"\n",
+                    number = index + 1,
+                    monitor = toml::Value::String(entry.monitor.clone()),
+                    value = entry.input_source()?
+                ));
+            }
+        }
+    }
+    Ok(text)
+}
+
+/// Split a shell command line into words, removing quotes.
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.get_or_insert_default().push(c),
+            (None, '#') if word.is_none() => break,
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_default();
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, ';' | '&' | '|') => words.extend(word.take()),
+            (None, c) => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+/// Parse an INI file into the section, the key, and the value without quotes.
+/// Keys before any sections are in the empty section.
+fn parse_ini(text: &str) -> Vec<(String, String, String)> {
+    let mut entries = vec![];
+    let mut section = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            section = name.trim().to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+        entries.push((section.clone(), key.trim().to_string(), value.to_string()));
+    }
+    entries
+}
+
+/// Parse a VCP value, which is decimal, or hexadecimal with `0x` or `x`.
+fn parse_vcp_value(value: &str) -> Option<u16> {
+    let value = value.trim();
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .or_else(|| value.strip_prefix('x'))
+    {
+        Some(digits) => u16::from_str_radix(digits, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(imported: &Imported, name: &str) -> Vec<String> {
+        imported.profiles[name].args()
+    }
+
+    #[test]
+    fn parse_ddcutil() {
+        let imported = Imported::parse(
+            ProfileFormat::Ddcutil,
+            r#"#!/bin/sh
+            # Switch to the laptop.
+            ddcutil --sn ABC123 setvcp 60 0x0f
+            sudo ddcutil --model="DELL U2723QE" setvcp 0x60 17 && echo done
+            ddcutil -d 2 setvcp 60 x1b
+            ddcutil -d 2 setvcp 10 50
+            ddcutil --bus 5 setvcp 60 0x11
+            "#,
+            "work",
+        )
+        .unwrap();
+        assert_eq!(
+            args(&imported, "work"),
+            ["ABC123=DP1", "DELL U2723QE=Hdmi1", "1=UsbC2"]
+        );
+        assert_eq!(imported.warnings.len(), 3);
+        assert!(Imported::parse(ProfileFormat::Ddcutil, "ls", "work").is_err());
+    }
+
+    #[test]
+    fn parse_control_my_monitor() {
+        let imported = Imported::parse(
+            ProfileFormat::ControlMyMonitor,
+            "[Monitor]\r\n\
+            MonitorDeviceName=\\\\.\\DISPLAY1\\Monitor0\r\n\
+            MonitorName=DELL U2723QE\r\n\
+            SerialNumber=ABC123\r\n\
+            [VCP]\r\n\
+            10=50,100\r\n\
+            60=17,0\r\n",
+            "work",
+        )
+        .unwrap();
+        assert_eq!(args(&imported, "work"), ["DELL U2723QE=Hdmi1"]);
+    }
+
+    #[test]
+    fn parse_display_switch() {
+        let imported = Imported::parse(
+            ProfileFormat::DisplaySwitch,
+            r#"
+            usb_device = "1050:0407"
+            on_usb_connect = "Hdmi1"
+            on_usb_disconnect = "DisplayPort2"
+            on_usb_connect_execute = "echo connected"
+
+            [monitor1]
+            monitor_id = "Dell"
+            on_usb_connect = "0x11"
+
+            [monitor2]
+            on_usb_disconnect = "Hdmi2"
+            "#,
+            "",
+        )
+        .unwrap();
+        assert!(imported.profiles.is_empty());
+        assert_eq!(imported.warnings.len(), 2);
+        assert_eq!(
+            imported.to_toml().unwrap(),
+            "[usb]\n\
+            interval_ms = 1000\n\
+            on_usb_connect = \"Hdmi1\"\n\
+            on_usb_disconnect = \"DP2\"\n\
+            usb_device = \"1050:0407\"\n\
+            \n\
+            [usb.monitors.monitor1]\n\
+            monitor_id = \"Dell\"\n\
+            on_usb_connect = \"Hdmi1\"\n"
+        );
+        assert!(
+            Imported::parse(
+                ProfileFormat::DisplaySwitch,
+                "on_usb_connect = \"Hdmi1\"",
+                ""
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn export_profile() {
+        let profile = Profile::parse(["DELL U2723QE=hdmi1", "1=dp1"]).unwrap();
+        assert_eq!(
+            super::export_profile(ProfileFormat::Ddcutil, &profile).unwrap(),
+            "#!/bin/sh\n\
+            ddcutil --model 'DELL U2723QE' setvcp 60 0x11\n\
+            ddcutil --display 2 setvcp 60 0x0f\n"
+        );
+        assert_eq!(
+            super::export_profile(ProfileFormat::ControlMyMonitor, &profile).unwrap(),
+            "ControlMyMonitor.exe /SetValue \"DELL U2723QE\" 60 17\n\
+            ControlMyMonitor.exe /SetValue \"1\" 60 15\n"
+        );
+    }
+
+    #[test]
+    fn from_path() {
+        let format = |path: &str| ProfileFormat::from_path(Path::new(path));
+        assert_eq!(format("switch.sh"), ProfileFormat::Ddcutil);
+        assert_eq!(format("DELL.cfg"), ProfileFormat::ControlMyMonitor);
+        assert_eq!(format("display-switch.ini"), ProfileFormat::DisplaySwitch);
+    }
+}
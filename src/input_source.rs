@@ -1,12 +1,13 @@
 use anyhow::Context;
 use std::str::FromStr;
-use strum_macros::{AsRefStr, EnumString, FromRepr};
+use strum::VariantArray;
+use strum_macros::{AsRefStr, EnumString, FromRepr, VariantArray};
 
 /// The raw representation of an input source value.
 /// See also [`InputSource`].
 pub type InputSourceRaw = u8;
 
-#[derive(Copy, Clone, Debug, PartialEq, AsRefStr, EnumString, FromRepr)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, AsRefStr, EnumString, FromRepr, VariantArray)]
 #[repr(u8)]
 #[strum(ascii_case_insensitive)]
 /// An input source value.
@@ -22,6 +23,23 @@ pub enum InputSource {
     UsbC2 = 0x1B,
 }
 
+/// Common names of input sources, in addition to the [`InputSource`] names.
+/// Each name is followed by a number, or the first one when it's omitted.
+/// The names should be normalized by [`InputSource::normalize_name()`].
+const ALIASES: &[(&str, &[InputSource])] = &[
+    (
+        "dp",
+        &[InputSource::DisplayPort1, InputSource::DisplayPort2],
+    ),
+    (
+        "displayport",
+        &[InputSource::DisplayPort1, InputSource::DisplayPort2],
+    ),
+    ("hdmi", &[InputSource::Hdmi1, InputSource::Hdmi2]),
+    ("usbc", &[InputSource::UsbC1, InputSource::UsbC2]),
+    ("typec", &[InputSource::UsbC1, InputSource::UsbC2]),
+];
+
 impl InputSource {
     /// Get [`InputSourceRaw`].
     /// ```
@@ -34,6 +52,8 @@ impl InputSource {
 
     /// Get [`InputSourceRaw`] from a string.
     /// The string is either the name of an [`InputSource`] or a number.
+    /// The number can be in hexadecimal with the `0x` prefix,
+    /// or in binary with the `0b` prefix.
     /// # Examples
     /// ```
     /// # use monitor_input::InputSource;
@@ -43,6 +63,8 @@ impl InputSource {
     ///     InputSource::Hdmi1.as_raw()
     /// );
     /// assert_eq!(InputSource::raw_from_str("27").unwrap(), 27);
+    /// assert_eq!(InputSource::raw_from_str("0x1B").unwrap(), 27);
+    /// assert_eq!(InputSource::raw_from_str("0b11011").unwrap(), 27);
     ///
     /// // Undefined string will be an error.
     /// assert!(InputSource::raw_from_str("xyz").is_err());
@@ -55,14 +77,96 @@ impl InputSource {
     /// );
     /// ```
     pub fn raw_from_str(input: &str) -> anyhow::Result<InputSourceRaw> {
-        if let Ok(value) = input.parse::<InputSourceRaw>() {
-            return Ok(value);
+        if let Some(value) = Self::parse_number(input) {
+            return value.with_context(|| format!("\"{input}\" is not a valid input source"));
         }
-        InputSource::from_str(input)
+        InputSource::from_alias(input)
             .map(|value| value.as_raw())
             .with_context(|| format!("\"{input}\" is not a valid input source"))
     }
 
+    /// Parse a number in decimal, hexadecimal (`0x`), or binary (`0b`).
+    /// Returns `None` if `input` doesn't look like a number.
+    fn parse_number(input: &str) -> Option<anyhow::Result<InputSourceRaw>> {
+        let (digits, radix) = match input.get(..2) {
+            Some("0x" | "0X") => (&input[2..], 16),
+            Some("0b" | "0B") => (&input[2..], 2),
+            _ if input.starts_with(|ch: char| ch.is_ascii_digit()) => (input, 10),
+            _ => return None,
+        };
+        Some(InputSourceRaw::from_str_radix(digits, radix).map_err(anyhow::Error::from))
+    }
+
+    /// Get [`InputSource`] from its name, a common name, or a unique prefix.
+    /// Cases, `-`, `_`, and spaces are ignored.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::InputSource;
+    /// assert_eq!(InputSource::from_alias("usb-c").unwrap(), InputSource::UsbC1);
+    /// assert_eq!(InputSource::from_alias("Type-C 2").unwrap(), InputSource::UsbC2);
+    /// assert_eq!(InputSource::from_alias("dp").unwrap(), InputSource::DisplayPort1);
+    /// assert_eq!(InputSource::from_alias("hdmi").unwrap(), InputSource::Hdmi1);
+    ///
+    /// // Ambiguous prefixes are errors with the candidates.
+    /// let error = InputSource::from_alias("usb").unwrap_err().to_string();
+    /// assert!(error.contains("UsbC1, UsbC2"));
+    /// ```
+    pub fn from_alias(input: &str) -> anyhow::Result<InputSource> {
+        if let Ok(value) = InputSource::from_str(input) {
+            return Ok(value);
+        }
+        let name = Self::normalize_name(input);
+        if name.is_empty() {
+            anyhow::bail!("The input source name is empty");
+        }
+        let names = Self::names();
+        if let Some((_, value)) = names.iter().find(|(n, _)| *n == name) {
+            return Ok(*value);
+        }
+
+        let mut candidates: Vec<InputSource> = vec![];
+        for (n, value) in &names {
+            if n.starts_with(&name) && !candidates.contains(value) {
+                candidates.push(*value);
+            }
+        }
+        match candidates.len() {
+            0 => anyhow::bail!("\"{input}\" is not a known input source name"),
+            1 => Ok(candidates[0]),
+            _ => anyhow::bail!(
+                "\"{input}\" is ambiguous; candidates are: {}",
+                candidates
+                    .iter()
+                    .map(|value| value.as_ref())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    fn normalize_name(name: &str) -> String {
+        name.chars()
+            .filter(|ch| !matches!(ch, '-' | '_' | ' '))
+            .map(|ch| ch.to_ascii_lowercase())
+            .collect()
+    }
+
+    /// All the normalized names and their values, including aliases.
+    fn names() -> Vec<(String, InputSource)> {
+        let mut names = vec![];
+        for value in InputSource::VARIANTS {
+            names.push((Self::normalize_name(value.as_ref()), *value));
+            names.push((Self::normalize_name(&format!("{value:?}")), *value));
+        }
+        for (alias, values) in ALIASES {
+            names.push((alias.to_string(), values[0]));
+            for (i, value) in values.iter().enumerate() {
+                names.push((format!("{alias}{}", i + 1), *value));
+            }
+        }
+        names
+    }
+
     /// Get a string from [`InputSourceRaw`].
     /// # Examples
     /// ```
@@ -72,9 +176,141 @@ impl InputSource {
     /// assert_eq!(InputSource::str_from_raw(255), "255");
     /// ```
     pub fn str_from_raw(value: InputSourceRaw) -> String {
+        InputValue::from(value).to_string()
+    }
+
+    /// Get a string from [`InputSourceRaw`],
+    /// with the hexadecimal value if it's not an [`InputSource`].
+    /// # Examples
+    /// ```
+    /// # use monitor_input::InputSource;
+    /// assert_eq!(InputSource::str_with_hex_from_raw(17), "Hdmi1");
+    /// assert_eq!(InputSource::str_with_hex_from_raw(255), "255 (0xFF)");
+    /// ```
+    pub fn str_with_hex_from_raw(value: InputSourceRaw) -> String {
+        format!("{:#}", InputValue::from(value))
+    }
+}
+
+/// An input source value, either an [`InputSource`] or a raw value.
+/// This is the type-safe form of [`InputSourceRaw`],
+/// and they can be converted to each other.
+/// # Examples
+/// ```
+/// # use monitor_input::{InputSource,InputValue};
+/// match InputValue::from(0x11) {
+///     InputValue::Known(InputSource::Hdmi1) => {}
+///     value => panic!("Unexpected {value}"),
+/// }
+/// assert_eq!(InputValue::from(0xFF), InputValue::Raw(0xFF));
+/// assert_eq!(u8::from(InputValue::Known(InputSource::UsbC2)), 0x1B);
+///
+/// // Display the name, or the number if it's not an `InputSource`.
+/// assert_eq!(InputValue::from(0x11).to_string(), "Hdmi1");
+/// assert_eq!(InputValue::from(0xFF).to_string(), "255");
+/// // The alternate form adds the hexadecimal value.
+/// assert_eq!(format!("{:#}", InputValue::from(0xFF)), "255 (0xFF)");
+///
+/// // Parse in the same way as `InputSource::raw_from_str()`.
+/// assert_eq!(
+///     "usb-c".parse::<InputValue>().unwrap(),
+///     InputValue::Known(InputSource::UsbC1)
+/// );
+/// assert_eq!("0xFF".parse::<InputValue>().unwrap(), InputValue::Raw(0xFF));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InputValue {
+    /// One of the [`InputSource`].
+    Known(InputSource),
+    /// A value not in [`InputSource`].
+    /// [`InputValue::from()`] never creates this for [`InputSource`] values.
+    Raw(InputSourceRaw),
+}
+
+impl InputValue {
+    /// Get [`InputSourceRaw`].
+    pub fn as_raw(self) -> InputSourceRaw {
+        match self {
+            InputValue::Known(input_source) => input_source.as_raw(),
+            InputValue::Raw(value) => value,
+        }
+    }
+
+    /// The [`InputSource`] if it's one of them.
+    pub fn input_source(self) -> Option<InputSource> {
+        match self {
+            InputValue::Known(input_source) => Some(input_source),
+            InputValue::Raw(_) => None,
+        }
+    }
+}
+
+impl From<InputSourceRaw> for InputValue {
+    fn from(value: InputSourceRaw) -> Self {
         match InputSource::from_repr(value) {
-            Some(input_source) => input_source.as_ref().to_string(),
-            None => value.to_string(),
+            Some(input_source) => InputValue::Known(input_source),
+            None => InputValue::Raw(value),
+        }
+    }
+}
+
+impl From<InputSource> for InputValue {
+    fn from(value: InputSource) -> Self {
+        InputValue::Known(value)
+    }
+}
+
+impl From<InputValue> for InputSourceRaw {
+    fn from(value: InputValue) -> Self {
+        value.as_raw()
+    }
+}
+
+/// The name of the [`InputSource`], or the number.
+/// The alternate form (`{:#}`) adds the hexadecimal value to the number.
+impl std::fmt::Display for InputValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputValue::Known(input_source) => f.write_str(input_source.as_ref()),
+            InputValue::Raw(value) if f.alternate() => write!(f, "{value} (0x{value:02X})"),
+            InputValue::Raw(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// Parsed by [`InputSource::raw_from_str()`].
+impl FromStr for InputValue {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        InputSource::raw_from_str(input).map(InputValue::from)
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Serialized as the name, such as `"DP1"`.
+impl serde::Serialize for InputSource {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_ref())
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Deserialized from a name as in [`InputSource::from_alias()`], such as `"DP1"` or `"usb-c"`.
+impl<'de> serde::Deserialize<'de> for InputSource {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        InputSource::from_alias(&name).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Serialized as the name, such as `"DP1"`, or the number.
+impl serde::Serialize for InputValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            InputValue::Known(input_source) => input_source.serialize(serializer),
+            InputValue::Raw(value) => serializer.serialize_u8(*value),
         }
     }
 }
@@ -95,4 +331,92 @@ mod tests {
         // Test failures.
         assert!(InputSource::from_str("xyz").is_err());
     }
+
+    #[test]
+    fn raw_from_str_number() {
+        assert_eq!(InputSource::raw_from_str("0").unwrap(), 0);
+        assert_eq!(InputSource::raw_from_str("255").unwrap(), 255);
+        assert_eq!(InputSource::raw_from_str("0x0f").unwrap(), 0x0F);
+        assert_eq!(InputSource::raw_from_str("0XFF").unwrap(), 0xFF);
+        assert_eq!(InputSource::raw_from_str("0b1").unwrap(), 1);
+        // Out of range or invalid digits.
+        assert!(InputSource::raw_from_str("256").is_err());
+        assert!(InputSource::raw_from_str("0x100").is_err());
+        assert!(InputSource::raw_from_str("0x").is_err());
+        assert!(InputSource::raw_from_str("0b2").is_err());
+        assert!(InputSource::raw_from_str("1a").is_err());
+    }
+
+    #[test]
+    fn input_source_from_alias() {
+        assert_eq!(InputSource::from_alias("usbc").unwrap(), InputSource::UsbC1);
+        assert_eq!(
+            InputSource::from_alias("USB_C").unwrap(),
+            InputSource::UsbC1
+        );
+        assert_eq!(
+            InputSource::from_alias("typec").unwrap(),
+            InputSource::UsbC1
+        );
+        assert_eq!(
+            InputSource::from_alias("usbc2").unwrap(),
+            InputSource::UsbC2
+        );
+        assert_eq!(
+            InputSource::from_alias("dp2").unwrap(),
+            InputSource::DisplayPort2
+        );
+        assert_eq!(
+            InputSource::from_alias("DisplayPort-2").unwrap(),
+            InputSource::DisplayPort2
+        );
+        assert_eq!(InputSource::from_alias("hdmi").unwrap(), InputSource::Hdmi1);
+        assert_eq!(
+            InputSource::from_alias("hdmi 2").unwrap(),
+            InputSource::Hdmi2
+        );
+        // Ambiguous prefixes.
+        assert!(InputSource::from_alias("disp").is_err());
+        assert!(InputSource::from_alias("ty").is_err());
+        assert!(InputSource::from_alias("h").is_err());
+        assert!(InputSource::from_alias("usb").is_err());
+        // Failures.
+        assert!(InputSource::from_alias("").is_err());
+        assert!(InputSource::from_alias("vga").is_err());
+    }
+
+    #[test]
+    fn input_value() {
+        for value in 0..=InputSourceRaw::MAX {
+            let input_value = InputValue::from(value);
+            assert_eq!(input_value.as_raw(), value);
+            assert_eq!(
+                input_value.input_source(),
+                InputSource::from_repr(value),
+                "{value}"
+            );
+        }
+        assert_eq!(
+            InputValue::from(InputSource::DisplayPort2),
+            InputValue::from(0x10)
+        );
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn serde() {
+        assert_eq!(
+            serde_json::to_string(&InputSource::DisplayPort1).unwrap(),
+            r#""DP1""#
+        );
+        assert_eq!(
+            serde_json::from_str::<InputSource>(r#""usb-c""#).unwrap(),
+            InputSource::UsbC1
+        );
+        assert!(serde_json::from_str::<InputSource>(r#""xyz""#).is_err());
+        assert_eq!(
+            serde_json::to_string(&[InputValue::from(0x11), InputValue::from(0xFF)]).unwrap(),
+            r#"["Hdmi1",255]"#
+        );
+    }
 }
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+/// The languages of user-facing messages.
+/// See [`tr()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Language {
+    #[default]
+    English,
+    Japanese,
+}
+
+/// The language set by [`Language::set_current()`], or `None` to detect.
+static CURRENT: RwLock<Option<Language>> = RwLock::new(None);
+
+impl Language {
+    /// The language of the language tag, such as `ja` or `ja_JP.UTF-8`.
+    /// Returns `None` if it's not supported.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag
+            .split(['-', '_', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match primary.as_str() {
+            "en" | "c" | "posix" => Some(Self::English),
+            "ja" => Some(Self::Japanese),
+            _ => None,
+        }
+    }
+
+    /// The language of messages,
+    /// set by [`Language::set_current()`] or detected from the system.
+    pub fn current() -> Self {
+        if let Some(language) = *CURRENT.read().unwrap() {
+            return language;
+        }
+        *CURRENT.write().unwrap().get_or_insert_with(Self::detect)
+    }
+
+    /// Set the language of messages, instead of detecting from the system.
+    pub fn set_current(language: Self) {
+        *CURRENT.write().unwrap() = Some(language);
+    }
+
+    /// Detect the language from the system settings.
+    fn detect() -> Self {
+        Self::system().unwrap_or_default()
+    }
+
+    #[cfg(windows)]
+    fn system() -> Option<Self> {
+        use windows_sys::Win32::Globalization::GetUserDefaultUILanguage;
+        // The primary language ID of Japanese is `LANG_JAPANESE`.
+        if unsafe { GetUserDefaultUILanguage() } & 0x3FF == 0x11 {
+            return Some(Self::Japanese);
+        }
+        None
+    }
+
+    #[cfg(not(windows))]
+    fn system() -> Option<Self> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Self::from_tag(&value))
+    }
+
+    /// The messages of this language, keyed by the English messages.
+    fn catalog(self) -> Option<&'static HashMap<&'static str, &'static str>> {
+        static JAPANESE: OnceLock<HashMap<&str, &str>> = OnceLock::new();
+        match self {
+            Self::English => None,
+            Self::Japanese => Some(JAPANESE.get_or_init(|| parse_catalog(JA_CATALOG))),
+        }
+    }
+}
+
+const JA_CATALOG: &str = include_str!("l10n/ja.tsv");
+
+/// Parse a catalog file, where each line is
+/// an English message and its translation separated by a tab.
+/// Lines starting with `#` are comments.
+fn parse_catalog(text: &'static str) -> HashMap<&'static str, &'static str> {
+    text.lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('\t'))
+        .collect()
+}
+
+/// Translate the English `message` to the [`Language::current()`].
+/// Returns the `message` as is if it's not in the catalog.
+/// # Examples
+/// ```
+/// # use monitor_input::tr;
+/// println!("{}: DP1", tr("Input Source"));
+/// ```
+pub fn tr(message: &'static str) -> &'static str {
+    tr_in(Language::current(), message)
+}
+
+/// Translate the English `message` as [`tr()`],
+/// and replace its `{name}` placeholders with the `args`.
+pub fn tr_args(message: &'static str, args: &[(&str, &dyn std::fmt::Display)]) -> String {
+    replace_args(tr(message), args)
+}
+
+fn tr_in(language: Language, message: &'static str) -> &'static str {
+    language
+        .catalog()
+        .and_then(|catalog| catalog.get(message).copied())
+        .unwrap_or(message)
+}
+
+fn replace_args(message: &str, args: &[(&str, &dyn std::fmt::Display)]) -> String {
+    let mut result = message.to_string();
+    for (name, value) in args {
+        result = result.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_tag() {
+        assert_eq!(Language::from_tag("ja"), Some(Language::Japanese));
+        assert_eq!(Language::from_tag("ja_JP.UTF-8"), Some(Language::Japanese));
+        assert_eq!(Language::from_tag("en-US"), Some(Language::English));
+        assert_eq!(Language::from_tag("C.UTF-8"), Some(Language::English));
+        assert_eq!(Language::from_tag("fr_FR"), None);
+    }
+
+    #[test]
+    fn tr_in() {
+        assert_eq!(super::tr_in(Language::English, "Model"), "Model");
+        assert_eq!(super::tr_in(Language::Japanese, "Model"), "モデル");
+        assert_eq!(super::tr_in(Language::Japanese, "Unknown"), "Unknown");
+        assert_eq!(
+            replace_args(
+                super::tr_in(Language::Japanese, "(expected {expected})"),
+                &[("expected", &"DP1")]
+            ),
+            "(期待値 DP1)"
+        );
+    }
+
+    #[test]
+    fn ja_catalog() {
+        let placeholders = |message: &str| {
+            let mut names: Vec<String> = message
+                .split('{')
+                .skip(1)
+                .filter_map(|s| s.split_once('}').map(|(name, _)| name.to_string()))
+                .collect();
+            names.sort();
+            names
+        };
+        let lines = JA_CATALOG
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .count();
+        let catalog = parse_catalog(JA_CATALOG);
+        assert_eq!(catalog.len(), lines, "Duplicated or broken lines");
+        for (english, japanese) in catalog {
+            assert_eq!(placeholders(english), placeholders(japanese), "{english}");
+        }
+    }
+}
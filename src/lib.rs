@@ -4,13 +4,244 @@
 //! * The [`Cli`] struct provides the command line tool.
 //! * The [`Monitor`] struct provides functions to
 //!   change input sources of display monitors.
+//! * The [`MockMonitor`] struct provides virtual display monitors for testing.
+//!
+//! The [`Cli`] struct and its dependencies are behind the `cli` feature,
+//! which is enabled by default.
+//! Libraries that need only the [`Monitor`] can disable the default features
+//! for a smaller dependency tree.
 //!
 //! [DDC/CI]: https://en.wikipedia.org/wiki/Display_Data_Channel
+#[cfg(feature = "cli")]
+mod app_identity;
+#[cfg(feature = "cli")]
+pub use app_identity::{
+    APP_USER_MODEL_ID, DEFAULT_APPLICATION_ID, URI_SCHEME, application_id, is_packaged,
+    is_start_on_login, set_start_on_login,
+};
+#[cfg(feature = "cli")]
+pub(crate) use app_identity::{register_app, unregister_app};
+
+mod arg;
+pub(crate) use arg::Arg;
+
+#[cfg(feature = "cli")]
+mod autostart;
+
+#[cfg(feature = "cli")]
+mod brightness;
+#[cfg(feature = "cli")]
+pub(crate) use brightness::BrightnessController;
+
+mod cancellation;
+pub use cancellation::*;
+
+mod capabilities;
+pub use capabilities::*;
+
+#[cfg(feature = "cli")]
 mod cli;
+#[cfg(feature = "cli")]
 pub use cli::*;
 
+#[cfg(feature = "cli")]
+mod config;
+#[cfg(feature = "cli")]
+pub use config::*;
+
+#[cfg(feature = "cli")]
+mod config_check;
+#[cfg(feature = "cli")]
+pub(crate) use config_check::{config_template, validate_config};
+
+mod ddc_device;
+pub use ddc_device::*;
+
+#[cfg(target_os = "linux")]
+mod ddcutil;
+#[cfg(target_os = "linux")]
+pub use ddcutil::*;
+
+#[cfg(feature = "cli")]
+mod diagnostics;
+#[cfg(feature = "cli")]
+pub(crate) use diagnostics::*;
+
+mod display_output;
+pub use display_output::*;
+
+#[cfg(feature = "cli")]
+mod dock;
+#[cfg(feature = "cli")]
+pub(crate) use dock::{DISPLAY_CHANGE_DELAY, DockEvent, PowerWatcher};
+
+mod enumeration;
+
+#[cfg(feature = "cli")]
+mod fault;
+#[cfg(feature = "cli")]
+pub use fault::SimulatedFailure;
+
+#[cfg(feature = "cli")]
+mod history;
+#[cfg(feature = "cli")]
+pub(crate) use history::*;
+
+#[cfg(feature = "cli")]
+mod hook;
+#[cfg(feature = "cli")]
+pub(crate) use hook::*;
+
+#[cfg(feature = "cli")]
+mod idle;
+#[cfg(feature = "cli")]
+pub(crate) use idle::*;
+
+#[cfg(feature = "cli")]
+mod import;
+#[cfg(feature = "cli")]
+pub use import::ProfileFormat;
+#[cfg(feature = "cli")]
+pub(crate) use import::{Imported, export_profile};
+
 mod input_source;
 pub use input_source::*;
 
+mod l10n;
+pub use l10n::*;
+
+mod log_file;
+pub use log_file::*;
+
+mod logging;
+
+mod mock;
+pub use mock::*;
+
 mod monitor;
 pub use monitor::*;
+
+mod monitor_cache;
+pub use monitor_cache::*;
+
+mod monitor_info;
+pub use monitor_info::*;
+
+mod monitor_set;
+pub use monitor_set::*;
+
+mod mst;
+pub(crate) use mst::*;
+
+#[cfg(feature = "cli")]
+mod night;
+#[cfg(feature = "cli")]
+pub(crate) use night::NightController;
+
+#[cfg(feature = "cli")]
+mod notification;
+#[cfg(feature = "cli")]
+pub use notification::ChangeSummary;
+
+#[cfg(feature = "cli")]
+mod output;
+#[cfg(feature = "cli")]
+pub use output::{ArgReport, OutputFormat, ReportFormat, RunReport, SetResult};
+
+#[cfg(feature = "cli")]
+mod platform;
+#[cfg(feature = "cli")]
+pub(crate) use platform::native;
+#[cfg(all(windows, feature = "cli"))]
+pub(crate) use platform::windows::registry;
+
+#[cfg(feature = "cli")]
+mod probe;
+#[cfg(feature = "cli")]
+pub(crate) use probe::{ProbeReport, parse_vcp_range};
+
+mod profile;
+pub use profile::*;
+
+mod quirks;
+pub(crate) use quirks::*;
+
+#[cfg(feature = "cli")]
+mod remote;
+#[cfg(feature = "cli")]
+pub(crate) use remote::*;
+
+#[cfg(feature = "cli")]
+mod rules;
+#[cfg(feature = "cli")]
+pub use rules::Rule;
+#[cfg(feature = "cli")]
+pub(crate) use rules::{CompiledRule, RuleEvent, RuleMonitor, compile_config_rules, compile_rules};
+
+#[cfg(feature = "cli")]
+mod safe_guard;
+#[cfg(feature = "cli")]
+pub(crate) use safe_guard::*;
+
+#[cfg(feature = "cli")]
+mod schedule;
+#[cfg(feature = "cli")]
+pub use schedule::Schedule;
+#[cfg(feature = "cli")]
+pub(crate) use schedule::{next_schedules, parse_day};
+
+mod selector;
+pub use selector::MatchOptions;
+pub(crate) use selector::*;
+
+#[cfg(feature = "cli")]
+mod selftest;
+#[cfg(feature = "cli")]
+pub(crate) use selftest::*;
+
+#[cfg(feature = "cli")]
+mod service;
+#[cfg(feature = "cli")]
+pub(crate) use service::{DaemonEvent, notify_ready, set_shutdown_handler};
+
+#[cfg(feature = "cli")]
+mod session;
+#[cfg(feature = "cli")]
+pub(crate) use session::{SessionEvent, SessionListener, SessionWatcher};
+
+mod shared_monitor;
+pub use shared_monitor::*;
+
+#[cfg(feature = "cli")]
+mod state;
+#[cfg(feature = "cli")]
+pub(crate) use state::*;
+
+#[cfg(feature = "cli")]
+mod stepped;
+#[cfg(feature = "cli")]
+pub(crate) use stepped::SteppedController;
+
+#[cfg(feature = "cli")]
+mod usb;
+#[cfg(feature = "cli")]
+pub(crate) use usb::UsbWatcher;
+
+mod vcp_write;
+pub use vcp_write::VcpWrite;
+pub(crate) use vcp_write::{COLOR_PRESET, FeatureValue, POWER_MODE, POWER_ON_DELAY, RGB_GAINS};
+
+mod vendor;
+pub use vendor::PbpMode;
+pub(crate) use vendor::manufacturer_id_from_edid;
+
+#[cfg(all(test, feature = "cli"))]
+mod testing;
+
+#[cfg(feature = "cli")]
+mod watch;
+#[cfg(feature = "cli")]
+pub(crate) use watch::*;
+
+#[cfg(feature = "cli")]
+mod websocket;
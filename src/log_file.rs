@@ -0,0 +1,119 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// A log file that rotates when its size exceeds a limit.
+///
+/// When rotating, the file is renamed to `<path>.1`,
+/// the existing `<path>.1` to `<path>.2`, and so on,
+/// and files beyond `max_files` are removed.
+/// # Examples
+/// ```no_run
+/// # use std::io::Write;
+/// # use monitor_input::RotatingFile;
+/// let mut file = RotatingFile::new("monitor-input.log", 1024 * 1024, 3)?;
+/// writeln!(file, "Hello")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Open the file at `path` to append.
+    /// The file is rotated when its size exceeds `max_bytes`,
+    /// keeping `max_files` rotated files.
+    pub fn new(path: impl AsRef<Path>, max_bytes: u64, max_files: usize) -> std::io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            let _ = std::fs::remove_file(self.rotated_path(self.max_files));
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    std::fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotate() {
+        let dir =
+            std::env::temp_dir().join(format!("monitor-input-log-test-{}", std::process::id()));
+        let path = dir.join("test.log");
+        let mut file = RotatingFile::new(&path, 10, 2).unwrap();
+        file.write_all(b"12345678\n").unwrap();
+        file.write_all(b"abc\n").unwrap();
+        file.write_all(b"def\n").unwrap();
+        file.write_all(b"ghijklmn\n").unwrap();
+        file.flush().unwrap();
+        let read = |path: &Path| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(&path), "ghijklmn\n");
+        assert_eq!(read(&file.rotated_path(1)), "abc\ndef\n");
+        assert_eq!(read(&file.rotated_path(2)), "12345678\n");
+
+        // Files beyond `max_files` are removed.
+        file.write_all(b"xyz\n").unwrap();
+        assert_eq!(read(&file.rotated_path(2)), "abc\ndef\n");
+        assert!(!file.rotated_path(3).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
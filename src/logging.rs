@@ -0,0 +1,13 @@
+//! The logging macros used in this crate.
+//! They're from [`tracing`] if the `tracing` feature is enabled,
+//! or from [`log`] otherwise.
+//!
+//! [`tracing`]: https://docs.rs/tracing
+
+// Not all of them are used when the `cli` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+#[allow(unused_imports)]
+pub(crate) use log::{debug, error, info, trace, warn};
+#[cfg(feature = "tracing")]
+#[allow(unused_imports)]
+pub(crate) use tracing::{debug, error, info, trace, warn};
@@ -1,37 +1,94 @@
-use std::env;
-use std::io::Write;
-
 use clap::Parser;
 
-use monitor_input::{Cli, Monitor};
+use monitor_input::{Cli, Config, LogFormat, RotatingFile};
 
 fn main() -> anyhow::Result<()> {
     let mut cli: Cli = Cli::parse();
-    init_logger(cli.verbose);
-    cli.monitors = Monitor::enumerate();
+    // Load the configurations before `run()`, for the log file configurations.
+    cli.config = Some(Config::load(cli.config_path.as_deref())?);
+    init_logger(cli.verbose, cli.quiet, cli.log_format, cli.open_log_file()?);
+    cli.monitors = cli.enumerate_monitors()?;
     cli.run()
 }
 
-fn init_logger(verbose: u8) {
+fn level_filter(verbose: u8, quiet: bool) -> log::LevelFilter {
+    match verbose {
+        _ if quiet => log::LevelFilter::Warn,
+        0 => log::LevelFilter::Info,
+        1 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn init_logger(verbose: u8, quiet: bool, log_format: LogFormat, log_file: Option<RotatingFile>) {
+    use tracing_subscriber::fmt::writer::BoxMakeWriter;
+
+    let level = match level_filter(verbose, quiet) {
+        log::LevelFilter::Warn => tracing::Level::WARN,
+        log::LevelFilter::Debug => tracing::Level::DEBUG,
+        log::LevelFilter::Trace => tracing::Level::TRACE,
+        _ => tracing::Level::INFO,
+    };
+    let is_file = log_file.is_some();
+    let writer = match log_file {
+        Some(file) => BoxMakeWriter::new(std::sync::Mutex::new(file)),
+        None => BoxMakeWriter::new(std::io::stderr),
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_ansi(!is_file)
+        .with_writer(writer);
+    match log_format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+fn init_logger(verbose: u8, quiet: bool, log_format: LogFormat, log_file: Option<RotatingFile>) {
+    use std::io::Write;
+
     // If `RUST_LOG` is set, initialize the `env_logger` in its default config.
-    if env::var("RUST_LOG").is_ok() {
+    if log_format == LogFormat::Text && log_file.is_none() && std::env::var("RUST_LOG").is_ok() {
         env_logger::init();
         return;
     }
 
     // Otherwise setup according to the `verbose` level, in a simpler format.
-    env_logger::Builder::new()
-        .filter_level(match verbose {
-            0 => log::LevelFilter::Info,
-            1 => log::LevelFilter::Debug,
-            _ => log::LevelFilter::Trace,
-        })
-        .format(|buf, record| match record.level() {
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(level_filter(verbose, quiet));
+    let is_file = log_file.is_some();
+    if let Some(file) = log_file {
+        builder.target(env_logger::Target::Pipe(Box::new(file)));
+    }
+    match log_format {
+        // Log files need timestamps to investigate later.
+        LogFormat::Text if is_file => builder.format(|buf, record| {
+            writeln!(
+                buf,
+                "{} {:5} {}",
+                buf.timestamp(),
+                record.level(),
+                record.args()
+            )
+        }),
+        LogFormat::Text => builder.format(|buf, record| match record.level() {
             log::Level::Info => writeln!(buf, "{}", record.args()),
             _ => {
                 let style = buf.default_level_style(record.level());
                 writeln!(buf, "{style}{}{style:#}: {}", record.level(), record.args())
             }
-        })
-        .init();
+        }),
+        LogFormat::Json => builder.format(|buf, record| {
+            let json = serde_json::json!({
+                "timestamp": buf.timestamp().to_string(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{json}")
+        }),
+    };
+    builder.init();
 }
@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+
+use super::*;
+use crate::monitor::INPUT_SELECT;
+
+#[derive(Debug, Default)]
+/// A virtual display monitor for testing without display monitors.
+/// # Examples
+/// ```
+/// # use monitor_input::{InputSource,MockMonitor,Monitor};
+/// let mock = MockMonitor::new("Mock")
+///     .with_input_source(InputSource::DisplayPort1.as_raw())
+///     .with_input_sources(&[InputSource::DisplayPort1.as_raw(), InputSource::Hdmi1.as_raw()]);
+/// let mut monitor = Monitor::from_device(mock);
+/// assert_eq!(monitor.input_source().unwrap(), InputSource::DisplayPort1.as_raw());
+/// monitor.set_input_source(InputSource::Hdmi1.as_raw()).unwrap();
+/// assert_eq!(monitor.input_source().unwrap(), InputSource::Hdmi1.as_raw());
+/// ```
+pub struct MockMonitor {
+    id: String,
+    model_name: Option<String>,
+    backend: Option<String>,
+    edid: Option<Vec<u8>>,
+    values: BTreeMap<VcpCode, u16>,
+    capabilities: Option<String>,
+    raw_replies: BTreeMap<Vec<u8>, Vec<u8>>,
+    is_internal: bool,
+    is_mst: bool,
+    is_stale: bool,
+}
+
+impl MockMonitor {
+    /// Create an instance with the `id`.
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the model name.
+    pub fn with_model_name(mut self, model_name: impl Into<String>) -> Self {
+        self.model_name = Some(model_name.into());
+        self
+    }
+
+    /// Make this an internal panel such as laptop screens.
+    pub fn with_internal(mut self) -> Self {
+        self.is_internal = true;
+        self
+    }
+
+    /// Set the backend name, which is `mock` by default.
+    pub fn with_backend(mut self, backend: impl Into<String>) -> Self {
+        self.backend = Some(backend.into());
+        self
+    }
+
+    /// Set the EDID.
+    pub fn with_edid(mut self, edid: impl Into<Vec<u8>>) -> Self {
+        self.edid = Some(edid.into());
+        self
+    }
+
+    /// Make this behind a DisplayPort MST hub. See [`DdcDevice::is_mst()`].
+    pub fn with_mst(mut self) -> Self {
+        self.is_mst = true;
+        self
+    }
+
+    /// Make the handle stale, so that operations fail until [`DdcDevice::reopen()`].
+    pub fn with_stale_handle(mut self) -> Self {
+        self.is_stale = true;
+        self
+    }
+
+    fn check_stale(&self) -> anyhow::Result<()> {
+        if self.is_stale {
+            anyhow::bail!("{}: The handle is invalid.", self.id);
+        }
+        Ok(())
+    }
+
+    /// Set the current input source.
+    pub fn with_input_source(mut self, value: InputSourceRaw) -> Self {
+        self.values.insert(INPUT_SELECT, value as u16);
+        self
+    }
+
+    /// Set the input sources in the capabilities.
+    pub fn with_input_sources(self, values: &[InputSourceRaw]) -> Self {
+        let values = values
+            .iter()
+            .map(|value| format!("{value:02X}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.with_capabilities(format!(
+            "(prot(monitor)type(lcd)mccs_ver(2.2)vcp(60({values})))"
+        ))
+    }
+
+    /// Set the `reply` to the raw DDC/CI command `data`.
+    /// See [`DdcDevice::ddc_raw()`].
+    pub fn with_raw_reply(mut self, data: &[u8], reply: &[u8]) -> Self {
+        self.raw_replies.insert(data.to_vec(), reply.to_vec());
+        self
+    }
+
+    /// Set the capabilities string,
+    /// such as `(prot(monitor)mccs_ver(2.2)vcp(60(0F 11)))`.
+    pub fn with_capabilities(mut self, capabilities: impl Into<String>) -> Self {
+        self.capabilities = Some(capabilities.into());
+        self
+    }
+}
+
+impl DdcDevice for MockMonitor {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.model_name.as_deref()
+    }
+
+    fn serial(&self) -> Option<String> {
+        None
+    }
+
+    fn backend_name(&self) -> String {
+        self.backend.as_deref().unwrap_or("mock").to_string()
+    }
+
+    fn edid(&self) -> Option<&[u8]> {
+        self.edid.as_deref()
+    }
+
+    fn is_internal(&self) -> bool {
+        self.is_internal
+    }
+
+    fn is_mst(&self) -> bool {
+        self.is_mst
+    }
+
+    fn capabilities_string(&mut self) -> anyhow::Result<String> {
+        self.check_stale()?;
+        match &self.capabilities {
+            Some(capabilities) => Ok(capabilities.clone()),
+            None => anyhow::bail!("{}: No capabilities", self.id),
+        }
+    }
+
+    fn get_vcp_feature(&mut self, code: VcpCode) -> anyhow::Result<VcpValue> {
+        self.check_stale()?;
+        let Some(value) = self.values.get(&code) else {
+            anyhow::bail!("{}: VCP feature {code:#04X} is not set", self.id);
+        };
+        Ok(VcpValue {
+            value: *value,
+            maximum: u16::MAX,
+        })
+    }
+
+    fn set_vcp_feature(&mut self, code: VcpCode, value: u16) -> anyhow::Result<()> {
+        self.check_stale()?;
+        self.values.insert(code, value);
+        Ok(())
+    }
+
+    fn sleep(&mut self) {}
+
+    fn ddc_raw(&mut self, data: &[u8], read_len: usize) -> anyhow::Result<Vec<u8>> {
+        self.check_stale()?;
+        if read_len == 0 {
+            return Ok(vec![]);
+        }
+        let Some(reply) = self.raw_replies.get(data) else {
+            anyhow::bail!("{}: No reply to {data:02X?}", self.id);
+        };
+        Ok(reply[..reply.len().min(read_len)].to_vec())
+    }
+
+    fn reopen(&mut self) -> anyhow::Result<()> {
+        self.is_stale = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_sources() {
+        let mut monitor = Monitor::from_device(MockMonitor::new("A").with_input_sources(&[
+            InputSource::DisplayPort1.as_raw(),
+            InputSource::Hdmi1.as_raw(),
+        ]));
+        assert_eq!(monitor.input_sources(), None);
+        monitor.update_capabilities().unwrap();
+        assert_eq!(monitor.input_sources(), Some(vec![0x0F, 0x11]));
+    }
+
+    #[test]
+    fn model_name_from_capabilities() {
+        let mut monitor = Monitor::from_device(
+            MockMonitor::new("A").with_capabilities("(prot(monitor)model(XYZ)vcp(60(0F)))"),
+        );
+        assert_eq!(monitor.model_name(), None);
+        monitor.update_capabilities().unwrap();
+        assert_eq!(monitor.model_name(), Some("XYZ"));
+        // No `mccs_ver`, no input sources.
+        assert_eq!(monitor.input_sources(), None);
+    }
+
+    #[test]
+    fn reopen_stale_handle() {
+        let mut monitor = Monitor::from_device(
+            MockMonitor::new("A")
+                .with_input_source(InputSource::DisplayPort1.as_raw())
+                .with_stale_handle(),
+        );
+        assert_eq!(
+            monitor.input_source().unwrap(),
+            InputSource::DisplayPort1.as_raw()
+        );
+        assert_eq!(monitor.is_responsive(), Some(true));
+    }
+
+    #[test]
+    fn input_source_not_set() {
+        let mut monitor = Monitor::from_device(MockMonitor::new("A"));
+        assert_eq!(monitor.is_responsive(), None);
+        assert!(monitor.input_source().is_err());
+        // Failures to read aren't kept until the device answers.
+        assert_eq!(monitor.is_responsive(), None);
+        assert!(monitor.update_capabilities().is_err());
+
+        let mut monitor = Monitor::from_device(
+            MockMonitor::new("A").with_input_source(InputSource::DisplayPort1.as_raw()),
+        );
+        monitor.input_source().unwrap();
+        assert_eq!(monitor.is_responsive(), Some(true));
+        assert!(monitor.vcp_feature(0x10).is_err());
+        assert_eq!(monitor.is_responsive(), Some(false));
+    }
+}
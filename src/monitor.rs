@@ -1,14 +1,36 @@
-use std::time::Instant;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
 
 use super::*;
-use ddc_hi::{Ddc, DdcHost, FeatureCode};
-use log::*;
+use crate::logging::*;
 
 /// VCP feature code for input select
-const INPUT_SELECT: FeatureCode = 0x60;
+pub(crate) const INPUT_SELECT: VcpCode = 0x60;
+
+/// VCP feature code for the luminance (brightness)
+pub(crate) const LUMINANCE: VcpCode = 0x10;
+
+/// VCP feature code for the contrast
+pub(crate) const CONTRAST: VcpCode = 0x12;
+
+/// VCP feature code for the audio speaker volume
+pub(crate) const AUDIO_VOLUME: VcpCode = 0x62;
 
 static mut DRY_RUN: bool = false;
 
+/// The maximum length of the payloads of DDC/CI messages.
+const MAX_DDC_RAW_LEN: usize = 32;
+
+/// The settings of a [`Monitor`] from the configurations. See [`Monitor::settings()`].
+#[cfg(feature = "cli")]
+#[derive(Clone, Debug)]
+pub(crate) struct MonitorSettings {
+    settle_delay: Duration,
+    input_remaps: Vec<(InputSourceRaw, u16)>,
+    retries: u32,
+    verify: bool,
+}
+
 /// Represents a display monitor.
 /// # Examples
 /// ```no_run
@@ -16,22 +38,59 @@ static mut DRY_RUN: bool = false;
 /// let mut monitors = Monitor::enumerate();
 /// monitors[0].set_input_source(InputSource::UsbC1.as_raw());
 /// ```
+/// See [`MockMonitor`] to create instances without display monitors.
+///
+/// # Threads
+/// [`Monitor`] isn't [`Send`],
+/// because handles of some backends can't be used from other threads,
+/// such as the `nvapi` backend on Windows.
+/// Use [`SharedMonitor`] to use display monitors from other threads.
 pub struct Monitor {
-    ddc_hi_display: ddc_hi::Display,
+    device: Box<dyn DdcDevice>,
+    capabilities: Option<Capabilities>,
+    capabilities_string: Option<String>,
+    mccs_database: mccs_db::Database,
     is_capabilities_updated: bool,
-    needs_sleep: bool,
+    /// The earliest time the device may be used again after the last change.
+    ready_at: Option<Instant>,
+    settle_delay: Duration,
+    /// The pairs of the standard input source and the value the device uses instead.
+    input_remaps: Vec<(InputSourceRaw, u16)>,
+    /// The capabilities string to use instead of the one from the device.
+    capabilities_override: Option<&'static str>,
+    input_source_names: BTreeMap<InputSourceRaw, String>,
+    last_input_source: Option<InputSourceRaw>,
+    /// The input source last read or set. See [`Monitor::cached_input_source()`].
+    input_source_cache: Option<InputSourceRaw>,
+    display_output: Option<DisplayOutput>,
+    /// The template to display the display monitor, set by [`Monitor::set_id_format()`].
+    id_format: Option<String>,
+    /// The number of times to retry failed requests.
+    retries: u32,
+    /// Whether to read the input source after changing it, set by [`Monitor::set_verify()`].
+    verify: bool,
+    is_responsive: Option<bool>,
+    /// Whether any request to the device succeeded. See [`Monitor::is_responsive()`].
+    has_answered: bool,
+    cancellation: CancellationToken,
 }
 
 impl std::fmt::Display for Monitor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.ddc_hi_display.info.id)
+        match self.formatted_id() {
+            Some(formatted) => write!(f, "{formatted}"),
+            None => write!(f, "{}", self.id()),
+        }
     }
 }
 
 impl std::fmt::Debug for Monitor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Monitor")
-            .field("info", &self.ddc_hi_display.info)
+            .field("id", &self.id())
+            .field("model_name", &self.model_name())
+            .field("serial", &self.serial())
+            .field("backend", &self.backend())
             .finish()
     }
 }
@@ -39,20 +98,139 @@ impl std::fmt::Debug for Monitor {
 impl Monitor {
     /// Create an instance from [`ddc_hi::Display`].
     pub fn new(ddc_hi_display: ddc_hi::Display) -> Self {
-        Monitor {
-            ddc_hi_display,
+        Self::from_device(ddc_hi_display)
+    }
+
+    /// Create an instance from a [`DdcDevice`].
+    /// Known deviations of the display monitor from the standard are applied,
+    /// such as different input source values.
+    pub fn from_device(device: impl DdcDevice + 'static) -> Self {
+        let mut monitor = Monitor {
+            device: Box::new(device),
+            capabilities: None,
+            capabilities_string: None,
+            mccs_database: mccs_db::Database::default(),
             is_capabilities_updated: false,
-            needs_sleep: false,
+            ready_at: None,
+            settle_delay: Duration::ZERO,
+            input_remaps: vec![],
+            capabilities_override: None,
+            input_source_names: BTreeMap::new(),
+            last_input_source: None,
+            input_source_cache: None,
+            display_output: None,
+            id_format: None,
+            retries: 0,
+            verify: false,
+            is_responsive: None,
+            has_answered: false,
+            cancellation: CancellationToken::default(),
+        };
+        monitor.apply_quirks();
+        if monitor.is_mst() || monitor.is_av_service() {
+            monitor.retries = MST_RETRIES;
         }
+        monitor
+    }
+
+    fn apply_quirks(&mut self) {
+        let quirks = Quirks::find(
+            self.manufacturer_id().as_deref(),
+            self.edid().and_then(product_code_from_edid),
+            self.device.model_name(),
+        );
+        for quirks in quirks {
+            debug!("Quirks({self}) = {quirks:?}");
+            self.input_remaps.extend_from_slice(quirks.input_remaps);
+            if let Some(settle_delay) = quirks.settle_delay {
+                self.settle_delay = settle_delay;
+            }
+            if quirks.capabilities.is_some() {
+                self.capabilities_override = quirks.capabilities;
+            }
+        }
+    }
+
+    /// Make the display monitor use the `device` value for the `standard` input source,
+    /// for display monitors that use nonstandard values.
+    /// [`Monitor::input_source()`] and [`Monitor::input_sources()`]
+    /// return the `standard` value for the `device` value.
+    /// The `device` value is the full 16 bits of the VCP feature,
+    /// for display monitors that use the high byte.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{InputSource,MockMonitor,Monitor};
+    /// let mut monitor = Monitor::from_device(MockMonitor::new("A").with_input_source(0x1B));
+    /// monitor.set_input_remap(InputSource::UsbC1.as_raw(), 0x1B);
+    /// assert_eq!(monitor.input_source().unwrap(), InputSource::UsbC1.as_raw());
+    /// ```
+    pub fn set_input_remap(&mut self, standard: InputSourceRaw, device: u16) {
+        self.input_remaps
+            .retain(|(s, d)| *s != standard && *d != device);
+        self.input_remaps.push((standard, device));
+    }
+
+    /// The 16-bit VCP value the device uses for the standard input source `value`.
+    fn input_source_to_device(&self, value: InputSourceRaw) -> u16 {
+        self.input_remaps
+            .iter()
+            .find(|(standard, _)| *standard == value)
+            .map_or(u16::from(value), |(_, device)| *device)
+    }
+
+    /// The standard input source value for the 16-bit VCP `value` the device uses.
+    /// The input select is in the low byte,
+    /// and the high byte is ignored unless it's remapped,
+    /// because some display monitors put other values in it.
+    fn input_source_from_device(&self, value: u16) -> InputSourceRaw {
+        let find = |value: u16| {
+            self.input_remaps
+                .iter()
+                .find(|(_, device)| *device == value)
+                .map(|(standard, _)| *standard)
+        };
+        find(value)
+            .or_else(|| find(value & 0xFF))
+            .unwrap_or(value as InputSourceRaw)
     }
 
     /// Enumerate all display monitors.
     /// See also [`ddc_hi::Display::enumerate()`].
     pub fn enumerate() -> Vec<Self> {
-        ddc_hi::Display::enumerate()
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("enumerate").entered();
+        let mut monitors: Vec<Self> = ddc_hi::Display::enumerate()
             .into_iter()
             .map(Monitor::new)
-            .collect()
+            .collect();
+        remove_duplicates(&mut monitors);
+        assign_display_outputs(&mut monitors);
+        monitors
+    }
+
+    /// Enumerate all display monitors as [`Monitor::enumerate()`],
+    /// and set the `token` to them by [`Monitor::set_cancellation_token()`].
+    ///
+    /// The enumeration itself can't be interrupted.
+    /// If the `token` is cancelled before or during the enumeration,
+    /// this returns the [`Cancelled`] error.
+    pub fn enumerate_with_cancellation(token: &CancellationToken) -> anyhow::Result<Vec<Self>> {
+        MonitorSet::enumerate_with_cancellation(token).map(MonitorSet::into_vec)
+    }
+
+    /// Enumerate all display monitors, and compare them with the `previous` enumeration.
+    /// This is to keep the `previous` instances of unchanged display monitors,
+    /// and to detect display monitors connected or disconnected since then.
+    /// See [`MonitorDiff`].
+    pub fn enumerate_diff(previous: &[Monitor]) -> MonitorDiff {
+        MonitorDiff::new(previous, Self::enumerate())
+    }
+
+    /// Whether `self` and `other` are the same display monitor,
+    /// possibly in different enumerations.
+    /// The EDIDs are compared if both are available, otherwise the IDs.
+    pub fn is_same_display(&self, other: &Monitor) -> bool {
+        is_same_device(self.device.as_ref(), other.device.as_ref())
     }
 
     fn is_dry_run() -> bool {
@@ -66,19 +244,24 @@ impl Monitor {
         unsafe { DRY_RUN = value }
     }
 
-    /// Updates the display info with data retrieved from the device's
-    /// reported capabilities.
-    /// See also [`ddc_hi::Display::update_capabilities()`].
+    /// Read and parse the capabilities of the display monitor.
+    /// See also [`DdcDevice::capabilities_string()`].
     pub fn update_capabilities(&mut self) -> anyhow::Result<()> {
         if self.is_capabilities_updated {
             return Ok(());
         }
         self.is_capabilities_updated = true;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("update_capabilities", monitor = self.id()).entered();
         debug!("update_capabilities({self})");
         let start_time = Instant::now();
+        let capabilities_override = self.capabilities_override;
         let result = self
-            .ddc_hi_display
-            .update_capabilities()
+            .with_device(|device| match capabilities_override {
+                Some(capabilities) => Ok(capabilities.to_string()),
+                None => device.capabilities_string(),
+            })
+            .and_then(|capabilities| self.apply_capabilities_string(capabilities))
             .inspect_err(|e| warn!("{self}: Failed to update capabilities: {e}"));
         debug!(
             "update_capabilities({self}) elapsed: {:?}",
@@ -87,24 +270,382 @@ impl Monitor {
         result
     }
 
+    /// Set the capabilities from the `capabilities` string,
+    /// such as the [`Monitor::capabilities_string()`] saved from a previous run,
+    /// instead of reading it from the display monitor.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{MockMonitor,Monitor};
+    /// let mut monitor = Monitor::from_device(MockMonitor::new("A"));
+    /// monitor.set_capabilities_string("(prot(monitor)mccs_ver(2.2)vcp(60(0F 11)))").unwrap();
+    /// assert_eq!(monitor.input_sources(), Some(vec![0x0F, 0x11]));
+    /// ```
+    pub fn set_capabilities_string(
+        &mut self,
+        capabilities: impl Into<String>,
+    ) -> anyhow::Result<()> {
+        self.is_capabilities_updated = true;
+        self.apply_capabilities_string(capabilities.into())
+    }
+
+    fn apply_capabilities_string(&mut self, capabilities: String) -> anyhow::Result<()> {
+        let parsed = parse_mccs_capabilities(capabilities.as_bytes())?;
+        self.apply_capabilities(parsed);
+        self.capabilities_string = Some(capabilities);
+        Ok(())
+    }
+
+    /// The capabilities string of the display monitor, if available.
+    /// Requires to call [`Monitor::update_capabilities()`] beforehand.
+    pub fn capabilities_string(&self) -> Option<&str> {
+        self.capabilities_string.as_deref()
+    }
+
+    fn apply_capabilities(&mut self, capabilities: mccs::Capabilities) {
+        if let Some(version) = &capabilities.mccs_version {
+            self.mccs_database = mccs_db::Database::from_version(version);
+            self.mccs_database.apply_capabilities(&capabilities);
+        }
+        self.capabilities = Some(Capabilities::from(&capabilities));
+    }
+
+    /// The capabilities of the display monitor, if available.
+    /// Requires to call [`Monitor::update_capabilities()`] beforehand.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{MockMonitor,Monitor};
+    /// let mut monitor = Monitor::from_device(
+    ///     MockMonitor::new("A").with_capabilities("(prot(monitor)vcp(10 60(0F 11)))"),
+    /// );
+    /// monitor.update_capabilities().unwrap();
+    /// let capabilities = monitor.capabilities().unwrap();
+    /// assert!(capabilities.vcp_features.contains_key(&0x10));
+    /// ```
+    pub fn capabilities(&self) -> Option<&Capabilities> {
+        self.capabilities.as_ref()
+    }
+
+    /// Whether the VCP feature `code` is in the [`Monitor::capabilities()`].
+    /// Returns `None` if the capabilities aren't available.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{MockMonitor,Monitor};
+    /// let mut monitor = Monitor::from_device(
+    ///     MockMonitor::new("A").with_capabilities("(prot(monitor)vcp(10 60(0F 11)))"),
+    /// );
+    /// assert_eq!(monitor.supports_feature(0x62), None);
+    /// monitor.update_capabilities().unwrap();
+    /// assert_eq!(monitor.supports_feature(0x10), Some(true));
+    /// assert_eq!(monitor.supports_feature(0x62), Some(false));
+    /// ```
+    pub fn supports_feature(&self, code: VcpCode) -> Option<bool> {
+        self.capabilities
+            .as_ref()
+            .map(|capabilities| capabilities.vcp_features.contains_key(&code))
+    }
+
+    /// The allowed values of the VCP feature `code` in the [`Monitor::capabilities()`].
+    /// Returns `None` if the capabilities aren't available,
+    /// the feature isn't supported, or the feature is continuous.
+    pub fn supported_values(&self, code: VcpCode) -> Option<&[u8]> {
+        self.capabilities
+            .as_ref()?
+            .vcp_features
+            .get(&code)
+            .map(|feature| feature.values.as_slice())
+            .filter(|values| !values.is_empty())
+    }
+
+    /// The identifier of the display monitor.
+    pub fn id(&self) -> &str {
+        self.device.id()
+    }
+
+    /// The model name of the display monitor, if available.
+    pub fn model_name(&self) -> Option<&str> {
+        self.device.model_name().or_else(|| {
+            self.capabilities
+                .as_ref()
+                .and_then(|capabilities| capabilities.model.as_deref())
+        })
+    }
+
+    /// The serial number of the display monitor, if available.
+    pub fn serial(&self) -> Option<String> {
+        self.device.serial()
+    }
+
+    /// The name of the backend that found the display monitor.
+    pub fn backend(&self) -> String {
+        self.device.backend_name()
+    }
+
+    /// Whether this is an internal panel such as laptop screens.
+    /// See [`DdcDevice::is_internal()`].
+    pub fn is_internal(&self) -> bool {
+        self.device.is_internal()
+    }
+
+    /// The display output of the OS that shows the display monitor, if known.
+    /// This is available for the `winapi` backend on Windows,
+    /// and on Linux by matching the EDIDs with `xrandr` or the DRM connectors.
+    pub fn display_output(&self) -> Option<&DisplayOutput> {
+        self.display_output.as_ref()
+    }
+
+    /// The EDID of the display monitor, if available.
+    pub fn edid(&self) -> Option<&[u8]> {
+        self.device.edid()
+    }
+
+    /// Read the current input source, and take a snapshot of the information.
+    /// The [`MonitorInfo`] doesn't hold the handle of the display monitor,
+    /// so it can be cached, or sent to other threads such as of user interfaces.
+    /// [`MonitorInfo::inputs`] is available
+    /// if [`Monitor::update_capabilities()`] was called beforehand.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{InputSource,MockMonitor,Monitor};
+    /// let mut monitor = Monitor::from_device(
+    ///     MockMonitor::new("A").with_input_source(InputSource::Hdmi1.as_raw()),
+    /// );
+    /// let info = monitor.info();
+    /// std::thread::spawn(move || {
+    ///     assert_eq!(info.id, "A");
+    ///     assert_eq!(info.current_input, Some(InputSource::Hdmi1.as_raw()));
+    /// })
+    /// .join()
+    /// .unwrap();
+    /// ```
+    pub fn info(&mut self) -> MonitorInfo {
+        MonitorInfo {
+            current_input: self.input_source().ok(),
+            inputs: self.input_sources(),
+            id: self.id().to_string(),
+            model: self.model_name().map(str::to_string),
+            serial: self.serial(),
+            backend: self.backend(),
+        }
+    }
+
+    /// The three-letter manufacturer ID in the EDID, such as `DEL` for Dell, if available.
+    pub fn manufacturer_id(&self) -> Option<String> {
+        self.edid().and_then(manufacturer_id_from_edid)
+    }
+
+    /// Set the [`Monitor::display_output()`].
+    pub fn set_display_output(&mut self, display_output: Option<DisplayOutput>) {
+        self.display_output = display_output;
+    }
+
     pub(crate) fn contains_backend(&self, backend: &str) -> bool {
-        self.ddc_hi_display
-            .info
-            .backend
-            .to_string()
-            .contains(backend)
+        self.device.backend_name().contains(backend)
+    }
+
+    /// Expand the `format` template with the information of the display monitor.
+    /// The placeholders are `{id}`, `{model}`, `{serial}`, `{manufacturer}`,
+    /// `{backend}`, and `{output}`.
+    /// Unavailable information is replaced with empty strings.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{MockMonitor,Monitor};
+    /// let monitor = Monitor::from_device(MockMonitor::new("A"));
+    /// assert_eq!(monitor.format_id("{backend}: {id}"), "mock: A");
+    /// ```
+    pub fn format_id(&self, format: &str) -> String {
+        let mut result = String::new();
+        let mut rest = format;
+        while let Some(start) = rest.find('{') {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+            match &rest[1..end] {
+                "id" => result.push_str(self.id()),
+                "model" => result.push_str(self.model_name().unwrap_or_default()),
+                "serial" => result.push_str(&self.serial().unwrap_or_default()),
+                "manufacturer" => result.push_str(&self.manufacturer_id().unwrap_or_default()),
+                "backend" => result.push_str(&self.backend()),
+                "output" => {
+                    if let Some(output) = self.display_output() {
+                        result.push_str(&output.name);
+                    }
+                }
+                _ => result.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        result.push_str(rest);
+        result.trim().to_string()
+    }
+
+    /// Display the display monitor by the `format` template of [`Monitor::format_id()`],
+    /// instead of the [`Monitor::id()`],
+    /// in logs, listings, and notifications.
+    /// The formatted string can also be used to match the display monitor by name.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{MockMonitor,Monitor};
+    /// let mut monitor = Monitor::from_device(MockMonitor::new("A"));
+    /// monitor.set_id_format(Some("[{id}]"));
+    /// assert_eq!(monitor.to_string(), "[A]");
+    /// ```
+    pub fn set_id_format(&mut self, format: Option<&str>) {
+        self.id_format = format.map(str::to_string);
+    }
+
+    /// The string of [`Monitor::set_id_format()`], if set.
+    pub(crate) fn formatted_id(&self) -> Option<String> {
+        self.id_format.as_ref().map(|format| self.format_id(format))
+    }
+
+    /// Run `f` with the device.
+    /// If it fails because the handle is no longer valid,
+    /// such as after the computer resumes from sleep,
+    /// reopen the device and retry once.
+    /// The result is kept for [`Monitor::is_responsive()`].
+    /// It waits for the [`Monitor::ready_at()`] first.
+    fn with_device<T>(
+        &mut self,
+        f: impl Fn(&mut dyn DdcDevice) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        self.sleep_if_needed();
+        self.cancellation.check()?;
+        let mut result = match f(self.device.as_mut()) {
+            Err(error) if is_stale_handle_error(&error) => {
+                info!("{self}: Reopening because of the error: {error}");
+                if let Err(reopen_error) = self.device.reopen() {
+                    warn!("{reopen_error}");
+                    Err(error)
+                } else {
+                    f(self.device.as_mut())
+                }
+            }
+            result => result,
+        };
+        for retry in 1..=self.retries {
+            let Err(error) = &result else {
+                break;
+            };
+            info!(
+                "{self}: Retrying ({retry}/{}) because of the error: {error}",
+                self.retries
+            );
+            self.cancellation.sleep(RETRY_DELAY)?;
+            result = f(self.device.as_mut());
+        }
+        if result.is_ok() {
+            self.has_answered = true;
+            self.is_responsive = Some(true);
+        } else if self.has_answered {
+            self.is_responsive = Some(false);
+        }
+        result
+    }
+
+    /// Same as [`Monitor::with_device()`],
+    /// but failures are kept for [`Monitor::is_responsive()`]
+    /// even if the device has never answered.
+    fn with_device_to_write<T>(
+        &mut self,
+        f: impl Fn(&mut dyn DdcDevice) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        let result = self.with_device(f);
+        if result.is_err() {
+            self.is_responsive = Some(false);
+        }
+        result
     }
 
-    pub(crate) fn contains(&self, name: &str) -> bool {
-        self.ddc_hi_display.info.id.contains(name)
+    /// Whether this display monitor is behind a DisplayPort MST hub,
+    /// such as daisy-chained display monitors.
+    /// Failed requests to them are retried by default,
+    /// because their DDC/CI fails more often than others.
+    pub fn is_mst(&self) -> bool {
+        self.device.is_mst()
     }
 
-    fn feature_descriptor(&self, feature_code: FeatureCode) -> Option<&mccs_db::Descriptor> {
-        self.ddc_hi_display.info.mccs_database.get(feature_code)
+    /// Whether the DDC/CI of this display monitor goes through the `IOAVService`
+    /// of Apple Silicon Macs, by the `macos` backend.
+    /// Failed requests to them are retried by default, as for [`Monitor::is_mst()`],
+    /// because the `IOAVService` fails intermittently.
+    pub fn is_av_service(&self) -> bool {
+        cfg!(all(target_os = "macos", target_arch = "aarch64")) && self.backend() == "macos"
     }
 
-    fn feature_code(&self, feature_code: FeatureCode) -> FeatureCode {
-        // TODO: `mccs_database` is initialized by `display.update_capabilities()`
+    /// Set the number of times to retry failed requests.
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    /// Set whether [`Monitor::set_input_source()`] reads the input source after changing it,
+    /// and fails if the display monitor didn't switch.
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    /// The settings from the configurations, to restore them by [`Monitor::restore_settings()`].
+    #[cfg(feature = "cli")]
+    pub(crate) fn settings(&self) -> MonitorSettings {
+        MonitorSettings {
+            settle_delay: self.settle_delay,
+            input_remaps: self.input_remaps.clone(),
+            retries: self.retries,
+            verify: self.verify,
+        }
+    }
+
+    /// Restore the settings saved by [`Monitor::settings()`].
+    /// The [`Monitor::cached_input_source()`] is cleared,
+    /// because it may be read with other input source remaps.
+    #[cfg(feature = "cli")]
+    pub(crate) fn restore_settings(&mut self, settings: MonitorSettings) {
+        self.input_source_cache = None;
+        self.settle_delay = settings.settle_delay;
+        self.input_remaps = settings.input_remaps;
+        self.retries = settings.retries;
+        self.verify = settings.verify;
+    }
+
+    /// Set the [`CancellationToken`] to cancel requests to this display monitor,
+    /// including reading capabilities, retries, and waiting for [`Monitor::ready_at()`].
+    /// Requests after it's cancelled fail with the [`Cancelled`] error.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation = token;
+    }
+
+    /// Whether the last request to the device succeeded,
+    /// or `None` if no requests were made.
+    /// Failures to read are ignored until the device answers any requests,
+    /// because some display monitors can't read some VCP features,
+    /// such as the input source.
+    pub fn is_responsive(&self) -> Option<bool> {
+        self.is_responsive
+    }
+
+    fn feature_descriptor(&self, feature_code: VcpCode) -> Option<&mccs_db::Descriptor> {
+        self.mccs_database.get(feature_code)
+    }
+
+    /// The name of the VCP feature `code`,
+    /// from the capabilities or the MCCS database of the MCCS version of the display monitor.
+    /// Requires to call [`Monitor::update_capabilities()`] beforehand.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{MockMonitor,Monitor};
+    /// let mut monitor = Monitor::from_device(
+    ///     MockMonitor::new("A").with_capabilities("(vcp(10 60(0F 11))mccs_ver(2.1))"),
+    /// );
+    /// monitor.update_capabilities().unwrap();
+    /// assert_eq!(monitor.feature_name(0x60), Some("Input Select"));
+    /// ```
+    pub fn feature_name(&self, code: VcpCode) -> Option<&str> {
+        self.feature_descriptor(code)?.name.as_deref()
+    }
+
+    fn feature_code(&self, feature_code: VcpCode) -> VcpCode {
+        // TODO: `mccs_database` is initialized by `update_capabilities()`
         // which is quite slow, and it seems to work without this.
         // See also https://github.com/mjkoo/monitor-switch/blob/master/src/main.rs.
         if let Some(feature) = self.feature_descriptor(feature_code) {
@@ -116,15 +657,103 @@ impl Monitor {
     /// Get the current input source.
     /// # Examples
     /// ```no_run
-    /// # use monitor_input::{InputSource,Monitor};
+    /// # use monitor_input::{InputValue,Monitor};
     /// # fn print(monitor: &mut Monitor) -> anyhow::Result<()> {
-    /// print!("{}", InputSource::str_from_raw(monitor.input_source()?));
+    /// print!("{}", InputValue::from(monitor.input_source()?));
     /// #   Ok(())
     /// # }
     /// ```
     pub fn input_source(&mut self) -> anyhow::Result<InputSourceRaw> {
-        let feature_code: FeatureCode = self.feature_code(INPUT_SELECT);
-        Ok(self.ddc_hi_display.handle.get_vcp_feature(feature_code)?.sl)
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("input_source", monitor = self.id()).entered();
+        let feature_code = self.feature_code(INPUT_SELECT);
+        let value = self
+            .with_device(|device| device.get_vcp_feature(feature_code))?
+            .value;
+        if value > 0xFF {
+            trace!("InputSource({self}) = {value:#06X}");
+        }
+        let value = self.input_source_from_device(value);
+        self.last_input_source = Some(value);
+        self.input_source_cache = Some(value);
+        Ok(value)
+    }
+
+    /// Get the current input source as [`InputValue`].
+    /// This is the same as [`Monitor::input_source()`],
+    /// but the value can be matched against [`InputSource`].
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{InputSource,InputValue,MockMonitor,Monitor};
+    /// let mut monitor = Monitor::from_device(MockMonitor::new("A").with_input_source(0x11));
+    /// assert_eq!(
+    ///     monitor.input_value().unwrap(),
+    ///     InputValue::Known(InputSource::Hdmi1)
+    /// );
+    /// ```
+    pub fn input_value(&mut self) -> anyhow::Result<InputValue> {
+        self.input_source().map(InputValue::from)
+    }
+
+    /// Get the current input source as [`Monitor::input_source()`],
+    /// but reuse the value last read or set if it's not invalidated.
+    /// This saves the round-trip to the display monitor
+    /// when the input source is needed multiple times in an invocation.
+    ///
+    /// The cached value is invalidated
+    /// when it's unknown after failures or other requests,
+    /// and by [`Monitor::invalidate_input_source_cache()`],
+    /// such as when the input source may be changed outside of this program.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{InputSource,MockMonitor,Monitor};
+    /// let mut monitor = Monitor::from_device(MockMonitor::new("A"));
+    /// let dp1 = InputSource::DisplayPort1.as_raw();
+    /// monitor.set_input_source(dp1).unwrap();
+    /// assert_eq!(monitor.cached_input_source().unwrap(), dp1);
+    /// // Reading again doesn't communicate with the display monitor.
+    /// assert_eq!(monitor.cached_input_source().unwrap(), dp1);
+    /// ```
+    pub fn cached_input_source(&mut self) -> anyhow::Result<InputSourceRaw> {
+        match self.input_source_cache {
+            Some(value) => Ok(value),
+            None => self.input_source(),
+        }
+    }
+
+    /// Invalidate the value [`Monitor::cached_input_source()`] reuses.
+    pub fn invalidate_input_source_cache(&mut self) {
+        self.input_source_cache = None;
+    }
+
+    /// The input source last read by [`Monitor::input_source()`]
+    /// or set by [`Monitor::set_input_source()`].
+    /// This doesn't communicate with the display monitor.
+    pub fn last_input_source(&self) -> Option<InputSourceRaw> {
+        self.last_input_source
+    }
+
+    /// Get the current input source,
+    /// and return it if it's different from [`Monitor::last_input_source()`].
+    /// This is to detect changes made outside of this program,
+    /// such as by the on-screen display of the display monitor.
+    /// The first call only reads the current input source and returns `None`.
+    /// # Examples
+    /// ```no_run
+    /// # use monitor_input::{InputValue,Monitor};
+    /// # fn watch(monitor: &mut Monitor) -> anyhow::Result<()> {
+    /// loop {
+    ///     if let Some(value) = monitor.poll_input_source()? {
+    ///         println!("Changed to {}", InputValue::from(value));
+    ///     }
+    ///     std::thread::sleep(std::time::Duration::from_secs(5));
+    /// }
+    /// # }
+    /// ```
+    pub fn poll_input_source(&mut self) -> anyhow::Result<Option<InputSourceRaw>> {
+        let last = self.last_input_source;
+        let value = self.input_source()?;
+        Ok((last.is_some() && last != Some(value)).then_some(value))
     }
 
     /// Set the current input source.
@@ -142,19 +771,293 @@ impl Monitor {
     /// }
     /// ```
     pub fn set_input_source(&mut self, value: InputSourceRaw) -> anyhow::Result<()> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("set_input_source", monitor = self.id(), value).entered();
+        let mode = if Self::is_dry_run() {
+            format!(" {}", tr("(dry-run)"))
+        } else {
+            String::new()
+        };
+        info!(
+            "{}",
+            tr_args(
+                "InputSource({monitor}) = {value}{mode}",
+                &[
+                    ("monitor", self),
+                    ("value", &InputValue::from(value)),
+                    ("mode", &mode)
+                ]
+            )
+        );
+        if Self::is_dry_run() {
+            // Following requests in the invocation see the value as if it were set.
+            self.input_source_cache = Some(value);
+            return Ok(());
+        }
+        let feature_code = self.feature_code(INPUT_SELECT);
+        let device_value = self.input_source_to_device(value);
+        self.input_source_cache = None;
+        self.with_device_to_write(|device| device.set_vcp_feature(feature_code, device_value))
+            .inspect(|_| {
+                self.ready_at = Some(Instant::now() + self.settle_delay);
+                self.last_input_source = Some(value);
+                self.input_source_cache = Some(value);
+            })?;
+        if self.verify {
+            self.verify_input_source(value)?;
+        }
+        Ok(())
+    }
+
+    /// Set the current input source as [`Monitor::set_input_source()`].
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{InputSource,MockMonitor,Monitor};
+    /// let mut monitor = Monitor::from_device(MockMonitor::new("A"));
+    /// monitor.set_input_value(InputSource::UsbC1.into()).unwrap();
+    /// monitor.set_input_value("0xFF".parse().unwrap()).unwrap();
+    /// assert_eq!(monitor.input_source().unwrap(), 0xFF);
+    /// ```
+    pub fn set_input_value(&mut self, value: InputValue) -> anyhow::Result<()> {
+        self.set_input_source(value.as_raw())
+    }
+
+    /// Check if the input source is the `expected` after changing it.
+    /// Failures to read are only logged,
+    /// because some display monitors don't respond while switching.
+    fn verify_input_source(&mut self, expected: InputSourceRaw) -> anyhow::Result<()> {
+        match self.input_source() {
+            Ok(value) if value == expected => Ok(()),
+            Ok(value) => anyhow::bail!(
+                "{self}: The input source is {value} after setting to {expected}",
+                value = InputValue::from(value),
+                expected = InputValue::from(expected)
+            ),
+            Err(e) => {
+                warn!("{self}: Failed to verify the input source: {e}");
+                Ok(())
+            }
+        }
+    }
+
+    /// Send the raw DDC/CI command `data`,
+    /// and read a reply of up to `read_len` bytes if it's not zero.
+    /// This is for vendor-specific commands that other functions don't support.
+    /// See [`DdcDevice::ddc_raw()`].
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{MockMonitor,Monitor};
+    /// let mut monitor = Monitor::from_device(MockMonitor::new("A").with_raw_reply(&[0xC2], &[1, 2]));
+    /// assert_eq!(monitor.ddc_raw(&[0xC2], 8).unwrap(), [1, 2]);
+    /// ```
+    pub fn ddc_raw(&mut self, data: &[u8], read_len: usize) -> anyhow::Result<Vec<u8>> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("ddc_raw", monitor = self.id()).entered();
+        if data.is_empty() || data.len() > MAX_DDC_RAW_LEN || read_len > MAX_DDC_RAW_LEN {
+            anyhow::bail!(
+                "{self}: Raw DDC/CI commands and replies must be 1 to {MAX_DDC_RAW_LEN} bytes"
+            );
+        }
         info!(
-            "InputSource({self}) = {value}{mode}",
-            value = InputSource::str_from_raw(value),
+            "DdcRaw({self}) = {data:02X?}{mode}",
+            mode = if Self::is_dry_run() { " (dry-run)" } else { "" }
+        );
+        if Self::is_dry_run() {
+            return Ok(vec![]);
+        }
+        // Vendor-specific commands may change the input source.
+        self.input_source_cache = None;
+        self.with_device_to_write(|device| device.ddc_raw(data, read_len))
+            .inspect(|_| self.ready_at = Some(Instant::now() + self.settle_delay))
+    }
+
+    /// Get the current and the maximum values of the VCP feature `code`.
+    pub fn vcp_feature(&mut self, code: VcpCode) -> anyhow::Result<VcpValue> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("vcp_feature", monitor = self.id(), code).entered();
+        self.with_device(|device| device.get_vcp_feature(code))
+    }
+
+    /// Get the current and the maximum values of the VCP feature `code`, and its [`VcpKind`].
+    /// If the device doesn't report the [`VcpKind`],
+    /// it's non-continuous if the capabilities list its values,
+    /// or it's from the MCCS specification for well-known features.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{MockMonitor,Monitor,VcpKind};
+    /// let mut monitor = Monitor::from_device(MockMonitor::new("A"));
+    /// monitor.set_brightness(50).unwrap();
+    /// let brightness = monitor.vcp_value(0x10).unwrap();
+    /// assert_eq!(brightness.value, 50);
+    /// assert_eq!(brightness.kind, VcpKind::Continuous);
+    /// ```
+    pub fn vcp_value(&mut self, code: VcpCode) -> anyhow::Result<TypedVcpValue> {
+        let value = self.vcp_feature(code)?;
+        Ok(TypedVcpValue {
+            value: value.value,
+            maximum: value.maximum,
+            kind: self.vcp_kind(code),
+        })
+    }
+
+    pub(crate) fn vcp_kind(&self, code: VcpCode) -> VcpKind {
+        let kind = self.device.vcp_kind(code);
+        if kind != VcpKind::Unknown {
+            return kind;
+        }
+        if let Some(feature) = self
+            .capabilities
+            .as_ref()
+            .and_then(|capabilities| capabilities.vcp_features.get(&code))
+            && !feature.values.is_empty()
+        {
+            return VcpKind::NonContinuous;
+        }
+        match code {
+            LUMINANCE | CONTRAST | AUDIO_VOLUME => VcpKind::Continuous,
+            code if RGB_GAINS.contains(&code) => VcpKind::Continuous,
+            INPUT_SELECT | POWER_MODE | COLOR_PRESET => VcpKind::NonContinuous,
+            _ => VcpKind::Unknown,
+        }
+    }
+
+    /// Get the current brightness and its maximum value.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{MockMonitor,Monitor};
+    /// let mut monitor = Monitor::from_device(MockMonitor::new("A"));
+    /// monitor.set_brightness(50).unwrap();
+    /// assert_eq!(monitor.brightness().unwrap().value, 50);
+    /// ```
+    pub fn brightness(&mut self) -> anyhow::Result<VcpValue> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("brightness", monitor = self.id()).entered();
+        self.vcp_feature(LUMINANCE)
+    }
+
+    /// Set the brightness.
+    /// The `value` should be in the range of the maximum value of [`Monitor::brightness()`].
+    pub fn set_brightness(&mut self, value: u16) -> anyhow::Result<()> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("set_brightness", monitor = self.id(), value).entered();
+        info!(
+            "Brightness({self}) = {value}{mode}",
+            mode = if Self::is_dry_run() { " (dry-run)" } else { "" }
+        );
+        if Self::is_dry_run() {
+            return Ok(());
+        }
+        self.with_device_to_write(|device| device.set_vcp_feature(LUMINANCE, value))
+            .inspect(|_| self.ready_at = Some(Instant::now() + self.settle_delay))
+    }
+
+    /// Set the VCP feature `code` to the `value`.
+    /// Prefer the functions for the features such as [`Monitor::set_input_source()`],
+    /// which handle the differences of display monitors.
+    pub fn set_vcp_feature(&mut self, code: VcpCode, value: u16) -> anyhow::Result<()> {
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!("set_vcp_feature", monitor = self.id(), code, value).entered();
+        info!(
+            "Vcp{code:02X}({self}) = {value}{mode}",
             mode = if Self::is_dry_run() { " (dry-run)" } else { "" }
         );
         if Self::is_dry_run() {
             return Ok(());
         }
-        let feature_code: FeatureCode = self.feature_code(INPUT_SELECT);
-        self.ddc_hi_display
-            .handle
-            .set_vcp_feature(feature_code, value as u16)
-            .inspect(|_| self.needs_sleep = true)
+        if code == INPUT_SELECT {
+            self.input_source_cache = None;
+        }
+        self.with_device_to_write(|device| device.set_vcp_feature(code, value))
+            .inspect(|_| self.ready_at = Some(Instant::now() + self.settle_delay))
+    }
+
+    /// Apply the `writes` in one run, and return the result of each of them.
+    ///
+    /// The `writes` are applied in the order that works reliably
+    /// regardless of the order in the `writes`:
+    /// turning on first, then the input source, other features,
+    /// and turning off at the end.
+    /// Delays that DDC/CI requires are inserted between them,
+    /// and a failure doesn't stop the rest.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{InputSource,MockMonitor,Monitor,VcpWrite};
+    /// let mut monitor = Monitor::from_device(MockMonitor::new("A"));
+    /// let results = monitor.apply(&[
+    ///     VcpWrite::Brightness(50),
+    ///     VcpWrite::InputSource(InputSource::Hdmi1.as_raw()),
+    /// ]);
+    /// assert_eq!(results[0].0, VcpWrite::InputSource(InputSource::Hdmi1.as_raw()));
+    /// assert!(results.iter().all(|(_, result)| result.is_ok()));
+    /// assert_eq!(monitor.brightness().unwrap().value, 50);
+    /// ```
+    pub fn apply(&mut self, writes: &[VcpWrite]) -> Vec<(VcpWrite, anyhow::Result<()>)> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("apply", monitor = self.id()).entered();
+        VcpWrite::sort(writes)
+            .into_iter()
+            .map(|write| {
+                let result = match write {
+                    VcpWrite::PowerMode(value) => self.set_vcp_feature(POWER_MODE, value),
+                    VcpWrite::InputSource(value) => self.set_input_source(value),
+                    VcpWrite::Brightness(value) => self.set_brightness(value),
+                    VcpWrite::Feature(code, value) => self.set_vcp_feature(code, value),
+                };
+                if result.is_ok() && write.is_power_on() && !Self::is_dry_run() {
+                    self.ready_at = Some(Instant::now() + self.settle_delay.max(POWER_ON_DELAY));
+                }
+                (write, result)
+            })
+            .collect()
+    }
+
+    /// The [`Quirks`] for the picture-by-picture of this display monitor.
+    fn pbp_quirks(&self) -> anyhow::Result<&'static Quirks> {
+        Quirks::find_pbp(self).ok_or_else(|| {
+            anyhow::anyhow!("{self}: Picture-by-picture isn't supported for this manufacturer")
+        })
+    }
+
+    /// Get the current picture-by-picture or picture-in-picture mode.
+    /// This is supported only for the manufacturers known by the `quirks` module.
+    pub fn pbp_mode(&mut self) -> anyhow::Result<PbpMode> {
+        let quirks = self.pbp_quirks()?;
+        let value = self.vcp_feature(quirks.pbp_code)?.value;
+        quirks
+            .pbp_mode(value)
+            .ok_or_else(|| anyhow::anyhow!("{self}: Unknown picture-by-picture mode {value:#04X}"))
+    }
+
+    /// Set the picture-by-picture or picture-in-picture mode.
+    /// This is supported only for the manufacturers known by the `quirks` module.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{MockMonitor,Monitor,PbpMode};
+    /// let mut monitor = Monitor::from_device(MockMonitor::new("A").with_model_name("DELL U3818DW"));
+    /// monitor.set_pbp_mode(PbpMode::Pbp2x1).unwrap();
+    /// assert_eq!(monitor.pbp_mode().unwrap(), PbpMode::Pbp2x1);
+    /// ```
+    pub fn set_pbp_mode(&mut self, mode: PbpMode) -> anyhow::Result<()> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("set_pbp_mode", monitor = self.id()).entered();
+        let quirks = self.pbp_quirks()?;
+        let Some(value) = quirks.pbp_value(mode) else {
+            anyhow::bail!(
+                "{self}: The picture-by-picture mode \"{}\" isn't supported",
+                mode.as_ref()
+            );
+        };
+        info!(
+            "PbpMode({self}) = {}{dry_run}",
+            mode.as_ref(),
+            dry_run = if Self::is_dry_run() { " (dry-run)" } else { "" }
+        );
+        if Self::is_dry_run() {
+            return Ok(());
+        }
+        self.with_device_to_write(|device| device.set_vcp_feature(quirks.pbp_code, value))
+            .inspect(|_| self.ready_at = Some(Instant::now() + self.settle_delay))
     }
 
     /// Get all input sources.
@@ -163,20 +1066,97 @@ impl Monitor {
         if let Some(feature) = self.feature_descriptor(INPUT_SELECT) {
             trace!("INPUT_SELECT({self}) = {feature:?}");
             if let mccs_db::ValueType::NonContinuous { values, .. } = &feature.ty {
-                return Some(values.keys().cloned().collect());
+                return Some(
+                    values
+                        .keys()
+                        .map(|value| self.input_source_from_device(u16::from(*value)))
+                        .collect(),
+                );
             }
         }
         None
     }
 
+    /// Get all input sources and their names.
+    /// The names are those set by [`Monitor::set_input_source_name()`],
+    /// or the [`InputValue`] otherwise.
+    /// Requires to call [`Monitor::update_capabilities()`] beforehand.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{MockMonitor,Monitor};
+    /// let mut monitor = Monitor::from_device(MockMonitor::new("A").with_input_sources(&[0x0F, 0x11]));
+    /// monitor.update_capabilities().unwrap();
+    /// monitor.set_input_source_name(0x11, "Laptop");
+    /// assert_eq!(
+    ///     monitor.input_source_names().unwrap(),
+    ///     [(0x0F, "DP1".to_string()), (0x11, "Laptop".to_string())]
+    /// );
+    /// ```
+    pub fn input_source_names(&mut self) -> Option<Vec<(InputSourceRaw, String)>> {
+        let input_sources = self.input_sources()?;
+        Some(
+            input_sources
+                .into_iter()
+                .map(|value| (value, self.input_source_name(value)))
+                .collect(),
+        )
+    }
+
+    /// The name of the input source `value`,
+    /// set by [`Monitor::set_input_source_name()`],
+    /// or the [`InputValue`] otherwise.
+    pub fn input_source_name(&self, value: InputSourceRaw) -> String {
+        match self.input_source_names.get(&value) {
+            Some(name) => name.clone(),
+            None => InputValue::from(value).to_string(),
+        }
+    }
+
+    /// Set the custom name of the input source `value`, such as `Laptop`,
+    /// for [`Monitor::input_source_name()`] and [`Monitor::input_source_names()`].
+    pub fn set_input_source_name(&mut self, value: InputSourceRaw, name: impl Into<String>) {
+        self.input_source_names.insert(value, name.into());
+    }
+
+    /// Replace the device with the one `f` returns, which usually wraps the current one.
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
+    pub(crate) fn wrap_device(&mut self, f: impl FnOnce(Box<dyn DdcDevice>) -> Box<dyn DdcDevice>) {
+        let device = std::mem::replace(&mut self.device, Box::new(MockMonitor::default()));
+        self.device = f(device);
+    }
+
+    /// Set the additional delay after changes.
+    /// This is for display monitors that need more time
+    /// than [`Monitor::sleep_if_needed()`] waits for by DDC/CI.
+    pub fn set_settle_delay(&mut self, delay: Duration) {
+        self.settle_delay = delay;
+    }
+
+    /// The earliest time this display monitor may be used again after the last change,
+    /// including the [`Monitor::set_settle_delay()`].
+    /// `None` if there are no changes to wait for.
+    /// Requests to this display monitor wait until then automatically,
+    /// so that changes to multiple display monitors can interleave without waiting.
+    pub fn ready_at(&self) -> Option<Instant> {
+        self.ready_at
+    }
+
     /// Sleep if any previous DDC commands need time to be executed.
-    /// See also [`ddc_hi::DdcHost::sleep()`].
+    /// See also [`DdcDevice::sleep()`] and [`Monitor::ready_at()`].
+    ///
+    /// The delays count from the last change, not from when this is called,
+    /// so that calling this for multiple display monitors in a row
+    /// waits only for the longest one, instead of the sum of them.
     pub fn sleep_if_needed(&mut self) {
-        if self.needs_sleep {
+        if let Some(ready_at) = self.ready_at.take() {
             debug!("sleep({self})");
             let start_time = Instant::now();
-            self.needs_sleep = false;
-            self.ddc_hi_display.handle.sleep();
+            self.device.sleep();
+            let remaining = ready_at.saturating_duration_since(Instant::now());
+            if !remaining.is_zero() {
+                // `with_device()` fails after this if cancelled.
+                let _ = self.cancellation.sleep(remaining);
+            }
             debug!("sleep({self}) elapsed {:?}", start_time.elapsed());
         }
     }
@@ -189,24 +1169,198 @@ impl Monitor {
         lines.push(format!(
             "Input Source: {}",
             match input_source {
-                Ok(value) => InputSource::str_from_raw(value),
+                Ok(value) => self.input_source_name(value),
                 Err(e) => e.to_string(),
             }
         ));
-        if let Some(input_sources) = self.input_sources() {
+        if let Some(input_sources) = self.input_source_names() {
             lines.push(format!(
                 "Input Sources: {}",
                 input_sources
-                    .iter()
-                    .map(|value| InputSource::str_from_raw(*value))
+                    .into_iter()
+                    .map(|(_, name)| name)
                     .collect::<Vec<_>>()
                     .join(", ")
             ));
         }
-        if let Some(model) = &self.ddc_hi_display.info.model_name {
+        if let Some(model) = self.model_name() {
             lines.push(format!("Model: {model}"));
         }
-        lines.push(format!("Backend: {}", self.ddc_hi_display.info.backend));
+        lines.push(format!("Backend: {}", self.backend()));
         lines.join("\n    ")
     }
 }
+
+/// The differences between two enumerations of display monitors.
+/// See [`Monitor::enumerate_diff()`].
+/// # Examples
+/// ```
+/// # use monitor_input::{MockMonitor,Monitor,MonitorDiff};
+/// let previous = [Monitor::from_device(MockMonitor::new("A"))];
+/// let current = vec![
+///     Monitor::from_device(MockMonitor::new("A")),
+///     Monitor::from_device(MockMonitor::new("B")),
+/// ];
+/// let diff = MonitorDiff::new(&previous, current);
+/// assert_eq!(diff.unchanged, [0]);
+/// assert_eq!(diff.added[0].id(), "B");
+/// ```
+#[derive(Debug, Default)]
+pub struct MonitorDiff {
+    /// The display monitors in the current enumeration that aren't in the previous one.
+    pub added: Vec<Monitor>,
+    /// The indices of the previous display monitors that aren't in the current enumeration.
+    pub removed: Vec<usize>,
+    /// The indices of the previous display monitors that are in the current enumeration.
+    pub unchanged: Vec<usize>,
+}
+
+impl MonitorDiff {
+    /// Compare the `current` enumeration with the `previous` one.
+    /// Display monitors are matched one-to-one by [`Monitor::is_same_display()`],
+    /// and the `current` instances of matched display monitors are dropped.
+    pub fn new(previous: &[Monitor], current: Vec<Monitor>) -> Self {
+        let mut diff = MonitorDiff::default();
+        let mut is_matched = vec![false; previous.len()];
+        for monitor in current {
+            match (0..previous.len())
+                .find(|&index| !is_matched[index] && previous[index].is_same_display(&monitor))
+            {
+                Some(index) => is_matched[index] = true,
+                None => diff.added.push(monitor),
+            }
+        }
+        for (index, is_matched) in is_matched.into_iter().enumerate() {
+            if is_matched {
+                diff.unchanged.push(index);
+            } else {
+                diff.removed.push(index);
+            }
+        }
+        diff
+    }
+
+    /// Whether there are no display monitors added or removed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_id() {
+        let mut monitor = Monitor::from_device(MockMonitor::new("A").with_model_name("U2723QE"));
+        assert_eq!(monitor.format_id("{model} ({serial})"), "U2723QE ()");
+        assert_eq!(monitor.format_id("{id} {unknown} {"), "A {unknown} {");
+        let matches =
+            |monitor: &Monitor, name: &str| Selector::from_name(name).matches_monitor(0, monitor);
+        assert!(matches(&monitor, "A"));
+        assert!(!matches(&monitor, "U27"));
+        monitor.set_id_format(Some("{model}"));
+        assert_eq!(monitor.to_string(), "U2723QE");
+        assert!(matches(&monitor, "A"));
+        assert!(matches(&monitor, "U27"));
+    }
+
+    #[test]
+    fn quirks_input_remaps() {
+        let mut monitor = Monitor::from_device(
+            MockMonitor::new("A")
+                .with_model_name("DELL U2720Q")
+                .with_input_sources(&[0x0F, 0x1B])
+                .with_input_source(0x1B),
+        );
+        assert_eq!(monitor.input_source().unwrap(), InputSource::UsbC1.as_raw());
+        monitor.update_capabilities().unwrap();
+        assert_eq!(monitor.input_sources(), Some(vec![0x0F, 0x19]));
+        monitor.set_input_source(0x0F).unwrap();
+        monitor.set_input_source(0x19).unwrap();
+        assert_eq!(monitor.vcp_feature(INPUT_SELECT).unwrap().value, 0x1B);
+
+        // Other Dell display monitors use the standard value.
+        let mut monitor =
+            Monitor::from_device(MockMonitor::new("B").with_model_name("DELL P2720DC"));
+        monitor.set_input_source(0x19).unwrap();
+        assert_eq!(monitor.vcp_feature(INPUT_SELECT).unwrap().value, 0x19);
+    }
+
+    #[test]
+    fn input_remaps_16_bits() {
+        let mut monitor = Monitor::from_device(MockMonitor::new("A"));
+        monitor.set_vcp_feature(INPUT_SELECT, 0x1111).unwrap();
+        assert_eq!(monitor.input_source().unwrap(), InputSource::Hdmi1.as_raw());
+
+        monitor.set_input_remap(InputSource::UsbC1.as_raw(), 0x0F1B);
+        monitor
+            .set_input_source(InputSource::UsbC1.as_raw())
+            .unwrap();
+        assert_eq!(monitor.vcp_feature(INPUT_SELECT).unwrap().value, 0x0F1B);
+        assert_eq!(monitor.input_source().unwrap(), InputSource::UsbC1.as_raw());
+        monitor.set_vcp_feature(INPUT_SELECT, 0x001B).unwrap();
+        assert_eq!(monitor.input_source().unwrap(), 0x1B);
+    }
+
+    #[test]
+    fn sleep_if_needed_overlaps() {
+        let mut monitors: Vec<Monitor> = ["A", "B", "C"]
+            .iter()
+            .map(|id| {
+                let mut monitor = Monitor::from_device(MockMonitor::new(*id));
+                monitor.set_settle_delay(Duration::from_millis(200));
+                monitor
+            })
+            .collect();
+        for monitor in &mut monitors {
+            monitor
+                .set_input_source(InputSource::Hdmi1.as_raw())
+                .unwrap();
+        }
+        let start_time = Instant::now();
+        for monitor in &mut monitors {
+            monitor.sleep_if_needed();
+        }
+        assert!(start_time.elapsed() < Duration::from_millis(400));
+    }
+
+    #[test]
+    fn wait_for_ready_at() {
+        let mut monitor = Monitor::from_device(MockMonitor::new("A"));
+        monitor.set_settle_delay(Duration::from_millis(100));
+        let start_time = Instant::now();
+        monitor
+            .set_input_source(InputSource::Hdmi1.as_raw())
+            .unwrap();
+        let ready_at = monitor.ready_at().unwrap();
+        assert!(ready_at >= start_time + Duration::from_millis(100));
+
+        // The next request waits until the `ready_at`.
+        monitor.input_source().unwrap();
+        assert!(Instant::now() >= ready_at);
+        assert_eq!(monitor.ready_at(), None);
+    }
+
+    #[test]
+    fn monitor_diff() {
+        let monitors = |ids: &[&str]| -> Vec<Monitor> {
+            ids.iter()
+                .map(|id| Monitor::from_device(MockMonitor::new(*id)))
+                .collect()
+        };
+        let previous = monitors(&["A", "B", "B", "C"]);
+        let diff = MonitorDiff::new(&previous, monitors(&["B", "D", "A"]));
+        assert!(!diff.is_empty());
+        assert_eq!(
+            diff.added.iter().map(Monitor::id).collect::<Vec<_>>(),
+            ["D"]
+        );
+        assert_eq!(diff.removed, [2, 3]);
+        assert_eq!(diff.unchanged, [0, 1]);
+
+        let diff = MonitorDiff::new(&previous, monitors(&["A", "B", "B", "C"]));
+        assert!(diff.is_empty());
+        assert_eq!(diff.unchanged, [0, 1, 2, 3]);
+    }
+}
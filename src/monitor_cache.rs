@@ -0,0 +1,116 @@
+use super::*;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// The information to open a display monitor again
+/// without enumerating all display monitors.
+/// See [`Monitor::cache_entry()`] and [`Monitor::open_cached()`].
+pub struct MonitorCacheEntry {
+    /// The [`Monitor::backend()`].
+    pub backend: String,
+    /// The [`Monitor::id()`].
+    pub id: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    /// The [`Monitor::edid()`], to keep the model name and the serial number.
+    pub edid: Option<Vec<u8>>,
+}
+
+impl MonitorCacheEntry {
+    /// Whether [`Monitor::open_cached()`] can open the display monitor of the `backend`.
+    /// Only the `i2c-dev` backend on Linux and the `winapi` backend on Windows can,
+    /// because other backends are slow to open, or need to enumerate all.
+    pub fn is_supported_backend(backend: &str) -> bool {
+        (cfg!(target_os = "linux") && backend == "i2c-dev")
+            || (cfg!(windows) && backend == "winapi")
+    }
+}
+
+impl Monitor {
+    /// The [`MonitorCacheEntry`] to open this display monitor again.
+    pub fn cache_entry(&self) -> MonitorCacheEntry {
+        MonitorCacheEntry {
+            backend: self.backend(),
+            id: self.id().to_string(),
+            edid: self.edid().map(<[u8]>::to_vec),
+        }
+    }
+
+    /// Open the display monitors of the `entries` from a previous enumeration,
+    /// without enumerating all display monitors.
+    /// This is faster than [`Monitor::enumerate()`],
+    /// but it's an error if any of them can't be opened,
+    /// such as when they're disconnected, or their backends aren't supported.
+    /// See [`MonitorCacheEntry::is_supported_backend()`].
+    pub fn open_cached(entries: &[MonitorCacheEntry]) -> anyhow::Result<Vec<Self>> {
+        #[cfg(windows)]
+        let mut winapi_monitors: Option<Vec<ddc_winapi::Monitor>> = None;
+        let mut monitors = vec![];
+        for entry in entries {
+            let display = match entry.backend.as_str() {
+                #[cfg(target_os = "linux")]
+                "i2c-dev" => open_i2c_device(entry)?,
+                #[cfg(windows)]
+                "winapi" => {
+                    let candidates = match &mut winapi_monitors {
+                        Some(candidates) => candidates,
+                        None => winapi_monitors.insert(ddc_winapi::Monitor::enumerate()?),
+                    };
+                    let Some(index) = candidates
+                        .iter()
+                        .position(|monitor| monitor.description() == entry.id)
+                    else {
+                        anyhow::bail!("{}: Not found", entry.id);
+                    };
+                    let handle = ddc_hi::Handle::WinApi(candidates.remove(index));
+                    let info = ddc_hi::DisplayInfo::new(ddc_hi::Backend::WinApi, entry.id.clone());
+                    ddc_hi::Display::new(handle, info)
+                }
+                backend => anyhow::bail!("{}: The backend {backend} isn't supported", entry.id),
+            };
+            monitors.push(Monitor::new(display));
+        }
+        Ok(monitors)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_i2c_device(entry: &MonitorCacheEntry) -> anyhow::Result<ddc_hi::Display> {
+    use std::os::unix::fs::MetadataExt;
+
+    // The `id` of `i2c-dev` is the device number of `/dev/i2c-*`.
+    let rdev: u64 = entry.id.parse()?;
+    let bus = (rdev & 0xFF) | ((rdev >> 12) & !0xFF);
+    let path = format!("/dev/i2c-{bus}");
+    let ddc = ddc_i2c::from_i2c_device(&path)?;
+    if ddc.inner_ref().inner_ref().metadata()?.rdev() != rdev {
+        anyhow::bail!("{}: {path} is a different device", entry.id);
+    }
+    let info = match &entry.edid {
+        Some(edid) => ddc_hi::DisplayInfo::from_edid(
+            ddc_hi::Backend::I2cDevice,
+            entry.id.clone(),
+            edid.clone(),
+        )?,
+        None => ddc_hi::DisplayInfo::new(ddc_hi::Backend::I2cDevice, entry.id.clone()),
+    };
+    Ok(ddc_hi::Display::new(ddc_hi::Handle::I2cDevice(ddc), info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_entry() {
+        let monitor = Monitor::from_device(MockMonitor::new("A"));
+        let entry = monitor.cache_entry();
+        assert_eq!(entry.backend, "mock");
+        assert_eq!(entry.id, "A");
+        assert!(!MonitorCacheEntry::is_supported_backend(&entry.backend));
+        assert!(Monitor::open_cached(&[entry]).is_err());
+        assert!(Monitor::open_cached(&[]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,23 @@
+use super::*;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+/// An owned snapshot of the information of a display monitor, from [`Monitor::info()`].
+/// This doesn't hold the handle of the display monitor,
+/// so it can be cloned, cached, and sent to other threads.
+/// With the `serde` feature, this can be serialized
+/// for other programs, or to cache across runs.
+pub struct MonitorInfo {
+    /// The [`Monitor::id()`].
+    pub id: String,
+    /// The [`Monitor::model_name()`].
+    pub model: Option<String>,
+    /// The [`Monitor::serial()`].
+    pub serial: Option<String>,
+    /// The [`Monitor::backend()`].
+    pub backend: String,
+    /// The [`Monitor::input_source()`], or `None` if it failed.
+    pub current_input: Option<InputSourceRaw>,
+    /// The [`Monitor::input_sources()`] in the capabilities, or `None` if not available.
+    pub inputs: Option<Vec<InputSourceRaw>>,
+}
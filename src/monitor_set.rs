@@ -0,0 +1,366 @@
+use std::ops::{Deref, DerefMut};
+use std::time::Duration;
+
+use super::*;
+use crate::logging::*;
+
+#[derive(Debug, Default)]
+/// A list of [`Monitor`]s, with functions to select display monitors.
+/// It dereferences to `[Monitor]` for indexing and iterating.
+/// # Examples
+/// ```
+/// # use monitor_input::{MockMonitor,Monitor,MonitorSet};
+/// let monitors = MonitorSet::from_iter(
+///     ["DELL U2723QE", "DELL P3223QE", "LG 27UP850"]
+///         .map(|id| Monitor::from_device(MockMonitor::new(id))),
+/// );
+/// let dells = monitors.filter_by_name("DELL");
+/// assert_eq!(dells.len(), 2);
+/// assert_eq!(dells[1].id(), "DELL P3223QE");
+/// assert!(dells.find_exact("DELL U2723QE").is_some());
+/// assert!(dells.find_exact("DELL").is_none());
+/// ```
+pub struct MonitorSet {
+    monitors: Vec<Monitor>,
+}
+
+impl MonitorSet {
+    /// Create an instance from the `monitors`.
+    pub fn new(monitors: Vec<Monitor>) -> Self {
+        Self { monitors }
+    }
+
+    /// Enumerate all display monitors. See [`Monitor::enumerate()`].
+    pub fn enumerate() -> Self {
+        Self::new(Monitor::enumerate())
+    }
+
+    /// Enumerate display monitors found within the `timeout`.
+    /// See [`Monitor::enumerate_with_timeout()`].
+    pub fn enumerate_with_timeout(timeout: Duration) -> Self {
+        Self::new(Monitor::enumerate_with_timeout(timeout))
+    }
+
+    /// Enumerate all display monitors with the `token` to cancel their requests.
+    /// See [`Monitor::enumerate_with_cancellation()`].
+    pub fn enumerate_with_cancellation(token: &CancellationToken) -> anyhow::Result<Self> {
+        token.check()?;
+        let mut monitors = Self::enumerate();
+        monitors.set_cancellation_token(token)?;
+        Ok(monitors)
+    }
+
+    /// Set the `token` to all display monitors by [`Monitor::set_cancellation_token()`].
+    /// Returns the [`Cancelled`] error if the `token` is already cancelled.
+    pub fn set_cancellation_token(&mut self, token: &CancellationToken) -> anyhow::Result<()> {
+        token.check()?;
+        for monitor in self.iter_mut() {
+            monitor.set_cancellation_token(token.clone());
+        }
+        Ok(())
+    }
+
+    /// Keep only the display monitors whose [`Monitor::backend()`] contains the `backend`,
+    /// such as `winapi` or `i2c-dev`.
+    pub fn filter_by_backend(mut self, backend: &str) -> Self {
+        self.retain(|monitor| monitor.contains_backend(backend));
+        self
+    }
+
+    /// Keep only the display monitors that the `name` matches,
+    /// by the same rules as names in the command line:
+    /// an index, `primary`, a part of the [`Monitor::id()`],
+    /// or the exact name of the [`Monitor::display_output()`].
+    pub fn filter_by_name(mut self, name: &str) -> Self {
+        let selector = Selector::from_name(name);
+        let mut index = 0;
+        self.retain(|monitor| {
+            index += 1;
+            selector.matches_monitor(index - 1, monitor)
+        });
+        self
+    }
+
+    /// Keep only one of the display monitors found by multiple backends,
+    /// such as both `nvapi` and `winapi` on Windows,
+    /// preferring the `backends` in the order.
+    /// Display monitors of the backends not in the `backends` are kept
+    /// only if no preferred backends found them.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{MockMonitor,Monitor,MonitorSet};
+    /// let edid = [1u8; 128];
+    /// let mut monitors = MonitorSet::from_iter(
+    ///     [MockMonitor::new("A").with_backend("nvapi"), MockMonitor::new("B").with_backend("winapi")]
+    ///         .map(|mock| Monitor::from_device(mock.with_edid(edid))),
+    /// );
+    /// monitors.prefer_backends(&["winapi"]);
+    /// assert_eq!(monitors.len(), 1);
+    /// assert_eq!(monitors[0].backend(), "winapi");
+    /// ```
+    pub fn prefer_backends(&mut self, backends: &[impl AsRef<str>]) {
+        let rank = |monitor: &Monitor| {
+            let backend = monitor.backend();
+            backends
+                .iter()
+                .position(|preferred| backend == preferred.as_ref())
+                .unwrap_or(backends.len())
+        };
+        let mut index = 0;
+        while index < self.monitors.len() {
+            let monitor = &self.monitors[index];
+            let Some(other) = (0..self.monitors.len()).find(|other| {
+                *other != index
+                    && rank(&self.monitors[*other]) < rank(monitor)
+                    && is_same_display_across_backends(monitor, &self.monitors[*other])
+            }) else {
+                index += 1;
+                continue;
+            };
+            info!(
+                "{monitor} ({}) is removed, because {} is preferred.",
+                monitor.backend(),
+                self.monitors[other].backend()
+            );
+            self.monitors.remove(index);
+        }
+    }
+
+    /// Sort the display monitors by their [`Monitor::backend()`],
+    /// then by their EDIDs, their [`Monitor::serial()`], and their [`Monitor::id()`],
+    /// so that their indices don't change when backends enumerate them in different orders.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{MockMonitor,Monitor,MonitorSet};
+    /// let mut monitors = MonitorSet::from_iter(
+    ///     [("B", "winapi"), ("A", "winapi"), ("C", "nvapi")]
+    ///         .map(|(id, backend)| Monitor::from_device(MockMonitor::new(id).with_backend(backend))),
+    /// );
+    /// monitors.sort_stable();
+    /// let ids: Vec<&str> = monitors.iter().map(Monitor::id).collect();
+    /// assert_eq!(ids, ["C", "A", "B"]);
+    /// ```
+    pub fn sort_stable(&mut self) {
+        self.monitors.sort_by(|a, b| {
+            (a.backend(), a.edid(), a.serial(), a.id()).cmp(&(
+                b.backend(),
+                b.edid(),
+                b.serial(),
+                b.id(),
+            ))
+        });
+    }
+
+    /// The display monitor whose [`Monitor::id()`] is the `id`.
+    pub fn find_exact(&self, id: &str) -> Option<&Monitor> {
+        self.monitors.iter().find(|monitor| monitor.id() == id)
+    }
+
+    /// The display monitor whose [`Monitor::id()`] is the `id`.
+    pub fn find_exact_mut(&mut self, id: &str) -> Option<&mut Monitor> {
+        self.monitors.iter_mut().find(|monitor| monitor.id() == id)
+    }
+
+    /// Append the `monitor` to the end.
+    pub fn push(&mut self, monitor: Monitor) {
+        self.monitors.push(monitor);
+    }
+
+    /// Remove the display monitor at the `index`.
+    pub fn remove(&mut self, index: usize) -> Monitor {
+        self.monitors.remove(index)
+    }
+
+    /// Keep only the display monitors that `f` returns `true`.
+    pub fn retain(&mut self, f: impl FnMut(&Monitor) -> bool) {
+        self.monitors.retain(f);
+    }
+
+    /// Convert to the `Vec` of the [`Monitor`]s.
+    pub fn into_vec(self) -> Vec<Monitor> {
+        self.monitors
+    }
+}
+
+/// Whether `a` and `b` from different backends are the same display monitor,
+/// by their EDIDs if both have them,
+/// their [`DisplayOutput`]s such as for the `winapi` backend that doesn't have EDIDs,
+/// or their model names and serial numbers.
+pub(crate) fn is_same_display_across_backends(a: &Monitor, b: &Monitor) -> bool {
+    if a.backend() == b.backend() {
+        return false;
+    }
+    if let (Some(a), Some(b)) = (a.edid(), b.edid()) {
+        return is_same_edid(a, b);
+    }
+    if let (Some(a), Some(b)) = (a.display_output(), b.display_output()) {
+        return a.name == b.name;
+    }
+    a.serial().is_some() && a.serial() == b.serial() && a.model_name() == b.model_name()
+}
+
+impl Deref for MonitorSet {
+    type Target = [Monitor];
+
+    fn deref(&self) -> &Self::Target {
+        &self.monitors
+    }
+}
+
+impl DerefMut for MonitorSet {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.monitors
+    }
+}
+
+impl From<Vec<Monitor>> for MonitorSet {
+    fn from(monitors: Vec<Monitor>) -> Self {
+        Self::new(monitors)
+    }
+}
+
+impl FromIterator<Monitor> for MonitorSet {
+    fn from_iter<T: IntoIterator<Item = Monitor>>(iter: T) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl Extend<Monitor> for MonitorSet {
+    fn extend<T: IntoIterator<Item = Monitor>>(&mut self, iter: T) {
+        self.monitors.extend(iter);
+    }
+}
+
+impl IntoIterator for MonitorSet {
+    type Item = Monitor;
+    type IntoIter = std::vec::IntoIter<Monitor>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.monitors.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a MonitorSet {
+    type Item = &'a Monitor;
+    type IntoIter = std::slice::Iter<'a, Monitor>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.monitors.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut MonitorSet {
+    type Item = &'a mut Monitor;
+    type IntoIter = std::slice::IterMut<'a, Monitor>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.monitors.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitors(ids: &[&str]) -> MonitorSet {
+        ids.iter()
+            .map(|id| Monitor::from_device(MockMonitor::new(*id)))
+            .collect()
+    }
+
+    fn ids(monitors: &MonitorSet) -> Vec<&str> {
+        monitors.iter().map(|monitor| monitor.id()).collect()
+    }
+
+    #[test]
+    fn filter() {
+        let monitors = monitors(&["A1", "A2", "B"]);
+        assert_eq!(ids(&monitors.filter_by_name("A")), ["A1", "A2"]);
+        let monitors = self::monitors(&["A1", "B"]);
+        assert_eq!(ids(&monitors.filter_by_backend("mock")), ["A1", "B"]);
+        let monitors = self::monitors(&["A1", "B"]);
+        assert!(monitors.filter_by_backend("winapi").is_empty());
+    }
+
+    #[test]
+    fn find_exact() {
+        let mut monitors = monitors(&["A1", "A"]);
+        assert_eq!(monitors.find_exact("A").unwrap().id(), "A");
+        assert!(monitors.find_exact("B").is_none());
+        monitors.remove(1);
+        assert!(monitors.find_exact_mut("A").is_none());
+        assert!(monitors.find_exact_mut("A1").is_some());
+    }
+
+    #[test]
+    fn prefer_backends() {
+        let monitors = || -> MonitorSet {
+            [
+                ("A", "nvapi", 1),
+                ("B", "nvapi", 2),
+                ("A", "winapi", 1),
+                ("C", "winapi", 3),
+                ("B", "other", 2),
+            ]
+            .into_iter()
+            .map(|(id, backend, edid)| {
+                Monitor::from_device(
+                    MockMonitor::new(id)
+                        .with_backend(backend)
+                        .with_edid([edid; 128]),
+                )
+            })
+            .collect()
+        };
+        let backends = |monitors: &MonitorSet| {
+            monitors
+                .iter()
+                .map(|monitor| format!("{}:{}", monitor.id(), monitor.backend()))
+                .collect::<Vec<_>>()
+        };
+        let mut set = monitors();
+        set.prefer_backends(&["winapi", "nvapi"]);
+        assert_eq!(backends(&set), ["B:nvapi", "A:winapi", "C:winapi"]);
+        let mut set = monitors();
+        set.prefer_backends(&["nvapi"]);
+        assert_eq!(backends(&set), ["A:nvapi", "B:nvapi", "C:winapi"]);
+        let mut set = monitors();
+        set.prefer_backends(&[] as &[&str]);
+        assert_eq!(set.len(), 5);
+    }
+
+    #[test]
+    fn is_same_display_across_backends() {
+        let with_output = |monitor: MockMonitor, output: &str| {
+            let mut monitor = Monitor::from_device(monitor);
+            monitor.set_display_output(Some(DisplayOutput {
+                name: output.into(),
+                ..Default::default()
+            }));
+            monitor
+        };
+        // The `winapi` backend doesn't have EDIDs.
+        let nvapi = with_output(
+            MockMonitor::new("A")
+                .with_backend("nvapi")
+                .with_edid([1; 128]),
+            r"\\.\DISPLAY1",
+        );
+        let winapi1 = with_output(
+            MockMonitor::new("Generic PnP Monitor").with_backend("winapi"),
+            r"\\.\DISPLAY1",
+        );
+        let winapi2 = with_output(
+            MockMonitor::new("Generic PnP Monitor").with_backend("winapi"),
+            r"\\.\DISPLAY2",
+        );
+        assert!(super::is_same_display_across_backends(&nvapi, &winapi1));
+        assert!(!super::is_same_display_across_backends(&nvapi, &winapi2));
+        assert!(!super::is_same_display_across_backends(&winapi1, &winapi2));
+
+        let mut set: MonitorSet = vec![nvapi, winapi1, winapi2].into();
+        set.prefer_backends(&["nvapi"]);
+        assert_eq!(set.len(), 2);
+        assert_eq!(set[0].backend(), "nvapi");
+    }
+}
@@ -0,0 +1,249 @@
+use std::time::Duration;
+
+use super::*;
+use crate::logging::*;
+
+/// The number of retries for display monitors behind DisplayPort MST hubs.
+/// See [`Monitor::is_mst()`].
+pub(crate) const MST_RETRIES: u32 = 2;
+
+/// The delay before retrying failed requests.
+pub(crate) const RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Whether the `edid` has the serial number,
+/// which tells display monitors of the same model apart.
+pub(crate) fn edid_has_serial(edid: &[u8]) -> bool {
+    const SERIAL_NUMBER: std::ops::Range<usize> = 12..16;
+    // The display product serial number descriptor.
+    const SERIAL_DESCRIPTOR_TAG: u8 = 0xFF;
+    if edid
+        .get(SERIAL_NUMBER)
+        .is_some_and(|serial| serial.iter().any(|byte| *byte != 0))
+    {
+        return true;
+    }
+    [54, 72, 90, 108].into_iter().any(|offset: usize| {
+        edid.get(offset..offset + 4)
+            .is_some_and(|header| header == [0, 0, 0, SERIAL_DESCRIPTOR_TAG])
+    })
+}
+
+/// Remove display monitors enumerated more than once,
+/// such as those behind DisplayPort MST hubs,
+/// which some drivers expose on the I2C bus of the hub as well.
+/// Display monitors are duplicates if their backends and EDIDs are the same,
+/// and the EDIDs have serial numbers.
+/// The one not behind the MST hub is kept, or the first one if none is.
+pub(crate) fn remove_duplicates(monitors: &mut Vec<Monitor>) {
+    let mut index = 0;
+    while index < monitors.len() {
+        let Some(duplicate) = (index + 1..monitors.len())
+            .find(|other| is_duplicate(&monitors[index], &monitors[*other]))
+        else {
+            index += 1;
+            continue;
+        };
+        let remove = if monitors[index].is_mst() && !monitors[duplicate].is_mst() {
+            index
+        } else {
+            duplicate
+        };
+        let removed = monitors.remove(remove);
+        info!(
+            "Removed {removed}, because it's the same display monitor as {}",
+            monitors[if remove == index {
+                duplicate - 1
+            } else {
+                index
+            }]
+        );
+    }
+    warn_same_edids(monitors);
+}
+
+fn is_duplicate(a: &Monitor, b: &Monitor) -> bool {
+    if a.backend() != b.backend() {
+        return false;
+    }
+    match (a.edid(), b.edid()) {
+        (Some(a), Some(b)) => is_same_edid(a, b) && edid_has_serial(a),
+        _ => false,
+    }
+}
+
+/// Log display monitors that can't be told apart by their EDIDs,
+/// such as display monitors of the same model without serial numbers.
+fn warn_same_edids(monitors: &[Monitor]) {
+    for (index, monitor) in monitors.iter().enumerate() {
+        let Some(edid) = monitor.edid() else {
+            continue;
+        };
+        if let Some(other) = monitors[index + 1..].iter().find(|other| {
+            other.backend() == monitor.backend()
+                && other.edid().is_some_and(|other| is_same_edid(edid, other))
+        }) {
+            info!(
+                "{monitor} and {other} have the same EDID without serial numbers. \
+                They're identified by their IDs, which may change when reconnected."
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create an EDID of the `product` code and the `serial` number.
+    fn edid(product: u16, serial: u32) -> Vec<u8> {
+        let mut edid = vec![0u8; 128];
+        edid[..8].copy_from_slice(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00]);
+        // `DEL`.
+        edid[8..10].copy_from_slice(&[0x10, 0xAC]);
+        edid[10..12].copy_from_slice(&product.to_le_bytes());
+        edid[12..16].copy_from_slice(&serial.to_le_bytes());
+        edid
+    }
+
+    /// Create display monitors from an enumeration,
+    /// a list of the ID, the EDID, and whether it's behind an MST hub.
+    fn enumerate(entries: &[(&str, Vec<u8>, bool)]) -> Vec<Monitor> {
+        entries
+            .iter()
+            .map(|(id, edid, is_mst)| {
+                let mock = MockMonitor::new(*id).with_edid(edid.clone());
+                Monitor::from_device(if *is_mst { mock.with_mst() } else { mock })
+            })
+            .collect()
+    }
+
+    /// Create display monitors from a synthetic enumeration in a fixture file,
+    /// lines of the backend, the ID, the name of the I2C bus, and the EDID in hex,
+    /// separated by tabs.
+    fn enumerate_fixture(fixture: &str) -> Vec<Monitor> {
+        fixture
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let [backend, id, bus_name, edid] = line.split('\t').collect::<Vec<_>>()[..] else {
+                    panic!("Invalid fixture line: {line}");
+                };
+                let edid: Vec<u8> = (0..edid.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&edid[i..i + 2], 16).unwrap())
+                    .collect();
+                let mock = MockMonitor::new(id).with_backend(backend).with_edid(edid);
+                Monitor::from_device(
+                    if backend == "i2c-dev" && crate::ddc_device::is_mst_i2c_bus_name(bus_name) {
+                        mock.with_mst()
+                    } else {
+                        mock
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn ids(monitors: &[Monitor]) -> Vec<&str> {
+        monitors.iter().map(|monitor| monitor.id()).collect()
+    }
+
+    #[test]
+    fn edid_has_serial() {
+        assert!(super::edid_has_serial(&edid(0x1234, 1)));
+        let mut no_serial = edid(0x1234, 0);
+        assert!(!super::edid_has_serial(&no_serial));
+        no_serial[72..76].copy_from_slice(&[0, 0, 0, 0xFF]);
+        assert!(super::edid_has_serial(&no_serial));
+        assert!(!super::edid_has_serial(&[]));
+    }
+
+    #[test]
+    fn remove_duplicates_daisy_chain() {
+        // Two U2723QE daisy-chained on amdgpu.
+        // The first one is also on the I2C bus of the DisplayPort connector.
+        let mut monitors = enumerate(&[
+            ("20739", edid(0x4276, 0x3131_3033), true),
+            ("20740", edid(0x4276, 0x3131_3034), true),
+            ("20741", edid(0x4276, 0x3131_3033), false),
+        ]);
+        remove_duplicates(&mut monitors);
+        assert_eq!(ids(&monitors), ["20740", "20741"]);
+    }
+
+    #[test]
+    fn remove_duplicates_no_serial() {
+        // Two display monitors of the same model without serial numbers
+        // can't be told apart, and both are kept.
+        let mut monitors = enumerate(&[
+            ("20739", edid(0x4276, 0), true),
+            ("20740", edid(0x4276, 0), true),
+        ]);
+        remove_duplicates(&mut monitors);
+        assert_eq!(ids(&monitors), ["20739", "20740"]);
+    }
+
+    #[test]
+    fn is_same_display() {
+        let monitors = enumerate(&[
+            ("20739", edid(0x4276, 0), true),
+            ("20740", edid(0x4276, 0), true),
+            ("20741", edid(0x4276, 1), true),
+        ]);
+        let reconnected = enumerate(&[
+            ("20739", edid(0x4276, 0), true),
+            ("20999", edid(0x4276, 1), true),
+        ]);
+        assert!(monitors[0].is_same_display(&reconnected[0]));
+        assert!(!monitors[1].is_same_display(&reconnected[0]));
+        assert!(monitors[2].is_same_display(&reconnected[1]));
+    }
+
+    #[test]
+    fn fixture_amdgpu_daisy_chain() {
+        let mut monitors = enumerate_fixture(include_str!("mst/amdgpu-daisy-chain.tsv"));
+        assert_eq!(monitors.len(), 3);
+        remove_duplicates(&mut monitors);
+        assert_eq!(ids(&monitors), ["22787", "22794"]);
+        assert!(!monitors[0].is_mst());
+        assert!(monitors[1].is_mst());
+    }
+
+    #[test]
+    fn fixture_i915_dock() {
+        let mut monitors = enumerate_fixture(include_str!("mst/i915-dock.tsv"));
+        remove_duplicates(&mut monitors);
+        assert_eq!(ids(&monitors), ["22788", "22796", "22797"]);
+        assert!(!monitors[0].is_mst());
+        assert!(monitors[1].is_mst());
+        assert!(monitors[2].is_mst());
+    }
+
+    #[test]
+    fn fixture_no_serial() {
+        let mut monitors = enumerate_fixture(include_str!("mst/no-serial.tsv"));
+        remove_duplicates(&mut monitors);
+        assert_eq!(ids(&monitors), ["22787", "22793", "22794"]);
+    }
+
+    /// Failed requests to display monitors behind MST hubs are retried
+    /// [`MST_RETRIES`] times, and others aren't.
+    #[cfg(feature = "cli")]
+    #[test]
+    fn fixture_retries() {
+        let config = Config::default();
+        let mut monitors = enumerate_fixture(include_str!("mst/amdgpu-daisy-chain.tsv"));
+        remove_duplicates(&mut monitors);
+        let failure: SimulatedFailure = format!("nack*{MST_RETRIES}").parse().unwrap();
+        for (index, monitor) in monitors.iter_mut().enumerate() {
+            monitor.set_brightness(10).unwrap();
+            failure.apply(index, monitor, &config);
+        }
+        assert!(monitors[0].brightness().is_err());
+        assert_eq!(monitors[1].brightness().unwrap().value, 10);
+
+        let failure: SimulatedFailure = format!("nack*{}", MST_RETRIES + 1).parse().unwrap();
+        failure.apply(1, &mut monitors[1], &config);
+        assert!(monitors[1].brightness().is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use jiff::Zoned;
+use jiff::civil::Time;
+
+use super::*;
+
+/// The minutes of a day.
+const DAY_MINUTES: i32 = 24 * 60;
+
+/// Shifts the color temperature by the [`NightConfig`] in the `daemon`.
+///
+/// The gains are left as they are until the first shift to the night,
+/// so that starting the `daemon` in the day doesn't overwrite calibrated gains.
+#[derive(Debug)]
+pub(crate) struct NightController {
+    config: NightConfig,
+    start: Time,
+    end: Time,
+    stepped: SteppedController,
+}
+
+impl NightController {
+    pub fn new(config: NightConfig) -> anyhow::Result<Self> {
+        let parse = |time: &str| -> anyhow::Result<Time> {
+            time.parse()
+                .map_err(|e| anyhow::anyhow!("Invalid time \"{time}\": {e}"))
+        };
+        let start = parse(&config.start)?;
+        let end = parse(&config.end)?;
+        if start == end {
+            anyhow::bail!("The `start` and the `end` of `night` are the same");
+        }
+        if let Some(gain) = config
+            .day
+            .iter()
+            .chain(&config.night)
+            .find(|gain| **gain > 100)
+        {
+            anyhow::bail!("The gain {gain}% is more than 100%");
+        }
+        let stepped =
+            SteppedController::new(config.interval_secs, config.step_percent, config.step_ms)
+                .with_last_percent(0);
+        Ok(Self {
+            config,
+            start,
+            end,
+            stepped,
+        })
+    }
+
+    /// The [`SteppedController`] to time and step the adjustments.
+    pub fn stepped(&self) -> &SteppedController {
+        &self.stepped
+    }
+
+    /// How much to shift to the night in percent,
+    /// if it's time to adjust and it changed.
+    pub fn update(&mut self, now: &Zoned) -> Option<u8> {
+        let (start, end) = (self.start, self.end);
+        let transition_mins = self.config.transition_mins;
+        self.stepped
+            .update(|| Some(night_percent(start, end, transition_mins, now.time())))
+    }
+
+    /// The red, green, and blue gains in percent of their maximums,
+    /// when shifted to the night by the `percent`.
+    pub fn gains(&self, percent: u8) -> [u8; 3] {
+        std::array::from_fn(|i| {
+            let day = i32::from(self.config.day[i]);
+            let night = i32::from(self.config.night[i]);
+            (day + (night - day) * i32::from(percent) / 100) as u8
+        })
+    }
+
+    /// The color preset to select before changing the gains.
+    pub fn preset(&self) -> Option<u16> {
+        self.config.preset
+    }
+
+    /// Whether the `monitor` is adjusted, by [`NightConfig::exclude`].
+    pub fn is_target(&self, index: usize, monitor: &Monitor) -> bool {
+        !self
+            .config
+            .exclude
+            .iter()
+            .any(|name| Selector::from_name(name).matches_monitor(index, monitor))
+    }
+}
+
+/// How much to shift to the night in percent at the `time`.
+/// It increases over `transition_mins` after the `start`,
+/// and decreases over `transition_mins` after the `end`.
+/// The transitions are shortened if the night or the day is shorter than them.
+fn night_percent(start: Time, end: Time, transition_mins: u64, time: Time) -> u8 {
+    let minutes = |time: Time| i32::from(time.hour()) * 60 + i32::from(time.minute());
+    let night_len = (minutes(end) - minutes(start)).rem_euclid(DAY_MINUTES);
+    let day_len = DAY_MINUTES - night_len;
+    let transition = (transition_mins.min(DAY_MINUTES as u64) as i32)
+        .min(night_len)
+        .min(day_len);
+    let since_start = (minutes(time) - minutes(start)).rem_euclid(DAY_MINUTES);
+    let percent = if since_start < night_len {
+        if since_start >= transition {
+            100
+        } else {
+            since_start * 100 / transition
+        }
+    } else {
+        let since_end = since_start - night_len;
+        if since_end >= transition {
+            0
+        } else {
+            100 - since_end * 100 / transition
+        }
+    };
+    percent as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: i8, minute: i8) -> Time {
+        Time::constant(hour, minute, 0, 0)
+    }
+
+    #[test]
+    fn night_percent() {
+        let percent =
+            |hour, minute| super::night_percent(time(21, 0), time(7, 0), 60, time(hour, minute));
+        assert_eq!(percent(12, 0), 0);
+        assert_eq!(percent(21, 0), 0);
+        assert_eq!(percent(21, 30), 50);
+        assert_eq!(percent(22, 0), 100);
+        assert_eq!(percent(3, 0), 100);
+        assert_eq!(percent(7, 0), 100);
+        assert_eq!(percent(7, 15), 75);
+        assert_eq!(percent(8, 0), 0);
+        assert_eq!(
+            super::night_percent(time(21, 0), time(21, 30), 60, time(21, 15)),
+            50
+        );
+        assert_eq!(
+            super::night_percent(time(21, 0), time(7, 0), 0, time(21, 0)),
+            100
+        );
+    }
+
+    #[test]
+    fn update() {
+        let noon: Zoned = "2025-01-01T12:00[UTC]".parse().unwrap();
+        let mut controller = NightController::new(NightConfig::default()).unwrap();
+        assert_eq!(controller.update(&noon), None);
+
+        let midnight: Zoned = "2025-01-01T00:00[UTC]".parse().unwrap();
+        let mut controller = NightController::new(NightConfig::default()).unwrap();
+        assert_eq!(controller.update(&midnight), Some(100));
+    }
+
+    #[test]
+    fn gains() {
+        let controller = NightController::new(NightConfig::default()).unwrap();
+        assert_eq!(controller.gains(0), [100, 100, 100]);
+        assert_eq!(controller.gains(50), [100, 90, 80]);
+        assert_eq!(controller.gains(100), [100, 80, 60]);
+        assert!(
+            NightController::new(NightConfig {
+                night: [100, 80, 160],
+                ..Default::default()
+            })
+            .is_err()
+        );
+    }
+}
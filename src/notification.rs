@@ -0,0 +1,135 @@
+use super::*;
+
+/// The summary of the changes made by a command, for notifications,
+/// such as "Desk profile applied: DELL→DP1, LG→Hdmi2".
+/// See [`Cli::change_summary()`].
+#[derive(Clone, Debug, Default)]
+pub struct ChangeSummary {
+    /// The name of the profile applied by the command, if any.
+    pub profile: Option<String>,
+    /// The results of the changes.
+    pub results: Vec<SetResult>,
+}
+
+impl ChangeSummary {
+    /// The summary of the `results`, if they should be notified as a summary;
+    /// i.e., when the `profile` is applied, or more than one display monitor is changed.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{ChangeSummary,Language,SetResult};
+    /// # Language::set_current(Language::English);
+    /// let result = |monitor: &str, requested: &str| SetResult {
+    ///     monitor: monitor.to_string(),
+    ///     requested: requested.to_string(),
+    ///     success: true,
+    ///     ..Default::default()
+    /// };
+    /// let results = [result("DELL", "DP1"), result("LG", "Hdmi2")];
+    /// let summary = ChangeSummary::new(Some("Desk"), &results).unwrap();
+    /// assert_eq!(summary.to_string(), "Desk profile applied: DELL→DP1, LG→Hdmi2");
+    ///
+    /// // A change of a display monitor without profiles isn't summarized.
+    /// assert!(ChangeSummary::new(None, &results[..1]).is_none());
+    /// ```
+    pub fn new(profile: Option<&str>, results: &[SetResult]) -> Option<Self> {
+        if results.is_empty() {
+            return None;
+        }
+        if profile.is_none() {
+            let first = &results[0].monitor;
+            if results.iter().all(|result| result.monitor == *first) {
+                return None;
+            }
+        }
+        Some(Self {
+            profile: profile.map(str::to_string),
+            results: results.to_vec(),
+        })
+    }
+
+    /// Whether all the changes succeeded.
+    pub fn is_success(&self) -> bool {
+        self.results
+            .iter()
+            .all(|result| result.success && !result.rolled_back)
+    }
+
+    /// The title, such as "Desk profile applied".
+    pub fn title(&self) -> String {
+        match &self.profile {
+            Some(profile) => tr_args("{profile} profile applied", &[("profile", profile)]),
+            None => tr("Input sources changed").to_string(),
+        }
+    }
+
+    /// The changes, such as "DELL→DP1, LG→Hdmi2".
+    /// Failed changes are marked.
+    pub fn body(&self) -> String {
+        self.results
+            .iter()
+            .map(|result| {
+                let change = format!("{}→{}", result.monitor, result.requested);
+                if result.rolled_back {
+                    format!("{change} ({})", tr("rolled back"))
+                } else if !result.success {
+                    format!("{change} ({})", tr("failed"))
+                } else {
+                    change
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// The title and the body, separated by `: `.
+impl std::fmt::Display for ChangeSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.title(), self.body())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(monitor: &str, requested: &str, success: bool) -> SetResult {
+        SetResult {
+            monitor: monitor.to_string(),
+            requested: requested.to_string(),
+            success,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn change_summary() {
+        Language::set_current(Language::English);
+        assert!(ChangeSummary::new(Some("Desk"), &[]).is_none());
+        assert!(ChangeSummary::new(None, &[result("A", "DP1", true)]).is_none());
+        // The same display monitor changed twice, such as by toggles.
+        assert!(
+            ChangeSummary::new(
+                None,
+                &[result("A", "DP1", true), result("A", "Hdmi1", true)]
+            )
+            .is_none()
+        );
+
+        let summary = ChangeSummary::new(Some("Desk"), &[result("A", "DP1", true)]).unwrap();
+        assert_eq!(summary.to_string(), "Desk profile applied: A→DP1");
+        assert!(summary.is_success());
+
+        let mut results = vec![result("A", "DP1", true), result("B", "Hdmi2", false)];
+        let summary = ChangeSummary::new(None, &results).unwrap();
+        assert_eq!(
+            summary.to_string(),
+            "Input sources changed: A→DP1, B→Hdmi2 (failed)"
+        );
+        assert!(!summary.is_success());
+
+        results[0].rolled_back = true;
+        let summary = ChangeSummary::new(None, &results).unwrap();
+        assert_eq!(summary.body(), "A→DP1 (rolled back), B→Hdmi2 (failed)");
+    }
+}
@@ -0,0 +1,971 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use super::*;
+use crate::logging::*;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, ValueEnum)]
+/// The format of the display monitor listings.
+pub enum OutputFormat {
+    /// Multi-line descriptions, one display monitor after another.
+    #[default]
+    Plain,
+    /// A table with aligned columns.
+    Table,
+    /// A JSON array of objects.
+    Json,
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
+/// The format of the reports of changes.
+pub enum ReportFormat {
+    /// A JSON array of objects.
+    Json,
+}
+
+#[derive(Debug, Default, Serialize)]
+/// A row of the display monitor listings.
+pub(crate) struct ListItem {
+    pub index: usize,
+    pub id: String,
+    /// The [`Monitor::set_id_format()`] string, if set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub backend: String,
+    pub current_input: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub inputs: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<DisplayOutput>,
+}
+
+impl ListItem {
+    /// Create an item of the `monitor` with its `input_source` read by the caller.
+    pub fn new(
+        index: usize,
+        monitor: &mut Monitor,
+        input_source: anyhow::Result<InputSourceRaw>,
+    ) -> Self {
+        let (current_input, error) = match input_source {
+            Ok(value) => (Some(InputSource::str_with_hex_from_raw(value)), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        ListItem {
+            index,
+            id: monitor.id().to_string(),
+            name: monitor.formatted_id(),
+            model: monitor.model_name().map(str::to_string),
+            serial: monitor.serial(),
+            backend: monitor.backend(),
+            current_input,
+            error,
+            inputs: monitor.input_sources().map(|values| {
+                values
+                    .iter()
+                    .map(|value| InputSource::str_with_hex_from_raw(*value))
+                    .collect()
+            }),
+            output: monitor.display_output().cloned(),
+        }
+    }
+
+    fn current_input_or_error(&self) -> &str {
+        self.current_input
+            .as_deref()
+            .or(self.error.as_deref())
+            .unwrap_or_default()
+    }
+
+    fn output_name(&self) -> &str {
+        self.output
+            .as_ref()
+            .map(|output| output.name.as_str())
+            .unwrap_or_default()
+    }
+
+    fn inputs_string(&self) -> Option<String> {
+        self.inputs.as_ref().map(|inputs| inputs.join(", "))
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+/// The result of changing the input source of a display monitor.
+/// See also [`Cli::set_results()`].
+pub struct SetResult {
+    /// The name of the display monitor.
+    pub monitor: String,
+    #[serde(default)]
+    /// The [`Monitor::id()`] of the display monitor.
+    pub id: String,
+    #[serde(default)]
+    /// The index of the display monitor.
+    /// Display monitors may have the same name and [`SetResult::id`],
+    /// such as "Generic PnP Monitor", but they have different indices.
+    pub index: usize,
+    /// The requested input source.
+    pub requested: String,
+    /// The input source before the change, if it's known.
+    pub previous: Option<String>,
+    /// Whether the change succeeded or not.
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// The error message if the change failed.
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    /// Whether the change was rolled back by `--rollback`.
+    pub rolled_back: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    /// Whether the change was skipped
+    /// because the input source is already the requested one.
+    pub skipped: bool,
+}
+
+impl SetResult {
+    pub(crate) fn new(
+        index: usize,
+        monitor: &Monitor,
+        requested: InputSourceRaw,
+        previous: Option<InputSourceRaw>,
+        result: &anyhow::Result<()>,
+    ) -> Self {
+        SetResult {
+            monitor: monitor.to_string(),
+            id: monitor.id().to_string(),
+            index,
+            requested: InputSource::str_from_raw(requested),
+            previous: previous.map(InputSource::str_from_raw),
+            success: result.is_ok(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+            rolled_back: false,
+            skipped: false,
+        }
+    }
+}
+
+impl SetResult {
+    /// The command line argument to undo the change,
+    /// or `None` if there's nothing to undo.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::SetResult;
+    /// let result = SetResult {
+    ///     monitor: "Dell U2723QE".to_string(),
+    ///     requested: "UsbC1".to_string(),
+    ///     previous: Some("DP1".to_string()),
+    ///     success: true,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(result.undo_arg().unwrap(), "Dell U2723QE=DP1");
+    /// ```
+    pub fn undo_arg(&self) -> Option<String> {
+        self.undo_entry()
+            .map(|entry| format!("{}={}", arg::quote(&entry.monitor), entry.input_source))
+    }
+
+    /// The command line argument to retry the change if it failed,
+    /// or `None` if it succeeded.
+    pub fn retry_arg(&self) -> Option<String> {
+        if self.success {
+            return None;
+        }
+        Some(format!("{}={}", arg::quote(&self.monitor), self.requested))
+    }
+
+    pub(crate) fn undo_entry(&self) -> Option<UndoEntry> {
+        if !self.success || self.rolled_back {
+            return None;
+        }
+        match &self.previous {
+            Some(previous) if *previous != self.requested => Some(UndoEntry {
+                monitor: self.monitor.clone(),
+                id: self.id.clone(),
+                input_source: previous.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// The summary statistics of [`Cli::run()`].
+/// See [`Cli::run_report()`].
+#[derive(Clone, Debug, Default)]
+pub struct RunReport {
+    /// The number of display monitors whose input sources are requested to change.
+    pub monitors: usize,
+    /// The number of changes applied.
+    pub applied: usize,
+    /// The number of changes skipped
+    /// because the input sources are already the requested ones.
+    pub skipped: usize,
+    /// The number of changes failed.
+    pub failures: usize,
+    /// The total elapsed time.
+    pub elapsed: Duration,
+    /// The elapsed time of each stage, such as `setup` or `command`, in order.
+    pub stages: Vec<(&'static str, Duration)>,
+    /// The outcomes of the arguments, in the order they ran.
+    /// Arguments after the one that failed aren't run and not included.
+    pub args: Vec<ArgReport>,
+}
+
+/// The outcome of an argument of [`Cli::args`].
+/// See [`RunReport::args`].
+#[derive(Clone, Debug, Default)]
+pub struct ArgReport {
+    /// The argument, such as `DELL=dp1`.
+    pub arg: String,
+    /// The results of changing the display monitors by the argument.
+    pub results: Vec<SetResult>,
+    /// The error message if the argument failed.
+    pub error: Option<String>,
+}
+
+impl RunReport {
+    /// # Examples
+    /// ```
+    /// # use monitor_input::{RunReport,SetResult};
+    /// # use std::time::Duration;
+    /// let result = |index: usize, success: bool, skipped: bool| SetResult {
+    ///     monitor: "Generic PnP Monitor".to_string(),
+    ///     index,
+    ///     success,
+    ///     skipped,
+    ///     ..Default::default()
+    /// };
+    /// let results = [
+    ///     result(0, true, false),
+    ///     result(0, true, true),
+    ///     result(1, false, false),
+    /// ];
+    /// let report = RunReport::new(&results, Duration::from_secs(1), vec![]);
+    /// assert_eq!(report.monitors, 2);
+    /// assert_eq!(report.applied, 1);
+    /// assert_eq!(report.skipped, 1);
+    /// assert_eq!(report.failures, 1);
+    /// ```
+    pub fn new(
+        results: &[SetResult],
+        elapsed: Duration,
+        stages: Vec<(&'static str, Duration)>,
+    ) -> Self {
+        let monitors: BTreeSet<usize> = results.iter().map(|result| result.index).collect();
+        let count = |f: fn(&SetResult) -> bool| results.iter().filter(|result| f(result)).count();
+        Self {
+            monitors: monitors.len(),
+            applied: count(|result| result.success && !result.skipped && !result.rolled_back),
+            skipped: count(|result| result.skipped),
+            failures: count(|result| !result.success),
+            elapsed,
+            stages,
+            args: Vec::new(),
+        }
+    }
+
+    /// Log the summary and the time of the stages.
+    pub(crate) fn log(&self) {
+        debug!(
+            "{monitors} monitors, {applied} applied, {skipped} skipped, {failures} failed in {elapsed:?}",
+            monitors = self.monitors,
+            applied = self.applied,
+            skipped = self.skipped,
+            failures = self.failures,
+            elapsed = self.elapsed
+        );
+        for (name, elapsed) in &self.stages {
+            debug!("  {name}: {elapsed:?}");
+        }
+    }
+}
+
+/// Write `results` to `writer` in the `format`.
+pub(crate) fn write_report(
+    writer: &mut dyn std::io::Write,
+    format: ReportFormat,
+    results: &[SetResult],
+) -> anyhow::Result<()> {
+    match format {
+        ReportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *writer, results)?;
+            writeln!(writer)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the changes that the `results` would make, for `--dry-run`,
+/// such as `DELL: DP1 -> Hdmi1 (would change)`.
+pub(crate) fn write_dry_run(
+    writer: &mut dyn std::io::Write,
+    results: &[SetResult],
+) -> anyhow::Result<()> {
+    for result in results {
+        let previous = result.previous.as_deref().unwrap_or("?");
+        match &result.error {
+            Some(error) => writeln!(
+                writer,
+                "{}: {previous} -> {} (would fail: {error})",
+                result.monitor, result.requested
+            )?,
+            None if previous == result.requested => {
+                writeln!(writer, "{}: {previous} (no change)", result.monitor)?
+            }
+            None => writeln!(
+                writer,
+                "{}: {previous} -> {} (would change)",
+                result.monitor, result.requested
+            )?,
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, Serialize)]
+/// Whether the display monitors are in a profile, for the `status` command.
+pub(crate) struct Status {
+    pub profile: String,
+    pub matches: bool,
+    pub monitors: Vec<StatusItem>,
+}
+
+#[derive(Debug, Default, Serialize)]
+/// Whether a display monitor is in the input source of a profile.
+pub(crate) struct StatusItem {
+    pub monitor: String,
+    pub current: Option<String>,
+    pub expected: String,
+    pub matches: bool,
+}
+
+#[derive(Debug, Serialize)]
+/// The build information for `--version --json`.
+pub(crate) struct VersionInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    /// The enabled cargo features.
+    pub features: Vec<&'static str>,
+    /// The backends available on this platform.
+    pub backends: Vec<String>,
+    pub os: &'static str,
+    pub arch: &'static str,
+    /// `debug` or `release`.
+    pub profile: &'static str,
+}
+
+impl VersionInfo {
+    pub fn new() -> Self {
+        let features = [
+            ("cli", cfg!(feature = "cli")),
+            ("console", cfg!(feature = "console")),
+            ("gui", cfg!(feature = "gui")),
+            ("serde", cfg!(feature = "serde")),
+            ("tracing", cfg!(feature = "tracing")),
+            ("webhook", cfg!(feature = "webhook")),
+            ("websocket", cfg!(feature = "websocket")),
+            ("winapp", cfg!(feature = "winapp")),
+        ];
+        let mut backends: Vec<String> = ddc_hi::Backend::values()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        if cfg!(target_os = "linux") {
+            backends.push("ddcutil".to_string());
+        }
+        Self {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            features: features
+                .into_iter()
+                .filter_map(|(name, is_enabled)| is_enabled.then_some(name))
+                .collect(),
+            backends,
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            profile: if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            },
+        }
+    }
+}
+
+/// Write the self-test `reports` to `writer` in the `format`.
+/// The `json` format writes a JSON array, and others write lines.
+pub(crate) fn write_self_test(
+    writer: &mut dyn std::io::Write,
+    format: OutputFormat,
+    reports: &[SelfTestReport],
+) -> anyhow::Result<()> {
+    if format == OutputFormat::Json {
+        serde_json::to_writer_pretty(&mut *writer, reports)?;
+        writeln!(writer)?;
+        return Ok(());
+    }
+    for report in reports {
+        writeln!(writer, "{} ({})", report.monitor, report.backend)?;
+        for check in &report.checks {
+            let status = match check.status {
+                CheckStatus::Pass => "PASS",
+                CheckStatus::Fail => "FAIL",
+                CheckStatus::Skip => "SKIP",
+            };
+            let latency = check
+                .latency_ms
+                .map(|ms| format!(" ({ms} ms)"))
+                .unwrap_or_default();
+            writeln!(
+                writer,
+                "    {status}  {}: {}{latency}",
+                check.name, check.detail
+            )?;
+            if let Some(hint) = check.hint {
+                writeln!(writer, "          {hint}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write the probe `reports` to `writer` in the `format`.
+/// The `json` format writes a JSON array, and others write lines.
+pub(crate) fn write_probe(
+    writer: &mut dyn std::io::Write,
+    format: OutputFormat,
+    reports: &[ProbeReport],
+) -> anyhow::Result<()> {
+    if format == OutputFormat::Json {
+        serde_json::to_writer_pretty(&mut *writer, reports)?;
+        writeln!(writer)?;
+        return Ok(());
+    }
+    for report in reports {
+        let version = report
+            .mccs_version
+            .as_ref()
+            .map(|version| format!(", MCCS {version}"))
+            .unwrap_or_default();
+        let firmware = report
+            .firmware_level
+            .as_ref()
+            .map(|level| format!(", firmware {level}"))
+            .unwrap_or_default();
+        writeln!(
+            writer,
+            "{} ({}{version}{firmware})",
+            report.monitor, report.backend
+        )?;
+        for feature in &report.features {
+            let values = if feature.values.is_empty() {
+                String::new()
+            } else {
+                let values: Vec<String> = feature
+                    .values
+                    .iter()
+                    .map(|value| format!("{value:02X}"))
+                    .collect();
+                format!(" [{}]", values.join(" "))
+            };
+            let unlisted = if feature.in_capabilities == Some(false) {
+                " (not in the capabilities)"
+            } else {
+                ""
+            };
+            writeln!(
+                writer,
+                "    {code:02X}  {name}: {value} / {maximum}{values}{unlisted}",
+                code = feature.code,
+                name = feature.display_name(),
+                value = feature.value,
+                maximum = feature.maximum,
+            )?;
+        }
+        if !report.unreadable.is_empty() {
+            let codes: Vec<String> = report
+                .unreadable
+                .iter()
+                .map(|code| format!("{code:02X}"))
+                .collect();
+            writeln!(
+                writer,
+                "    In the capabilities but failed to read: {}",
+                codes.join(" ")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the `status` to `writer` in the `format`.
+/// The `json` format writes a JSON object, and others write lines.
+pub(crate) fn write_status(
+    writer: &mut dyn std::io::Write,
+    format: OutputFormat,
+    status: &Status,
+) -> anyhow::Result<()> {
+    if format == OutputFormat::Json {
+        serde_json::to_writer_pretty(&mut *writer, status)?;
+        writeln!(writer)?;
+        return Ok(());
+    }
+    for item in &status.monitors {
+        let current = item.current.as_deref().unwrap_or("?");
+        if item.matches {
+            writeln!(writer, "{}: {current} {}", item.monitor, tr("(matches)"))?;
+        } else {
+            writeln!(
+                writer,
+                "{}: {current} {}",
+                item.monitor,
+                tr_args("(expected {expected})", &[("expected", &item.expected)])
+            )?;
+        }
+    }
+    writeln!(
+        writer,
+        "{}: {}",
+        status.profile,
+        tr(if status.matches { "yes" } else { "no" })
+    )?;
+    Ok(())
+}
+
+/// Write the history `entries` to `writer` in the `format`.
+/// The `json` format writes a JSON array, and others write lines.
+pub(crate) fn write_history(
+    writer: &mut dyn std::io::Write,
+    format: OutputFormat,
+    entries: &[HistoryEntry],
+) -> anyhow::Result<()> {
+    if format == OutputFormat::Json {
+        serde_json::to_writer_pretty(&mut *writer, entries)?;
+        writeln!(writer)?;
+        return Ok(());
+    }
+    for entry in entries {
+        writeln!(
+            writer,
+            "{time}\t{monitor}: {previous} -> {input_source} ({trigger})",
+            time = entry.local_time(),
+            monitor = entry.monitor,
+            previous = entry.previous.as_deref().unwrap_or("?"),
+            input_source = entry.input_source,
+            trigger = entry.trigger
+        )?;
+    }
+    Ok(())
+}
+
+/// Write `items` to `writer` in the `format`.
+pub(crate) fn write_list(
+    writer: &mut dyn std::io::Write,
+    format: OutputFormat,
+    items: &[ListItem],
+) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Plain => write_plain(writer, items),
+        OutputFormat::Table => write_table(writer, items),
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *writer, items)?;
+            writeln!(writer)?;
+            Ok(())
+        }
+        OutputFormat::Csv => write_csv(writer, items),
+    }
+}
+
+fn write_plain(writer: &mut dyn std::io::Write, items: &[ListItem]) -> anyhow::Result<()> {
+    for item in items {
+        let mut lines = vec![
+            format!("{}: {}", item.index, item.name.as_ref().unwrap_or(&item.id)),
+            format!("{}: {}", tr("Input Source"), item.current_input_or_error()),
+        ];
+        if let Some(inputs) = item.inputs_string() {
+            lines.push(format!("{}: {inputs}", tr("Input Sources")));
+        }
+        if let Some(model) = &item.model {
+            lines.push(format!("{}: {model}", tr("Model")));
+        }
+        if let Some(output) = &item.output {
+            lines.push(format!("{}: {output}", tr("Output")));
+        }
+        lines.push(format!("{}: {}", tr("Backend"), item.backend));
+        writeln!(writer, "{}", lines.join("\n    "))?;
+    }
+    Ok(())
+}
+
+fn write_table(writer: &mut dyn std::io::Write, items: &[ListItem]) -> anyhow::Result<()> {
+    // The "Output" column only when it's available, to keep the table compact.
+    let has_output = items.iter().any(|item| item.output.is_some());
+    let mut header = vec!["#", "ID", "Model", "Backend"];
+    if has_output {
+        header.push("Output");
+    }
+    header.extend(["Input", "Inputs"]);
+    let mut rows: Vec<Vec<String>> = vec![header.iter().map(|s| s.to_string()).collect()];
+    for item in items {
+        let mut row = vec![
+            item.index.to_string(),
+            item.id.clone(),
+            item.model.clone().unwrap_or_default(),
+            item.backend.clone(),
+        ];
+        if has_output {
+            row.push(item.output_name().to_string());
+        }
+        row.extend([
+            item.current_input_or_error().to_string(),
+            item.inputs_string().unwrap_or_default(),
+        ]);
+        rows.push(row);
+    }
+    for line in align_columns(&rows) {
+        writeln!(writer, "{line}")?;
+    }
+    Ok(())
+}
+
+fn write_csv(writer: &mut dyn std::io::Write, items: &[ListItem]) -> anyhow::Result<()> {
+    writeln!(
+        writer,
+        "index,id,model,serial,backend,current_input,inputs,output"
+    )?;
+    for item in items {
+        let fields = [
+            item.index.to_string(),
+            item.id.clone(),
+            item.model.clone().unwrap_or_default(),
+            item.serial.clone().unwrap_or_default(),
+            item.backend.clone(),
+            item.current_input.clone().unwrap_or_default(),
+            item.inputs_string().unwrap_or_default(),
+            item.output_name().to_string(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| csv_escape(field)).collect();
+        writeln!(writer, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+/// Quote a CSV field if needed, as defined in RFC 4180.
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", field.replace('"', "\"\""));
+    }
+    field.to_string()
+}
+
+/// Pad cells so that columns are aligned.
+/// The last column isn't padded to avoid trailing spaces.
+fn align_columns(rows: &[Vec<String>]) -> Vec<String> {
+    let mut widths: Vec<usize> = vec![];
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            let width = cell.chars().count();
+            if i >= widths.len() {
+                widths.push(width);
+            } else {
+                widths[i] = widths[i].max(width);
+            }
+        }
+    }
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .map(|(i, cell)| {
+                    if i + 1 == row.len() {
+                        cell.to_string()
+                    } else {
+                        format!("{cell:width$}", width = widths[i])
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item() -> ListItem {
+        ListItem {
+            index: 1,
+            id: "Dell U2723QE".to_string(),
+            model: Some("U2723QE".to_string()),
+            backend: "winapi".to_string(),
+            current_input: Some("DP1".to_string()),
+            inputs: Some(vec!["DP1".to_string(), "Hdmi1".to_string()]),
+            ..Default::default()
+        }
+    }
+
+    fn to_string(format: OutputFormat, items: &[ListItem]) -> String {
+        let mut buffer = Vec::new();
+        write_list(&mut buffer, format, items).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn write_status() {
+        Language::set_current(Language::English);
+        let item = |monitor: &str, current: Option<&str>, matches: bool| StatusItem {
+            monitor: monitor.to_string(),
+            current: current.map(str::to_string),
+            expected: "DP1".to_string(),
+            matches,
+        };
+        let status = Status {
+            profile: "work".to_string(),
+            matches: false,
+            monitors: vec![
+                item("DELL", Some("DP1"), true),
+                item("LG", Some("Hdmi1"), false),
+                item("BenQ", None, false),
+            ],
+        };
+        let mut buffer = Vec::new();
+        super::write_status(&mut buffer, OutputFormat::Plain, &status).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "DELL: DP1 (matches)\n\
+            LG: Hdmi1 (expected DP1)\n\
+            BenQ: ? (expected DP1)\n\
+            work: no\n"
+        );
+    }
+
+    #[test]
+    fn write_probe() {
+        let mut monitor = Monitor::from_device(
+            MockMonitor::new("A")
+                .with_capabilities("(vcp(10 12 60(0F 11))mccs_ver(2.1))")
+                .with_input_source(0x11),
+        );
+        monitor.set_vcp_feature(0xE2, 3).unwrap();
+        let report = ProbeReport::run(&mut monitor, 0x00..=0xFF);
+        let mut buffer = Vec::new();
+        super::write_probe(&mut buffer, OutputFormat::Plain, &[report]).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "A (mock, MCCS 2.1)\n    \
+            60  Input Select: 17 / 65535 [0F 11]\n    \
+            E2  Manufacturer specific: 3 / 65535 (not in the capabilities)\n    \
+            In the capabilities but failed to read: 10 12\n"
+        );
+    }
+
+    #[test]
+    fn write_dry_run() {
+        let result = |monitor: &str, previous: Option<&str>, error: Option<&str>| SetResult {
+            monitor: monitor.to_string(),
+            requested: "Hdmi1".to_string(),
+            previous: previous.map(str::to_string),
+            success: error.is_none(),
+            error: error.map(str::to_string),
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        super::write_dry_run(
+            &mut buffer,
+            &[
+                result("DELL", Some("DP1"), None),
+                result("LG", Some("Hdmi1"), None),
+                result("BenQ", None, Some("Unsupported")),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "DELL: DP1 -> Hdmi1 (would change)\n\
+            LG: Hdmi1 (no change)\n\
+            BenQ: ? -> Hdmi1 (would fail: Unsupported)\n"
+        );
+    }
+
+    #[test]
+    fn align_columns() {
+        let rows: Vec<Vec<String>> = [["a", "bbb", "c"], ["aaa", "b", ""]]
+            .iter()
+            .map(|row| row.iter().map(|s| s.to_string()).collect())
+            .collect();
+        assert_eq!(super::align_columns(&rows), ["a    bbb  c", "aaa  b"]);
+    }
+
+    #[test]
+    fn write_plain() {
+        Language::set_current(Language::English);
+        assert_eq!(
+            to_string(OutputFormat::Plain, &[item()]),
+            "1: Dell U2723QE\n    Input Source: DP1\n    Input Sources: DP1, Hdmi1\n    \
+             Model: U2723QE\n    Backend: winapi\n"
+        );
+    }
+
+    #[test]
+    fn write_table() {
+        assert_eq!(
+            to_string(OutputFormat::Table, &[item()]),
+            "#  ID            Model    Backend  Input  Inputs\n\
+             1  Dell U2723QE  U2723QE  winapi   DP1    DP1, Hdmi1\n"
+        );
+    }
+
+    #[test]
+    fn write_table_with_output() {
+        Language::set_current(Language::English);
+        let with_output = || ListItem {
+            output: Some(DisplayOutput {
+                name: r"\\.\DISPLAY1".to_string(),
+                width: 2560,
+                height: 1440,
+                ..Default::default()
+            }),
+            ..item()
+        };
+        assert_eq!(
+            to_string(OutputFormat::Table, &[with_output(), item()]),
+            "#  ID            Model    Backend  Output        Input  Inputs\n\
+             1  Dell U2723QE  U2723QE  winapi   \\\\.\\DISPLAY1  DP1    DP1, Hdmi1\n\
+             1  Dell U2723QE  U2723QE  winapi                 DP1    DP1, Hdmi1\n"
+        );
+        assert!(
+            to_string(OutputFormat::Plain, &[with_output()])
+                .contains("\n    Output: \\\\.\\DISPLAY1 2560x1440+0+0\n")
+        );
+    }
+
+    #[test]
+    fn write_csv() {
+        assert_eq!(
+            to_string(OutputFormat::Csv, &[item()]),
+            "index,id,model,serial,backend,current_input,inputs,output\n\
+             1,Dell U2723QE,U2723QE,,winapi,DP1,\"DP1, Hdmi1\",\n"
+        );
+    }
+
+    #[test]
+    fn csv_escape() {
+        assert_eq!(super::csv_escape("abc"), "abc");
+        assert_eq!(super::csv_escape("a,b"), "\"a,b\"");
+        assert_eq!(super::csv_escape("a\"b"), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn write_report() {
+        let results = [
+            SetResult {
+                monitor: "A".to_string(),
+                requested: "DP1".to_string(),
+                previous: Some("Hdmi1".to_string()),
+                success: true,
+                ..Default::default()
+            },
+            SetResult {
+                monitor: "B".to_string(),
+                requested: "DP1".to_string(),
+                error: Some("failed".to_string()),
+                ..Default::default()
+            },
+        ];
+        let mut buffer = Vec::new();
+        super::write_report(&mut buffer, ReportFormat::Json, &results).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json[0]["previous"], "Hdmi1");
+        assert_eq!(json[0]["success"], true);
+        assert!(json[0].get("error").is_none());
+        assert_eq!(json[1]["previous"], serde_json::Value::Null);
+        assert_eq!(json[1]["success"], false);
+        assert_eq!(json[1]["error"], "failed");
+    }
+
+    #[test]
+    fn undo_arg() {
+        let mut result = SetResult {
+            monitor: "A".to_string(),
+            requested: "DP1".to_string(),
+            previous: Some("Hdmi1".to_string()),
+            success: true,
+            ..Default::default()
+        };
+        assert_eq!(result.undo_arg().unwrap(), "A=Hdmi1");
+        result.previous = Some("DP1".to_string());
+        assert!(result.undo_arg().is_none());
+        result.previous = None;
+        assert!(result.undo_arg().is_none());
+        result.previous = Some("Hdmi1".to_string());
+        result.success = false;
+        assert!(result.undo_arg().is_none());
+
+        // Special characters in the name are escaped to round-trip.
+        result.monitor = r#"A=1, "B": C?"#.to_string();
+        result.success = true;
+        let undo_arg = result.undo_arg().unwrap();
+        let Ok(Arg::Set { name, values, .. }) = undo_arg.parse::<Arg>() else {
+            panic!("{undo_arg}");
+        };
+        assert_eq!(name, result.monitor);
+        assert_eq!(values, ["Hdmi1"]);
+    }
+
+    #[test]
+    fn retry_arg() {
+        let mut result = SetResult {
+            monitor: "A=1".to_string(),
+            requested: "DP1".to_string(),
+            previous: Some("Hdmi1".to_string()),
+            success: true,
+            ..Default::default()
+        };
+        assert!(result.retry_arg().is_none());
+        result.success = false;
+        assert_eq!(result.retry_arg().unwrap(), r"A\=1=DP1");
+    }
+
+    #[test]
+    fn write_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&to_string(OutputFormat::Json, &[item()])).unwrap();
+        assert_eq!(json[0]["id"], "Dell U2723QE");
+        assert_eq!(json[0]["current_input"], "DP1");
+        assert!(json[0].get("error").is_none());
+    }
+
+    #[test]
+    fn write_history() {
+        let entry = HistoryEntry {
+            time: "2026-01-02T03:04:05Z".into(),
+            monitor: "A".into(),
+            previous: Some("DP1".into()),
+            input_source: "Hdmi1".into(),
+            trigger: "schedule".into(),
+        };
+        let mut output = vec![];
+        super::write_history(&mut output, OutputFormat::Plain, &[entry]).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("2026-01-0"));
+        assert!(output.ends_with("\tA: DP1 -> Hdmi1 (schedule)\n"));
+    }
+
+    #[test]
+    fn version_info() {
+        let info = VersionInfo::new();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(info.features.contains(&"cli"));
+        assert!(info.features.contains(&"serde"));
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["name"], "monitor-input");
+        assert!(json["backends"].is_array());
+    }
+}
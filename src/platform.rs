@@ -0,0 +1,59 @@
+//! Services that differ by platforms, behind the [`Platform`] trait,
+//! so that their callers don't need `cfg` attributes.
+use std::sync::mpsc;
+use std::time::Duration;
+
+use super::*;
+
+#[cfg(not(any(windows, target_os = "macos")))]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(windows)]
+pub(crate) mod windows;
+
+#[cfg(not(any(windows, target_os = "macos")))]
+use linux::Linux as Native;
+#[cfg(target_os = "macos")]
+use macos::MacOs as Native;
+#[cfg(windows)]
+use windows::Windows as Native;
+
+/// The platform-specific services.
+/// Use [`native()`] to get the one for the current platform.
+pub(crate) trait Platform {
+    /// Show a desktop notification of the `title` and the `body`.
+    fn notify(&self, title: &str, body: &str) -> anyhow::Result<()>;
+
+    /// Add the autostart entry to run the `args` when the current user signs in.
+    fn enable_autostart(&self, args: &[String]) -> anyhow::Result<()>;
+
+    /// Remove the autostart entry added by [`Platform::enable_autostart()`].
+    /// It's not an error if it doesn't exist.
+    fn disable_autostart(&self) -> anyhow::Result<()>;
+
+    /// The location of the autostart entry if it's enabled.
+    fn autostart_status(&self) -> Option<String>;
+
+    /// Send [`DockEvent`]s to the `sender`
+    /// when the display configuration, devices, or the power source change.
+    fn listen_hotplug(&self, sender: mpsc::Sender<DaemonEvent>) -> anyhow::Result<()>;
+
+    /// The duration since the last user input,
+    /// or `None` if it's not available.
+    fn idle_time(&self) -> Option<Duration>;
+
+    /// Send [`SessionEvent`]s to the `sender`
+    /// when the session is locked or unlocked, or the lid is closed or opened,
+    /// until the returned [`SessionListener`] is dropped.
+    fn listen_session(&self, sender: mpsc::Sender<DaemonEvent>) -> anyhow::Result<SessionListener>;
+
+    /// Whether the USB device of the vendor ID and the product ID is connected,
+    /// or `None` if it's not available.
+    fn is_usb_device_connected(&self, vendor_id: u16, product_id: u16) -> Option<bool>;
+}
+
+/// The [`Platform`] of the current platform.
+pub(crate) fn native() -> &'static dyn Platform {
+    &Native
+}
@@ -0,0 +1,329 @@
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::Duration;
+
+use anyhow::Context;
+
+use super::*;
+use crate::logging::*;
+
+/// The interval to check the DRM connectors and the power supplies
+/// for [`Linux::listen_hotplug()`].
+const HOTPLUG_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Linux, and other platforms that follow the XDG specifications.
+pub(crate) struct Linux;
+
+impl Linux {
+    /// The XDG autostart desktop entry file.
+    fn autostart_path() -> anyhow::Result<PathBuf> {
+        let Some(dir) = dirs::config_dir() else {
+            anyhow::bail!("The configuration directory is not found");
+        };
+        Ok(dir
+            .join("autostart")
+            .join(format!("{}.desktop", autostart::AUTOSTART_NAME)))
+    }
+
+    /// The DRM connectors and their statuses, such as `card1-DP-1` and `connected`.
+    fn drm_connectors() -> Vec<(String, String)> {
+        let Ok(entries) = std::fs::read_dir("/sys/class/drm") else {
+            return vec![];
+        };
+        let mut connectors: Vec<(String, String)> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let status = std::fs::read_to_string(entry.path().join("status")).ok()?;
+                Some((
+                    entry.file_name().to_string_lossy().into_owned(),
+                    status.trim().to_string(),
+                ))
+            })
+            .collect();
+        connectors.sort();
+        connectors
+    }
+
+    /// Whether any of the `Mains` power supplies is online,
+    /// or `None` if there are no `Mains` power supplies, such as desktop computers.
+    fn is_on_ac_power() -> Option<bool> {
+        let entries = std::fs::read_dir("/sys/class/power_supply").ok()?;
+        let read = |path: &std::path::Path, name: &str| {
+            std::fs::read_to_string(path.join(name)).unwrap_or_default()
+        };
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| read(path, "type").trim() == "Mains")
+            .map(|path| read(&path, "online").trim() == "1")
+            .reduce(|a, b| a || b)
+    }
+
+    /// The ID of the `systemd-logind` session of this process.
+    /// This is the `XDG_SESSION_ID`, or the graphical session of the user by `loginctl`
+    /// if it's not set, such as when started by systemd user units.
+    fn session_id() -> Option<String> {
+        if let Ok(id) = std::env::var("XDG_SESSION_ID") {
+            return Some(id);
+        }
+        let id = Command::new("loginctl")
+            .args(["show-user", "--property=Display", "--value"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|id| !id.is_empty());
+        match &id {
+            Some(id) => {
+                info!("`XDG_SESSION_ID` isn't set, using the session \"{id}\" of `loginctl`.")
+            }
+            None => warn!(
+                "`XDG_SESSION_ID` isn't set and `loginctl` found no graphical sessions, \
+                 lock events of all sessions are used."
+            ),
+        }
+        id
+    }
+}
+
+impl Platform for Linux {
+    /// Runs the `notify-send` command.
+    fn notify(&self, title: &str, body: &str) -> anyhow::Result<()> {
+        let status = Command::new("notify-send")
+            .args(["--app-name=monitor-input", title, body])
+            .status()?;
+        if !status.success() {
+            anyhow::bail!("notify-send failed: {status}");
+        }
+        Ok(())
+    }
+
+    fn enable_autostart(&self, args: &[String]) -> anyhow::Result<()> {
+        autostart::write_file(
+            &Self::autostart_path()?,
+            &autostart::xdg_desktop_entry(args),
+        )
+    }
+
+    fn disable_autostart(&self) -> anyhow::Result<()> {
+        autostart::remove_file(&Self::autostart_path()?)
+    }
+
+    fn autostart_status(&self) -> Option<String> {
+        let path = Self::autostart_path().ok()?;
+        path.exists().then(|| path.display().to_string())
+    }
+
+    /// Checks the statuses of the DRM connectors and the power supplies in `/sys`
+    /// periodically, which doesn't need DDC/CI.
+    fn listen_hotplug(&self, sender: mpsc::Sender<DaemonEvent>) -> anyhow::Result<()> {
+        std::thread::spawn(move || {
+            let mut connectors = Self::drm_connectors();
+            let mut on_ac = None;
+            loop {
+                let current = Self::is_on_ac_power();
+                if current.is_some() && current != on_ac {
+                    on_ac = current;
+                    let event = DockEvent::Power(current.unwrap_or_default());
+                    if sender.send(DaemonEvent::Dock(event)).is_err() {
+                        return;
+                    }
+                }
+                std::thread::sleep(HOTPLUG_INTERVAL);
+                let current = Self::drm_connectors();
+                if current != connectors {
+                    debug!("DRM connectors: {current:?}");
+                    connectors = current;
+                    let event = DaemonEvent::Dock(DockEvent::DisplayChange);
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Uses the `xprintidle` command if it's installed.
+    fn idle_time(&self) -> Option<Duration> {
+        let output = Command::new("xprintidle").output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let millis = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .ok()?;
+        Some(Duration::from_millis(millis))
+    }
+
+    /// Reads signals of `systemd-logind` by the `gdbus monitor` command.
+    /// Lock events are only for the session of [`Linux::session_id()`],
+    /// or of all sessions if it's not found.
+    fn listen_session(&self, sender: mpsc::Sender<DaemonEvent>) -> anyhow::Result<SessionListener> {
+        let session_path = Self::session_id().map(|id| logind_session_path(&id));
+        let mut child = Command::new("gdbus")
+            .args(["monitor", "--system", "--dest", "org.freedesktop.login1"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("Failed to run `gdbus`, which is needed for the `session` configuration")?;
+        let stdout = child.stdout.take().unwrap();
+        let child = Arc::new(Mutex::new(Some(child)));
+        let listener = SessionListener {
+            stop: Some(Box::new({
+                let child = child.clone();
+                move || {
+                    if let Some(child) = child.lock().unwrap().take() {
+                        stop_child(child);
+                    }
+                }
+            })),
+        };
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                trace!("gdbus: {line}");
+                if let Some(event) = parse_gdbus_line(&line, session_path.as_deref()) {
+                    debug!("{event:?}");
+                    if sender.send(DaemonEvent::Session(event)).is_err() {
+                        break;
+                    }
+                }
+            }
+            // The child is taken by the `SessionListener` if it's dropped.
+            if let Some(child) = child.lock().unwrap().take() {
+                stop_child(child);
+                warn!("`gdbus monitor` stopped, the `session` configuration doesn't work anymore.");
+            }
+        });
+        Ok(listener)
+    }
+
+    /// Checks the devices in `/sys/bus/usb/devices`.
+    fn is_usb_device_connected(&self, vendor_id: u16, product_id: u16) -> Option<bool> {
+        let read = |path: PathBuf| -> Option<u16> {
+            u16::from_str_radix(std::fs::read_to_string(path).ok()?.trim(), 16).ok()
+        };
+        let entries = std::fs::read_dir("/sys/bus/usb/devices").ok()?;
+        Some(entries.flatten().any(|entry| {
+            let dir = entry.path();
+            read(dir.join("idVendor")) == Some(vendor_id)
+                && read(dir.join("idProduct")) == Some(product_id)
+        }))
+    }
+}
+
+fn stop_child(mut child: Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// The D-Bus object path of the `systemd-logind` session of the `id`,
+/// escaped as `sd_bus_path_encode()`.
+fn logind_session_path(id: &str) -> String {
+    let mut path = String::from("/org/freedesktop/login1/session/");
+    for (i, c) in id.chars().enumerate() {
+        if c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit()) {
+            path.push(c);
+        } else {
+            let mut buffer = [0u8; 4];
+            for byte in c.encode_utf8(&mut buffer).bytes() {
+                path.push_str(&format!("_{byte:02x}"));
+            }
+        }
+    }
+    path
+}
+
+/// Parse a line of `gdbus monitor` for the `systemd-logind`.
+/// Lock events are only for the `session_path` if specified.
+fn parse_gdbus_line(line: &str, session_path: Option<&str>) -> Option<SessionEvent> {
+    let (path, message) = line.split_once(": ")?;
+    if path == "/org/freedesktop/login1" {
+        if message.contains("'LidClosed': <true>") {
+            return Some(SessionEvent::LidClose);
+        }
+        if message.contains("'LidClosed': <false>") {
+            return Some(SessionEvent::LidOpen);
+        }
+        return None;
+    }
+    if !path.starts_with("/org/freedesktop/login1/session/")
+        || session_path.is_some_and(|session_path| path != session_path)
+    {
+        return None;
+    }
+    if message.starts_with("org.freedesktop.login1.Session.Lock ")
+        || message.contains("'LockedHint': <true>")
+    {
+        return Some(SessionEvent::Lock);
+    }
+    if message.starts_with("org.freedesktop.login1.Session.Unlock ")
+        || message.contains("'LockedHint': <false>")
+    {
+        return Some(SessionEvent::Unlock);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logind_session_path() {
+        assert_eq!(
+            super::logind_session_path("2"),
+            "/org/freedesktop/login1/session/_32"
+        );
+        assert_eq!(
+            super::logind_session_path("c12"),
+            "/org/freedesktop/login1/session/c12"
+        );
+    }
+
+    #[test]
+    fn parse_gdbus_line() {
+        let session = Some("/org/freedesktop/login1/session/_32");
+        let parse = |line| super::parse_gdbus_line(line, session);
+        assert_eq!(
+            parse("/org/freedesktop/login1/session/_32: org.freedesktop.login1.Session.Lock ()"),
+            Some(SessionEvent::Lock)
+        );
+        assert_eq!(
+            parse("/org/freedesktop/login1/session/_33: org.freedesktop.login1.Session.Lock ()"),
+            None
+        );
+        assert_eq!(
+            parse(
+                "/org/freedesktop/login1/session/_32: org.freedesktop.DBus.Properties.PropertiesChanged \
+                ('org.freedesktop.login1.Session', {'LockedHint': <false>}, @as [])"
+            ),
+            Some(SessionEvent::Unlock)
+        );
+        assert_eq!(
+            parse(
+                "/org/freedesktop/login1: org.freedesktop.DBus.Properties.PropertiesChanged \
+                ('org.freedesktop.login1.Manager', {'LidClosed': <true>}, @as [])"
+            ),
+            Some(SessionEvent::LidClose)
+        );
+        assert_eq!(
+            parse(
+                "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (true,)"
+            ),
+            None
+        );
+        assert_eq!(
+            super::parse_gdbus_line(
+                "/org/freedesktop/login1/session/_33: org.freedesktop.login1.Session.Unlock ()",
+                None
+            ),
+            Some(SessionEvent::Unlock)
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use super::*;
+use crate::logging::*;
+
+pub(crate) struct MacOs;
+
+impl MacOs {
+    /// The LaunchAgent property list file.
+    fn autostart_path() -> anyhow::Result<PathBuf> {
+        let Some(dir) = dirs::home_dir() else {
+            anyhow::bail!("The home directory is not found");
+        };
+        Ok(dir
+            .join("Library")
+            .join("LaunchAgents")
+            .join(format!("{}.plist", autostart::LAUNCH_AGENT_LABEL)))
+    }
+}
+
+impl Platform for MacOs {
+    /// Runs `display notification` by the `osascript` command.
+    fn notify(&self, title: &str, body: &str) -> anyhow::Result<()> {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        let script = format!(
+            "display notification {} with title {}",
+            quote(body),
+            quote(title)
+        );
+        let status = Command::new("osascript").args(["-e", &script]).status()?;
+        if !status.success() {
+            anyhow::bail!("osascript failed: {status}");
+        }
+        Ok(())
+    }
+
+    fn enable_autostart(&self, args: &[String]) -> anyhow::Result<()> {
+        autostart::write_file(
+            &Self::autostart_path()?,
+            &autostart::launch_agent_plist(args),
+        )
+    }
+
+    fn disable_autostart(&self) -> anyhow::Result<()> {
+        autostart::remove_file(&Self::autostart_path()?)
+    }
+
+    fn autostart_status(&self) -> Option<String> {
+        let path = Self::autostart_path().ok()?;
+        path.exists().then(|| path.display().to_string())
+    }
+
+    fn listen_hotplug(&self, _sender: mpsc::Sender<DaemonEvent>) -> anyhow::Result<()> {
+        anyhow::bail!("Docking events are not available on macOS")
+    }
+
+    /// Uses `HIDIdleTime` of `ioreg`.
+    fn idle_time(&self) -> Option<Duration> {
+        let output = Command::new("ioreg")
+            .args(["-c", "IOHIDSystem", "-d", "4"])
+            .output()
+            .ok()?;
+        idle::parse_ioreg(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    fn listen_session(
+        &self,
+        _sender: mpsc::Sender<DaemonEvent>,
+    ) -> anyhow::Result<SessionListener> {
+        warn!("Session and lid events are available only on Windows and Linux.");
+        Ok(SessionListener::default())
+    }
+
+    /// USB devices can be watched only on Linux and Windows.
+    fn is_usb_device_connected(&self, _vendor_id: u16, _product_id: u16) -> Option<bool> {
+        None
+    }
+}
@@ -0,0 +1,297 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use super::*;
+use crate::logging::*;
+
+mod event_window;
+pub(crate) mod registry;
+
+/// The registry key of the programs to run when the current user signs in.
+const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+
+/// The sender to the `daemon` for [`window_proc()`].
+static DOCK_SENDER: std::sync::Mutex<Option<mpsc::Sender<DaemonEvent>>> =
+    std::sync::Mutex::new(None);
+
+/// The sender to the `daemon` for [`session_window_proc()`].
+static SESSION_SENDER: std::sync::Mutex<Option<mpsc::Sender<DaemonEvent>>> =
+    std::sync::Mutex::new(None);
+
+pub(crate) struct Windows;
+
+impl Platform for Windows {
+    /// Shows a toast notification.
+    /// Requires the `winapp` feature.
+    #[cfg(feature = "winapp")]
+    fn notify(&self, title: &str, body: &str) -> anyhow::Result<()> {
+        let application_id = application_id();
+        let manager = winrt_toast::ToastManager::new(
+            application_id.as_deref().unwrap_or(DEFAULT_APPLICATION_ID),
+        );
+        let mut toast = winrt_toast::Toast::new();
+        toast.text1(title).text2(body);
+        manager.show(&toast)?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "winapp"))]
+    fn notify(&self, _title: &str, _body: &str) -> anyhow::Result<()> {
+        anyhow::bail!("Notifications on Windows require the `winapp` feature");
+    }
+
+    /// Adds the `HKEY_CURRENT_USER` `Run` registry value.
+    fn enable_autostart(&self, args: &[String]) -> anyhow::Result<()> {
+        let command_line = autostart::windows_command_line(args);
+        registry::set_value(RUN_KEY, autostart::AUTOSTART_NAME, &command_line)?;
+        info!(
+            r"Added HKEY_CURRENT_USER\{RUN_KEY}\{name}: {command_line}",
+            name = autostart::AUTOSTART_NAME
+        );
+        Ok(())
+    }
+
+    fn disable_autostart(&self) -> anyhow::Result<()> {
+        registry::delete_value(RUN_KEY, autostart::AUTOSTART_NAME)?;
+        info!(
+            r"Removed HKEY_CURRENT_USER\{RUN_KEY}\{name}",
+            name = autostart::AUTOSTART_NAME
+        );
+        Ok(())
+    }
+
+    fn autostart_status(&self) -> Option<String> {
+        let name = autostart::AUTOSTART_NAME;
+        registry::get_string(RUN_KEY, name)
+            .map(|command_line| format!(r"HKEY_CURRENT_USER\{RUN_KEY}\{name}: {command_line}"))
+    }
+
+    /// Listens to `WM_DISPLAYCHANGE`, `WM_DEVICECHANGE`, and `WM_POWERBROADCAST`
+    /// by a hidden window.
+    /// The current power source is sent first,
+    /// so that [`PowerWatcher`] can detect the next change.
+    fn listen_hotplug(&self, sender: mpsc::Sender<DaemonEvent>) -> anyhow::Result<()> {
+        if let Some(on_ac) = is_on_ac_power() {
+            let _ = sender.send(DaemonEvent::Dock(DockEvent::Power(on_ac)));
+        }
+        let is_first = DOCK_SENDER.lock().unwrap().replace(sender).is_none();
+        if is_first {
+            event_window::spawn("monitor-input-dock", window_proc, |_| Ok(()))?;
+        }
+        Ok(())
+    }
+
+    /// Uses `GetLastInputInfo`.
+    fn idle_time(&self) -> Option<Duration> {
+        use windows_sys::Win32::System::SystemInformation::GetTickCount;
+        use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+        let mut info = LASTINPUTINFO {
+            cbSize: size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        // SAFETY: `info` is a valid `LASTINPUTINFO` with `cbSize` set.
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return None;
+        }
+        // SAFETY: `GetTickCount` has no preconditions.
+        let now = unsafe { GetTickCount() };
+        Some(Duration::from_millis(now.wrapping_sub(info.dwTime) as u64))
+    }
+
+    /// Listens to `WM_WTSSESSION_CHANGE`
+    /// and the `GUID_LIDSWITCH_STATE_CHANGE` power setting by a hidden window.
+    fn listen_session(&self, sender: mpsc::Sender<DaemonEvent>) -> anyhow::Result<SessionListener> {
+        use windows_sys::Win32::System::Power::RegisterPowerSettingNotification;
+        use windows_sys::Win32::System::RemoteDesktop::{
+            NOTIFY_FOR_THIS_SESSION, WTSRegisterSessionNotification,
+        };
+        use windows_sys::Win32::System::SystemServices::GUID_LIDSWITCH_STATE_CHANGE;
+        use windows_sys::Win32::UI::WindowsAndMessaging::DEVICE_NOTIFY_WINDOW_HANDLE;
+
+        let is_first = SESSION_SENDER.lock().unwrap().replace(sender).is_none();
+        if !is_first {
+            return Ok(SessionListener::default());
+        }
+        event_window::spawn("monitor-input-session", session_window_proc, |hwnd| {
+            // SAFETY: `hwnd` is a valid window on this thread.
+            if unsafe { WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) } == 0 {
+                anyhow::bail!(
+                    "Failed to register for session notifications: {}",
+                    std::io::Error::last_os_error()
+                );
+            }
+            // Computers without lids fail, which isn't an error.
+            // SAFETY: `hwnd` is a valid window on this thread.
+            if unsafe {
+                RegisterPowerSettingNotification(
+                    hwnd,
+                    &GUID_LIDSWITCH_STATE_CHANGE,
+                    DEVICE_NOTIFY_WINDOW_HANDLE,
+                )
+            } == 0
+            {
+                debug!(
+                    "Failed to register for lid notifications: {}",
+                    std::io::Error::last_os_error()
+                );
+            }
+            Ok(())
+        })?;
+        Ok(SessionListener::default())
+    }
+
+    /// Checks the instance IDs of the present devices, such as `USB\VID_1050&PID_0407\...`.
+    fn is_usb_device_connected(&self, vendor_id: u16, product_id: u16) -> Option<bool> {
+        use windows_sys::Win32::Devices::DeviceAndDriverInstallation::{
+            DIGCF_ALLCLASSES, DIGCF_PRESENT, HDEVINFO, SP_DEVINFO_DATA,
+            SetupDiDestroyDeviceInfoList, SetupDiEnumDeviceInfo, SetupDiGetClassDevsW,
+            SetupDiGetDeviceInstanceIdW,
+        };
+        use windows_sys::Win32::Foundation::INVALID_HANDLE_VALUE;
+
+        let enumerator: Vec<u16> = "USB".encode_utf16().chain([0]).collect();
+        // SAFETY: `enumerator` is null-terminated.
+        let devices = unsafe {
+            SetupDiGetClassDevsW(
+                std::ptr::null(),
+                enumerator.as_ptr(),
+                std::ptr::null_mut(),
+                DIGCF_ALLCLASSES | DIGCF_PRESENT,
+            )
+        };
+        if devices == INVALID_HANDLE_VALUE as HDEVINFO {
+            return None;
+        }
+        let prefix = format!(r"USB\VID_{vendor_id:04X}&PID_{product_id:04X}");
+        let mut data = SP_DEVINFO_DATA {
+            cbSize: size_of::<SP_DEVINFO_DATA>() as u32,
+            ..Default::default()
+        };
+        let mut buffer = [0u16; 256];
+        let mut found = false;
+        let mut index = 0;
+        // SAFETY: `data` has the `cbSize`, and `buffer` has the given size.
+        while unsafe { SetupDiEnumDeviceInfo(devices, index, &mut data) } != 0 {
+            index += 1;
+            if unsafe {
+                SetupDiGetDeviceInstanceIdW(
+                    devices,
+                    &data,
+                    buffer.as_mut_ptr(),
+                    buffer.len() as u32,
+                    std::ptr::null_mut(),
+                )
+            } == 0
+            {
+                continue;
+            }
+            let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+            let id = String::from_utf16_lossy(&buffer[..len]);
+            if id.to_ascii_uppercase().starts_with(&prefix) {
+                found = true;
+                break;
+            }
+        }
+        // SAFETY: `devices` is valid, and not used after this.
+        unsafe { SetupDiDestroyDeviceInfoList(devices) };
+        Some(found)
+    }
+}
+
+fn send(event: DockEvent) {
+    debug!("{event:?}");
+    if let Some(sender) = DOCK_SENDER.lock().unwrap().as_ref() {
+        let _ = sender.send(DaemonEvent::Dock(event));
+    }
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: windows_sys::Win32::Foundation::HWND,
+    msg: u32,
+    wparam: windows_sys::Win32::Foundation::WPARAM,
+    lparam: windows_sys::Win32::Foundation::LPARAM,
+) -> windows_sys::Win32::Foundation::LRESULT {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        DBT_DEVNODES_CHANGED, DefWindowProcW, PBT_APMPOWERSTATUSCHANGE, WM_DEVICECHANGE,
+        WM_DISPLAYCHANGE, WM_POWERBROADCAST,
+    };
+
+    match msg {
+        WM_DISPLAYCHANGE => send(DockEvent::DisplayChange),
+        WM_DEVICECHANGE if wparam as u32 == DBT_DEVNODES_CHANGED => send(DockEvent::DisplayChange),
+        WM_POWERBROADCAST if wparam as u32 == PBT_APMPOWERSTATUSCHANGE => {
+            if let Some(on_ac) = is_on_ac_power() {
+                send(DockEvent::Power(on_ac));
+            }
+        }
+        _ => {}
+    }
+    // SAFETY: The arguments are from the system.
+    unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+}
+
+fn send_session(event: SessionEvent) {
+    debug!("{event:?}");
+    if let Some(sender) = SESSION_SENDER.lock().unwrap().as_ref() {
+        let _ = sender.send(DaemonEvent::Session(event));
+    }
+}
+
+unsafe extern "system" fn session_window_proc(
+    hwnd: windows_sys::Win32::Foundation::HWND,
+    msg: u32,
+    wparam: windows_sys::Win32::Foundation::WPARAM,
+    lparam: windows_sys::Win32::Foundation::LPARAM,
+) -> windows_sys::Win32::Foundation::LRESULT {
+    use windows_sys::Win32::System::Power::POWERBROADCAST_SETTING;
+    use windows_sys::Win32::System::SystemServices::GUID_LIDSWITCH_STATE_CHANGE;
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        DefWindowProcW, PBT_POWERSETTINGCHANGE, WM_POWERBROADCAST, WM_WTSSESSION_CHANGE,
+        WTS_SESSION_LOCK, WTS_SESSION_UNLOCK,
+    };
+
+    match msg {
+        WM_WTSSESSION_CHANGE => match wparam as u32 {
+            WTS_SESSION_LOCK => send_session(SessionEvent::Lock),
+            WTS_SESSION_UNLOCK => send_session(SessionEvent::Unlock),
+            _ => {}
+        },
+        WM_POWERBROADCAST if wparam as u32 == PBT_POWERSETTINGCHANGE && lparam != 0 => {
+            // SAFETY: `lparam` is a `POWERBROADCAST_SETTING` for `PBT_POWERSETTINGCHANGE`.
+            let setting = unsafe { &*(lparam as *const POWERBROADCAST_SETTING) };
+            let guid = &setting.PowerSetting;
+            let lid = &GUID_LIDSWITCH_STATE_CHANGE;
+            if (guid.data1, guid.data2, guid.data3, guid.data4)
+                == (lid.data1, lid.data2, lid.data3, lid.data4)
+                && setting.DataLength > 0
+            {
+                // The current state is also notified when registered.
+                send_session(if setting.Data[0] == 0 {
+                    SessionEvent::LidClose
+                } else {
+                    SessionEvent::LidOpen
+                });
+            }
+        }
+        _ => {}
+    }
+    // SAFETY: The arguments are from the system.
+    unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) }
+}
+
+/// Whether the computer is on AC power, or `None` if unknown.
+fn is_on_ac_power() -> Option<bool> {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    // SAFETY: `status` is a valid `SYSTEM_POWER_STATUS`.
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return None;
+    }
+    match status.ACLineStatus {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    }
+}
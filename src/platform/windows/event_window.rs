@@ -0,0 +1,90 @@
+use std::sync::mpsc;
+
+use windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+
+/// The window procedure of [`spawn()`].
+pub(crate) type WindowProc = unsafe extern "system" fn(HWND, u32, WPARAM, LPARAM) -> LRESULT;
+
+/// Create a hidden window of the `class_name` on its own thread,
+/// and dispatch its messages to the `window_proc` until the process exits.
+/// The `register` is called with the window on the thread,
+/// to register the window for notifications.
+///
+/// The window is a top-level window, not a message-only window,
+/// because message-only windows don't receive broadcast messages
+/// such as `WM_DISPLAYCHANGE`.
+pub(crate) fn spawn(
+    class_name: &'static str,
+    window_proc: WindowProc,
+    register: impl FnOnce(HWND) -> anyhow::Result<()> + Send + 'static,
+) -> anyhow::Result<()> {
+    let (ready_sender, ready_receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let result = create(class_name, window_proc).and_then(register);
+        let is_ok = result.is_ok();
+        let _ = ready_sender.send(result);
+        if is_ok {
+            run_message_loop();
+        }
+    });
+    ready_receiver.recv()?
+}
+
+fn create(class_name: &str, window_proc: WindowProc) -> anyhow::Result<HWND> {
+    use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, RegisterClassW, WNDCLASSW, WS_OVERLAPPED,
+    };
+
+    let class_name: Vec<u16> = class_name.encode_utf16().chain([0]).collect();
+    // SAFETY: The null name gets the handle of the executable.
+    let instance = unsafe { GetModuleHandleW(std::ptr::null()) };
+    let class = WNDCLASSW {
+        lpfnWndProc: Some(window_proc),
+        hInstance: instance,
+        lpszClassName: class_name.as_ptr(),
+        ..Default::default()
+    };
+    // SAFETY: `class` is valid during the call.
+    if unsafe { RegisterClassW(&class) } == 0 {
+        anyhow::bail!(
+            "Failed to register the window class: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    // SAFETY: The class is registered above.
+    let hwnd = unsafe {
+        CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            class_name.as_ptr(),
+            WS_OVERLAPPED,
+            0,
+            0,
+            0,
+            0,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            instance,
+            std::ptr::null(),
+        )
+    };
+    if hwnd.is_null() {
+        anyhow::bail!(
+            "Failed to create the window: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    Ok(hwnd)
+}
+
+fn run_message_loop() {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{DispatchMessageW, GetMessageW, MSG};
+
+    let mut msg = MSG::default();
+    // SAFETY: `msg` is valid, and the window is created on this thread.
+    while unsafe { GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) } > 0 {
+        // SAFETY: `msg` is retrieved by `GetMessageW`.
+        unsafe { DispatchMessageW(&msg) };
+    }
+}
@@ -0,0 +1,121 @@
+//! Functions to read and write the registry of the current user.
+use windows_sys::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS, WIN32_ERROR};
+use windows_sys::Win32::System::Registry::{
+    HKEY_CURRENT_USER, REG_SZ, RRF_RT_ANY, RRF_RT_REG_SZ, RegDeleteKeyValueW, RegDeleteTreeW,
+    RegGetValueW, RegSetKeyValueW,
+};
+
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+fn check(result: WIN32_ERROR, key: &str) -> anyhow::Result<()> {
+    if result != ERROR_SUCCESS {
+        anyhow::bail!(
+            r"HKEY_CURRENT_USER\{key}: {}",
+            std::io::Error::from_raw_os_error(result as i32)
+        );
+    }
+    Ok(())
+}
+
+/// Set the string `value` of the `name` in the `key`, creating the `key` if needed.
+/// The empty `name` sets the default value.
+pub(crate) fn set_value(key: &str, name: &str, value: &str) -> anyhow::Result<()> {
+    let (key_w, name_w, value_w) = (wide(key), wide(name), wide(value));
+    // SAFETY: All strings are null-terminated, and `value_w` has the given size.
+    let result = unsafe {
+        RegSetKeyValueW(
+            HKEY_CURRENT_USER,
+            key_w.as_ptr(),
+            if name.is_empty() {
+                std::ptr::null()
+            } else {
+                name_w.as_ptr()
+            },
+            REG_SZ,
+            value_w.as_ptr().cast(),
+            (value_w.len() * size_of::<u16>()) as u32,
+        )
+    };
+    check(result, key)
+}
+
+/// Whether the `key` has the value of the `name`.
+pub(crate) fn has_value(key: &str, name: &str) -> bool {
+    let (key_w, name_w) = (wide(key), wide(name));
+    // SAFETY: All strings are null-terminated, and no data is read.
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key_w.as_ptr(),
+            name_w.as_ptr(),
+            RRF_RT_ANY,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    result == ERROR_SUCCESS
+}
+
+/// The string value of the `name` in the `key`, or `None` if it doesn't exist.
+pub(crate) fn get_string(key: &str, name: &str) -> Option<String> {
+    let (key_w, name_w) = (wide(key), wide(name));
+    let mut size = 0u32;
+    // SAFETY: All strings are null-terminated. Querying the size with the null buffer is allowed.
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key_w.as_ptr(),
+            name_w.as_ptr(),
+            RRF_RT_REG_SZ,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &mut size,
+        )
+    };
+    if result != ERROR_SUCCESS {
+        return None;
+    }
+    let mut buffer = vec![0u16; (size as usize).div_ceil(size_of::<u16>())];
+    // SAFETY: `buffer` has `size` bytes.
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key_w.as_ptr(),
+            name_w.as_ptr(),
+            RRF_RT_REG_SZ,
+            std::ptr::null_mut(),
+            buffer.as_mut_ptr().cast(),
+            &mut size,
+        )
+    };
+    if result != ERROR_SUCCESS {
+        return None;
+    }
+    let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+    Some(String::from_utf16_lossy(&buffer[..len]))
+}
+
+/// Delete the value of the `name` in the `key`. It's not an error if it doesn't exist.
+pub(crate) fn delete_value(key: &str, name: &str) -> anyhow::Result<()> {
+    let (key_w, name_w) = (wide(key), wide(name));
+    // SAFETY: All strings are null-terminated.
+    let result = unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, key_w.as_ptr(), name_w.as_ptr()) };
+    if result == ERROR_FILE_NOT_FOUND {
+        return Ok(());
+    }
+    check(result, key)
+}
+
+/// Delete the `key` and its subkeys. It's not an error if the `key` doesn't exist.
+pub(crate) fn delete_tree(key: &str) -> anyhow::Result<()> {
+    let key_w = wide(key);
+    // SAFETY: `key_w` is null-terminated.
+    let result = unsafe { RegDeleteTreeW(HKEY_CURRENT_USER, key_w.as_ptr()) };
+    if result == ERROR_FILE_NOT_FOUND {
+        return Ok(());
+    }
+    check(result, key)
+}
@@ -0,0 +1,180 @@
+use std::ops::RangeInclusive;
+
+use super::*;
+use crate::logging::*;
+use serde::Serialize;
+
+/// The VCP feature code of the firmware version of the display controller.
+const FIRMWARE_LEVEL: VcpCode = 0xC9;
+
+#[derive(Debug, Serialize)]
+/// A VCP feature that responded in [`ProbeReport::run()`].
+pub(crate) struct ProbeFeature {
+    pub code: VcpCode,
+    /// The name from the capabilities or the MCCS database, see [`Monitor::feature_name()`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub value: u16,
+    pub maximum: u16,
+    /// Whether the capabilities list the code,
+    /// or `None` if the capabilities aren't available.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_capabilities: Option<bool>,
+    /// The allowed values in the capabilities, for non-continuous features.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<u8>,
+}
+
+impl ProbeFeature {
+    /// The name, or what kind of code it is if it's not known.
+    pub fn display_name(&self) -> &str {
+        match &self.name {
+            Some(name) => name,
+            None if self.code >= 0xE0 => "Manufacturer specific",
+            None => "Unknown",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+/// The results of the `probe` command for a display monitor.
+pub(crate) struct ProbeReport {
+    pub monitor: String,
+    pub backend: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mccs_version: Option<String>,
+    /// The firmware version from the [`FIRMWARE_LEVEL`], such as `1.2`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub firmware_level: Option<String>,
+    pub features: Vec<ProbeFeature>,
+    /// The codes in the capabilities that couldn't be read.
+    pub unreadable: Vec<VcpCode>,
+}
+
+impl ProbeReport {
+    /// Read the VCP features of the `codes` from the `monitor`.
+    /// This only reads from the display monitor, and doesn't change anything.
+    pub fn run(monitor: &mut Monitor, codes: RangeInclusive<VcpCode>) -> Self {
+        if let Err(e) = monitor.update_capabilities() {
+            info!("{monitor}: No capabilities: {e}");
+        }
+        let capabilities = monitor.capabilities().cloned();
+        let mccs_version = capabilities
+            .as_ref()
+            .and_then(|capabilities| capabilities.mccs_version.clone());
+        let mut features = vec![];
+        let mut unreadable = vec![];
+        for code in codes {
+            let supported = capabilities
+                .as_ref()
+                .map(|capabilities| capabilities.vcp_features.get(&code));
+            let value = match monitor.vcp_feature(code) {
+                Ok(value) => value,
+                Err(e) => {
+                    debug!("{monitor}: {code:#04X}: {e}");
+                    if let Some(Some(_)) = supported {
+                        unreadable.push(code);
+                    }
+                    continue;
+                }
+            };
+            let feature = supported.flatten();
+            features.push(ProbeFeature {
+                code,
+                name: monitor.feature_name(code).map(str::to_string),
+                value: value.value,
+                maximum: value.maximum,
+                in_capabilities: supported.map(|feature| feature.is_some()),
+                values: feature
+                    .map(|feature| feature.values.clone())
+                    .unwrap_or_default(),
+            });
+        }
+        let firmware_level = features
+            .iter()
+            .find(|feature| feature.code == FIRMWARE_LEVEL)
+            .map(|feature| format!("{}.{}", feature.value >> 8, feature.value & 0xFF));
+        Self {
+            monitor: monitor.to_string(),
+            backend: monitor.backend(),
+            mccs_version,
+            firmware_level,
+            features,
+            unreadable,
+        }
+    }
+}
+
+/// Parse the range of VCP codes in hexadecimal, such as `10-1F`, or a code, such as `0xE0`.
+pub(crate) fn parse_vcp_range(range: &str) -> anyhow::Result<RangeInclusive<VcpCode>> {
+    let parse = |code: &str| {
+        crate::arg::parse_vcp_code(code)
+            .ok_or_else(|| anyhow::anyhow!("Invalid VCP code \"{code}\" in \"{range}\""))
+    };
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (parse(start)?, parse(end)?),
+        None => {
+            let code = parse(range)?;
+            (code, code)
+        }
+    };
+    if start > end {
+        anyhow::bail!("The range \"{range}\" is empty");
+    }
+    Ok(start..=end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run() {
+        let mut monitor = Monitor::from_device(
+            MockMonitor::new("A")
+                .with_capabilities("(vcp(10 12 60(0F 11))vcpname(10(Brightness))mccs_ver(2.1))")
+                .with_input_source(InputSource::Hdmi1.as_raw()),
+        );
+        monitor.set_brightness(30).unwrap();
+        monitor.set_vcp_feature(0xC9, 0x0102).unwrap();
+        monitor.set_vcp_feature(0xE2, 3).unwrap();
+        let report = ProbeReport::run(&mut monitor, 0x00..=0xFF);
+        let features: Vec<(VcpCode, &str, u16, Option<bool>)> = report
+            .features
+            .iter()
+            .map(|feature| {
+                (
+                    feature.code,
+                    feature.display_name(),
+                    feature.value,
+                    feature.in_capabilities,
+                )
+            })
+            .collect();
+        assert_eq!(
+            features,
+            [
+                (0x10, "Brightness", 30, Some(true)),
+                (0x60, "Input Select", 0x11, Some(true)),
+                (0xC9, "Unknown", 0x0102, Some(false)),
+                (0xE2, "Manufacturer specific", 3, Some(false)),
+            ]
+        );
+        assert_eq!(report.features[1].values, [0x0F, 0x11]);
+        assert_eq!(report.unreadable, [0x12]);
+        assert_eq!(report.mccs_version.as_deref(), Some("2.1"));
+        assert_eq!(report.firmware_level.as_deref(), Some("1.2"));
+
+        let report = ProbeReport::run(&mut monitor, 0x10..=0x1F);
+        assert_eq!(report.features.len(), 1);
+    }
+
+    #[test]
+    fn parse_vcp_range() {
+        assert_eq!(super::parse_vcp_range("10-1F").unwrap(), 0x10..=0x1F);
+        assert_eq!(super::parse_vcp_range("0x00-0xFF").unwrap(), 0x00..=0xFF);
+        assert_eq!(super::parse_vcp_range("E0").unwrap(), 0xE0..=0xE0);
+        assert!(super::parse_vcp_range("1F-10").is_err());
+        assert!(super::parse_vcp_range("10-xyz").is_err());
+    }
+}
@@ -0,0 +1,587 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use super::*;
+
+/// A display monitor and its input source in a [`Profile`],
+/// written as `name=input` in the command line and the configuration file.
+/// It's written back with special characters in the name escaped, such as `A\=B=dp1`,
+/// so that it's parsed to the same entry; other names are written as they are.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileEntry {
+    /// A part of the names of the display monitors.
+    /// The [`Cli`] also accepts group names and indices.
+    pub monitor: String,
+
+    /// The input source, such as `DP1` or `0x0F`.
+    pub input: String,
+}
+
+impl ProfileEntry {
+    /// The input source as [`InputSourceRaw`].
+    pub fn input_source(&self) -> anyhow::Result<InputSourceRaw> {
+        InputSource::raw_from_str(&self.input)
+    }
+}
+
+impl FromStr for ProfileEntry {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let Arg::Set {
+            name, mut values, ..
+        } = s.parse()?
+        else {
+            anyhow::bail!("\"{s}\" is not in the form of `name=input`.");
+        };
+        if values.len() != 1 {
+            anyhow::bail!("\"{s}\" is not in the form of `name=input`.");
+        }
+        let entry = Self {
+            monitor: name,
+            input: values.pop().unwrap(),
+        };
+        entry.input_source()?;
+        Ok(entry)
+    }
+}
+
+impl fmt::Display for ProfileEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", crate::arg::quote(&self.monitor), self.input)
+    }
+}
+
+/// A display monitor and a value of its VCP feature in a [`Profile`],
+/// written as `name.feature=value`, such as `name.brightness=50%` or `name.power=off`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileFeature {
+    /// A part of the names of the display monitors, as [`ProfileEntry::monitor`].
+    pub monitor: String,
+
+    /// The feature, one of `brightness`, `contrast`, `volume`, or `power`.
+    pub feature: String,
+
+    /// The value, a number or a percentage of the maximum such as `50%`.
+    pub value: String,
+}
+
+impl ProfileFeature {
+    /// The VCP feature code and the value.
+    fn parse_value(&self) -> anyhow::Result<(VcpCode, FeatureValue)> {
+        let code = crate::arg::feature_code(&self.feature)
+            .ok_or_else(|| anyhow::anyhow!("Unknown feature \"{}\"", self.feature))?;
+        Ok((code, FeatureValue::parse(&self.feature, &self.value)?))
+    }
+}
+
+impl FromStr for ProfileFeature {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let Arg::SetVcp {
+            name,
+            feature,
+            value,
+            ..
+        } = s.parse()?
+        else {
+            anyhow::bail!("\"{s}\" is not in the form of `name.feature=value`.");
+        };
+        let feature = Self {
+            monitor: name,
+            feature: feature.to_string(),
+            value,
+        };
+        feature.parse_value()?;
+        Ok(feature)
+    }
+}
+
+impl fmt::Display for ProfileFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}={}",
+            crate::arg::quote(&self.monitor),
+            self.feature,
+            self.value
+        )
+    }
+}
+
+/// A set of input sources of display monitors,
+/// such as the `work` profile in the `profiles` of the configuration file.
+///
+/// Names match display monitors by the same rules as the [`Cli`] with the [`MatchOptions`],
+/// except that group names are available only in the [`Cli`].
+///
+/// It's serialized as the list of `name=input` and `name.feature=value`.
+/// # Examples
+/// ```
+/// # use monitor_input::{InputSource,MatchOptions,MockMonitor,Monitor,Profile,VcpWrite};
+/// let profile = Profile::parse(["A=dp1", "B=hdmi1", "B.brightness=30"]).unwrap();
+/// let mut monitors = vec![
+///     Monitor::from_device(MockMonitor::new("A")),
+///     Monitor::from_device(MockMonitor::new("B")),
+/// ];
+/// # monitors[1].set_brightness(50).unwrap();
+/// let options = MatchOptions::default();
+/// let changes = profile.apply(&mut monitors, &options).unwrap();
+/// assert_eq!(changes.len(), 2);
+/// assert_eq!(
+///     changes[1].writes,
+///     [VcpWrite::InputSource(InputSource::Hdmi1.as_raw()), VcpWrite::Brightness(30)]
+/// );
+/// assert!(changes.iter().all(|change| change.result.is_ok()));
+/// let diffs = profile.diff(&mut monitors, &options).unwrap();
+/// assert!(diffs.iter().all(|diff| diff.matches()));
+/// assert_eq!(profile.args(), ["A=dp1", "B=hdmi1", "B.brightness=30"]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(try_from = "Vec<String>", into = "Vec<String>")
+)]
+pub struct Profile {
+    /// The display monitors and their input sources.
+    /// When a display monitor matches multiple entries, the last one wins.
+    pub entries: Vec<ProfileEntry>,
+    /// The display monitors and the values of their VCP features,
+    /// applied with the input sources in a batch. See [`Monitor::apply()`].
+    /// When a display monitor matches multiple ones of a feature, the last one wins.
+    pub features: Vec<ProfileFeature>,
+}
+
+impl Profile {
+    /// Parse the `name=input` and the `name.feature=value` arguments.
+    pub fn parse<S: AsRef<str>>(args: impl IntoIterator<Item = S>) -> anyhow::Result<Self> {
+        let mut profile = Self::default();
+        for arg in args {
+            let arg = arg.as_ref();
+            if matches!(arg.parse(), Ok(Arg::SetVcp { .. })) {
+                profile.features.push(arg.parse()?);
+            } else {
+                profile.entries.push(arg.parse()?);
+            }
+        }
+        Ok(profile)
+    }
+
+    /// The `name=input` and the `name.feature=value` arguments.
+    /// Special characters in names are escaped, as [`ProfileEntry`] does.
+    pub fn args(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(ToString::to_string)
+            .chain(self.features.iter().map(ToString::to_string))
+            .collect()
+    }
+
+    /// Create a profile of the current input sources of the `monitors`,
+    /// by their [`Monitor::id()`].
+    pub fn from_monitors(monitors: &mut [Monitor]) -> anyhow::Result<Self> {
+        let mut entries = vec![];
+        for monitor in monitors {
+            let input_source = monitor.input_source()?;
+            entries.push(ProfileEntry {
+                monitor: monitor.id().to_string(),
+                input: InputSource::str_from_raw(input_source),
+            });
+        }
+        Ok(Self {
+            entries,
+            ..Default::default()
+        })
+    }
+
+    /// The indices of the `monitors` that the `name` matches.
+    /// Set `to_set` to check [`MatchOptions::all`] for changes.
+    fn matched_indices(
+        name: &str,
+        monitors: &[Monitor],
+        options: &MatchOptions,
+        to_set: bool,
+    ) -> anyhow::Result<Vec<usize>> {
+        let selector = Selector::from_name(name);
+        let indices = (0..monitors.len())
+            .filter(|index| selector.matches_monitor(*index, &monitors[*index]))
+            .collect();
+        options.limit(name, &selector, indices, to_set, monitors)
+    }
+
+    /// The current and the expected input sources of the `monitors` in this profile.
+    /// The current input source is `None` if it can't be read.
+    pub fn diff(
+        &self,
+        monitors: &mut [Monitor],
+        options: &MatchOptions,
+    ) -> anyhow::Result<Vec<ProfileDiff>> {
+        let mut diffs = vec![];
+        for entry in &self.entries {
+            let expected = entry.input_source()?;
+            for index in Self::matched_indices(&entry.monitor, monitors, options, false)? {
+                let monitor = &mut monitors[index];
+                diffs.push(ProfileDiff {
+                    monitor: monitor.to_string(),
+                    current: monitor.input_source().ok(),
+                    expected,
+                });
+            }
+        }
+        Ok(diffs)
+    }
+
+    /// Set the `monitors` to the input sources and the features in this profile.
+    /// The input sources are set only if they're not in the input sources yet.
+    /// The changes of each display monitor are applied in a batch by [`Monitor::apply()`].
+    ///
+    /// Returns the changes of each display monitor.
+    /// A failure of a display monitor doesn't stop changing others.
+    /// It fails without any changes if names don't match as the `options`.
+    pub fn apply(
+        &self,
+        monitors: &mut [Monitor],
+        options: &MatchOptions,
+    ) -> anyhow::Result<Vec<ProfileChange>> {
+        let mut inputs: Vec<Option<InputSourceRaw>> = vec![None; monitors.len()];
+        for entry in &self.entries {
+            let expected = entry.input_source()?;
+            for index in Self::matched_indices(&entry.monitor, monitors, options, true)? {
+                inputs[index] = Some(expected);
+            }
+        }
+        let mut features: Vec<BTreeMap<VcpCode, (&str, FeatureValue)>> =
+            vec![BTreeMap::new(); monitors.len()];
+        for feature in &self.features {
+            let (code, value) = feature.parse_value()?;
+            for index in Self::matched_indices(&feature.monitor, monitors, options, true)? {
+                features[index].insert(code, (&feature.feature, value));
+            }
+        }
+
+        let mut changes = vec![];
+        for ((input, features), monitor) in inputs.into_iter().zip(features).zip(monitors) {
+            let mut change = ProfileChange {
+                monitor: monitor.to_string(),
+                previous: None,
+                writes: vec![],
+                result: Ok(()),
+            };
+            if let Some(expected) = input {
+                let current = monitor.input_source().ok();
+                if current != Some(expected) {
+                    change.previous = current;
+                    change.writes.push(VcpWrite::InputSource(expected));
+                }
+            }
+            for (code, (feature, value)) in features {
+                match value.resolve(feature, code, monitor) {
+                    Ok(value) => change.writes.push(VcpWrite::new(code, value)),
+                    Err(e) if change.result.is_ok() => change.result = Err(e),
+                    Err(_) => {}
+                }
+            }
+            if change.writes.is_empty() && change.result.is_ok() {
+                continue;
+            }
+            for (_, result) in monitor.apply(&change.writes) {
+                if let Err(e) = result
+                    && change.result.is_ok()
+                {
+                    change.result = Err(e);
+                }
+            }
+            changes.push(change);
+        }
+        Ok(changes)
+    }
+}
+
+impl TryFrom<Vec<String>> for Profile {
+    type Error = anyhow::Error;
+
+    fn try_from(args: Vec<String>) -> anyhow::Result<Self> {
+        Self::parse(args)
+    }
+}
+
+impl From<Profile> for Vec<String> {
+    fn from(profile: Profile) -> Self {
+        profile.args()
+    }
+}
+
+/// The changes of a display monitor by [`Profile::apply()`].
+#[derive(Debug)]
+pub struct ProfileChange {
+    /// The display monitor.
+    pub monitor: String,
+    /// The input source before the change, or `None` if it's not changed or can't be read.
+    pub previous: Option<InputSourceRaw>,
+    /// The changes, applied in the order of [`Monitor::apply()`].
+    pub writes: Vec<VcpWrite>,
+    /// The first error of the changes.
+    /// Other changes are applied even if one of them fails.
+    pub result: anyhow::Result<()>,
+}
+
+/// The current and the expected input sources of a display monitor in a [`Profile`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileDiff {
+    /// The display monitor.
+    pub monitor: String,
+    /// The current input source, or `None` if it can't be read.
+    pub current: Option<InputSourceRaw>,
+    /// The input source in the [`Profile`].
+    pub expected: InputSourceRaw,
+}
+
+impl ProfileDiff {
+    /// Whether the display monitor is in the expected input source.
+    pub fn matches(&self) -> bool {
+        self.current == Some(self.expected)
+    }
+}
+
+/// Named [`Profile`]s, such as the `profiles` in the configuration file.
+/// # Examples
+/// ```
+/// # use monitor_input::{Profile,ProfileStore};
+/// let path = std::env::temp_dir().join("monitor-input-doctest-profiles.toml");
+/// let mut store = ProfileStore::default();
+/// store.insert("work", Profile::parse(["desk=dp1"]).unwrap());
+/// store.save(&path).unwrap();
+/// let store = ProfileStore::load(&path).unwrap();
+/// assert_eq!(store.get("work").unwrap().args(), ["desk=dp1"]);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[cfg(feature = "cli")]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProfileStore {
+    profiles: std::collections::BTreeMap<String, Profile>,
+}
+
+#[cfg(feature = "cli")]
+impl ProfileStore {
+    /// The `profiles` of the `config`.
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let mut store = Self::default();
+        for (name, args) in &config.profiles {
+            let profile = Profile::parse(args).map_err(|e| anyhow::anyhow!("{name}: {e}"))?;
+            store.insert(name, profile);
+        }
+        Ok(store)
+    }
+
+    /// Load the `profiles` from the configuration file at the `path`.
+    /// It's empty if the file doesn't exist.
+    pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::from_config(&Config::load(Some(path))?)
+    }
+
+    /// Save the `profiles` to the configuration file at the `path`.
+    /// Other configurations in the file are kept, but comments are not.
+    pub fn save(&self, path: &std::path::Path) -> anyhow::Result<()> {
+        let mut table: toml::Table = match std::fs::read_to_string(path) {
+            Ok(text) => text.parse()?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let profiles: toml::Table = self
+            .profiles
+            .iter()
+            .map(|(name, profile)| {
+                let args = profile.args().into_iter().map(toml::Value::from).collect();
+                (name.clone(), toml::Value::Array(args))
+            })
+            .collect();
+        table.insert("profiles".to_string(), toml::Value::Table(profiles));
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string(&table)?)?;
+        Ok(())
+    }
+
+    /// The profile of the `name`.
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.get(name)
+    }
+
+    /// Add or replace the profile of the `name`, and return the replaced one.
+    pub fn insert(&mut self, name: impl Into<String>, profile: Profile) -> Option<Profile> {
+        self.profiles.insert(name.into(), profile)
+    }
+
+    /// Remove the profile of the `name`, and return it.
+    pub fn remove(&mut self, name: &str) -> Option<Profile> {
+        self.profiles.remove(name)
+    }
+
+    /// The names of the profiles, in the sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let profile = Profile::parse(["A=dp1", "B=0x11"]).unwrap();
+        assert_eq!(
+            profile.entries[1],
+            ProfileEntry {
+                monitor: "B".into(),
+                input: "0x11".into()
+            }
+        );
+        assert!(Profile::parse(["A"]).is_err());
+        assert!(Profile::parse(["=dp1"]).is_err());
+        assert!(Profile::parse(["A="]).is_err());
+        assert!(Profile::parse(["A=xyz"]).is_err());
+    }
+
+    #[test]
+    fn args() {
+        let args = ["DELL U2723QE=dp1", r"\\.\DISPLAY1=0x11", "A.brightness=50%"];
+        assert_eq!(Profile::parse(args).unwrap().args(), args);
+
+        let profile = Profile::parse([r"A\=B=dp1", r"Bob's Monitor=hdmi1"]).unwrap();
+        assert_eq!(profile.entries[0].monitor, "A=B");
+        assert_eq!(profile.args(), [r"A\=B=dp1", r"Bob\'s Monitor=hdmi1"]);
+        assert_eq!(Profile::parse(profile.args()).unwrap(), profile);
+    }
+
+    #[test]
+    fn apply() {
+        let mut monitors = vec![
+            Monitor::from_device(MockMonitor::new("A1")),
+            Monitor::from_device(MockMonitor::new("A2")),
+            Monitor::from_device(MockMonitor::new("B")),
+        ];
+        monitors[1]
+            .set_input_source(InputSource::DisplayPort1.as_raw())
+            .unwrap();
+        let profile = Profile::parse(["A=dp1", "A1=hdmi1"]).unwrap();
+        let all = MatchOptions {
+            all: true,
+            ..Default::default()
+        };
+        let diffs = profile.diff(&mut monitors, &all).unwrap();
+        assert_eq!(diffs.len(), 3);
+        assert!(diffs[1].matches());
+
+        // "A" matches multiple display monitors, as the `Cli` without `--all`.
+        assert!(
+            profile
+                .apply(&mut monitors, &MatchOptions::default())
+                .is_err()
+        );
+        assert_eq!(monitors[0].input_source().ok(), None);
+
+        let changes = profile.apply(&mut monitors, &all).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].monitor, "A1");
+        assert_eq!(
+            changes[0].writes,
+            [VcpWrite::InputSource(InputSource::Hdmi1.as_raw())]
+        );
+        assert!(changes[0].result.is_ok());
+        assert_eq!(
+            monitors[0].input_source().unwrap(),
+            InputSource::Hdmi1.as_raw()
+        );
+
+        let current = Profile::from_monitors(&mut monitors[..2]).unwrap();
+        assert_eq!(current.args(), ["A1=Hdmi1", "A2=DP1"]);
+
+        // Indices are the same as the `Cli`.
+        let profile = Profile::parse(["2=dp1", "3=dp1"]).unwrap();
+        assert!(profile.apply(&mut monitors, &all).is_err());
+        let profile = Profile::parse(["2=dp1"]).unwrap();
+        let changes = profile.apply(&mut monitors, &all).unwrap();
+        assert_eq!(changes[0].monitor, "B");
+    }
+
+    #[test]
+    fn apply_features() {
+        let mut monitors = vec![Monitor::from_device(MockMonitor::new("A"))];
+        monitors[0].set_brightness(10).unwrap();
+        let profile = Profile::parse([
+            "A.power=on",
+            "A.brightness=50%",
+            "A=hdmi1",
+            "A.brightness=30",
+            "A.power=off",
+        ])
+        .unwrap();
+        assert_eq!(
+            profile.args(),
+            [
+                "A=hdmi1",
+                "A.power=on",
+                "A.brightness=50%",
+                "A.brightness=30",
+                "A.power=off"
+            ]
+        );
+        let changes = profile
+            .apply(&mut monitors, &MatchOptions::default())
+            .unwrap();
+        // The last one of each feature wins, in the order of `Monitor::apply()`.
+        assert_eq!(
+            changes[0].writes,
+            [
+                VcpWrite::InputSource(InputSource::Hdmi1.as_raw()),
+                VcpWrite::Brightness(30),
+                VcpWrite::PowerMode(4),
+            ]
+        );
+        assert!(changes[0].result.is_ok());
+        assert_eq!(monitors[0].brightness().unwrap().value, 30);
+
+        assert!(Profile::parse(["A.brightness=x"]).is_err());
+        assert!(Profile::parse(["A.brightness=101%"]).is_err());
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn apply_failure() {
+        use crate::testing::FakeDevice;
+        let devices = [FakeDevice::new("A"), FakeDevice::new("B")];
+        devices[0].fail_code(INPUT_SELECT);
+        let mut monitors: Vec<Monitor> = devices
+            .iter()
+            .map(|device| Monitor::from_device(device.clone()))
+            .collect();
+        let profile = Profile::parse(["A=hdmi1", "B=hdmi1"]).unwrap();
+        let changes = profile
+            .apply(&mut monitors, &MatchOptions::default())
+            .unwrap();
+        assert_eq!(changes.len(), 2);
+        assert!(changes[0].result.is_err());
+        assert!(changes[1].result.is_ok());
+        assert_eq!(devices[1].input_source().unwrap(), "Hdmi1");
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn store_from_config() {
+        let config = Config::from_toml(r#"profiles.work = ["desk=dp1"]"#).unwrap();
+        let store = ProfileStore::from_config(&config).unwrap();
+        assert_eq!(store.names().collect::<Vec<_>>(), ["work"]);
+
+        let config = Config::from_toml(r#"profiles.bad = ["desk"]"#).unwrap();
+        let error = ProfileStore::from_config(&config).unwrap_err();
+        assert!(error.to_string().starts_with("bad: "));
+    }
+}
@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use super::*;
+
+/// Known deviations of display monitors from the MCCS standard,
+/// and their vendor-specific VCP features,
+/// applied automatically by [`Monitor`].
+#[derive(Debug)]
+pub(crate) struct Quirks {
+    /// The manufacturer ID in the EDID, such as `DEL` for Dell.
+    pub manufacturer_id: &'static str,
+    /// The prefix of the model names, for when the EDID isn't available.
+    pub model_prefix: &'static str,
+    /// The product codes in the EDID.
+    /// If this and the `model_names` are empty, all products of the manufacturer.
+    pub product_codes: &'static [u16],
+    /// The model names, for products whose product codes aren't known.
+    /// If this and the `product_codes` are empty, all products of the manufacturer.
+    pub model_names: &'static [&'static str],
+    /// The pairs of the standard input source and the value the display monitor uses instead.
+    pub input_remaps: &'static [(InputSourceRaw, u16)],
+    /// The delay after changes, for display monitors that ignore requests for a while.
+    pub settle_delay: Option<Duration>,
+    /// The capabilities string to use instead of the broken one from the display monitor.
+    pub capabilities: Option<&'static str>,
+    /// The vendor-specific VCP feature code of the [`PbpMode`].
+    pub pbp_code: VcpCode,
+    /// The values of the [`PbpMode`]s for the `pbp_code`,
+    /// or empty if picture-by-picture isn't supported.
+    pub pbp_values: &'static [(PbpMode, u16)],
+}
+
+/// The known [`Quirks`].
+/// All entries that match a display monitor apply.
+const QUIRKS: &[Quirks] = &[
+    Quirks {
+        // These Dell display monitors use `0x1B` for their USB-C input.
+        model_names: &["DELL U2720Q", "DELL U2723QE", "DELL U3223QE"],
+        input_remaps: &[(0x19, 0x1B)],
+        ..Quirks::new("DEL", "DELL")
+    },
+    Quirks {
+        // Not all models support all the modes.
+        pbp_code: 0xE9,
+        pbp_values: &[
+            (PbpMode::Off, 0x00),
+            (PbpMode::PipSmall, 0x01),
+            (PbpMode::PipLarge, 0x02),
+            (PbpMode::Pbp2x1, 0x24),
+            (PbpMode::Pbp2x2, 0x41),
+        ],
+        ..Quirks::new("DEL", "DELL")
+    },
+    Quirks {
+        pbp_code: 0xF7,
+        pbp_values: &[
+            (PbpMode::Off, 0x00),
+            (PbpMode::Pbp2x1, 0x01),
+            (PbpMode::PipSmall, 0x02),
+        ],
+        ..Quirks::new("GSM", "LG ")
+    },
+];
+
+impl Quirks {
+    /// The [`Quirks`] of all products of the manufacturer without any deviations.
+    const fn new(manufacturer_id: &'static str, model_prefix: &'static str) -> Self {
+        Self {
+            manufacturer_id,
+            model_prefix,
+            product_codes: &[],
+            model_names: &[],
+            input_remaps: &[],
+            settle_delay: None,
+            capabilities: None,
+            pbp_code: 0,
+            pbp_values: &[],
+        }
+    }
+
+    /// The [`Quirks`] of the display monitor.
+    pub fn find(
+        manufacturer_id: Option<&str>,
+        product_code: Option<u16>,
+        model_name: Option<&str>,
+    ) -> impl Iterator<Item = &'static Self> + use<> {
+        Self::find_in(QUIRKS, manufacturer_id, product_code, model_name)
+    }
+
+    /// The [`Quirks`] of the picture-by-picture of the `monitor`, if known.
+    pub fn find_pbp(monitor: &Monitor) -> Option<&'static Self> {
+        Self::find(
+            monitor.manufacturer_id().as_deref(),
+            monitor.edid().and_then(product_code_from_edid),
+            monitor.model_name(),
+        )
+        .find(|quirks| !quirks.pbp_values.is_empty())
+    }
+
+    fn find_in<'a>(
+        quirks: &'a [Self],
+        manufacturer_id: Option<&str>,
+        product_code: Option<u16>,
+        model_name: Option<&str>,
+    ) -> impl Iterator<Item = &'a Self> + use<'a> {
+        let manufacturer_id = manufacturer_id.map(str::to_string);
+        let model_name = model_name.map(str::to_ascii_uppercase);
+        quirks.iter().filter(move |quirks| {
+            let is_manufacturer = match (&manufacturer_id, &model_name) {
+                (Some(manufacturer_id), _) => quirks.manufacturer_id == manufacturer_id,
+                (None, Some(model_name)) => model_name.starts_with(quirks.model_prefix),
+                (None, None) => false,
+            };
+            is_manufacturer && quirks.is_product(product_code, model_name.as_deref())
+        })
+    }
+
+    /// Whether the `product_code` or the upper-cased `model_name`
+    /// is one of the products of this [`Quirks`].
+    fn is_product(&self, product_code: Option<u16>, model_name: Option<&str>) -> bool {
+        (self.product_codes.is_empty() && self.model_names.is_empty())
+            || product_code.is_some_and(|code| self.product_codes.contains(&code))
+            || model_name.is_some_and(|name| self.model_names.contains(&name))
+    }
+
+    /// The value of the `mode` for the [`Quirks::pbp_code`].
+    pub fn pbp_value(&self, mode: PbpMode) -> Option<u16> {
+        self.pbp_values
+            .iter()
+            .find(|(m, _)| *m == mode)
+            .map(|(_, value)| *value)
+    }
+
+    /// The [`PbpMode`] of the `value` of the [`Quirks::pbp_code`].
+    pub fn pbp_mode(&self, value: u16) -> Option<PbpMode> {
+        self.pbp_values
+            .iter()
+            .find(|(_, v)| *v == value)
+            .map(|(mode, _)| *mode)
+    }
+}
+
+/// The product code in the `edid`.
+pub(crate) fn product_code_from_edid(edid: &[u8]) -> Option<u16> {
+    Some(u16::from_le_bytes([*edid.get(10)?, *edid.get(11)?]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_in() {
+        static QUIRKS: &[Quirks] = &[
+            Quirks {
+                product_codes: &[0x1234],
+                settle_delay: Some(Duration::from_secs(1)),
+                ..Quirks::new("ABC", "ABC")
+            },
+            Quirks {
+                model_names: &["ABC 2"],
+                input_remaps: &[(0x19, 0x1B)],
+                ..Quirks::new("ABC", "ABC")
+            },
+            Quirks {
+                pbp_values: &[(PbpMode::Off, 0)],
+                ..Quirks::new("ABC", "ABC")
+            },
+        ];
+        let find = |manufacturer_id, product_code, model_name| {
+            Quirks::find_in(QUIRKS, manufacturer_id, product_code, model_name)
+                .map(|quirks| quirks as *const Quirks)
+                .collect::<Vec<_>>()
+        };
+        let ptr = |index: usize| &QUIRKS[index] as *const Quirks;
+        assert_eq!(find(Some("ABC"), Some(0x1234), None), [ptr(0), ptr(2)]);
+        assert_eq!(find(Some("ABC"), Some(0x5678), None), [ptr(2)]);
+        assert_eq!(find(Some("ABC"), None, Some("abc 2")), [ptr(1), ptr(2)]);
+        assert_eq!(find(Some("XYZ"), Some(0x1234), Some("ABC 2")), []);
+        assert_eq!(find(None, None, Some("abc 1")), [ptr(2)]);
+        assert_eq!(find(None, None, Some("abc 2")), [ptr(1), ptr(2)]);
+        assert_eq!(find(None, None, None), []);
+    }
+
+    #[test]
+    fn product_code_from_edid() {
+        let mut edid = [0u8; 128];
+        edid[10..12].copy_from_slice(&[0x34, 0x12]);
+        assert_eq!(super::product_code_from_edid(&edid), Some(0x1234));
+        assert_eq!(super::product_code_from_edid(&edid[..11]), None);
+    }
+}
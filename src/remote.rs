@@ -0,0 +1,231 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, mpsc};
+use std::time::Duration;
+
+use super::*;
+use crate::logging::*;
+use serde::{Deserialize, Serialize};
+
+/// The port of the `daemon --listen` when it's omitted.
+pub(crate) const DEFAULT_PORT: u16 = 4747;
+
+/// The timeout of reading requests from and writing responses to clients,
+/// so that idle clients don't keep their threads forever.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+/// A request from `--host` to the `daemon --listen`, as a JSON line.
+pub(crate) struct RemoteRequest {
+    pub args: Vec<String>,
+    /// The profile in the configuration file of the `daemon` to run in addition to the `args`.
+    pub profile: Option<String>,
+    pub dry_run: bool,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+/// A response to [`RemoteRequest`], as a JSON line.
+pub(crate) struct RemoteResponse {
+    /// The standard output, such as the display monitor listings.
+    pub output: String,
+    pub results: Vec<SetResult>,
+    pub error: Option<String>,
+}
+
+/// A [`RemoteRequest`] and the channel to send its response.
+pub(crate) type RemoteCall = (RemoteRequest, mpsc::Sender<RemoteResponse>);
+
+/// Append the `default_port` if `host` doesn't have a port.
+pub(crate) fn with_default_port(host: &str, default_port: u16) -> String {
+    if host
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| port.parse::<u16>().is_ok())
+    {
+        return host.to_string();
+    }
+    format!("{host}:{default_port}")
+}
+
+/// Send the `request` to the `daemon --listen` at the `host`.
+pub(crate) fn send(host: &str, request: &RemoteRequest) -> anyhow::Result<RemoteResponse> {
+    let address = with_default_port(host, DEFAULT_PORT);
+    debug!("Remote: {address}");
+    let stream = TcpStream::connect(&address)
+        .map_err(|e| anyhow::anyhow!("Failed to connect to {address}: {e}"))?;
+    // Changes may take seconds, such as when capabilities are needed.
+    stream.set_read_timeout(Some(Duration::from_secs(60)))?;
+    let mut writer = &stream;
+    writeln!(writer, "{}", serde_json::to_string(request)?)?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// Fail if the `address` accepts connections from other computers without the `token`,
+/// because anyone on the network could change the display monitors.
+pub(crate) fn check_listen_address(address: &str, token: Option<&str>) -> anyhow::Result<()> {
+    if token.is_some() {
+        return Ok(());
+    }
+    let addresses = address
+        .to_socket_addrs()
+        .map_err(|e| anyhow::anyhow!("Failed to resolve {address}: {e}"))?;
+    for resolved in addresses {
+        if !resolved.ip().is_loopback() {
+            anyhow::bail!(
+                "Listening to {address} requires the `token` in the `[remote]` configuration, \
+                because anyone on the network could change the display monitors"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Listen to [`RemoteRequest`]s at the `address`,
+/// and send them to the `sender` from background threads, one for each connection.
+/// Requests without the `token` are rejected if it's set.
+/// Addresses other than the loopback require the `token`.
+/// Returns the address listening to.
+pub(crate) fn listen<T: From<RemoteCall> + Send + 'static>(
+    address: &str,
+    token: Option<String>,
+    sender: mpsc::Sender<T>,
+) -> anyhow::Result<SocketAddr> {
+    let address = with_default_port(address, DEFAULT_PORT);
+    check_listen_address(&address, token.as_deref())?;
+    let listener = TcpListener::bind(&address)
+        .map_err(|e| anyhow::anyhow!("Failed to listen to {address}: {e}"))?;
+    let local_address = listener.local_addr()?;
+    info!("Listening to {local_address}");
+    let token = Arc::new(token);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("Remote: {e}");
+                    continue;
+                }
+            };
+            let token = token.clone();
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = handle_connection(&stream, token.as_deref(), &sender) {
+                    warn!("Remote: {e}");
+                }
+            });
+        }
+    });
+    Ok(local_address)
+}
+
+fn handle_connection<T: From<RemoteCall>>(
+    stream: &TcpStream,
+    token: Option<&str>,
+    sender: &mpsc::Sender<T>,
+) -> anyhow::Result<()> {
+    let peer = stream.peer_addr()?;
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let request: RemoteRequest = serde_json::from_str(&line)?;
+    info!("Remote({peer}): {}", request.args.join(" "));
+    let response = if token.is_some() && request.token.as_deref() != token {
+        RemoteResponse {
+            error: Some("Invalid token".into()),
+            ..Default::default()
+        }
+    } else {
+        let (response_sender, response_receiver) = mpsc::channel();
+        sender
+            .send((request, response_sender).into())
+            .map_err(|_| anyhow::anyhow!("The daemon has stopped"))?;
+        response_receiver.recv()?
+    };
+    let mut writer = stream;
+    writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_default_port() {
+        assert_eq!(super::with_default_port("pc", DEFAULT_PORT), "pc:4747");
+        assert_eq!(super::with_default_port("pc:80", DEFAULT_PORT), "pc:80");
+        assert_eq!(
+            super::with_default_port("[::1]", DEFAULT_PORT),
+            "[::1]:4747"
+        );
+        assert_eq!(
+            super::with_default_port("[::1]:80", DEFAULT_PORT),
+            "[::1]:80"
+        );
+    }
+
+    #[test]
+    fn send_and_listen() {
+        let (sender, receiver) = mpsc::channel::<RemoteCall>();
+        let address = listen("127.0.0.1:0", Some("secret".into()), sender).unwrap();
+        std::thread::spawn(move || {
+            for (request, response_sender) in receiver {
+                let response = RemoteResponse {
+                    output: request.args.join(" "),
+                    ..Default::default()
+                };
+                response_sender.send(response).unwrap();
+            }
+        });
+
+        let host = address.to_string();
+        let mut request = RemoteRequest {
+            args: vec!["a".into(), "b=c".into()],
+            token: Some("secret".into()),
+            ..Default::default()
+        };
+        let response = send(&host, &request).unwrap();
+        assert_eq!(response.output, "a b=c");
+        assert!(response.error.is_none());
+
+        request.token = None;
+        let response = send(&host, &request).unwrap();
+        assert_eq!(response.error.as_deref(), Some("Invalid token"));
+    }
+
+    #[test]
+    fn check_listen_address() {
+        assert!(super::check_listen_address("127.0.0.1:0", None).is_ok());
+        assert!(super::check_listen_address("[::1]:0", None).is_ok());
+        assert!(super::check_listen_address("0.0.0.0:0", None).is_err());
+        assert!(super::check_listen_address("0.0.0.0:0", Some("secret")).is_ok());
+    }
+
+    #[test]
+    fn idle_client() {
+        let (sender, receiver) = mpsc::channel::<RemoteCall>();
+        let address = listen("127.0.0.1:0", None, sender).unwrap();
+        std::thread::spawn(move || {
+            for (request, response_sender) in receiver {
+                let response = RemoteResponse {
+                    output: request.args.join(" "),
+                    ..Default::default()
+                };
+                response_sender.send(response).unwrap();
+            }
+        });
+
+        // A client that doesn't send requests doesn't block others.
+        let _idle = TcpStream::connect(address).unwrap();
+        let request = RemoteRequest {
+            args: vec!["a".into()],
+            ..Default::default()
+        };
+        let response = send(&address.to_string(), &request).unwrap();
+        assert_eq!(response.output, "a");
+    }
+}
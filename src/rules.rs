@@ -0,0 +1,954 @@
+use std::fmt;
+use std::str::FromStr;
+
+use jiff::Zoned;
+use jiff::civil::{Time, Weekday};
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+/// A rule to run when its condition is met, in the `daemon` command.
+/// Rules are evaluated in addition to other triggers of the `daemon`,
+/// such as the [`Config::schedules`] and the [`Config::idle`],
+/// which keep running independently of rules.
+/// # Examples
+/// ```toml
+/// [[rules]]
+/// when = "hotplug && monitor ~ 'DELL'"
+/// then = "set DP1"
+///
+/// [[rules]]
+/// when = "minute && time == '09:00' && day == 'weekdays'"
+/// then = "run desk=DP1"
+///
+/// [[rules]]
+/// when = "unlock"
+/// then = "profile work"
+/// ```
+pub struct Rule {
+    /// The condition, such as `hotplug && monitor ~ 'DELL'`.
+    /// It combines events such as `hotplug`, `idle(10)`, or `lock`,
+    /// and comparisons of `monitor`, `time`, `day`,
+    /// and `input('NAME')` for the input sources of other display monitors,
+    /// with `&&`, `||`, `!`, and parentheses.
+    pub when: String,
+
+    /// The action, `set INPUT` to set the display monitors that match the `when`,
+    /// `run ARGS` to run the command line arguments,
+    /// or `profile NAME` to run the profile with its `profile_settings`.
+    pub then: String,
+}
+
+/// An event that [`Rule`]s can react to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum RuleEvent {
+    /// The `daemon` started.
+    Start,
+    /// A new minute started, to run at times of the day.
+    Minute,
+    /// Display monitors are connected.
+    Hotplug,
+    /// Display monitors are disconnected.
+    Unplug,
+    /// The user is idle for the minutes.
+    Idle(u64),
+    /// The user became active after the shortest [`RuleEvent::Idle`].
+    Active,
+    /// The session is locked.
+    Lock,
+    /// The session is unlocked.
+    Unlock,
+    /// The lid is closed.
+    LidClose,
+    /// The lid is opened.
+    LidOpen,
+    /// The AC power is connected.
+    Ac,
+    /// The AC power is disconnected.
+    Battery,
+    /// The USB device of the [`UsbConfig`] is connected.
+    UsbConnect,
+    /// The USB device of the [`UsbConfig`] is disconnected.
+    UsbDisconnect,
+}
+
+impl RuleEvent {
+    /// The events without parameters, and their names.
+    const NAMES: [(&'static str, RuleEvent); 13] = [
+        ("start", RuleEvent::Start),
+        ("minute", RuleEvent::Minute),
+        ("hotplug", RuleEvent::Hotplug),
+        ("unplug", RuleEvent::Unplug),
+        ("active", RuleEvent::Active),
+        ("lock", RuleEvent::Lock),
+        ("unlock", RuleEvent::Unlock),
+        ("lid_close", RuleEvent::LidClose),
+        ("lid_open", RuleEvent::LidOpen),
+        ("ac", RuleEvent::Ac),
+        ("battery", RuleEvent::Battery),
+        ("usb_connect", RuleEvent::UsbConnect),
+        ("usb_disconnect", RuleEvent::UsbDisconnect),
+    ];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, event)| *event)
+    }
+
+    /// Whether the event needs [`Platform::listen_hotplug()`][crate::platform::Platform::listen_hotplug].
+    pub fn is_dock(&self) -> bool {
+        matches!(
+            self,
+            RuleEvent::Hotplug | RuleEvent::Unplug | RuleEvent::Ac | RuleEvent::Battery
+        )
+    }
+
+    /// Whether the event needs the [`UsbWatcher`].
+    pub fn is_usb(&self) -> bool {
+        matches!(self, RuleEvent::UsbConnect | RuleEvent::UsbDisconnect)
+    }
+
+    /// Whether the event needs [`Platform::listen_session()`][crate::platform::Platform::listen_session].
+    pub fn is_session(&self) -> bool {
+        matches!(
+            self,
+            RuleEvent::Lock | RuleEvent::Unlock | RuleEvent::LidClose | RuleEvent::LidOpen
+        )
+    }
+}
+
+impl From<SessionEvent> for RuleEvent {
+    fn from(event: SessionEvent) -> Self {
+        match event {
+            SessionEvent::Lock => RuleEvent::Lock,
+            SessionEvent::Unlock => RuleEvent::Unlock,
+            SessionEvent::LidClose => RuleEvent::LidClose,
+            SessionEvent::LidOpen => RuleEvent::LidOpen,
+        }
+    }
+}
+
+impl fmt::Display for RuleEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let RuleEvent::Idle(minutes) = self {
+            return write!(f, "idle({minutes})");
+        }
+        let (name, _) = Self::NAMES.iter().find(|(_, event)| event == self).unwrap();
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for RuleEvent {
+    type Err = anyhow::Error;
+
+    /// Parse the name of an event, such as `hotplug` or `idle(10)`.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut parser = Parser::new(s)?;
+        let expr = parser.parse()?;
+        match expr {
+            Expr::Event(event) => Ok(event),
+            _ => anyhow::bail!("\"{s}\" is not an event"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum CompareOp {
+    Contains,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn compare<T: PartialOrd>(self, left: &T, right: &T) -> bool {
+        match self {
+            CompareOp::Contains | CompareOp::Eq => left == right,
+            CompareOp::Ne => left != right,
+            CompareOp::Lt => left < right,
+            CompareOp::Le => left <= right,
+            CompareOp::Gt => left > right,
+            CompareOp::Ge => left >= right,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(u64),
+    Str(String),
+    Op(CompareOp),
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+}
+
+fn tokenize(text: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let mut next_is = |expected: char| chars.next_if_eq(&expected).is_some();
+        let token = match c {
+            _ if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '&' if next_is('&') => Token::And,
+            '|' if next_is('|') => Token::Or,
+            '!' if next_is('=') => Token::Op(CompareOp::Ne),
+            '!' => Token::Not,
+            '=' if next_is('=') => Token::Op(CompareOp::Eq),
+            '<' if next_is('=') => Token::Op(CompareOp::Le),
+            '<' => Token::Op(CompareOp::Lt),
+            '>' if next_is('=') => Token::Op(CompareOp::Ge),
+            '>' => Token::Op(CompareOp::Gt),
+            '~' => Token::Op(CompareOp::Contains),
+            '\'' | '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(ch) if ch == c => break,
+                        Some(ch) => value.push(ch),
+                        None => anyhow::bail!("Unterminated string \"{c}{value}\""),
+                    }
+                }
+                Token::Str(value)
+            }
+            _ if c.is_ascii_digit() => {
+                let mut value = c.to_string();
+                while let Some(ch) = chars.next_if(|ch| ch.is_ascii_digit()) {
+                    value.push(ch);
+                }
+                Token::Number(value.parse()?)
+            }
+            _ if c.is_ascii_alphabetic() || c == '_' => {
+                let mut value = c.to_string();
+                while let Some(ch) = chars.next_if(|ch| ch.is_ascii_alphanumeric() || *ch == '_') {
+                    value.push(ch);
+                }
+                Token::Ident(value)
+            }
+            _ => anyhow::bail!("Unexpected \"{c}\""),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// A parsed condition of [`Rule::when`].
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Event(RuleEvent),
+    Monitor(CompareOp, String),
+    Time(CompareOp, Time),
+    Day(CompareOp, Vec<Weekday>),
+    /// `input('NAME') == 'INPUT'`.
+    InputOf(CompareOp, String, InputSourceRaw),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, context: &RuleContext) -> bool {
+        match self {
+            Expr::Event(event) => context.event == *event,
+            Expr::Monitor(op, value) => context.monitor.is_some_and(|monitor| {
+                let is_matched = match op {
+                    CompareOp::Contains => monitor.matches(&Selector::from_name(value)),
+                    _ => monitor.all_names().any(|name| name == value),
+                };
+                is_matched != (*op == CompareOp::Ne)
+            }),
+            Expr::Time(op, time) => {
+                let now = context.now;
+                let now = Time::constant(now.hour(), now.minute(), 0, 0);
+                op.compare(&now, time)
+            }
+            Expr::Day(op, days) => days.contains(&context.now.weekday()) != (*op == CompareOp::Ne),
+            Expr::InputOf(op, name, value) => {
+                let selector = Selector::from_name(name);
+                context
+                    .connected
+                    .iter()
+                    .any(|monitor| monitor.input == Some(*value) && monitor.matches(&selector))
+                    != (*op == CompareOp::Ne)
+            }
+            Expr::Not(expr) => !expr.eval(context),
+            Expr::And(left, right) => left.eval(context) && right.eval(context),
+            Expr::Or(left, right) => left.eval(context) || right.eval(context),
+        }
+    }
+
+    /// Collect the events in the condition.
+    fn events(&self, events: &mut Vec<RuleEvent>) {
+        match self {
+            Expr::Event(event) => events.push(*event),
+            Expr::Not(expr) => expr.events(events),
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                left.events(events);
+                right.events(events);
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether the condition has `input('NAME')`.
+    fn uses_input(&self) -> bool {
+        match self {
+            Expr::InputOf(..) => true,
+            Expr::Not(expr) => expr.uses_input(),
+            Expr::And(left, right) | Expr::Or(left, right) => {
+                left.uses_input() || right.uses_input()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A recursive descent parser of [`Rule::when`].
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(text: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            tokens: tokenize(text)?,
+            pos: 0,
+        })
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn next_if(&mut self, expected: &Token) -> bool {
+        if self.tokens.get(self.pos) == Some(expected) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn parse(&mut self) -> anyhow::Result<Expr> {
+        let expr = self.parse_or()?;
+        if let Some(token) = self.next() {
+            anyhow::bail!("Unexpected {token:?}");
+        }
+        Ok(expr)
+    }
+
+    fn parse_or(&mut self) -> anyhow::Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.next_if(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> anyhow::Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.next_if(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> anyhow::Result<Expr> {
+        match self.next() {
+            Some(Token::Not) => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                if !self.next_if(&Token::RParen) {
+                    anyhow::bail!("Missing \")\"");
+                }
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) => self.parse_name(&name),
+            Some(token) => anyhow::bail!("Unexpected {token:?}"),
+            None => anyhow::bail!("Unexpected end"),
+        }
+    }
+
+    fn parse_name(&mut self, name: &str) -> anyhow::Result<Expr> {
+        match name {
+            "monitor" | "time" | "day" => {
+                let (Some(Token::Op(op)), Some(Token::Str(value))) = (self.next(), self.next())
+                else {
+                    anyhow::bail!("`{name}` needs a comparison, such as `{name} == '...'`");
+                };
+                match name {
+                    "monitor"
+                        if matches!(op, CompareOp::Contains | CompareOp::Eq | CompareOp::Ne) =>
+                    {
+                        Ok(Expr::Monitor(op, value))
+                    }
+                    "time" if op != CompareOp::Contains => {
+                        let time = value
+                            .parse()
+                            .map_err(|e| anyhow::anyhow!("Invalid time \"{value}\": {e}"))?;
+                        Ok(Expr::Time(op, time))
+                    }
+                    "day" if matches!(op, CompareOp::Eq | CompareOp::Ne) => {
+                        Ok(Expr::Day(op, parse_day(&value)?))
+                    }
+                    _ => anyhow::bail!("`{name}` can't be compared by {op:?}"),
+                }
+            }
+            "input" => {
+                let (
+                    Some(Token::LParen),
+                    Some(Token::Str(monitor)),
+                    Some(Token::RParen),
+                    Some(Token::Op(op @ (CompareOp::Eq | CompareOp::Ne))),
+                    Some(Token::Str(value)),
+                ) = (
+                    self.next(),
+                    self.next(),
+                    self.next(),
+                    self.next(),
+                    self.next(),
+                )
+                else {
+                    anyhow::bail!(
+                        "`input` needs a name and an input source, such as `input('DELL') == 'DP1'`"
+                    );
+                };
+                Ok(Expr::InputOf(
+                    op,
+                    monitor,
+                    InputSource::raw_from_str(&value)?,
+                ))
+            }
+            "idle" => {
+                let (Some(Token::LParen), Some(Token::Number(minutes)), Some(Token::RParen)) =
+                    (self.next(), self.next(), self.next())
+                else {
+                    anyhow::bail!("`idle` needs minutes, such as `idle(10)`");
+                };
+                Ok(Expr::Event(RuleEvent::Idle(minutes)))
+            }
+            _ => RuleEvent::from_name(name)
+                .map(Expr::Event)
+                .ok_or_else(|| anyhow::anyhow!("Unknown name `{name}`")),
+        }
+    }
+}
+
+/// A display monitor to evaluate [`Rule`]s for.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RuleMonitor {
+    /// The index of the display monitor, or `None` if it's disconnected.
+    pub index: Option<usize>,
+    /// The names of the display monitor,
+    /// the [`Monitor::id()`] and the string of [`Monitor::set_id_format()`].
+    pub names: Vec<String>,
+    /// The display output, whose name is also matched.
+    pub output: Option<DisplayOutput>,
+    /// The current input source for `input('NAME')`, if read.
+    pub input: Option<InputSourceRaw>,
+}
+
+impl RuleMonitor {
+    pub fn new(index: Option<usize>, monitor: &Monitor) -> Self {
+        let mut names = vec![monitor.id().to_string()];
+        names.extend(monitor.formatted_id());
+        Self {
+            index,
+            names,
+            output: monitor.display_output().cloned(),
+            input: None,
+        }
+    }
+
+    /// The [`RuleMonitor::names`] and the name of the [`RuleMonitor::output`].
+    fn all_names(&self) -> impl Iterator<Item = &str> {
+        self.names
+            .iter()
+            .map(String::as_str)
+            .chain(self.output.as_ref().map(|output| output.name.as_str()))
+    }
+
+    /// Whether the `selector` matches, by the same rules as the command line,
+    /// so that a part of the names matches, but the display output must match exactly.
+    fn matches(&self, selector: &Selector) -> bool {
+        selector.matches_names(
+            self.index,
+            self.names.iter().map(String::as_str),
+            self.output.as_ref(),
+        )
+    }
+}
+
+struct RuleContext<'a> {
+    event: RuleEvent,
+    monitor: Option<&'a RuleMonitor>,
+    /// The connected display monitors with their input sources, for `input('NAME')`.
+    connected: &'a [RuleMonitor],
+    now: &'a Zoned,
+}
+
+/// The action of [`Rule::then`].
+#[derive(Clone, Debug, PartialEq)]
+enum RuleAction {
+    Set(String),
+    Run(Vec<String>),
+    Profile(String),
+}
+
+impl FromStr for RuleAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut words = s.split_whitespace();
+        let verb = words.next().unwrap_or_default();
+        let args: Vec<String> = words.map(str::to_string).collect();
+        match verb {
+            "set" if args.len() == 1 => Ok(RuleAction::Set(args[0].clone())),
+            "set" => anyhow::bail!("`set` needs one input source, such as `set DP1`"),
+            "run" if !args.is_empty() => Ok(RuleAction::Run(args)),
+            "run" => anyhow::bail!("`run` needs arguments, such as `run desk=DP1`"),
+            "profile" if args.len() == 1 => Ok(RuleAction::Profile(args[0].clone())),
+            "profile" => anyhow::bail!("`profile` needs one profile, such as `profile work`"),
+            _ => anyhow::bail!("Unknown action \"{s}\", it should be `set`, `run`, or `profile`"),
+        }
+    }
+}
+
+/// A [`Rule`] parsed to evaluate.
+#[derive(Debug)]
+pub(crate) struct CompiledRule {
+    /// Where in the configuration file, such as `rules[0]` or `usb.monitors.monitor1`.
+    pub location: String,
+    when: Expr,
+    then: RuleAction,
+}
+
+impl CompiledRule {
+    fn new(index: usize, rule: &Rule) -> anyhow::Result<Self> {
+        let when = Parser::new(&rule.when)?.parse()?;
+        let mut events = vec![];
+        when.events(&mut events);
+        if events.is_empty() {
+            anyhow::bail!("The condition has no events, such as `hotplug` or `lock`");
+        }
+        Ok(Self {
+            location: format!("rules[{index}]"),
+            when,
+            then: rule.then.parse()?,
+        })
+    }
+
+    /// The rules to set the input sources of the [`UsbConfig`],
+    /// as `set INPUT` on [`RuleEvent::UsbConnect`] and [`RuleEvent::UsbDisconnect`].
+    /// Each display monitor is set by the first [`UsbMonitorConfig`] whose `monitor_id` matches
+    /// and has the input source for the event, or by the global one otherwise.
+    fn from_usb_config(config: &UsbConfig) -> Vec<Self> {
+        let mut rules = vec![];
+        for event in [RuleEvent::UsbConnect, RuleEvent::UsbDisconnect] {
+            let input = |on_connect: &Option<String>, on_disconnect: &Option<String>| match event {
+                RuleEvent::UsbConnect => on_connect.clone(),
+                _ => on_disconnect.clone(),
+            };
+            // The display monitors set by the sections before.
+            let mut excluded: Option<Expr> = None;
+            let mut push = |location: String, input: String, monitor: Option<Expr>| {
+                let mut when = Expr::Event(event);
+                if let Some(excluded) = &excluded {
+                    when = Expr::And(
+                        Box::new(when),
+                        Box::new(Expr::Not(Box::new(excluded.clone()))),
+                    );
+                }
+                if let Some(monitor) = monitor {
+                    when = Expr::And(Box::new(when), Box::new(monitor.clone()));
+                    excluded = Some(match excluded.take() {
+                        Some(excluded) => Expr::Or(Box::new(excluded), Box::new(monitor)),
+                        None => monitor,
+                    });
+                }
+                rules.push(Self {
+                    location,
+                    when,
+                    then: RuleAction::Set(input),
+                });
+            };
+            for (section, monitor_config) in &config.monitors {
+                if let Some(input) = input(
+                    &monitor_config.on_usb_connect,
+                    &monitor_config.on_usb_disconnect,
+                ) {
+                    let monitor =
+                        Expr::Monitor(CompareOp::Contains, monitor_config.monitor_id.clone());
+                    push(format!("usb.monitors.{section}"), input, Some(monitor));
+                }
+            }
+            if let Some(input) = input(&config.on_usb_connect, &config.on_usb_disconnect) {
+                push("usb".to_string(), input, None);
+            }
+        }
+        rules
+    }
+
+    /// The events in the condition.
+    pub fn events(&self) -> Vec<RuleEvent> {
+        let mut events = vec![];
+        self.when.events(&mut events);
+        events
+    }
+
+    /// Whether the condition has `input('NAME')`,
+    /// which needs [`RuleMonitor::input`] of the connected display monitors.
+    pub fn uses_input(&self) -> bool {
+        self.when.uses_input()
+    }
+
+    /// The profile to run with the [`CompiledRule::args()`], for `profile NAME`.
+    pub fn profile(&self) -> Option<&str> {
+        match &self.then {
+            RuleAction::Profile(profile) => Some(profile),
+            _ => None,
+        }
+    }
+
+    /// The command line arguments to run for the `event`,
+    /// or `None` if the rule doesn't match.
+    /// They're empty for `profile NAME`; run the [`CompiledRule::profile()`] instead.
+    /// The condition is evaluated for each of the `monitors`,
+    /// and `set` changes the display monitors that match.
+    /// `input('NAME')` is evaluated with the `connected` display monitors.
+    pub fn args(
+        &self,
+        event: RuleEvent,
+        monitors: &[RuleMonitor],
+        connected: &[RuleMonitor],
+        now: &Zoned,
+    ) -> Option<Vec<String>> {
+        let context = |monitor| RuleContext {
+            event,
+            monitor,
+            connected,
+            now,
+        };
+        let matched: Vec<&RuleMonitor> = monitors
+            .iter()
+            .filter(|monitor| self.when.eval(&context(Some(monitor))))
+            .collect();
+        let is_matched = if monitors.is_empty() {
+            self.when.eval(&context(None))
+        } else {
+            !matched.is_empty()
+        };
+        if !is_matched {
+            return None;
+        }
+        match &self.then {
+            RuleAction::Run(args) => Some(args.clone()),
+            RuleAction::Profile(_) => Some(vec![]),
+            RuleAction::Set(input) => {
+                let args: Vec<String> = matched
+                    .iter()
+                    .filter_map(|monitor| monitor.index)
+                    .map(|index| format!("{index}={input}"))
+                    .collect();
+                (!args.is_empty()).then_some(args)
+            }
+        }
+    }
+}
+
+/// Parse the [`Config::rules`], and add the rules of the [`Config::usb`] after them.
+/// It's an error if the rules have [`RuleEvent::is_usb()`] events without the [`Config::usb`].
+pub(crate) fn compile_config_rules(config: &Config) -> anyhow::Result<Vec<CompiledRule>> {
+    let mut rules = compile_rules(&config.rules)?;
+    match &config.usb {
+        Some(usb) => rules.extend(CompiledRule::from_usb_config(usb)),
+        None => {
+            if let Some(rule) = rules
+                .iter()
+                .find(|rule| rule.events().iter().any(RuleEvent::is_usb))
+            {
+                anyhow::bail!(
+                    "{}: `usb_connect` and `usb_disconnect` need the `usb` table",
+                    rule.location
+                );
+            }
+        }
+    }
+    Ok(rules)
+}
+
+/// Parse the `rules`.
+/// Errors have the index of the rule.
+pub(crate) fn compile_rules(rules: &[Rule]) -> anyhow::Result<Vec<CompiledRule>> {
+    rules
+        .iter()
+        .enumerate()
+        .map(|(index, rule)| {
+            CompiledRule::new(index, rule).map_err(|e| {
+                anyhow::anyhow!(
+                    "rules[{index}]: {e}: when = \"{}\", then = \"{}\"",
+                    rule.when,
+                    rule.then
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile_rule(when: &str, then: &str) -> anyhow::Result<CompiledRule> {
+        CompiledRule::new(
+            0,
+            &Rule {
+                when: when.into(),
+                then: then.into(),
+            },
+        )
+    }
+
+    fn monitor(index: Option<usize>, name: &str) -> RuleMonitor {
+        RuleMonitor {
+            index,
+            names: vec![name.to_string()],
+            output: None,
+            input: None,
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Parser::new("hotplug && !(monitor ~ 'DELL' || time >= \"09:30\")")
+                .unwrap()
+                .parse()
+                .unwrap(),
+            Expr::And(
+                Box::new(Expr::Event(RuleEvent::Hotplug)),
+                Box::new(Expr::Not(Box::new(Expr::Or(
+                    Box::new(Expr::Monitor(CompareOp::Contains, "DELL".into())),
+                    Box::new(Expr::Time(CompareOp::Ge, Time::constant(9, 30, 0, 0))),
+                )))),
+            )
+        );
+        assert!(compile_rule("hotplug", "set DP1").is_ok());
+        assert!(compile_rule("idle(10) || lid_close", "run desk=Hdmi1").is_ok());
+        assert!(compile_rule("monitor ~ 'DELL'", "set DP1").is_err());
+        assert!(compile_rule("idle", "set DP1").is_err());
+        assert!(compile_rule("hotplug &&", "set DP1").is_err());
+        assert!(compile_rule("hotplug && monitor < 'A'", "set DP1").is_err());
+        assert!(compile_rule("hotplug && day == 'someday'", "set DP1").is_err());
+        assert!(compile_rule("hotplug && monitor ~ 'A", "set DP1").is_err());
+        assert!(compile_rule("hotplug", "set").is_err());
+        assert!(compile_rule("unlock", "profile work").is_ok());
+        assert!(compile_rule("unlock", "profile").is_err());
+        assert!(compile_rule("hotplug", "switch DP1").is_err());
+    }
+
+    #[test]
+    fn event_from_str() {
+        assert_eq!(
+            "lid_close".parse::<RuleEvent>().unwrap(),
+            RuleEvent::LidClose
+        );
+        assert_eq!("idle(5)".parse::<RuleEvent>().unwrap(), RuleEvent::Idle(5));
+        assert!("hotplug && lock".parse::<RuleEvent>().is_err());
+        assert_eq!(RuleEvent::Idle(5).to_string(), "idle(5)");
+        assert_eq!(RuleEvent::LidOpen.to_string(), "lid_open");
+    }
+
+    #[test]
+    fn args() {
+        // 2025-01-06 is Monday.
+        let now: Zoned = "2025-01-06T09:00:30[UTC]".parse().unwrap();
+        let monitors = [
+            monitor(Some(0), "DELL U2723QE"),
+            monitor(Some(1), "LG 27UK850"),
+        ];
+
+        let rule = compile_rule("hotplug && monitor ~ 'DELL'", "set DP1").unwrap();
+        assert_eq!(
+            rule.args(RuleEvent::Hotplug, &monitors, &[], &now),
+            Some(vec!["0=DP1".into()])
+        );
+        assert_eq!(rule.args(RuleEvent::Lock, &monitors, &[], &now), None);
+        assert_eq!(
+            rule.args(RuleEvent::Hotplug, &monitors[1..], &[], &now),
+            None
+        );
+        assert_eq!(rule.args(RuleEvent::Hotplug, &[], &[], &now), None);
+        // Disconnected display monitors can't be set.
+        let unplugged = [monitor(None, "DELL U2723QE")];
+        assert_eq!(rule.args(RuleEvent::Hotplug, &unplugged, &[], &now), None);
+
+        // Display outputs match exactly, by the same rules as the command line.
+        let mut outputs = monitors.clone();
+        outputs[0].output = Some(DisplayOutput {
+            name: r"\\.\DISPLAY10".into(),
+            ..Default::default()
+        });
+        let rule = compile_rule("hotplug && monitor ~ 'DISPLAY1'", "set DP1").unwrap();
+        assert_eq!(rule.args(RuleEvent::Hotplug, &outputs, &[], &now), None);
+        let rule = compile_rule("hotplug && monitor ~ 'DISPLAY10'", "set DP1").unwrap();
+        assert_eq!(
+            rule.args(RuleEvent::Hotplug, &outputs, &[], &now),
+            Some(vec!["0=DP1".into()])
+        );
+
+        let rule = compile_rule("unplug && monitor != 'LG 27UK850'", "run desk=Hdmi1").unwrap();
+        assert_eq!(
+            rule.args(RuleEvent::Unplug, &unplugged, &[], &now),
+            Some(vec!["desk=Hdmi1".into()])
+        );
+
+        let rule = compile_rule(
+            "minute && time == '09:00' && day == 'weekdays'",
+            "run desk=DP1",
+        )
+        .unwrap();
+        assert!(rule.args(RuleEvent::Minute, &monitors, &[], &now).is_some());
+        assert!(rule.args(RuleEvent::Minute, &[], &[], &now).is_some());
+        let saturday: Zoned = "2025-01-04T09:00[UTC]".parse().unwrap();
+        assert!(
+            rule.args(RuleEvent::Minute, &monitors, &[], &saturday)
+                .is_none()
+        );
+
+        let rule = compile_rule("idle(10)", "set Hdmi1").unwrap();
+        assert_eq!(
+            rule.args(RuleEvent::Idle(10), &monitors, &[], &now),
+            Some(vec!["0=Hdmi1".into(), "1=Hdmi1".into()])
+        );
+        assert_eq!(rule.args(RuleEvent::Idle(5), &monitors, &[], &now), None);
+        assert_eq!(rule.events(), [RuleEvent::Idle(10)]);
+
+        let rule = compile_rule("unlock", "profile work").unwrap();
+        assert_eq!(
+            rule.args(RuleEvent::Unlock, &monitors, &[], &now),
+            Some(vec![])
+        );
+        assert_eq!(rule.profile(), Some("work"));
+    }
+
+    #[test]
+    fn args_input_of() {
+        let now: Zoned = "2025-01-01T09:00[UTC]".parse().unwrap();
+        let monitors = [monitor(Some(1), "LG 27UK850")];
+        let mut connected = [
+            monitor(Some(0), "DELL U2723QE"),
+            monitor(Some(1), "LG 27UK850"),
+        ];
+        connected[0].input = Some(InputSource::DisplayPort1.as_raw());
+
+        let rule = compile_rule("minute && input('DELL') == 'dp1'", "set Hdmi2").unwrap();
+        assert!(rule.uses_input());
+        assert_eq!(
+            rule.args(RuleEvent::Minute, &monitors, &connected, &now),
+            Some(vec!["1=Hdmi2".into()])
+        );
+        connected[0].input = Some(InputSource::Hdmi1.as_raw());
+        assert_eq!(
+            rule.args(RuleEvent::Minute, &monitors, &connected, &now),
+            None
+        );
+
+        let rule = compile_rule("minute && input('DELL') != 'dp1'", "set Hdmi2").unwrap();
+        assert!(
+            rule.args(RuleEvent::Minute, &monitors, &connected, &now)
+                .is_some()
+        );
+        assert!(!compile_rule("minute", "set Hdmi2").unwrap().uses_input());
+
+        // Display outputs match exactly, by the same rules as the command line.
+        connected[0].input = Some(InputSource::DisplayPort1.as_raw());
+        connected[0].output = Some(DisplayOutput {
+            name: r"\\.\DISPLAY10".into(),
+            ..Default::default()
+        });
+        let rule = compile_rule("minute && input('DISPLAY1') == 'dp1'", "set Hdmi2").unwrap();
+        assert_eq!(
+            rule.args(RuleEvent::Minute, &monitors, &connected, &now),
+            None
+        );
+        let rule = compile_rule("minute && input('DISPLAY10') == 'dp1'", "set Hdmi2").unwrap();
+        assert!(
+            rule.args(RuleEvent::Minute, &monitors, &connected, &now)
+                .is_some()
+        );
+
+        assert!(compile_rule("minute && input('DELL') ~ 'dp1'", "set Hdmi2").is_err());
+        assert!(compile_rule("minute && input('DELL') == 'xyz'", "set Hdmi2").is_err());
+    }
+
+    #[test]
+    fn usb_rules() {
+        let now: Zoned = "2025-01-01T09:00[UTC]".parse().unwrap();
+        let config = Config::from_toml(
+            r#"
+            [usb]
+            usb_device = "1050:0407"
+            on_usb_connect = "Hdmi1"
+            on_usb_disconnect = "DisplayPort2"
+
+            [usb.monitors.monitor1]
+            monitor_id = "DELL"
+            on_usb_connect = "DisplayPort1"
+            "#,
+        )
+        .unwrap();
+        let rules = compile_config_rules(&config).unwrap();
+        let monitors = [monitor(Some(0), "DELL U2723QE"), monitor(Some(1), "LG")];
+        let args = |event| -> Vec<String> {
+            rules
+                .iter()
+                .filter_map(|rule| rule.args(event, &monitors, &[], &now))
+                .flatten()
+                .collect()
+        };
+        assert_eq!(args(RuleEvent::UsbConnect), ["0=DisplayPort1", "1=Hdmi1"]);
+        assert_eq!(
+            args(RuleEvent::UsbDisconnect),
+            ["0=DisplayPort2", "1=DisplayPort2"]
+        );
+
+        let config =
+            Config::from_toml("rules = [{ when = 'usb_connect', then = 'set dp1' }]").unwrap();
+        assert!(compile_config_rules(&config).is_err());
+    }
+
+    #[test]
+    fn compile_rules() {
+        let rules = [
+            Rule {
+                when: "lock".into(),
+                then: "set Hdmi1".into(),
+            },
+            Rule {
+                when: "unknown".into(),
+                then: "set Hdmi1".into(),
+            },
+        ];
+        let error = super::compile_rules(&rules).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("rules[1]: Unknown name `unknown`")
+        );
+        assert_eq!(super::compile_rules(&rules[..1]).unwrap().len(), 1);
+    }
+}
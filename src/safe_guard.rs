@@ -0,0 +1,120 @@
+use std::io::{BufRead, IsTerminal, Write};
+
+use super::*;
+use crate::logging::*;
+
+#[derive(Debug, Default)]
+/// Prevents switching all display monitors away from this computer.
+///
+/// The input source connected to this computer is
+/// [`MonitorConfig::local_input`] if configured,
+/// or the input source when the [`SafeGuard`] is created.
+pub(crate) struct SafeGuard {
+    /// The input sources connected to this computer, for each display monitor.
+    local_inputs: Vec<Option<InputSourceRaw>>,
+    /// The current input sources, for each display monitor.
+    current_inputs: Vec<Option<InputSourceRaw>>,
+}
+
+impl SafeGuard {
+    pub fn new(monitors: &mut [Monitor], config: &Config) -> Self {
+        let mut guard = SafeGuard::default();
+        for monitor in monitors {
+            let current = monitor.input_source().ok();
+            let local = config
+                .monitor(monitor.id())
+                .and_then(|monitor_config| monitor_config.local_input.as_deref())
+                .and_then(|value| InputSource::raw_from_str(value).ok())
+                .or(current);
+            debug!(
+                "SafeGuard({monitor}): local={local:?}, current={current:?}",
+                local = local.map(InputSource::str_from_raw),
+                current = current.map(InputSource::str_from_raw)
+            );
+            guard.local_inputs.push(local);
+            guard.current_inputs.push(current);
+        }
+        guard
+    }
+
+    fn is_local(&self, index: usize, value: Option<InputSourceRaw>) -> bool {
+        value.is_some() && self.local_inputs[index] == value
+    }
+
+    /// Determine whether changing the display monitor at `index` to `value`
+    /// leaves no display monitors showing this computer.
+    fn is_unsafe(&self, index: usize, value: InputSourceRaw) -> bool {
+        if !self.is_local(index, self.current_inputs[index]) || self.is_local(index, Some(value)) {
+            return false;
+        }
+        !(0..self.current_inputs.len())
+            .any(|i| i != index && self.is_local(i, self.current_inputs[i]))
+    }
+
+    /// Check the change of the display monitor at `index` to `value`.
+    /// If it's unsafe, ask for the confirmation when the standard input is a terminal.
+    /// Otherwise it's an error.
+    pub fn check(
+        &self,
+        index: usize,
+        monitor: &Monitor,
+        value: InputSourceRaw,
+    ) -> anyhow::Result<()> {
+        if !self.is_unsafe(index, value) {
+            return Ok(());
+        }
+        let message = format!(
+            "Changing {monitor} to {value} leaves no display monitors showing this computer",
+            value = InputSource::str_from_raw(value)
+        );
+        if !std::io::stdin().is_terminal() {
+            anyhow::bail!("{message}.");
+        }
+        print!("{message}. Continue? [y/N] ");
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().lock().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            anyhow::bail!("Canceled.");
+        }
+        Ok(())
+    }
+
+    /// Update the current input source of the display monitor at `index`.
+    pub fn update(&mut self, index: usize, value: InputSourceRaw) {
+        self.current_inputs[index] = Some(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_unsafe() {
+        let mut guard = SafeGuard {
+            local_inputs: vec![Some(1), Some(2)],
+            current_inputs: vec![Some(1), Some(2)],
+        };
+        // The other display monitor remains.
+        assert!(!guard.is_unsafe(0, 3));
+        guard.update(0, 3);
+        // No display monitors remain.
+        assert!(guard.is_unsafe(1, 3));
+        // Changing to the local input source is always safe.
+        assert!(!guard.is_unsafe(1, 2));
+        assert!(!guard.is_unsafe(0, 1));
+        // Changing display monitors not showing this computer is safe.
+        assert!(!guard.is_unsafe(0, 4));
+    }
+
+    #[test]
+    fn is_unsafe_unknown() {
+        // Display monitors whose input sources are unknown are not counted.
+        let guard = SafeGuard {
+            local_inputs: vec![Some(1), None],
+            current_inputs: vec![Some(1), None],
+        };
+        assert!(guard.is_unsafe(0, 3));
+    }
+}
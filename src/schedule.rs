@@ -0,0 +1,257 @@
+use jiff::Zoned;
+use jiff::civil::{Time, Weekday};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+/// A rule to run command line arguments at a time of the day,
+/// in the `daemon` command.
+/// # Examples
+/// ```toml
+/// [[schedules]]
+/// time = "09:00"
+/// days = ["weekdays"]
+/// args = ["Dell=DP1"]
+///
+/// [[schedules]]
+/// time = "18:00"
+/// profile = "home"
+/// ```
+pub struct Schedule {
+    /// The time of the day, such as `09:00`.
+    pub time: String,
+
+    #[serde(default)]
+    /// The days of the week, such as `mon` or `tue`,
+    /// or `weekdays` or `weekends`.
+    /// Every day if empty.
+    pub days: Vec<String>,
+
+    #[serde(default)]
+    /// The command line arguments to run, such as `Dell=DP1`.
+    pub args: Vec<String>,
+
+    #[serde(default)]
+    /// The profile in the configuration file to run in addition to the `args`,
+    /// with its `profile_settings`.
+    pub profile: Option<String>,
+}
+
+impl Schedule {
+    fn parse_time(&self) -> anyhow::Result<Time> {
+        self.time
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid time \"{}\": {e}", self.time))
+    }
+
+    fn parse_days(&self) -> anyhow::Result<Vec<Weekday>> {
+        let mut weekdays = vec![];
+        for day in &self.days {
+            weekdays.extend(parse_day(day)?);
+        }
+        Ok(weekdays)
+    }
+
+    /// Check if the schedule is valid.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.parse_time()?;
+        self.parse_days()?;
+        if self.args.is_empty() && self.profile.is_none() {
+            anyhow::bail!("The schedule at {} has no `args` or `profile`", self.time);
+        }
+        Ok(())
+    }
+
+    /// The next time to run after `now`.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::Schedule;
+    /// let schedule = Schedule {
+    ///     time: "09:00".into(),
+    ///     days: vec!["weekdays".into()],
+    ///     ..Default::default()
+    /// };
+    /// // 2025-01-04 is Saturday.
+    /// let now = "2025-01-04T12:00[UTC]".parse().unwrap();
+    /// let next = schedule.next_run(&now).unwrap();
+    /// assert_eq!(next.to_string(), "2025-01-06T09:00:00+00:00[UTC]");
+    /// ```
+    pub fn next_run(&self, now: &Zoned) -> anyhow::Result<Zoned> {
+        let time = self.parse_time()?;
+        let days = self.parse_days()?;
+        let mut date = now.date();
+        // The next run is within a week, plus one day if today's time has passed.
+        for _ in 0..8 {
+            if days.is_empty() || days.contains(&date.weekday()) {
+                let next = date.to_datetime(time).to_zoned(now.time_zone().clone())?;
+                if next > *now {
+                    return Ok(next);
+                }
+            }
+            date = date.tomorrow()?;
+        }
+        unreachable!("No next run for {self:?}");
+    }
+
+    /// Whether this and the `other` run at the same time on any day.
+    pub(crate) fn overlaps(&self, other: &Schedule) -> bool {
+        let (Ok(time), Ok(other_time)) = (self.parse_time(), other.parse_time()) else {
+            return false;
+        };
+        let (Ok(days), Ok(other_days)) = (self.parse_days(), other.parse_days()) else {
+            return false;
+        };
+        time == other_time
+            && (days.is_empty()
+                || other_days.is_empty()
+                || days.iter().any(|day| other_days.contains(day)))
+    }
+}
+
+/// Parse a day of the week, such as `mon` or `tue`,
+/// or `weekdays` or `weekends`.
+pub(crate) fn parse_day(day: &str) -> anyhow::Result<Vec<Weekday>> {
+    Ok(match day.to_ascii_lowercase().as_str() {
+        "weekdays" => vec![
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+        ],
+        "weekends" => vec![Weekday::Saturday, Weekday::Sunday],
+        "mon" | "monday" => vec![Weekday::Monday],
+        "tue" | "tuesday" => vec![Weekday::Tuesday],
+        "wed" | "wednesday" => vec![Weekday::Wednesday],
+        "thu" | "thursday" => vec![Weekday::Thursday],
+        "fri" | "friday" => vec![Weekday::Friday],
+        "sat" | "saturday" => vec![Weekday::Saturday],
+        "sun" | "sunday" => vec![Weekday::Sunday],
+        _ => anyhow::bail!("Invalid day \"{day}\""),
+    })
+}
+
+/// The earliest next run of the `schedules` after `now`,
+/// and the schedules to run at the time.
+pub(crate) fn next_schedules<'a>(
+    schedules: &'a [Schedule],
+    now: &Zoned,
+) -> anyhow::Result<Option<(Zoned, Vec<&'a Schedule>)>> {
+    let mut next: Option<(Zoned, Vec<&Schedule>)> = None;
+    for schedule in schedules {
+        let time = schedule.next_run(now)?;
+        match &mut next {
+            Some((next_time, due)) if *next_time == time => due.push(schedule),
+            Some((next_time, _)) if *next_time < time => {}
+            _ => next = Some((time, vec![schedule])),
+        }
+    }
+    Ok(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(time: &str, days: &[&str]) -> Schedule {
+        Schedule {
+            time: time.into(),
+            days: days.iter().map(|day| day.to_string()).collect(),
+            args: vec!["a=b".into()],
+            profile: None,
+        }
+    }
+
+    fn next_run(schedule: &Schedule, now: &str) -> String {
+        schedule
+            .next_run(&now.parse().unwrap())
+            .unwrap()
+            .datetime()
+            .to_string()
+    }
+
+    #[test]
+    fn next_run_every_day() {
+        let schedule = schedule("09:00", &[]);
+        assert_eq!(
+            next_run(&schedule, "2025-01-01T08:00[UTC]"),
+            "2025-01-01T09:00:00"
+        );
+        assert_eq!(
+            next_run(&schedule, "2025-01-01T09:00[UTC]"),
+            "2025-01-02T09:00:00"
+        );
+    }
+
+    #[test]
+    fn next_run_days() {
+        // 2025-01-01 is Wednesday.
+        let schedule = schedule("18:00", &["mon", "Wed"]);
+        assert_eq!(
+            next_run(&schedule, "2025-01-01T08:00[UTC]"),
+            "2025-01-01T18:00:00"
+        );
+        assert_eq!(
+            next_run(&schedule, "2025-01-01T19:00[UTC]"),
+            "2025-01-06T18:00:00"
+        );
+        // The same day of the next week.
+        let schedule = self::schedule("09:00", &["wed"]);
+        assert_eq!(
+            next_run(&schedule, "2025-01-01T10:00[UTC]"),
+            "2025-01-08T09:00:00"
+        );
+    }
+
+    #[test]
+    fn next_schedules() {
+        let schedules = [
+            schedule("18:00", &[]),
+            schedule("09:00", &["weekdays"]),
+            schedule("09:00", &[]),
+        ];
+        let now = "2025-01-01T08:00[UTC]".parse().unwrap();
+        let (time, due) = super::next_schedules(&schedules, &now).unwrap().unwrap();
+        assert_eq!(time.datetime().to_string(), "2025-01-01T09:00:00");
+        assert_eq!(due.len(), 2);
+
+        // 2025-01-04 is Saturday.
+        let now = "2025-01-04T08:00[UTC]".parse().unwrap();
+        let (time, due) = super::next_schedules(&schedules, &now).unwrap().unwrap();
+        assert_eq!(time.datetime().to_string(), "2025-01-04T09:00:00");
+        assert_eq!(due.len(), 1);
+
+        assert!(super::next_schedules(&[], &now).unwrap().is_none());
+    }
+
+    #[test]
+    fn overlaps() {
+        assert!(schedule("09:00", &[]).overlaps(&schedule("09:00", &["mon"])));
+        assert!(schedule("09:00", &["weekdays"]).overlaps(&schedule("09:00", &["mon"])));
+        assert!(!schedule("09:00", &["weekends"]).overlaps(&schedule("09:00", &["mon"])));
+        assert!(!schedule("09:00", &[]).overlaps(&schedule("10:00", &[])));
+    }
+
+    #[test]
+    fn validate() {
+        assert!(schedule("09:00", &["weekends"]).validate().is_ok());
+        assert!(schedule("25:00", &[]).validate().is_err());
+        assert!(schedule("09:00", &["xyz"]).validate().is_err());
+        assert!(
+            Schedule {
+                time: "09:00".into(),
+                ..Default::default()
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            Schedule {
+                time: "09:00".into(),
+                profile: Some("work".into()),
+                ..Default::default()
+            }
+            .validate()
+            .is_ok()
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use super::*;
+#[cfg(feature = "cli")]
+use regex::Regex;
+
+#[derive(Debug)]
+/// Selects display monitors by a name in the command line.
+pub(crate) enum Selector {
+    /// All display monitors.
+    All,
+    /// The display monitor at the index.
+    Index(usize),
+    /// Display monitors whose names contain the string.
+    Name(String),
+    /// Display monitors whose names match any of the patterns of a group.
+    #[cfg(feature = "cli")]
+    Group(Vec<Regex>),
+    /// The display monitor of the primary display.
+    Primary,
+}
+
+impl Selector {
+    /// Parse a name in the command line.
+    /// A name is a group name in the `config`, or a name for [`Selector::from_name()`].
+    #[cfg(feature = "cli")]
+    pub fn parse(name: &str, config: &Config) -> anyhow::Result<Self> {
+        if let Some(patterns) = config.groups.get(name) {
+            let patterns = patterns
+                .iter()
+                .map(|pattern| regex_from_wildcard(pattern))
+                .collect::<anyhow::Result<_>>()?;
+            return Ok(Selector::Group(patterns));
+        }
+        Ok(Self::from_name(name))
+    }
+
+    /// Parse a name without groups.
+    /// A name is empty for all, `primary`, an index, or a part of names.
+    pub fn from_name(name: &str) -> Self {
+        if name.is_empty() {
+            return Selector::All;
+        }
+        if name == "primary" {
+            return Selector::Primary;
+        }
+        if let Ok(index) = name.parse::<usize>() {
+            return Selector::Index(index);
+        }
+        Selector::Name(name.to_string())
+    }
+
+    /// Determine whether the `name` of the display monitor at `index` matches.
+    pub fn matches(&self, index: usize, name: &str) -> bool {
+        match self {
+            Selector::All => true,
+            Selector::Index(i) => *i == index,
+            Selector::Name(s) => name.contains(s.as_str()),
+            #[cfg(feature = "cli")]
+            Selector::Group(patterns) => patterns.iter().any(|re| re.is_match(name)),
+            Selector::Primary => false,
+        }
+    }
+
+    /// Determine whether the `monitor` at `index` matches,
+    /// by its name, by its [`Monitor::set_id_format()`],
+    /// or by the name of its [`DisplayOutput`].
+    pub fn matches_monitor(&self, index: usize, monitor: &Monitor) -> bool {
+        let formatted = monitor.formatted_id();
+        self.matches_names(
+            Some(index),
+            [Some(monitor.id()), formatted.as_deref()]
+                .into_iter()
+                .flatten(),
+            monitor.display_output(),
+        )
+    }
+
+    /// Same as [`Selector::matches_monitor()`],
+    /// for the `names` and the `output` of a display monitor at `index`,
+    /// or `None` if it doesn't have the index, such as when it's disconnected.
+    pub fn matches_names<'a>(
+        &self,
+        index: Option<usize>,
+        names: impl IntoIterator<Item = &'a str>,
+        output: Option<&DisplayOutput>,
+    ) -> bool {
+        match self {
+            Selector::Index(i) => index == Some(*i),
+            Selector::Primary => output.is_some_and(|output| output.primary),
+            _ => {
+                names.into_iter().any(|name| self.matches(0, name))
+                    || output.is_some_and(|output| self.matches_output(&output.name))
+            }
+        }
+    }
+
+    /// Determine whether the `name` of a [`DisplayOutput`] matches.
+    /// Unlike the names of display monitors, it must match exactly,
+    /// with or without the `\\.\` prefix on Windows,
+    /// so that `DISPLAY1` doesn't match `DISPLAY10`.
+    fn matches_output(&self, name: &str) -> bool {
+        match self {
+            Selector::Name(s) => name == s || name.strip_prefix(r"\\.\") == Some(s.as_str()),
+            #[cfg(feature = "cli")]
+            Selector::Group(patterns) => patterns.iter().any(|re| re.is_match(name)),
+            _ => false,
+        }
+    }
+}
+
+/// Convert a wildcard pattern to a [`Regex`] that matches the whole string.
+/// `*` matches any characters and `?` matches one character.
+#[cfg(feature = "cli")]
+pub(crate) fn regex_from_wildcard(pattern: &str) -> anyhow::Result<Regex> {
+    let mut re = String::from("^");
+    for ch in pattern.chars() {
+        match ch {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            _ => re.push_str(&regex::escape(&ch.to_string())),
+        }
+    }
+    re.push('$');
+    Ok(Regex::new(&re)?)
+}
+
+/// How names match multiple display monitors,
+/// the same as the `--all`, `--first`, and `--exactly-one` options of the [`Cli`].
+/// See [`Profile::apply()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MatchOptions {
+    /// Allow a part of names to change multiple display monitors.
+    /// Without this, it's an error, so that a change doesn't hit unexpected ones.
+    pub all: bool,
+    /// Use only the first display monitor when a name matches multiple display monitors.
+    pub first: bool,
+    /// Fail when a name matches multiple display monitors.
+    pub exactly_one: bool,
+}
+
+impl MatchOptions {
+    /// The indices of the display monitors in the `indices`,
+    /// which the `selector` matched, limited by these options.
+    /// Set `to_set` to check [`MatchOptions::all`] for changes.
+    pub(crate) fn limit(
+        &self,
+        name: &str,
+        selector: &Selector,
+        mut indices: Vec<usize>,
+        to_set: bool,
+        monitors: &[Monitor],
+    ) -> anyhow::Result<Vec<usize>> {
+        let ids = |indices: &[usize]| {
+            indices
+                .iter()
+                .map(|index| monitors[*index].to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        if indices.is_empty() {
+            if let Selector::Index(index) = selector {
+                anyhow::bail!(tr_args(
+                    "No display monitors found for the index {index}.",
+                    &[("index", index)]
+                ));
+            }
+            anyhow::bail!(tr_args(
+                "No display monitors found for \"{name}\".",
+                &[("name", &name)]
+            ));
+        }
+        if self.exactly_one && indices.len() > 1 {
+            anyhow::bail!(
+                "{count} display monitors found for \"{name}\": {ids}.",
+                count = indices.len(),
+                ids = ids(&indices)
+            );
+        }
+        if self.first {
+            indices.truncate(1);
+        }
+        if to_set && indices.len() > 1 && matches!(selector, Selector::Name(_)) && !self.all {
+            anyhow::bail!(tr_args(
+                "\"{name}\" matches {count} display monitors: {ids}. \
+                Use `--all` to change all of them, or `--first` to change the first one.",
+                &[
+                    ("name", &name),
+                    ("count", &indices.len()),
+                    ("ids", &ids(&indices))
+                ]
+            ));
+        }
+        Ok(indices)
+    }
+}
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config::from_toml(r#"groups.desk = ["DELL*", "LG?"]"#).unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        let config = config();
+        assert!(matches!(
+            Selector::parse("", &config).unwrap(),
+            Selector::All
+        ));
+        assert!(matches!(
+            Selector::parse("2", &config).unwrap(),
+            Selector::Index(2)
+        ));
+        assert!(matches!(
+            Selector::parse("desk", &config).unwrap(),
+            Selector::Group(_)
+        ));
+        assert!(matches!(
+            Selector::parse("DELL", &config).unwrap(),
+            Selector::Name(_)
+        ));
+    }
+
+    #[test]
+    fn matches_group() {
+        let selector = Selector::parse("desk", &config()).unwrap();
+        assert!(selector.matches(0, "DELL U2723QE"));
+        assert!(selector.matches(0, "LG1"));
+        assert!(!selector.matches(0, "LG"));
+        assert!(!selector.matches(0, "LG12"));
+        assert!(!selector.matches(0, "My DELL"));
+    }
+
+    #[test]
+    fn matches_name() {
+        let selector = Selector::parse("U27", &config()).unwrap();
+        assert!(selector.matches(0, "DELL U2723QE"));
+        assert!(!selector.matches(0, "DELL P3223QE"));
+    }
+
+    #[test]
+    fn matches_monitor() {
+        let mut monitor = Monitor::from_device(MockMonitor::new("DELL U2723QE"));
+        let primary = Selector::parse("primary", &config()).unwrap();
+        let display1 = Selector::parse("DISPLAY1", &config()).unwrap();
+        assert!(!primary.matches_monitor(0, &monitor));
+        assert!(!display1.matches_monitor(0, &monitor));
+
+        monitor.set_display_output(Some(DisplayOutput {
+            name: r"\\.\DISPLAY1".into(),
+            primary: true,
+            ..Default::default()
+        }));
+        assert!(primary.matches_monitor(0, &monitor));
+        assert!(display1.matches_monitor(0, &monitor));
+        assert!(
+            Selector::parse(r"\\.\DISPLAY1", &config())
+                .unwrap()
+                .matches_monitor(0, &monitor)
+        );
+        assert!(
+            !Selector::parse("DISPLAY", &config())
+                .unwrap()
+                .matches_monitor(0, &monitor)
+        );
+        monitor.set_display_output(Some(DisplayOutput {
+            name: r"\\.\DISPLAY10".into(),
+            ..Default::default()
+        }));
+        assert!(!display1.matches_monitor(0, &monitor));
+        assert!(
+            Selector::parse("U27", &config())
+                .unwrap()
+                .matches_monitor(0, &monitor)
+        );
+    }
+
+    #[test]
+    fn regex_from_wildcard() {
+        let re = super::regex_from_wildcard("a.b*").unwrap();
+        assert!(re.is_match("a.b"));
+        assert!(re.is_match("a.bcd"));
+        assert!(!re.is_match("axb"));
+    }
+}
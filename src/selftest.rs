@@ -0,0 +1,220 @@
+use std::time::Instant;
+
+use super::*;
+use serde::Serialize;
+
+/// The VCP feature codes to read in [`SelfTestReport::run()`], other than the input select.
+/// They're only read, so the display monitor doesn't change.
+const READ_FEATURES: &[(VcpCode, &str)] = &[
+    (LUMINANCE, "Read brightness"),
+    (0x12, "Read contrast"),
+    (POWER_MODE, "Read power mode"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+/// The result of a [`SelfTestCheck`].
+pub(crate) enum CheckStatus {
+    Pass,
+    Fail,
+    /// Not run, such as when the display monitor doesn't support the feature.
+    Skip,
+}
+
+#[derive(Debug, Serialize)]
+/// A check of [`SelfTestReport`].
+pub(crate) struct SelfTestCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    /// The time the display monitor took to respond.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// The value read, or the error message.
+    pub detail: String,
+    /// What to try if the check failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<&'static str>,
+}
+
+impl SelfTestCheck {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            latency_ms: None,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    /// Run `f` and check its result, with the time it took.
+    fn measure<T>(
+        name: &'static str,
+        f: impl FnOnce() -> anyhow::Result<T>,
+        detail: impl FnOnce(T) -> String,
+    ) -> Self {
+        let start_time = Instant::now();
+        let result = f();
+        let latency = start_time.elapsed();
+        let mut check = match result {
+            Ok(value) => Self::new(name, CheckStatus::Pass, detail(value)),
+            Err(e) => Self::new(name, CheckStatus::Fail, e.to_string()),
+        };
+        check.latency_ms = Some(latency.as_millis() as u64);
+        check
+    }
+
+    fn with_hint(mut self, hint: &'static str) -> Self {
+        if self.status == CheckStatus::Fail {
+            self.hint = Some(hint);
+        }
+        self
+    }
+}
+
+#[derive(Debug, Serialize)]
+/// The results of the `selftest` command for a display monitor.
+pub(crate) struct SelfTestReport {
+    pub monitor: String,
+    pub backend: String,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    /// Run the checks on the `monitor`.
+    /// This only reads from the display monitor, and doesn't change anything.
+    pub fn run(monitor: &mut Monitor) -> Self {
+        let mut checks = vec![];
+        checks.push(
+            SelfTestCheck::measure(
+                "Capabilities",
+                || {
+                    monitor.update_capabilities()?;
+                    monitor
+                        .capabilities()
+                        .map(|capabilities| capabilities.vcp_features.len())
+                        .ok_or_else(|| anyhow::anyhow!("Not available"))
+                },
+                |count| format!("{count} VCP features"),
+            )
+            .with_hint(
+                "Check if DDC/CI is enabled in the on-screen display menu of the display monitor.",
+            ),
+        );
+
+        checks.push(match monitor.supports_feature(INPUT_SELECT) {
+            Some(true) => SelfTestCheck::new(
+                "Input select",
+                CheckStatus::Pass,
+                monitor
+                    .supported_values(INPUT_SELECT)
+                    .map(|values| {
+                        values
+                            .iter()
+                            .map(|value| InputSource::str_from_raw(*value))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    })
+                    .unwrap_or_default(),
+            ),
+            Some(false) => {
+                SelfTestCheck::new("Input select", CheckStatus::Fail, "Not in the capabilities")
+                    .with_hint(
+                        "The display monitor may not support changing input sources by DDC/CI. \
+                Try `--force` if it's known to work.",
+                    )
+            }
+            None => SelfTestCheck::new("Input select", CheckStatus::Skip, "No capabilities"),
+        });
+
+        checks.push(
+            SelfTestCheck::measure(
+                "Read input source",
+                || monitor.input_source(),
+                InputSource::str_from_raw,
+            )
+            .with_hint(
+                "DDC/CI may not pass through the connection. \
+                Try connecting the display monitor directly, without docks, adapters, or KVM switches.",
+            ),
+        );
+
+        for (code, name) in READ_FEATURES {
+            if monitor.supports_feature(*code) == Some(false) {
+                checks.push(SelfTestCheck::new(
+                    name,
+                    CheckStatus::Skip,
+                    "Not in the capabilities",
+                ));
+                continue;
+            }
+            checks.push(SelfTestCheck::measure(
+                name,
+                || monitor.vcp_feature(*code),
+                |value| format!("{} / {}", value.value, value.maximum),
+            ));
+        }
+
+        Self {
+            monitor: monitor.to_string(),
+            backend: monitor.backend(),
+            checks,
+        }
+    }
+
+    /// Whether no checks failed.
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status != CheckStatus::Fail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statuses(report: &SelfTestReport) -> Vec<(&str, CheckStatus)> {
+        report
+            .checks
+            .iter()
+            .map(|check| (check.name, check.status))
+            .collect()
+    }
+
+    #[test]
+    fn run() {
+        let mut monitor = Monitor::from_device(
+            MockMonitor::new("A")
+                .with_capabilities("(vcp(10 60(0F 11)))")
+                .with_input_source(InputSource::Hdmi1.as_raw()),
+        );
+        monitor.set_brightness(30).unwrap();
+        let report = SelfTestReport::run(&mut monitor);
+        assert_eq!(
+            statuses(&report),
+            [
+                ("Capabilities", CheckStatus::Pass),
+                ("Input select", CheckStatus::Pass),
+                ("Read input source", CheckStatus::Pass),
+                ("Read brightness", CheckStatus::Pass),
+                ("Read contrast", CheckStatus::Skip),
+                ("Read power mode", CheckStatus::Skip),
+            ]
+        );
+        assert_eq!(report.checks[1].detail, "DP1, Hdmi1");
+        assert_eq!(report.checks[2].detail, "Hdmi1");
+        assert!(report.passed());
+    }
+
+    #[test]
+    fn run_without_ddc() {
+        let mut monitor = Monitor::from_device(MockMonitor::new("A"));
+        let report = SelfTestReport::run(&mut monitor);
+        assert_eq!(report.checks[0].status, CheckStatus::Fail);
+        assert!(report.checks[0].hint.is_some());
+        assert_eq!(report.checks[1].status, CheckStatus::Skip);
+        assert_eq!(report.checks[2].status, CheckStatus::Fail);
+        assert!(!report.passed());
+    }
+}
@@ -0,0 +1,334 @@
+use std::path::Path;
+use std::sync::{Mutex, mpsc};
+
+use super::*;
+#[cfg(any(windows, target_os = "linux"))]
+use crate::logging::*;
+
+/// The name of the scheduled task of the `service install`.
+#[cfg_attr(not(windows), allow(dead_code))]
+const TASK_NAME: &str = "monitor-input";
+
+/// Events to the `daemon` from other threads.
+pub(crate) enum DaemonEvent {
+    /// A request from `--host`.
+    Remote(RemoteCall),
+    /// A request to stop the `daemon`.
+    Shutdown,
+    /// A change of docking.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    Dock(DockEvent),
+    /// A change of the session or the lid.
+    #[cfg_attr(not(any(windows, target_os = "linux")), allow(dead_code))]
+    Session(SessionEvent),
+}
+
+impl From<RemoteCall> for DaemonEvent {
+    fn from(call: RemoteCall) -> Self {
+        DaemonEvent::Remote(call)
+    }
+}
+
+/// The sender to the `daemon` for the shutdown handler.
+static SHUTDOWN_SENDER: Mutex<Option<mpsc::Sender<DaemonEvent>>> = Mutex::new(None);
+
+/// Send [`DaemonEvent::Shutdown`] to the `sender` when the process is requested to stop,
+/// such as by Ctrl+C, closing the console, signing out, or shutting down.
+pub(crate) fn set_shutdown_handler(sender: mpsc::Sender<DaemonEvent>) -> anyhow::Result<()> {
+    let is_first = SHUTDOWN_SENDER.lock().unwrap().replace(sender).is_none();
+    if is_first {
+        platform_set_shutdown_handler()?;
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+fn request_shutdown() {
+    if let Some(sender) = SHUTDOWN_SENDER.lock().unwrap().as_ref() {
+        let _ = sender.send(DaemonEvent::Shutdown);
+    }
+}
+
+#[cfg(windows)]
+fn platform_set_shutdown_handler() -> anyhow::Result<()> {
+    use windows_sys::Win32::System::Console::SetConsoleCtrlHandler;
+    use windows_sys::core::BOOL;
+
+    unsafe extern "system" fn handler(_ctrl_type: u32) -> BOOL {
+        request_shutdown();
+        // Give the `daemon` time to stop, before the process is terminated
+        // for closing the console, signing out, or shutting down.
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        1
+    }
+
+    // SAFETY: `handler` is a valid handler routine for the lifetime of the process.
+    if unsafe { SetConsoleCtrlHandler(Some(handler), 1) } == 0 {
+        anyhow::bail!(
+            "Failed to set the console control handler: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn platform_set_shutdown_handler() -> anyhow::Result<()> {
+    // Signals terminate the process by default.
+    Ok(())
+}
+
+/// The command line to run the `daemon` in the scheduled task or the systemd unit.
+#[cfg_attr(not(any(windows, target_os = "linux")), allow(dead_code))]
+fn task_command_line(exe: &Path, config_path: Option<&Path>) -> String {
+    let mut command_line = format!("\"{}\"", exe.display());
+    if let Some(config_path) = config_path {
+        command_line.push_str(&format!(" --config \"{}\"", config_path.display()));
+    }
+    command_line.push_str(" service run");
+    command_line
+}
+
+/// Register a task to run the `daemon` when the current user signs in.
+///
+/// This is a scheduled task rather than a Windows service,
+/// because services run in a separate session
+/// that can't access display monitors of the user.
+#[cfg(windows)]
+pub(crate) fn install(config_path: Option<&Path>) -> anyhow::Result<()> {
+    check_no_autostart()?;
+    let exe = std::env::current_exe()?;
+    let config_path = config_path.map(std::path::absolute).transpose()?;
+    let command_line = task_command_line(&exe, config_path.as_deref());
+    let user = std::env::var("USERNAME")?;
+    run_schtasks(&[
+        "/Create",
+        "/F",
+        "/TN",
+        TASK_NAME,
+        "/SC",
+        "ONLOGON",
+        "/RU",
+        &user,
+        "/IT",
+        "/RL",
+        "LIMITED",
+        "/TR",
+        &command_line,
+    ])?;
+    info!("Installed the task \"{TASK_NAME}\": {command_line}");
+    Ok(())
+}
+
+/// Remove the task registered by [`install()`].
+#[cfg(windows)]
+pub(crate) fn uninstall() -> anyhow::Result<()> {
+    run_schtasks(&["/Delete", "/F", "/TN", TASK_NAME])?;
+    info!("Uninstalled the task \"{TASK_NAME}\"");
+    Ok(())
+}
+
+/// Whether the task registered by [`install()`] exists.
+#[cfg(windows)]
+pub(crate) fn is_installed() -> bool {
+    std::process::Command::new("schtasks")
+        .args(["/Query", "/TN", TASK_NAME])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Fail if the [`autostart`] entry is enabled,
+/// so that the `daemon` isn't started twice at sign-in.
+#[cfg(any(windows, target_os = "linux"))]
+fn check_no_autostart() -> anyhow::Result<()> {
+    if let Some(location) = autostart::status() {
+        anyhow::bail!(
+            "The autostart entry is enabled at {location}. Run `autostart disable` first."
+        );
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn run_schtasks(args: &[&str]) -> anyhow::Result<()> {
+    debug!("schtasks {}", args.join(" "));
+    let output = std::process::Command::new("schtasks").args(args).output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "schtasks failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub(crate) fn install(_config_path: Option<&Path>) -> anyhow::Result<()> {
+    if cfg!(target_os = "linux") {
+        anyhow::bail!(
+            "The `service` command is available only on Windows. Use `--install-systemd`."
+        );
+    }
+    anyhow::bail!("The `service` command is available only on Windows.");
+}
+
+#[cfg(not(windows))]
+pub(crate) fn uninstall() -> anyhow::Result<()> {
+    install(None)
+}
+
+/// Whether the systemd user unit of the `--install-systemd` is enabled.
+#[cfg(target_os = "linux")]
+pub(crate) fn is_installed() -> bool {
+    std::process::Command::new("systemctl")
+        .args(["--user", "--quiet", "is-enabled", SYSTEMD_UNIT_NAME])
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub(crate) fn is_installed() -> bool {
+    false
+}
+
+/// The name of the systemd user unit of the `--install-systemd`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const SYSTEMD_UNIT_NAME: &str = "monitor-input.service";
+
+/// The contents of the systemd user unit to run the `daemon`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn systemd_unit(exe: &Path, config_path: Option<&Path>) -> String {
+    format!(
+        "[Unit]
+Description=Change input sources of display monitors
+After=graphical-session.target
+
+[Service]
+Type=notify
+ExecStart={command_line}
+Restart=on-failure
+
+[Install]
+WantedBy=default.target
+",
+        command_line = task_command_line(exe, config_path)
+    )
+}
+
+/// Write a systemd user unit to run the `daemon`, and enable and start it.
+#[cfg(target_os = "linux")]
+pub(crate) fn install_systemd(config_path: Option<&Path>) -> anyhow::Result<()> {
+    check_no_autostart()?;
+    let Some(dir) = dirs::config_dir().map(|dir| dir.join("systemd").join("user")) else {
+        anyhow::bail!("The configuration directory is not found");
+    };
+    let exe = std::env::current_exe()?;
+    let config_path = config_path.map(std::path::absolute).transpose()?;
+    let path = dir.join(SYSTEMD_UNIT_NAME);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(&path, systemd_unit(&exe, config_path.as_deref()))?;
+    info!("Wrote {}", path.display());
+    run_systemctl(&["daemon-reload"])?;
+    run_systemctl(&["enable", "--now", SYSTEMD_UNIT_NAME])?;
+    info!("Enabled {SYSTEMD_UNIT_NAME}");
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn run_systemctl(args: &[&str]) -> anyhow::Result<()> {
+    debug!("systemctl --user {}", args.join(" "));
+    let output = std::process::Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run `systemctl`: {e}"))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`systemctl --user {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn install_systemd(_config_path: Option<&Path>) -> anyhow::Result<()> {
+    anyhow::bail!("`--install-systemd` is available only on Linux.");
+}
+
+/// Notify systemd that the `daemon` is ready, if it's started by systemd with `Type=notify`.
+pub(crate) fn notify_ready() -> anyhow::Result<()> {
+    #[cfg(target_os = "linux")]
+    if let Some(socket_path) = std::env::var_os("NOTIFY_SOCKET") {
+        sd_notify(&socket_path, "READY=1")?;
+    }
+    Ok(())
+}
+
+/// Send the `state` to the `NOTIFY_SOCKET` of systemd.
+/// See [`sd_notify(3)`](https://www.freedesktop.org/software/systemd/man/latest/sd_notify.html).
+#[cfg(target_os = "linux")]
+fn sd_notify(socket_path: &std::ffi::OsStr, state: &str) -> anyhow::Result<()> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    debug!("sd_notify: {state}");
+    let socket = UnixDatagram::unbound()?;
+    let address = match socket_path.as_bytes().strip_prefix(b"@") {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(socket_path)?,
+    };
+    socket.send_to_addr(state.as_bytes(), &address)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_command_line() {
+        let exe = Path::new("C:/bin/monitor-input.exe");
+        assert_eq!(
+            super::task_command_line(exe, None),
+            r#""C:/bin/monitor-input.exe" service run"#
+        );
+        assert_eq!(
+            super::task_command_line(exe, Some(Path::new("C:/my config.toml"))),
+            r#""C:/bin/monitor-input.exe" --config "C:/my config.toml" service run"#
+        );
+    }
+
+    #[test]
+    fn systemd_unit() {
+        let unit = super::systemd_unit(Path::new("/usr/bin/monitor-input"), None);
+        assert!(unit.contains("\nType=notify\n"));
+        assert!(unit.contains("\nExecStart=\"/usr/bin/monitor-input\" service run\n"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn sd_notify() {
+        use std::os::unix::net::UnixDatagram;
+        let dir = std::env::temp_dir().join(format!("monitor-input-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket_path = dir.join("notify");
+        let _ = std::fs::remove_file(&socket_path);
+        let receiver = UnixDatagram::bind(&socket_path).unwrap();
+        super::sd_notify(socket_path.as_os_str(), "READY=1").unwrap();
+        let mut buf = [0; 16];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"READY=1");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn shutdown() {
+        let (sender, receiver) = mpsc::channel();
+        set_shutdown_handler(sender).unwrap();
+        request_shutdown();
+        assert!(matches!(receiver.recv().unwrap(), DaemonEvent::Shutdown));
+    }
+}
@@ -0,0 +1,72 @@
+/// Changes of the session or the lid detected by [`Platform::listen_session()`].
+///
+/// [`Platform::listen_session()`]: crate::platform::Platform::listen_session
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(not(any(windows, target_os = "linux")), allow(dead_code))]
+pub(crate) enum SessionEvent {
+    /// The session is locked.
+    Lock,
+    /// The session is unlocked.
+    Unlock,
+    /// The lid is closed.
+    LidClose,
+    /// The lid is opened.
+    LidOpen,
+}
+
+/// Detects changes from [`SessionEvent`]s,
+/// because the same event may be notified more than once.
+#[derive(Debug, Default)]
+pub(crate) struct SessionWatcher {
+    is_locked: bool,
+    is_lid_closed: bool,
+}
+
+impl SessionWatcher {
+    /// Update with the `event`, and return whether it changed the state.
+    /// The session is unlocked and the lid is open initially.
+    pub fn update(&mut self, event: SessionEvent) -> bool {
+        let (state, value) = match event {
+            SessionEvent::Lock => (&mut self.is_locked, true),
+            SessionEvent::Unlock => (&mut self.is_locked, false),
+            SessionEvent::LidClose => (&mut self.is_lid_closed, true),
+            SessionEvent::LidOpen => (&mut self.is_lid_closed, false),
+        };
+        let is_changed = *state != value;
+        *state = value;
+        is_changed
+    }
+}
+
+/// Keeps [`Platform::listen_session()`] listening until the `daemon` drops this.
+///
+/// [`Platform::listen_session()`]: crate::platform::Platform::listen_session
+#[derive(Default)]
+pub(crate) struct SessionListener {
+    /// Called when dropped, to stop listening, such as to stop a child process.
+    pub stop: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl Drop for SessionListener {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_watcher() {
+        let mut watcher = SessionWatcher::default();
+        assert!(!watcher.update(SessionEvent::Unlock));
+        assert!(watcher.update(SessionEvent::Lock));
+        assert!(!watcher.update(SessionEvent::Lock));
+        assert!(!watcher.update(SessionEvent::LidOpen));
+        assert!(watcher.update(SessionEvent::LidClose));
+        assert!(watcher.update(SessionEvent::Unlock));
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::mpsc;
+
+use super::*;
+
+type Job = Box<dyn FnOnce(&mut [Monitor]) + Send>;
+
+/// A handle to a [`Monitor`] that can be used from any threads.
+///
+/// The [`Monitor`]s are created and owned by a dedicated thread,
+/// and the closures given to [`SharedMonitor::with()`] run on that thread,
+/// because [`Monitor`] isn't [`Send`].
+/// [`SharedMonitor`]s from the same [`SharedMonitor::spawn_all()`]
+/// share the thread, so their requests run one at a time.
+/// The thread stops when all of its [`SharedMonitor`]s are dropped.
+/// # Examples
+/// ```
+/// # use monitor_input::{InputSource,MockMonitor,Monitor,SharedMonitor};
+/// let monitors = SharedMonitor::spawn_all(|| {
+///     vec![Monitor::from_device(MockMonitor::new("A"))]
+/// })
+/// .unwrap();
+/// let monitor = monitors[0].clone();
+/// std::thread::spawn(move || monitor.set_input_source(InputSource::Hdmi1.as_raw()))
+///     .join()
+///     .unwrap()
+///     .unwrap();
+/// assert_eq!(
+///     monitors[0].input_source().unwrap(),
+///     InputSource::Hdmi1.as_raw()
+/// );
+/// ```
+#[derive(Clone)]
+pub struct SharedMonitor {
+    id: String,
+    index: usize,
+    sender: mpsc::Sender<Job>,
+}
+
+impl std::fmt::Display for SharedMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+impl std::fmt::Debug for SharedMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedMonitor")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+impl SharedMonitor {
+    /// Enumerate all display monitors by [`Monitor::enumerate()`] on a new thread.
+    pub fn enumerate() -> anyhow::Result<Vec<Self>> {
+        Self::spawn_all(Monitor::enumerate)
+    }
+
+    /// Create [`Monitor`]s by the `factory` on a new thread,
+    /// and return the [`SharedMonitor`]s for them.
+    pub fn spawn_all(
+        factory: impl FnOnce() -> Vec<Monitor> + Send + 'static,
+    ) -> anyhow::Result<Vec<Self>> {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let (ids_sender, ids_receiver) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("monitor-input".into())
+            .spawn(move || {
+                let mut monitors = factory();
+                let ids: Vec<String> = monitors
+                    .iter()
+                    .map(|monitor| monitor.id().to_string())
+                    .collect();
+                if ids_sender.send(ids).is_err() {
+                    return;
+                }
+                for job in receiver {
+                    job(&mut monitors);
+                }
+            })?;
+        let ids = ids_receiver
+            .recv()
+            .map_err(|_| anyhow::anyhow!("Failed to create display monitors"))?;
+        Ok(ids
+            .into_iter()
+            .enumerate()
+            .map(|(index, id)| Self {
+                id,
+                index,
+                sender: sender.clone(),
+            })
+            .collect())
+    }
+
+    /// The [`Monitor::id()`].
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Run `f` with the [`Monitor`] on its thread, and wait for the result.
+    pub fn with<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Monitor) -> R + Send + 'static,
+    ) -> anyhow::Result<R> {
+        let (result_sender, result_receiver) = mpsc::sync_channel(1);
+        let index = self.index;
+        self.sender
+            .send(Box::new(move |monitors: &mut [Monitor]| {
+                // Keep the thread running for other requests if `f` panics.
+                let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    f(&mut monitors[index])
+                }));
+                if let Ok(result) = result {
+                    let _ = result_sender.send(result);
+                }
+            }))
+            .map_err(|_| anyhow::anyhow!("{self}: The thread has stopped"))?;
+        result_receiver
+            .recv()
+            .map_err(|_| anyhow::anyhow!("{self}: The request panicked"))
+    }
+
+    /// The [`Monitor::info()`].
+    pub fn info(&self) -> anyhow::Result<MonitorInfo> {
+        self.with(|monitor| monitor.info())
+    }
+
+    /// The [`Monitor::input_source()`].
+    pub fn input_source(&self) -> anyhow::Result<InputSourceRaw> {
+        self.with(|monitor| monitor.input_source())?
+    }
+
+    /// The [`Monitor::set_input_source()`].
+    pub fn set_input_source(&self, value: InputSourceRaw) -> anyhow::Result<()> {
+        self.with(move |monitor| monitor.set_input_source(value))?
+    }
+
+    /// The [`Monitor::brightness()`].
+    pub fn brightness(&self) -> anyhow::Result<VcpValue> {
+        self.with(|monitor| monitor.brightness())?
+    }
+
+    /// The [`Monitor::set_brightness()`].
+    pub fn set_brightness(&self, value: u16) -> anyhow::Result<()> {
+        self.with(move |monitor| monitor.set_brightness(value))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedMonitor>();
+        assert_send_sync::<MonitorInfo>();
+    }
+
+    #[test]
+    fn threads() {
+        let monitors = SharedMonitor::spawn_all(|| {
+            ["A", "B"]
+                .iter()
+                .map(|id| Monitor::from_device(MockMonitor::new(*id)))
+                .collect()
+        })
+        .unwrap();
+        assert_eq!(monitors.len(), 2);
+        let handles: Vec<_> = monitors
+            .iter()
+            .cloned()
+            .zip([InputSource::DisplayPort1, InputSource::Hdmi1])
+            .map(|(monitor, value)| {
+                std::thread::spawn(move || monitor.set_input_source(value.as_raw()))
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap().unwrap();
+        }
+        assert_eq!(monitors[0].info().unwrap().id, "A");
+        assert_eq!(
+            monitors[1].input_source().unwrap(),
+            InputSource::Hdmi1.as_raw()
+        );
+        assert!(monitors[0].with(|_| panic!()).is_err());
+        assert!(monitors[0].input_source().is_ok());
+    }
+}
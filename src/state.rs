@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::MonitorCacheEntry;
+use crate::logging::*;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+/// The states kept across runs of the command line tool.
+/// See [`crate::Cli::state_path`] for the location.
+pub(crate) struct State {
+    /// The changes of the last run, to undo them.
+    pub undo: Vec<UndoEntry>,
+    /// The display monitors found by the last enumeration, for `--fast`.
+    pub monitors: Vec<MonitorCacheEntry>,
+    /// The numbers of consecutive runs that failed to communicate with display monitors,
+    /// by their IDs and display outputs. See [`crate::Config::unresponsive_after`].
+    pub failures: BTreeMap<String, u32>,
+    /// The capabilities strings of display monitors by their IDs,
+    /// for [`crate::CapabilitiesStrategy::Cached`].
+    pub capabilities: BTreeMap<String, CapabilitiesCacheEntry>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+/// The capabilities string of a display monitor. See [`State::capabilities`].
+pub(crate) struct CapabilitiesCacheEntry {
+    /// The EDID of the display monitor,
+    /// to find when a different display monitor has the same ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edid: Option<Vec<u8>>,
+    pub capabilities: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+/// The input source of a display monitor before a change.
+pub(crate) struct UndoEntry {
+    pub monitor: String,
+    /// The [`Monitor::id()`], to find the same display monitor exactly.
+    /// Empty in the states saved by old versions.
+    #[serde(default)]
+    pub id: String,
+    pub input_source: String,
+}
+
+impl State {
+    /// The default path of the state file,
+    /// `monitor-input/state.json` in the [`dirs::state_dir()`],
+    /// or in the [`dirs::data_local_dir()`] if the platform doesn't have it.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join("monitor-input").join("state.json"))
+    }
+
+    /// Load the states from the `path`, usually [`crate::Cli::state_path`].
+    /// Returns the default if the `path` is `None` or the file doesn't exist.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        match path {
+            Some(path) if path.exists() => Self::load_from(path),
+            _ => Ok(State::default()),
+        }
+    }
+
+    fn load_from(path: &Path) -> anyhow::Result<Self> {
+        debug!("State: {path:?}");
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the state file {path:?}"))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse the state file {path:?}"))
+    }
+
+    /// Save the states to the `path`, usually [`crate::Cli::state_path`].
+    pub fn save(&self, path: Option<&Path>) -> anyhow::Result<()> {
+        let Some(path) = path else {
+            anyhow::bail!("The state directory is not available");
+        };
+        self.save_to(path)
+    }
+
+    fn save_to(&self, path: &Path) -> anyhow::Result<()> {
+        debug!("Save state: {path:?}");
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let text = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, text)
+            .with_context(|| format!("Failed to write the state file {path:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("monitor-input-test-{}", std::process::id()));
+        let path = dir.join("state.json");
+        let state = State {
+            undo: vec![UndoEntry {
+                monitor: "A".to_string(),
+                id: "A".to_string(),
+                input_source: "DP1".to_string(),
+            }],
+            ..Default::default()
+        };
+        state.save_to(&path).unwrap();
+        let loaded = State::load_from(&path).unwrap();
+        assert_eq!(loaded.undo, state.undo);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::{Duration, Instant};
+
+/// Adjusts display monitors to a percent at intervals in the `daemon`,
+/// in steps to avoid visible jumps.
+/// This is shared by the [`BrightnessController`] and the [`NightController`].
+///
+/// [`BrightnessController`]: crate::BrightnessController
+/// [`NightController`]: crate::NightController
+#[derive(Debug)]
+pub(crate) struct SteppedController {
+    interval: Duration,
+    step_percent: u8,
+    step_delay: Duration,
+    next_at: Instant,
+    last_percent: Option<u8>,
+}
+
+impl SteppedController {
+    /// Create an instance that adjusts every `interval_secs`,
+    /// by `step_percent` at a time with `step_ms` between the steps.
+    pub fn new(interval_secs: u64, step_percent: u8, step_ms: u64) -> Self {
+        Self {
+            interval: Duration::from_secs(interval_secs.max(1)),
+            step_percent,
+            step_delay: Duration::from_millis(step_ms),
+            next_at: Instant::now(),
+            last_percent: None,
+        }
+    }
+
+    /// Assume the display monitors are already at the `percent`,
+    /// so that nothing is adjusted until the target changes from it.
+    pub fn with_last_percent(mut self, percent: u8) -> Self {
+        self.last_percent = Some(percent);
+        self
+    }
+
+    /// The duration until the next adjustment.
+    pub fn timeout(&self) -> Duration {
+        self.next_at.saturating_duration_since(Instant::now())
+    }
+
+    /// The percent from the `target`, if it's time to adjust and it changed.
+    /// The `target` is called only when it's time to adjust.
+    pub fn update(&mut self, target: impl FnOnce() -> Option<u8>) -> Option<u8> {
+        if Instant::now() < self.next_at {
+            return None;
+        }
+        self.next_at = Instant::now() + self.interval;
+        let percent = target()?;
+        if self.last_percent == Some(percent) {
+            return None;
+        }
+        self.last_percent = Some(percent);
+        Some(percent)
+    }
+
+    /// The values to set from the `current` to the `target`,
+    /// in steps of the `step_percent` of the `max`.
+    pub fn steps(&self, current: u16, target: u16, max: u16) -> Vec<u16> {
+        let step = (u32::from(max) * u32::from(self.step_percent.max(1)) / 100).max(1);
+        let mut values = vec![];
+        let mut value = u32::from(current);
+        let target = u32::from(target);
+        while value != target {
+            value = if value < target {
+                (value + step).min(target)
+            } else {
+                value.saturating_sub(step).max(target)
+            };
+            values.push(value as u16);
+        }
+        values
+    }
+
+    /// The delay between [`SteppedController::steps()`].
+    pub fn step_delay(&self) -> Duration {
+        self.step_delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps() {
+        let controller = SteppedController::new(60, 10, 100);
+        assert_eq!(controller.steps(50, 75, 100), [60, 70, 75]);
+        assert_eq!(controller.steps(30, 10, 100), [20, 10]);
+        assert_eq!(controller.steps(10, 10, 100), [] as [u16; 0]);
+    }
+
+    #[test]
+    fn update() {
+        let mut controller = SteppedController::new(60, 10, 100);
+        assert_eq!(controller.update(|| Some(50)), Some(50));
+        // Not until the interval passes.
+        assert_eq!(controller.update(|| Some(60)), None);
+        assert!(controller.timeout() > Duration::ZERO);
+
+        let mut controller = SteppedController::new(60, 10, 100).with_last_percent(0);
+        assert_eq!(controller.update(|| Some(0)), None);
+    }
+}
@@ -0,0 +1,736 @@
+//! Test utilities to run the [`Cli`] end-to-end on fake display monitors.
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use super::*;
+use crate::monitor::INPUT_SELECT;
+use clap::Parser;
+
+/// A request made to a [`FakeDevice`].
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum FakeRequest {
+    Capabilities,
+    Get(VcpCode),
+    Set(VcpCode, u16),
+    Raw(Vec<u8>),
+    Reopen,
+}
+
+#[derive(Debug, Default)]
+struct FakeState {
+    values: BTreeMap<VcpCode, VcpValue>,
+    capabilities: Option<String>,
+    raw_replies: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// The errors of the next requests, in the order.
+    errors: VecDeque<String>,
+    /// The VCP feature codes that always fail.
+    failing_codes: BTreeSet<VcpCode>,
+    /// The VCP feature codes that accept changes without changing their values.
+    ignored_codes: BTreeSet<VcpCode>,
+    latency: Duration,
+    requests: Vec<FakeRequest>,
+}
+
+/// A scriptable DDC/CI device.
+///
+/// Unlike the [`MockMonitor`], clones share the state,
+/// so that tests can keep a clone to script and inspect the device
+/// after the [`Monitor`] takes the other.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FakeDevice {
+    id: String,
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl FakeDevice {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap()
+    }
+
+    pub fn with_capabilities(self, capabilities: impl Into<String>) -> Self {
+        self.state().capabilities = Some(capabilities.into());
+        self
+    }
+
+    pub fn with_value(self, code: VcpCode, value: u16, maximum: u16) -> Self {
+        self.state()
+            .values
+            .insert(code, VcpValue { value, maximum });
+        self
+    }
+
+    pub fn with_input_source(self, value: InputSource) -> Self {
+        self.with_value(INPUT_SELECT, value.as_raw() as u16, u16::MAX)
+    }
+
+    /// Change the input source,
+    /// as if it's changed outside of this program, such as by the on-screen display.
+    pub fn set_input_source(&self, value: InputSource) {
+        self.state()
+            .values
+            .entry(INPUT_SELECT)
+            .or_insert(VcpValue {
+                value: 0,
+                maximum: u16::MAX,
+            })
+            .value = value.as_raw() as u16;
+    }
+
+    pub fn with_raw_reply(self, data: &[u8], reply: &[u8]) -> Self {
+        self.state()
+            .raw_replies
+            .insert(data.to_vec(), reply.to_vec());
+        self
+    }
+
+    /// Make every request take the `latency`.
+    pub fn with_latency(self, latency: Duration) -> Self {
+        self.state().latency = latency;
+        self
+    }
+
+    /// Make the next request fail with the `message`.
+    /// Call this multiple times to fail multiple requests.
+    pub fn fail_next(&self, message: impl Into<String>) {
+        self.state().errors.push_back(message.into());
+    }
+
+    /// Make all requests for the VCP feature `code` fail.
+    pub fn fail_code(&self, code: VcpCode) {
+        self.state().failing_codes.insert(code);
+    }
+
+    /// Make changes of the VCP feature `code` succeed without changing the value,
+    /// as display monitors that don't switch to input sources without signals.
+    pub fn ignore_code(&self, code: VcpCode) {
+        self.state().ignored_codes.insert(code);
+    }
+
+    /// The current value of the VCP feature `code`.
+    pub fn value(&self, code: VcpCode) -> Option<u16> {
+        self.state().values.get(&code).map(|value| value.value)
+    }
+
+    pub fn input_source(&self) -> Option<String> {
+        self.value(INPUT_SELECT)
+            .map(|value| InputSource::str_from_raw(value as InputSourceRaw))
+    }
+
+    /// The requests made so far, in the order.
+    pub fn requests(&self) -> Vec<FakeRequest> {
+        self.state().requests.clone()
+    }
+
+    /// Record the `request`, then simulate the latency and the errors.
+    fn request(&self, request: FakeRequest) -> anyhow::Result<MutexGuard<'_, FakeState>> {
+        let mut state = self.state();
+        let code = match request {
+            FakeRequest::Get(code) | FakeRequest::Set(code, _) => Some(code),
+            _ => None,
+        };
+        state.requests.push(request);
+        if !state.latency.is_zero() {
+            std::thread::sleep(state.latency);
+        }
+        if let Some(message) = state.errors.pop_front() {
+            anyhow::bail!("{}: {message}", self.id);
+        }
+        if let Some(code) = code
+            && state.failing_codes.contains(&code)
+        {
+            anyhow::bail!("{}: VCP feature {code:#04X} failed", self.id);
+        }
+        Ok(state)
+    }
+}
+
+impl DdcDevice for FakeDevice {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        None
+    }
+
+    fn serial(&self) -> Option<String> {
+        None
+    }
+
+    fn backend_name(&self) -> String {
+        "fake".to_string()
+    }
+
+    fn capabilities_string(&mut self) -> anyhow::Result<String> {
+        let state = self.request(FakeRequest::Capabilities)?;
+        match &state.capabilities {
+            Some(capabilities) => Ok(capabilities.clone()),
+            None => anyhow::bail!("{}: No capabilities", self.id),
+        }
+    }
+
+    fn get_vcp_feature(&mut self, code: VcpCode) -> anyhow::Result<VcpValue> {
+        let state = self.request(FakeRequest::Get(code))?;
+        match state.values.get(&code) {
+            Some(value) => Ok(*value),
+            None => anyhow::bail!("{}: VCP feature {code:#04X} is not supported", self.id),
+        }
+    }
+
+    fn set_vcp_feature(&mut self, code: VcpCode, value: u16) -> anyhow::Result<()> {
+        let mut state = self.request(FakeRequest::Set(code, value))?;
+        if state.ignored_codes.contains(&code) {
+            return Ok(());
+        }
+        state
+            .values
+            .entry(code)
+            .or_insert(VcpValue {
+                value,
+                maximum: u16::MAX,
+            })
+            .value = value;
+        Ok(())
+    }
+
+    fn sleep(&mut self) {}
+
+    fn ddc_raw(&mut self, data: &[u8], read_len: usize) -> anyhow::Result<Vec<u8>> {
+        let state = self.request(FakeRequest::Raw(data.to_vec()))?;
+        if read_len == 0 {
+            return Ok(vec![]);
+        }
+        let Some(reply) = state.raw_replies.get(data) else {
+            anyhow::bail!("{}: No reply to {data:02X?}", self.id);
+        };
+        Ok(reply[..reply.len().min(read_len)].to_vec())
+    }
+
+    fn reopen(&mut self) -> anyhow::Result<()> {
+        self.state().requests.push(FakeRequest::Reopen);
+        Ok(())
+    }
+}
+
+/// Create a [`Cli`] from the command line `args` as `main()` does,
+/// with [`FakeDevice`]s instead of display monitors,
+/// and the configurations from the `config` TOML instead of the file.
+pub(crate) fn fake_cli(args: &[&str], devices: &[FakeDevice], config: &str) -> Cli {
+    let mut cli =
+        Cli::try_parse_from(std::iter::once("monitor-input").chain(args.iter().copied())).unwrap();
+    cli.config = Some(Config::from_toml(config).unwrap());
+    cli.state_path = Some(temp_state_path());
+    cli.monitors = devices
+        .iter()
+        .map(|device| Monitor::from_device(device.clone()))
+        .collect();
+    cli
+}
+
+/// A new path of the state file in the temporary directory,
+/// so that tests don't use the states of the user nor of other tests.
+pub(crate) fn temp_state_path() -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir()
+        .join(format!("monitor-input-test-{}", std::process::id()))
+        .join(COUNT.fetch_add(1, Ordering::Relaxed).to_string())
+        .join("state.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    fn devices() -> [FakeDevice; 3] {
+        [
+            FakeDevice::new("A1").with_input_source(InputSource::DisplayPort1),
+            FakeDevice::new("A2").with_input_source(InputSource::Hdmi1),
+            FakeDevice::new("B").with_input_source(InputSource::DisplayPort1),
+        ]
+    }
+
+    fn input_sources(devices: &[FakeDevice]) -> Vec<String> {
+        devices
+            .iter()
+            .map(|device| device.input_source().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn set() {
+        let devices = devices();
+        let mut cli = fake_cli(&["g=usbc1", "B=hdmi2"], &devices, r#"groups.g = ["A*"]"#);
+        cli.run().unwrap();
+        assert_eq!(input_sources(&devices), ["UsbC1", "UsbC1", "Hdmi2"]);
+        assert_eq!(
+            devices[2].requests().last(),
+            Some(&FakeRequest::Set(
+                INPUT_SELECT,
+                InputSource::Hdmi2.as_raw() as u16
+            ))
+        );
+    }
+
+    #[test]
+    fn session() {
+        // The current input source is read only once for all args.
+        let devices = [FakeDevice::new("A")
+            .with_capabilities("(vcp(60(0F 11 1B)))")
+            .with_input_source(InputSource::DisplayPort1)];
+        let mut cli = fake_cli(
+            &["-c", "A=hdmi1", "A?=hdmi1:usbc1", "A=dp1,usbc1", "A"],
+            &devices,
+            "",
+        );
+        cli.run().unwrap();
+        let raw = |value: InputSource| value.as_raw() as u16;
+        assert_eq!(
+            devices[0].requests(),
+            [
+                FakeRequest::Capabilities,
+                FakeRequest::Get(INPUT_SELECT),
+                FakeRequest::Set(INPUT_SELECT, raw(InputSource::Hdmi1)),
+                FakeRequest::Set(INPUT_SELECT, raw(InputSource::UsbC1)),
+                FakeRequest::Set(INPUT_SELECT, raw(InputSource::DisplayPort1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn cached_input_source() {
+        let device = FakeDevice::new("A").with_input_source(InputSource::Hdmi1);
+        let mut monitor = Monitor::from_device(device.clone());
+        let hdmi1 = InputSource::Hdmi1.as_raw();
+        assert_eq!(monitor.cached_input_source().unwrap(), hdmi1);
+        assert_eq!(monitor.cached_input_source().unwrap(), hdmi1);
+        assert_eq!(device.requests().len(), 1);
+
+        // The value set is reused until invalidated.
+        let dp1 = InputSource::DisplayPort1.as_raw();
+        monitor.set_input_source(dp1).unwrap();
+        assert_eq!(monitor.cached_input_source().unwrap(), dp1);
+        assert_eq!(device.requests().len(), 2);
+        monitor.invalidate_input_source_cache();
+        assert_eq!(monitor.cached_input_source().unwrap(), dp1);
+        assert_eq!(device.requests().len(), 3);
+    }
+
+    #[test]
+    fn wait_for() {
+        let devices = devices();
+        let mut cli = fake_cli(&["--wait-for", "B", "B=hdmi2"], &devices, "");
+        cli.run().unwrap();
+        assert_eq!(input_sources(&devices)[2], "Hdmi2");
+
+        let mut cli = fake_cli(
+            &["--wait-for", "C", "--wait-timeout", "0s", "B=hdmi1"],
+            &devices,
+            "",
+        );
+        assert!(cli.run().is_err());
+        assert_eq!(input_sources(&devices)[2], "Hdmi2");
+
+        // Display monitors excluded by filters don't match.
+        let mut cli = fake_cli(
+            &[
+                "--backend",
+                "other",
+                "--wait-for",
+                "B",
+                "--wait-timeout",
+                "0s",
+                "B=hdmi1",
+            ],
+            &devices,
+            "",
+        );
+        let error = cli.run().unwrap_err().to_string();
+        assert!(error.contains("waited for"), "{error}");
+    }
+
+    #[test]
+    fn id_format() {
+        let devices = devices();
+        let mut cli = fake_cli(&["--id-format", "desk-{id}", "desk-B=hdmi2"], &devices, "");
+        cli.run().unwrap();
+        assert_eq!(input_sources(&devices)[2], "Hdmi2");
+        assert_eq!(cli.set_results()[0].monitor, "desk-B");
+
+        let mut cli = fake_cli(&["desk-B=hdmi1"], &devices, r#"id_format = "desk-{id}""#);
+        cli.run().unwrap();
+        assert_eq!(input_sources(&devices)[2], "Hdmi1");
+    }
+
+    #[test]
+    fn input_of() {
+        let devices = devices();
+        let mut cli = fake_cli(&["--input-of", "A2=dp1", "B=hdmi2"], &devices, "");
+        cli.run().unwrap();
+        assert_eq!(input_sources(&devices)[2], "DP1");
+
+        let mut cli = fake_cli(
+            &["--input-of", "A2=hdmi1", "--input-of", "A1=dp1", "B=hdmi2"],
+            &devices,
+            "",
+        );
+        cli.run().unwrap();
+        assert_eq!(input_sources(&devices)[2], "Hdmi2");
+
+        // Names must match one display monitor.
+        let mut cli = fake_cli(&["--input-of", "A=dp1", "B=hdmi1"], &devices, "");
+        assert!(cli.run().is_err());
+        assert_eq!(input_sources(&devices)[2], "Hdmi2");
+
+        // Commands don't use it.
+        let mut cli = fake_cli(&["--input-of", "A2=dp1", "status", "p"], &devices, "");
+        let error = cli.run().unwrap_err().to_string();
+        assert!(error.contains("--input-of"), "{error}");
+    }
+
+    #[test]
+    fn profile_settings() {
+        let devices = devices();
+        let config = r#"
+            profiles.p = ["A2=dp1"]
+            defaults.verify = true
+            profile_settings.p.verify = false
+            "#;
+        let mut cli = fake_cli(&["--profile", "p", "B=hdmi1"], &devices, config);
+        cli.run().unwrap();
+        assert_eq!(input_sources(&devices), ["DP1", "DP1", "Hdmi1"]);
+
+        devices[0].ignore_code(INPUT_SELECT);
+        let mut cli = fake_cli(&["A1=hdmi1"], &devices, config);
+        assert!(cli.run().is_err());
+        let mut cli = fake_cli(&["--profile", "p", "A1=hdmi1"], &devices, config);
+        cli.run().unwrap();
+        assert_eq!(input_sources(&devices)[0], "DP1");
+    }
+
+    #[test]
+    fn profile_settings_of_profile_run() {
+        let devices = devices();
+        let config = r#"
+            profiles.q = ["A1=usbc1"]
+            profile_settings.q.inputs.UsbC1 = 0x0F
+
+            [[rules]]
+            when = "unlock"
+            then = "profile q"
+
+            [[rules]]
+            when = "unlock"
+            then = "run A1=usbc1"
+            "#;
+        let mut cli = fake_cli(&["status", "q"], &devices, config);
+        cli.run().unwrap();
+
+        // The settings are restored after the profile.
+        let mut cli = fake_cli(&["rules", "test", "unlock"], &devices, config);
+        cli.run().unwrap();
+        let skipped: Vec<bool> = cli.set_results().iter().map(|r| r.skipped).collect();
+        assert_eq!(skipped, [true, false]);
+    }
+
+    #[test]
+    fn test_rules() {
+        let devices = devices();
+        let config = r#"
+            [[rules]]
+            when = "hotplug && monitor ~ 'A'"
+            then = "set hdmi2"
+            "#;
+        let mut cli = fake_cli(&["rules", "test", "hotplug"], &devices, config);
+        cli.run().unwrap();
+        assert_eq!(cli.set_results().len(), 2);
+        assert_eq!(input_sources(&devices), ["DP1", "Hdmi1", "DP1"]);
+
+        let mut cli = fake_cli(&["rules", "test", "lock"], &devices, config);
+        cli.run().unwrap();
+        assert!(cli.set_results().is_empty());
+
+        let mut cli = fake_cli(
+            &["rules", "test"],
+            &devices,
+            "rules = [{ when = 'x', then = 'y' }]",
+        );
+        assert!(cli.run().is_err());
+    }
+
+    #[test]
+    fn poll_input_changes() {
+        let devices = devices();
+        let mut cli = fake_cli(&[], &devices, "");
+        let changes = |cli: &mut Cli| {
+            cli.poll_input_changes(&[0, 2])
+                .into_iter()
+                .map(|change| match change {
+                    Ok(change) => change.to_string(),
+                    Err(e) => format!("{e:#}"),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(changes(&mut cli), ["A1: DP1", "B: DP1"]);
+        assert!(changes(&mut cli).is_empty());
+
+        devices[2].set_input_source(InputSource::UsbC1);
+        assert_eq!(changes(&mut cli), ["B: DP1 -> UsbC1"]);
+        assert!(changes(&mut cli).is_empty());
+
+        devices[0].fail_next("No response");
+        assert_eq!(changes(&mut cli), ["A1: No response"]);
+    }
+
+    #[test]
+    fn raw_ddc() {
+        let devices = [FakeDevice::new("A").with_raw_reply(&[0xC2], &[1, 2, 3])];
+        let mut cli = fake_cli(
+            &["--raw-ddc", "C2", "--raw-ddc-read", "2", "A"],
+            &devices,
+            "",
+        );
+        cli.run().unwrap();
+        assert_eq!(devices[0].requests(), [FakeRequest::Raw(vec![0xC2])]);
+    }
+
+    #[test]
+    fn selftest() {
+        let devices = [FakeDevice::new("A")
+            .with_capabilities("(vcp(10 60(0F 11)))")
+            .with_input_source(InputSource::DisplayPort1)
+            .with_value(LUMINANCE, 30, 100)];
+        fake_cli(&["selftest", "A"], &devices, "").run().unwrap();
+
+        devices[0].fail_code(LUMINANCE);
+        assert!(fake_cli(&["selftest", "A"], &devices, "").run().is_err());
+        assert_eq!(devices[0].value(LUMINANCE), Some(30));
+    }
+
+    #[test]
+    fn set_vcp() {
+        let devices = [FakeDevice::new("A").with_value(LUMINANCE, 30, 200)];
+        fake_cli(&["A.brightness=150"], &devices, "").run().unwrap();
+        assert_eq!(devices[0].value(LUMINANCE), Some(150));
+        fake_cli(&["A.brightness=25%"], &devices, "").run().unwrap();
+        assert_eq!(devices[0].value(LUMINANCE), Some(50));
+        assert!(fake_cli(&["A.brightness=201"], &devices, "").run().is_err());
+        assert!(
+            fake_cli(&["A.brightness=101%"], &devices, "")
+                .run()
+                .is_err()
+        );
+        assert_eq!(devices[0].value(LUMINANCE), Some(50));
+    }
+
+    #[test]
+    fn save_failures() {
+        let dir = std::env::temp_dir().join(format!(
+            "monitor-input-test-failures-{}",
+            std::process::id()
+        ));
+        let state_path = dir.join("state.json");
+        let devices = devices();
+        devices[2].fail_code(INPUT_SELECT);
+        let mut cli = fake_cli(&["B=hdmi1"], &devices, "");
+        cli.state_path = Some(state_path.clone());
+        assert!(cli.run().is_err());
+        let state = State::load(Some(&state_path)).unwrap();
+        assert_eq!(state.failures.get("B"), Some(&1));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn undo() {
+        let dir =
+            std::env::temp_dir().join(format!("monitor-input-test-undo-{}", std::process::id()));
+        let state_path = dir.join("state.json");
+        // The name of "A" matches both, but the undo should change only "A".
+        let devices = [
+            FakeDevice::new("A").with_input_source(InputSource::DisplayPort1),
+            FakeDevice::new("AB").with_input_source(InputSource::Hdmi1),
+        ];
+        let run = |args: &[&str]| {
+            let mut cli = fake_cli(args, &devices, "");
+            cli.state_path = Some(state_path.clone());
+            cli.run()
+        };
+        run(&["0=usbc1"]).unwrap();
+        assert_eq!(input_sources(&devices), ["UsbC1", "Hdmi1"]);
+        run(&["undo"]).unwrap();
+        assert_eq!(input_sources(&devices), ["DP1", "Hdmi1"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn run_report() {
+        let devices = devices();
+        let mut cli = fake_cli(&["A1=hdmi1", "A2=hdmi1"], &devices, "");
+        cli.run().unwrap();
+        let report = cli.run_report();
+        assert_eq!(report.monitors, 2);
+        assert_eq!(report.applied, 1);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.failures, 0);
+        assert_eq!(
+            report
+                .stages
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>(),
+            ["setup", "wait", "command", "save"]
+        );
+    }
+
+    #[test]
+    fn run_with_report() {
+        let devices = devices();
+        let report = fake_cli(&["--all", "A=hdmi2", "B=dp1"], &devices, "")
+            .run_with_report()
+            .unwrap();
+        let args = report
+            .args
+            .iter()
+            .map(|arg| {
+                (
+                    arg.arg.as_str(),
+                    arg.results
+                        .iter()
+                        .map(|result| result.monitor.as_str())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(args, [("A=hdmi2", vec!["A1", "A2"]), ("B=dp1", vec!["B"])]);
+        assert!(report.args.iter().all(|arg| arg.error.is_none()));
+
+        // The failed argument has the error, and the following ones don't run.
+        devices[0].fail_code(INPUT_SELECT);
+        let mut cli = fake_cli(&["A1=hdmi2", "B=hdmi2"], &devices, "");
+        assert!(cli.run_with_report().is_err());
+        let report = cli.run_report();
+        assert_eq!(report.args.len(), 1);
+        assert!(report.args[0].error.is_some());
+        assert_eq!(report.failures, 1);
+    }
+
+    #[test]
+    fn capabilities() {
+        let capabilities_requests = |args: &[&str], config: &str| {
+            let devices = devices().map(|device| device.with_capabilities("(vcp(60(0F 11)))"));
+            fake_cli(args, &devices, config).run().unwrap();
+            devices
+                .iter()
+                .map(|device| {
+                    device
+                        .requests()
+                        .iter()
+                        .filter(|request| **request == FakeRequest::Capabilities)
+                        .count()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(capabilities_requests(&["B=hdmi1"], ""), [0, 0, 0]);
+        assert_eq!(capabilities_requests(&["-c", "B=hdmi1"], ""), [1, 1, 1]);
+        assert_eq!(
+            capabilities_requests(&["--capabilities=lazy", "B=hdmi1"], ""),
+            [0, 0, 1]
+        );
+        assert_eq!(
+            capabilities_requests(&["B=hdmi1"], r#"capabilities = "always""#),
+            [1, 1, 1]
+        );
+        assert_eq!(
+            capabilities_requests(
+                &["--no-capabilities", "B=hdmi1"],
+                r#"capabilities = "always""#
+            ),
+            [0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn error_rollback() {
+        let devices = devices();
+        devices[1].fail_code(INPUT_SELECT);
+        let mut cli = fake_cli(&["--rollback", "A1=usbc1", "A2=usbc1"], &devices, "");
+        assert!(cli.run().is_err());
+        assert_eq!(devices[0].input_source().unwrap(), "DP1");
+        assert_eq!(
+            devices[0].requests().last(),
+            Some(&FakeRequest::Set(
+                INPUT_SELECT,
+                InputSource::DisplayPort1.as_raw() as u16
+            ))
+        );
+    }
+
+    #[test]
+    fn simulate_failure() {
+        let devices = devices();
+        let args = [
+            "--simulate-failure",
+            "B=nack",
+            "--rollback",
+            "A1=usbc1",
+            "B=usbc1",
+        ];
+        assert!(fake_cli(&args, &devices, "").run().is_err());
+        assert_eq!(input_sources(&devices), ["DP1", "Hdmi1", "DP1"]);
+
+        let args = ["--simulate-failure", "nack*1", "A1=usbc1", "A2=usbc1"];
+        fake_cli(&args, &devices, "").run().unwrap();
+        assert_eq!(input_sources(&devices), ["UsbC1", "UsbC1", "DP1"]);
+    }
+
+    #[test]
+    fn error_stale_handle() {
+        let devices = devices();
+        devices[0].fail_next("The handle is invalid.");
+        let mut cli = fake_cli(&["A1=hdmi1"], &devices, "");
+        cli.run().unwrap();
+        assert_eq!(devices[0].input_source().unwrap(), "Hdmi1");
+        assert!(devices[0].requests().contains(&FakeRequest::Reopen));
+    }
+
+    #[test]
+    fn retries() {
+        let device = FakeDevice::new("A").with_input_source(InputSource::DisplayPort1);
+        let mut monitor = Monitor::from_device(device.clone());
+        device.fail_next("NACK");
+        assert!(monitor.input_source().is_err());
+
+        monitor.set_retries(MST_RETRIES);
+        device.fail_next("NACK");
+        device.fail_next("NACK");
+        assert_eq!(
+            monitor.input_source().unwrap(),
+            InputSource::DisplayPort1.as_raw()
+        );
+        assert_eq!(device.requests().len(), 4);
+    }
+
+    #[test]
+    fn latency() {
+        let latency = Duration::from_millis(20);
+        let devices = [FakeDevice::new("A")
+            .with_input_source(InputSource::DisplayPort1)
+            .with_latency(latency)];
+        let mut cli = fake_cli(&["A=hdmi1"], &devices, "");
+        let start_time = Instant::now();
+        cli.run().unwrap();
+        assert!(start_time.elapsed() >= latency);
+        assert_eq!(input_sources(&devices), ["Hdmi1"]);
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+
+use super::*;
+use crate::logging::*;
+
+/// Watches the USB device of the [`UsbConfig`] in the `daemon`,
+/// for [`RuleEvent::UsbConnect`] and [`RuleEvent::UsbDisconnect`].
+#[derive(Debug)]
+pub(crate) struct UsbWatcher {
+    interval: Duration,
+    vendor_id: u16,
+    product_id: u16,
+    next_at: Instant,
+    connected: Option<bool>,
+}
+
+impl UsbWatcher {
+    pub fn new(config: &UsbConfig) -> anyhow::Result<Self> {
+        let (vendor_id, product_id) = parse_usb_device(&config.usb_device)?;
+        if native()
+            .is_usb_device_connected(vendor_id, product_id)
+            .is_none()
+        {
+            warn!("Failed to check the USB devices, `usb_device` doesn't work.");
+        }
+        Ok(Self {
+            interval: Duration::from_millis(config.interval_ms.max(100)),
+            vendor_id,
+            product_id,
+            next_at: Instant::now(),
+            connected: None,
+        })
+    }
+
+    /// The duration until the next check.
+    pub fn timeout(&self) -> Duration {
+        self.next_at.saturating_duration_since(Instant::now())
+    }
+
+    /// Whether the USB device is connected, if it's time to check and it changed.
+    /// The first check only records the state.
+    pub fn update(&mut self) -> Option<bool> {
+        if Instant::now() < self.next_at {
+            return None;
+        }
+        self.next_at = Instant::now() + self.interval;
+        let connected = native().is_usb_device_connected(self.vendor_id, self.product_id)?;
+        let previous = self.connected.replace(connected);
+        match previous {
+            Some(previous) if previous != connected => Some(connected),
+            _ => None,
+        }
+    }
+}
+
+/// Parse the [`UsbConfig::usb_device`] into the vendor ID and the product ID.
+pub(crate) fn parse_usb_device(usb_device: &str) -> anyhow::Result<(u16, u16)> {
+    let parse = |id: &str| u16::from_str_radix(id.trim(), 16).ok();
+    usb_device
+        .split_once(':')
+        .and_then(|(vendor_id, product_id)| Some((parse(vendor_id)?, parse(product_id)?)))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Invalid `usb_device` \"{usb_device}\", \
+                it should be hexadecimal IDs of the vendor and the product, such as \"1050:0407\""
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse_usb_device() {
+        assert_eq!(
+            super::parse_usb_device("1050:0407").unwrap(),
+            (0x1050, 0x0407)
+        );
+        assert_eq!(
+            super::parse_usb_device("046d:C52B").unwrap(),
+            (0x046D, 0xC52B)
+        );
+        assert!(super::parse_usb_device("1050").is_err());
+        assert!(super::parse_usb_device("1050:xyz").is_err());
+    }
+}
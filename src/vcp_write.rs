@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use anyhow::Context;
+
+use super::*;
+
+/// VCP feature code for the power mode
+pub(crate) const POWER_MODE: VcpCode = 0xD6;
+
+/// VCP feature code to select the color preset
+pub(crate) const COLOR_PRESET: VcpCode = 0x14;
+
+/// VCP feature codes for the red, green, and blue video gains
+pub(crate) const RGB_GAINS: [VcpCode; 3] = [0x16, 0x18, 0x1A];
+
+/// The value of [`POWER_MODE`] to turn on.
+const POWER_ON: u16 = 1;
+
+/// The value of [`POWER_MODE`] to turn off,
+/// the DPM off that DDC/CI can turn on again.
+const POWER_OFF: u16 = 4;
+
+/// The delay after turning on, before the display monitor accepts other changes.
+pub(crate) const POWER_ON_DELAY: Duration = Duration::from_secs(1);
+
+/// A change of a VCP feature for [`Monitor::apply()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum VcpWrite {
+    /// Set the power mode, `1` for on, and `4` or `5` for off.
+    PowerMode(u16),
+    /// Set the input source. See [`Monitor::set_input_source()`].
+    InputSource(InputSourceRaw),
+    /// Set the brightness. See [`Monitor::set_brightness()`].
+    Brightness(u16),
+    /// Set the VCP feature of the code to the value.
+    Feature(VcpCode, u16),
+}
+
+impl VcpWrite {
+    /// The write to set the VCP feature `code` to the `value`.
+    /// # Examples
+    /// ```
+    /// # use monitor_input::VcpWrite;
+    /// assert_eq!(VcpWrite::new(0x10, 50), VcpWrite::Brightness(50));
+    /// assert_eq!(VcpWrite::new(0x12, 70), VcpWrite::Feature(0x12, 70));
+    /// ```
+    pub fn new(code: VcpCode, value: u16) -> Self {
+        match code {
+            POWER_MODE => VcpWrite::PowerMode(value),
+            LUMINANCE => VcpWrite::Brightness(value),
+            code => VcpWrite::Feature(code, value),
+        }
+    }
+
+    /// The order to apply.
+    /// Turning on is the first so that other changes take effect,
+    /// and other power modes are the last.
+    /// Other features are after the input source,
+    /// because some display monitors keep them for each input source.
+    fn order(&self) -> u8 {
+        match self {
+            VcpWrite::PowerMode(POWER_ON) => 0,
+            VcpWrite::InputSource(_) => 1,
+            VcpWrite::Brightness(_) | VcpWrite::Feature(..) => 2,
+            VcpWrite::PowerMode(_) => 3,
+        }
+    }
+
+    /// Whether this turns on the display monitor.
+    pub(crate) fn is_power_on(&self) -> bool {
+        *self == VcpWrite::PowerMode(POWER_ON)
+    }
+
+    /// Sort the `writes` in the order to apply, keeping the order of the same kinds.
+    pub(crate) fn sort(writes: &[VcpWrite]) -> Vec<VcpWrite> {
+        let mut writes = writes.to_vec();
+        writes.sort_by_key(VcpWrite::order);
+        writes
+    }
+}
+
+/// A value of a VCP feature in arguments such as `name.brightness=value`;
+/// a number, or a percentage of the maximum such as `50%`.
+/// The `power` also accepts `on` and `off`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct FeatureValue {
+    number: u16,
+    is_percent: bool,
+}
+
+impl FeatureValue {
+    /// Parse the `value` of the `feature`, such as `brightness`.
+    pub fn parse(feature: &str, value: &str) -> anyhow::Result<Self> {
+        if feature == "power" {
+            match value {
+                "on" => return Ok(Self::number(POWER_ON)),
+                "off" => return Ok(Self::number(POWER_OFF)),
+                _ => {}
+            }
+        }
+        let (number, is_percent) = match value.strip_suffix('%') {
+            Some(number) => (number, true),
+            None => (value, false),
+        };
+        let number: u16 = number
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid {feature} \"{value}\""))?;
+        if is_percent && number > 100 {
+            anyhow::bail!("The {feature} {value} is more than 100%");
+        }
+        Ok(Self { number, is_percent })
+    }
+
+    fn number(number: u16) -> Self {
+        Self {
+            number,
+            is_percent: false,
+        }
+    }
+
+    /// The value of the VCP feature `code` for the `monitor`.
+    /// The maximum is read from the `monitor`,
+    /// except for numbers of non-continuous features.
+    pub fn resolve(
+        &self,
+        feature: &str,
+        code: VcpCode,
+        monitor: &mut Monitor,
+    ) -> anyhow::Result<u16> {
+        if !self.is_percent && monitor.vcp_kind(code) == VcpKind::NonContinuous {
+            return Ok(self.number);
+        }
+        let current = monitor.vcp_value(code)?;
+        let target = if self.is_percent {
+            if current.maximum == 0 {
+                anyhow::bail!("The maximum {feature} of {monitor} is unknown");
+            }
+            (u32::from(current.maximum) * u32::from(self.number) / 100) as u16
+        } else {
+            self.number
+        };
+        if !current.accepts(target) {
+            anyhow::bail!(
+                "The {feature} {target} is more than the maximum {maximum} of {monitor}",
+                maximum = current.maximum
+            );
+        }
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort() {
+        assert_eq!(
+            VcpWrite::sort(&[
+                VcpWrite::PowerMode(4),
+                VcpWrite::Brightness(50),
+                VcpWrite::Feature(0x12, 70),
+                VcpWrite::InputSource(0x0F),
+                VcpWrite::PowerMode(1),
+            ]),
+            [
+                VcpWrite::PowerMode(1),
+                VcpWrite::InputSource(0x0F),
+                VcpWrite::Brightness(50),
+                VcpWrite::Feature(0x12, 70),
+                VcpWrite::PowerMode(4),
+            ]
+        );
+    }
+
+    #[test]
+    fn feature_value() {
+        let mut monitor = Monitor::from_device(MockMonitor::new("A"));
+        monitor.set_brightness(10).unwrap();
+        let resolve = |feature: &str, code: VcpCode, value: &str, monitor: &mut Monitor| {
+            FeatureValue::parse(feature, value)?.resolve(feature, code, monitor)
+        };
+        assert_eq!(
+            resolve("brightness", LUMINANCE, "30", &mut monitor).unwrap(),
+            30
+        );
+        // The maximum of `MockMonitor` is `u16::MAX`.
+        assert_eq!(
+            resolve("brightness", LUMINANCE, "50%", &mut monitor).unwrap(),
+            u16::MAX / 2
+        );
+        assert!(resolve("brightness", LUMINANCE, "101%", &mut monitor).is_err());
+        assert!(resolve("brightness", LUMINANCE, "x", &mut monitor).is_err());
+        assert_eq!(
+            resolve("power", POWER_MODE, "on", &mut monitor).unwrap(),
+            POWER_ON
+        );
+        assert_eq!(
+            resolve("power", POWER_MODE, "off", &mut monitor).unwrap(),
+            POWER_OFF
+        );
+        assert_eq!(resolve("power", POWER_MODE, "5", &mut monitor).unwrap(), 5);
+    }
+}
@@ -0,0 +1,89 @@
+use strum_macros::{AsRefStr, EnumString, VariantArray};
+
+#[derive(Copy, Clone, Debug, PartialEq, AsRefStr, EnumString, VariantArray)]
+#[strum(ascii_case_insensitive)]
+/// Picture-by-picture (PBP) and picture-in-picture (PIP) modes,
+/// which show multiple input sources at once.
+/// See [`crate::Monitor::set_pbp_mode()`].
+/// # Examples
+/// ```
+/// # use monitor_input::PbpMode;
+/// assert_eq!("2x1".parse::<PbpMode>().unwrap(), PbpMode::Pbp2x1);
+/// assert_eq!(PbpMode::PipSmall.as_ref(), "pip-small");
+/// ```
+pub enum PbpMode {
+    /// Show only one input source.
+    #[strum(serialize = "off")]
+    Off,
+    /// Show another input source in a small window.
+    #[strum(serialize = "pip", to_string = "pip-small")]
+    PipSmall,
+    /// Show another input source in a large window.
+    #[strum(serialize = "pip-large")]
+    PipLarge,
+    /// Show two input sources side by side.
+    #[strum(serialize = "2x1")]
+    Pbp2x1,
+    /// Show four input sources in quadrants.
+    #[strum(serialize = "2x2")]
+    Pbp2x2,
+}
+
+/// The three-letter manufacturer ID in the `edid`, such as `DEL` for Dell.
+pub(crate) fn manufacturer_id_from_edid(edid: &[u8]) -> Option<String> {
+    let id = u16::from_be_bytes([*edid.get(8)?, *edid.get(9)?]);
+    [10, 5, 0]
+        .iter()
+        .map(|shift| match (id >> shift) & 0x1F {
+            letter @ 1..=26 => Some((b'A' + letter as u8 - 1) as char),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn manufacturer_id_from_edid() {
+        let mut edid = [0u8; 128];
+        edid[8..10].copy_from_slice(&[0x10, 0xAC]);
+        assert_eq!(
+            super::manufacturer_id_from_edid(&edid).as_deref(),
+            Some("DEL")
+        );
+        edid[8..10].copy_from_slice(&[0x1E, 0x6D]);
+        assert_eq!(
+            super::manufacturer_id_from_edid(&edid).as_deref(),
+            Some("GSM")
+        );
+        assert_eq!(super::manufacturer_id_from_edid(&edid[..8]), None);
+        assert_eq!(super::manufacturer_id_from_edid(&[0; 10]), None);
+    }
+
+    #[test]
+    fn find_pbp() {
+        let monitor = Monitor::from_device(MockMonitor::new("A").with_model_name("DELL U3818DW"));
+        let quirks = Quirks::find_pbp(&monitor).unwrap();
+        assert_eq!(quirks.pbp_value(PbpMode::Pbp2x1), Some(0x24));
+        assert_eq!(quirks.pbp_mode(0x24), Some(PbpMode::Pbp2x1));
+        let monitor = Monitor::from_device(MockMonitor::new("B").with_model_name("Unknown"));
+        assert!(Quirks::find_pbp(&monitor).is_none());
+    }
+
+    #[test]
+    fn set_pbp_mode_lg() {
+        let mut edid = [0u8; 128];
+        edid[8..10].copy_from_slice(&[0x1E, 0x6D]);
+        let mut monitor = Monitor::from_device(MockMonitor::new("A").with_edid(edid));
+        monitor.set_pbp_mode(PbpMode::Pbp2x1).unwrap();
+        assert_eq!(monitor.vcp_feature(0xF7).unwrap().value, 0x01);
+        assert_eq!(monitor.pbp_mode().unwrap(), PbpMode::Pbp2x1);
+        assert!(monitor.set_pbp_mode(PbpMode::Pbp2x2).is_err());
+
+        let monitor = Monitor::from_device(MockMonitor::new("B").with_model_name("LG HDR WQHD"));
+        assert_eq!(Quirks::find_pbp(&monitor).unwrap().manufacturer_id, "GSM");
+    }
+}
@@ -0,0 +1,94 @@
+use super::*;
+use crate::logging::*;
+use serde::Serialize;
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+/// A change of the input source made outside of this program,
+/// detected by [`Monitor::poll_input_source()`].
+pub(crate) struct InputSourceChange {
+    pub monitor: String,
+    pub previous: Option<String>,
+    pub input_source: String,
+}
+
+impl InputSourceChange {
+    pub fn new(monitor: &Monitor, previous: Option<InputSourceRaw>, value: InputSourceRaw) -> Self {
+        Self {
+            monitor: monitor.id().to_string(),
+            previous: previous.map(InputSource::str_from_raw),
+            input_source: InputSource::str_from_raw(value),
+        }
+    }
+
+    /// Log the change, print it as a JSON line if `report` is set,
+    /// post it to the [`WatchConfig::webhook`],
+    /// and show a desktop notification if [`WatchConfig::notification`].
+    pub fn notify(&self, config: &WatchConfig, report: Option<ReportFormat>) {
+        info!(
+            "InputSource({monitor}) changed: {previous} -> {value}",
+            monitor = self.monitor,
+            previous = self.previous.as_deref().unwrap_or("?"),
+            value = self.input_source
+        );
+        if let Some(ReportFormat::Json) = report {
+            match serde_json::to_string(self) {
+                Ok(json) => println!("{json}"),
+                Err(e) => error!("{e}"),
+            }
+        }
+        if let Some(url) = &config.webhook
+            && let Err(e) = self.post(url)
+        {
+            warn!("Failed to post to {url}: {e}");
+        }
+        if config.notification
+            && let Err(e) = native().notify(tr("Input source changed"), &self.to_string())
+        {
+            warn!("Failed to show the notification: {e}");
+        }
+    }
+
+    #[cfg(feature = "webhook")]
+    fn post(&self, url: &str) -> anyhow::Result<()> {
+        let json = serde_json::to_string(self)?;
+        debug!("POST {url}: {json}");
+        ureq::post(url)
+            .header("Content-Type", "application/json")
+            .send(json)?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "webhook"))]
+    fn post(&self, _url: &str) -> anyhow::Result<()> {
+        anyhow::bail!("The `webhook` feature is not enabled");
+    }
+}
+
+/// The display monitor and the input sources, such as `A: DP1 -> Hdmi1`,
+/// or only the current input source if the previous one is unknown.
+impl std::fmt::Display for InputSourceChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.previous {
+            Some(previous) => write!(f, "{}: {previous} -> {}", self.monitor, self.input_source),
+            None => write!(f, "{}: {}", self.monitor, self.input_source),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_json() {
+        let change = InputSourceChange {
+            monitor: "A".into(),
+            previous: Some("DP1".into()),
+            input_source: "Hdmi1".into(),
+        };
+        assert_eq!(
+            serde_json::to_string(&change).unwrap(),
+            r#"{"monitor":"A","previous":"DP1","input_source":"Hdmi1"}"#
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use std::net::SocketAddr;
+use std::sync::mpsc;
+
+use super::*;
+
+/// The port of the `daemon --websocket` when it's omitted.
+#[cfg_attr(not(feature = "websocket"), allow(dead_code))]
+const DEFAULT_PORT: u16 = 4748;
+
+/// Listen to WebSocket connections at the `address`, such as from Stream Deck plugins.
+/// Each text message is a [`RemoteRequest`] in JSON, sent to the `sender`,
+/// and its [`RemoteResponse`] is sent back as a `response` event.
+/// Changes of input sources are sent to all clients as `input_source` events.
+/// Requests without the `token` are rejected if it's set.
+/// Addresses other than the loopback require the `token`.
+///
+/// Connections from web pages, which have the `Origin` header,
+/// are rejected unless the `token` is set or the origin is in the `origins`,
+/// so that web pages in browsers can't change input sources.
+/// Returns the address listening to.
+#[cfg(feature = "websocket")]
+pub(crate) fn listen<T: From<RemoteCall> + Send + 'static>(
+    address: &str,
+    token: Option<String>,
+    origins: Vec<String>,
+    sender: mpsc::Sender<T>,
+) -> anyhow::Result<SocketAddr> {
+    imp::listen(address, token, origins, sender)
+}
+
+#[cfg(not(feature = "websocket"))]
+pub(crate) fn listen<T: From<RemoteCall> + Send + 'static>(
+    _address: &str,
+    _token: Option<String>,
+    _origins: Vec<String>,
+    _sender: mpsc::Sender<T>,
+) -> anyhow::Result<SocketAddr> {
+    anyhow::bail!("The `websocket` feature is not enabled");
+}
+
+/// Send the `change` to all clients connected to the `daemon --websocket`.
+pub(crate) fn broadcast_input_source(change: &InputSourceChange) {
+    #[cfg(feature = "websocket")]
+    imp::broadcast(&imp::Event::InputSource(change));
+    #[cfg(not(feature = "websocket"))]
+    let _ = change;
+}
+
+#[cfg(feature = "websocket")]
+mod imp {
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::sync::{Mutex, mpsc};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::logging::*;
+    use serde::Serialize;
+    use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+    use tungstenite::http::StatusCode;
+    use tungstenite::protocol::WebSocketConfig;
+    use tungstenite::{Message, WebSocket};
+
+    /// The maximum length of messages from clients.
+    const MAX_MESSAGE_LEN: usize = 64 * 1024;
+
+    /// The timeout of the opening handshake.
+    const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// The interval to check events to send while waiting for messages from clients.
+    const READ_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// The timeout of writing to clients.
+    /// Clients that don't read events in this time are disconnected,
+    /// so that they don't block the daemon.
+    const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+    /// The clients connected to the `daemon --websocket`, to send events to.
+    static CLIENTS: Mutex<Vec<mpsc::Sender<String>>> = Mutex::new(Vec::new());
+
+    #[derive(Serialize)]
+    #[serde(tag = "event", rename_all = "snake_case")]
+    /// Messages to clients.
+    pub(super) enum Event<'a> {
+        Response(&'a RemoteResponse),
+        InputSource(&'a InputSourceChange),
+    }
+
+    pub(super) fn listen<T: From<RemoteCall> + Send + 'static>(
+        address: &str,
+        token: Option<String>,
+        origins: Vec<String>,
+        sender: mpsc::Sender<T>,
+    ) -> anyhow::Result<SocketAddr> {
+        let address = with_default_port(address, DEFAULT_PORT);
+        check_listen_address(&address, token.as_deref())?;
+        let listener = TcpListener::bind(&address)
+            .map_err(|e| anyhow::anyhow!("Failed to listen to {address}: {e}"))?;
+        let local_address = listener.local_addr()?;
+        info!("WebSocket: Listening to {local_address}");
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let token = token.clone();
+                let origins = origins.clone();
+                let sender = sender.clone();
+                // Each connection has its own thread, because connections are long-lived.
+                std::thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, token.as_deref(), &origins, &sender) {
+                        warn!("WebSocket: {e}");
+                    }
+                });
+            }
+        });
+        Ok(local_address)
+    }
+
+    /// Queue the `event` to all clients.
+    /// Each connection sends it from its own thread,
+    /// so that slow clients don't block others.
+    pub(super) fn broadcast(event: &Event) {
+        let Ok(json) = serde_json::to_string(event) else {
+            return;
+        };
+        CLIENTS
+            .lock()
+            .unwrap()
+            .retain(|client| client.send(json.clone()).is_ok());
+    }
+
+    fn handle_connection<T: From<RemoteCall>>(
+        stream: TcpStream,
+        token: Option<&str>,
+        origins: &[String],
+        sender: &mpsc::Sender<T>,
+    ) -> anyhow::Result<()> {
+        let peer = stream.peer_addr()?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let mut rejected_origin = None;
+        // The `Err` type is defined by `tungstenite`.
+        #[allow(clippy::result_large_err)]
+        let check_origin = |request: &Request, response: Response| {
+            let origin = request
+                .headers()
+                .get("Origin")
+                .map(|origin| String::from_utf8_lossy(origin.as_bytes()).to_string());
+            if let Some(origin) = origin
+                && token.is_none()
+                && !origins.contains(&origin)
+            {
+                rejected_origin = Some(origin);
+                let mut response = ErrorResponse::new(None);
+                *response.status_mut() = StatusCode::FORBIDDEN;
+                return Err(response);
+            }
+            Ok(response)
+        };
+        let config = WebSocketConfig::default().max_message_size(Some(MAX_MESSAGE_LEN));
+        let websocket = tungstenite::accept_hdr_with_config(stream, check_origin, Some(config))
+            .map_err(|e| anyhow::anyhow!("({peer}): {e}"));
+        if let Some(origin) = rejected_origin {
+            anyhow::bail!(
+                "({peer}): The origin {origin:?} isn't allowed without the `token`, \
+                nor in the `origins`"
+            );
+        }
+        let mut websocket = websocket?;
+        websocket.get_ref().set_read_timeout(Some(READ_INTERVAL))?;
+        info!("WebSocket({peer}): Connected");
+        let (event_sender, events) = mpsc::channel();
+        CLIENTS.lock().unwrap().push(event_sender);
+        let result = handle_messages(&mut websocket, &events, token, sender);
+        info!("WebSocket({peer}): Disconnected");
+        result
+    }
+
+    /// Handle messages from the client, and send events queued in the `events`,
+    /// until the connection is closed.
+    fn handle_messages<T: From<RemoteCall>>(
+        websocket: &mut WebSocket<TcpStream>,
+        events: &mpsc::Receiver<String>,
+        token: Option<&str>,
+        sender: &mpsc::Sender<T>,
+    ) -> anyhow::Result<()> {
+        loop {
+            for event in events.try_iter() {
+                websocket.send(Message::text(event))?;
+            }
+            match websocket.read() {
+                Ok(Message::Text(text)) => {
+                    let response = handle_request(text.as_bytes(), token, sender)?;
+                    let json = serde_json::to_string(&Event::Response(&response))?;
+                    websocket.send(Message::text(json))?;
+                }
+                // Pings and closes are answered by `tungstenite`.
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) => {}
+                Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    fn handle_request<T: From<RemoteCall>>(
+        payload: &[u8],
+        token: Option<&str>,
+        sender: &mpsc::Sender<T>,
+    ) -> anyhow::Result<RemoteResponse> {
+        let request: RemoteRequest = match serde_json::from_slice(payload) {
+            Ok(request) => request,
+            Err(e) => {
+                return Ok(RemoteResponse {
+                    error: Some(e.to_string()),
+                    ..Default::default()
+                });
+            }
+        };
+        info!("WebSocket: {}", request.args.join(" "));
+        if token.is_some() && request.token.as_deref() != token {
+            return Ok(RemoteResponse {
+                error: Some("Invalid token".into()),
+                ..Default::default()
+            });
+        }
+        let (response_sender, response_receiver) = mpsc::channel();
+        sender
+            .send((request, response_sender).into())
+            .map_err(|_| anyhow::anyhow!("The daemon has stopped"))?;
+        Ok(response_receiver.recv()?)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use tungstenite::client::IntoClientRequest;
+        use tungstenite::handshake::HandshakeError;
+
+        /// Connect to the `address` with the `origin`,
+        /// and return the client, or the status of the rejected handshake.
+        fn connect(
+            address: SocketAddr,
+            origin: Option<&str>,
+        ) -> Result<WebSocket<TcpStream>, StatusCode> {
+            let mut request = format!("ws://{address}/").into_client_request().unwrap();
+            if let Some(origin) = origin {
+                request
+                    .headers_mut()
+                    .insert("Origin", origin.parse().unwrap());
+            }
+            let stream = TcpStream::connect(address).unwrap();
+            match tungstenite::client(request, stream) {
+                Ok((websocket, _)) => Ok(websocket),
+                Err(HandshakeError::Failure(tungstenite::Error::Http(response))) => {
+                    Err(response.status())
+                }
+                Err(e) => panic!("{e}"),
+            }
+        }
+
+        #[test]
+        fn origin() {
+            let (sender, _receiver) = mpsc::channel::<RemoteCall>();
+            let origins = vec!["https://allowed.example".to_string()];
+            let address = super::listen("127.0.0.1:0", None, origins, sender.clone()).unwrap();
+            assert!(connect(address, None).is_ok());
+            assert_eq!(
+                connect(address, Some("https://evil.example")).err(),
+                Some(StatusCode::FORBIDDEN)
+            );
+            assert!(connect(address, Some("https://allowed.example")).is_ok());
+
+            // Web pages can't know the token, so any origins are allowed with it.
+            let address = super::listen("127.0.0.1:0", Some("t".into()), vec![], sender).unwrap();
+            assert!(connect(address, Some("https://any.example")).is_ok());
+        }
+
+        #[test]
+        fn listen_address() {
+            let (sender, _receiver) = mpsc::channel::<RemoteCall>();
+            assert!(super::listen("0.0.0.0:0", None, vec![], sender.clone()).is_err());
+            assert!(super::listen("0.0.0.0:0", Some("t".into()), vec![], sender).is_ok());
+        }
+
+        #[test]
+        fn listen() {
+            let (sender, receiver) = mpsc::channel::<RemoteCall>();
+            let address = super::listen("127.0.0.1:0", None, vec![], sender).unwrap();
+            std::thread::spawn(move || {
+                for (request, response_sender) in receiver {
+                    let response = RemoteResponse {
+                        output: request.args.join(" "),
+                        ..Default::default()
+                    };
+                    response_sender.send(response).unwrap();
+                }
+            });
+
+            let mut websocket = connect(address, None).unwrap();
+            websocket.send(Message::text(r#"{"args":["a"]}"#)).unwrap();
+            let json: serde_json::Value =
+                serde_json::from_str(websocket.read().unwrap().to_text().unwrap()).unwrap();
+            assert_eq!(json["event"], "response");
+            assert_eq!(json["output"], "a");
+
+            let change = InputSourceChange {
+                monitor: "A".into(),
+                previous: None,
+                input_source: "DP1".into(),
+            };
+            broadcast(&Event::InputSource(&change));
+            let json: serde_json::Value =
+                serde_json::from_str(websocket.read().unwrap().to_text().unwrap()).unwrap();
+            assert_eq!(json["event"], "input_source");
+            assert_eq!(json["input_source"], "DP1");
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! The Windows app, which shows the logs as toast notifications
+//! instead of the console.
+use std::fmt;
+
+use clap::Parser;
+use toast_logger_win::ToastLogger;
+use winrt_toast::url::Url;
+
+use monitor_input::{Cli, URI_SCHEME};
+
+use crate::toast;
+
+pub(crate) fn main() -> anyhow::Result<()> {
+    let args = args();
+    let mut cli: Cli = Cli::parse_from(&args);
+    init_logger(cli.verbose);
+    cli.monitors = cli.enumerate_monitors()?;
+    let result = cli.run();
+    if let Err(e) = &result {
+        log::error!("{e}");
+    }
+    toast::show_results(&cli)?;
+    result
+}
+
+/// The command line arguments,
+/// or the arguments in the URI if it's run by [`URI_SCHEME`].
+fn args() -> Vec<String> {
+    let mut args: Vec<String> = std::env::args().collect();
+    if args.len() == 2
+        && args[1].starts_with(&format!("{URI_SCHEME}:"))
+        && let Ok(url) = Url::parse(&args[1])
+    {
+        args.truncate(1);
+        args.extend(
+            url.query_pairs()
+                .filter(|(key, _)| key == "arg")
+                .map(|(_, value)| value.into_owned()),
+        );
+    }
+    args
+}
+
+fn init_logger(verbose: u8) {
+    ToastLogger::builder()
+        .application_id(&toast::application_id_or_default())
+        .auto_flush(false)
+        .max_level(match verbose {
+            0 => log::LevelFilter::Error,
+            1 => log::LevelFilter::Info,
+            2 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        })
+        .format(
+            |buf: &mut dyn fmt::Write, record: &log::Record| match record.level() {
+                log::Level::Info => write!(buf, "{}", record.args()),
+                _ => write!(buf, "{}: {}", record.level(), record.args()),
+            },
+        )
+        .create_notification(toast::create_notification)
+        .init()
+        .unwrap();
+}
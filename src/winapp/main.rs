@@ -0,0 +1,17 @@
+#![cfg_attr(
+    all(feature = "winapp", target_os = "windows"),
+    windows_subsystem = "windows"
+)]
+
+#[cfg(all(feature = "winapp", target_os = "windows"))]
+mod app;
+#[cfg(all(feature = "winapp", target_os = "windows"))]
+mod toast;
+
+#[cfg(all(feature = "winapp", target_os = "windows"))]
+fn main() -> anyhow::Result<()> {
+    app::main()
+}
+
+#[cfg(not(all(feature = "winapp", target_os = "windows")))]
+include!("../main.rs");
@@ -0,0 +1,119 @@
+//! Composes the toast notifications from the results of [`Cli::run()`]
+//! and the log records.
+use std::sync::Mutex;
+use std::time::Duration;
+
+use toast_logger_win::{BufferedRecord, Notification, ToastLogger};
+use winrt_toast::{Action, ToastManager, content::action::ActivationType};
+
+use monitor_input::{ChangeSummary, Cli, DEFAULT_APPLICATION_ID, URI_SCHEME, application_id, tr};
+
+/// The duration to keep notifications without warnings or errors.
+const EXPIRES_IN: Duration = Duration::from_secs(10);
+
+/// The results of [`Cli::run()`] to compose notifications with.
+#[derive(Default)]
+struct Results {
+    /// The summary to show instead of the informational log records.
+    summary: Option<ChangeSummary>,
+    /// The command line arguments to undo the changes.
+    undo_args: Vec<String>,
+    /// The command line arguments to retry the failed changes.
+    retry_args: Vec<String>,
+}
+
+static RESULTS: Mutex<Results> = Mutex::new(Results {
+    summary: None,
+    undo_args: Vec::new(),
+    retry_args: Vec::new(),
+});
+
+/// The application ID for [`ToastLogger`] and [`ToastManager`].
+pub(crate) fn application_id_or_default() -> String {
+    application_id().unwrap_or_else(|| DEFAULT_APPLICATION_ID.to_string())
+}
+
+/// Show the results of the `cli` and the buffered log records
+/// in a single notification.
+/// If any changes failed, the notification can retry only them,
+/// so that toggles and steps don't advance the succeeded ones again.
+pub(crate) fn show_results(cli: &Cli) -> anyhow::Result<()> {
+    {
+        let mut results = RESULTS.lock().unwrap();
+        results.summary = cli.change_summary();
+        results.undo_args = cli
+            .set_results()
+            .iter()
+            .filter_map(|result| result.undo_arg())
+            .collect();
+        results.retry_args = cli
+            .set_results()
+            .iter()
+            .filter_map(|result| result.retry_arg())
+            .collect();
+    }
+    ToastLogger::flush()?;
+    // Show the summary if there were no log records to show it with.
+    if RESULTS.lock().unwrap().summary.is_some() {
+        let notification = create_notification(&[])?;
+        ToastManager::new(application_id_or_default()).show(notification.inner())?;
+    }
+    Ok(())
+}
+
+/// Create a notification of the `records`, for [`ToastLogger`].
+///
+/// If there's a [`ChangeSummary`], it's shown instead of the informational records,
+/// followed by warnings and errors if any.
+pub(crate) fn create_notification(
+    records: &[BufferedRecord],
+) -> toast_logger_win::Result<Notification> {
+    let mut results = RESULTS.lock().unwrap();
+    let is_success = results
+        .summary
+        .as_ref()
+        .is_none_or(|summary| summary.is_success());
+    let mut notification = match results.summary.take() {
+        Some(summary) => {
+            let mut notification = Notification::new_with_text(&summary.title())?;
+            let mut lines = vec![summary.body()];
+            lines.extend(
+                records
+                    .iter()
+                    .filter(|record| record.level() < log::Level::Info)
+                    .map(|record| record.args().to_string()),
+            );
+            notification.inner_mut().text2(lines.join("\n"));
+            notification
+        }
+        None => Notification::new_with_records(records)?,
+    };
+    let min_level = records.iter().map(|record| record.level()).min();
+    if is_success && min_level.is_none_or(|level| level >= log::Level::Info) {
+        notification.expires_in(EXPIRES_IN)?;
+    }
+    if !results.retry_args.is_empty() {
+        notification
+            .inner_mut()
+            .action(protocol_action(tr("Retry"), &results.retry_args));
+    }
+    if !results.undo_args.is_empty() {
+        notification
+            .inner_mut()
+            .action(protocol_action(tr("Undo"), &results.undo_args));
+    }
+    Ok(notification)
+}
+
+/// The action to run the `monitor-inputw` with the `args` by the [`URI_SCHEME`].
+/// The `args` are read back by `args()` when it's activated.
+fn protocol_action(content: &str, args: &[String]) -> Action {
+    Action::new(content, uri_from_args(args), "").with_activation_type(ActivationType::Protocol)
+}
+
+fn uri_from_args(args: &[String]) -> String {
+    let query = winrt_toast::url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(args.iter().map(|arg| ("arg", arg)))
+        .finish();
+    format!("{URI_SCHEME}:?{query}")
+}